@@ -23,7 +23,10 @@
 //! Converts raw collectl text output (containing epoch markers, disk, cpu, meminfo, net)
 //! into CSV lines compatible with serverstats_grab tools.
 //!
-//! - Emits DISK, CPU, MEM, and NET lines in correct format for .dat playback
+//! - Emits DISK, CPU, MEM, and NET lines in correct format for .dat playback,
+//!   using the serverstats_format record writer
+//! - Accepts RHEL7 (14-field) and RHEL8+ (18/20-field) diskstats in the same binary;
+//!   missing discard counters are written as 0 and a DISKLAYOUT record says which layout was seen
 //! - Handles large files efficiently and tracks progress
 //! - Fails safe on missing/partial fields
 //!
@@ -34,8 +37,9 @@
 use std::env;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader, Write};
+use serverstats_format::{CpuStat, DiskStat, MemStat, NetStat, Record, Writer};
 
-const VERSION_NUMBER: &str = "2.1.3";
+const VERSION_NUMBER: &str = "2.2.0";
 
 fn main() -> io::Result<()> {
     let args: Vec<String> = env::args().collect();
@@ -56,12 +60,13 @@ fn parse_collectl_raw_to_dat(raw_path: &str, out_path: &str) -> io::Result<()> {
     let reader = BufReader::new(infile);
     let lines: Vec<String> = reader.lines().filter_map(Result::ok).collect();
 
-    let mut out = File::create(out_path)?;
-    writeln!(out, "#TYPE,ts_epoch,<fields...>")?;
+    let mut out = Writer::new(io::BufWriter::new(File::create(out_path)?));
+    out.write_header()?;
 
     let mut i = 0;
     let mut input_line_num = 0usize;
     let mut epoch_count = 0usize;
+    let mut last_layout: Option<u8> = None;
     let progress_every = 10_000;
 
     while i < lines.len() {
@@ -84,11 +89,12 @@ fn parse_collectl_raw_to_dat(raw_path: &str, out_path: &str) -> io::Result<()> {
         epoch_count += 1;
         let ts = ts_match.unwrap() as u64;
 
-        let mut cpu_vals: Option<Vec<String>> = None;
+        let mut cpu: Option<CpuStat> = None;
         let mut procs_running = 0;
         let mut procs_blocked = 0;
-        let mut meminfo = std::collections::HashMap::<String, String>::new();
-        let mut net_lines: Vec<Vec<String>> = Vec::new();
+        let mut mem = MemStat::default();
+        let mut have_mem = false;
+        let mut net_stats: Vec<NetStat> = Vec::new();
 
         // Scan until next epoch or EOF
         i += 1;
@@ -105,107 +111,52 @@ fn parse_collectl_raw_to_dat(raw_path: &str, out_path: &str) -> io::Result<()> {
             if l.starts_with("cpu") && !l.starts_with("cpu ") {
                 // per-cpu, ignore
             } else if l.starts_with("cpu ") {
-                cpu_vals = Some(l.split_whitespace().map(|s| s.to_string()).collect());
+                cpu = CpuStat::from_cpu_line(l);
             } else if l.starts_with("procs_running") {
                 procs_running = l.split_whitespace().nth(1).and_then(|v| v.parse().ok()).unwrap_or(0);
             } else if l.starts_with("procs_blocked") {
                 procs_blocked = l.split_whitespace().nth(1).and_then(|v| v.parse().ok()).unwrap_or(0);
-            } else if l.starts_with("disk") {
-                let disk_fields: Vec<&str> = l.split_whitespace().collect();
-                if disk_fields.len() >= 18 {
-                    let devname = disk_fields[3];
-                    if wanted_disks.iter().any(|prefix| devname.starts_with(prefix)) {
-                        writeln!(
-                            out,
-                            "DISK,{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
-                            ts,
-                            disk_fields[1],   // major
-                            disk_fields[2],   // minor
-                            disk_fields[3],   // name
-                            disk_fields[4], disk_fields[5], disk_fields[6], disk_fields[7],
-                            disk_fields[8], disk_fields[9], disk_fields[10], disk_fields[11],
-                            disk_fields[12], disk_fields[13], disk_fields[14], disk_fields[15],
-                            disk_fields[16], disk_fields[17],
-                            if disk_fields.len() > 18 { disk_fields[18] } else { "0" }
-                        )?;
+            } else if let Some(disk) = l.strip_prefix("disk ") {
+                // "disk" then a /proc/diskstats line: 14 fields on RHEL7,
+                // 17-18 with discards (4.18+), 20 with flushes (5.5+)
+                if let Some(stat) = DiskStat::from_line(disk)
+                    && wanted_disks.iter().any(|prefix| stat.name.starts_with(prefix))
+                {
+                    if last_layout != Some(stat.layout) {
+                        out.write(&Record::DiskLayout { ts, fields: stat.layout })?;
+                        last_layout = Some(stat.layout);
                     }
+                    out.write(&Record::Disk { ts, stat })?;
                 }
             } else if l.contains(':') && !l.starts_with("Net ") {
                 let parts: Vec<&str> = l.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    let k = parts[0].trim_end_matches(':');
-                    let wanted = [
-                        "MemTotal","MemFree","MemAvailable","Buffers","Cached","SwapTotal",
-                        "SwapFree","Dirty","Writeback","Active(file)","Inactive(file)","Slab",
-                        "KReclaimable","SReclaimable"
-                    ];
-                    if wanted.contains(&k) {
-                        meminfo.insert(k.to_string(), parts[1].to_string());
-                    }
-                }
-            } else if l.trim_start().starts_with("Net ") {
-                // Working NET line logic
-                let parts: Vec<&str> = l.split_whitespace().collect();
-                if parts.len() >= 14 {
-                    let iface = parts[1].trim_end_matches(':');
-                    let rx_bytes   = parts[2];
-                    let rx_packets = parts[3];
-                    let rx_errors  = parts[4];
-                    let rx_dropped = parts[5];
-                    let tx_bytes   = parts[10];
-                    let tx_packets = parts[11];
-                    let tx_errors  = parts[12];
-                    let tx_dropped = parts[13];
-
-                    net_lines.push(vec![
-                        iface.to_string(),
-                        rx_bytes.to_string(),
-                        tx_bytes.to_string(),
-                        rx_packets.to_string(),
-                        tx_packets.to_string(),
-                        rx_errors.to_string(),
-                        tx_errors.to_string(),
-                        rx_dropped.to_string(),
-                        tx_dropped.to_string(),
-                    ]);
+                if parts.len() >= 2 && mem.set(parts[0].trim_end_matches(':'), parts[1].parse().unwrap_or(0)) {
+                    have_mem = true;
                 }
+            } else if let Some(net) = l.trim_start().strip_prefix("Net ")
+                && let Some(stat) = NetStat::from_net_dev_line(net)
+            {
+                net_stats.push(stat);
             }
             i += 1;
         }
 
         // Write CPU line if present for epoch
-        if let Some(cpu_fields) = cpu_vals {
-            if cpu_fields.len() >= 10 {
-                writeln!(
-                    out,
-                    "CPU,{},{},{},{},{},{},{},{},{},{},{},{}",
-                    ts,
-                    cpu_fields[1], cpu_fields[2], cpu_fields[3], cpu_fields[4], cpu_fields[5],
-                    cpu_fields[6], cpu_fields[7], cpu_fields[8], cpu_fields[9],
-                    procs_running, procs_blocked
-                )?;
-            }
+        if let Some(mut stat) = cpu {
+            stat.procs_running = procs_running;
+            stat.procs_blocked = procs_blocked;
+            out.write(&Record::Cpu { ts, stat })?;
         }
         // Write MEM line if present for epoch
-        if !meminfo.is_empty() {
-            let fields = [
-                "MemTotal","MemFree","MemAvailable","Buffers","Cached","SwapTotal","SwapFree","Dirty","Writeback","Active(file)","Inactive(file)","Slab","KReclaimable","SReclaimable"
-            ];
-            writeln!(
-                out,
-                "MEM,{},{}",
-                ts,
-                fields.iter()
-                    .map(|k| meminfo.get(*k).cloned().unwrap_or_else(|| "0".to_string()))
-                    .collect::<Vec<_>>()
-                    .join(",")
-            )?;
+        if have_mem {
+            out.write(&Record::Mem { ts, stat: mem })?;
         }
         // Write NET lines if present for this epoch
-        for net in &net_lines {
-            writeln!(out, "NET,{},{}", ts, net.join(","))?;
+        for stat in net_stats {
+            out.write(&Record::Net { ts, stat })?;
         }
     }
+    out.flush()?;
     println!("\rProcessed {} input lines ({} epochs). Done!", input_line_num, epoch_count);
     Ok(())
 }
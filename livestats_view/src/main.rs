@@ -8,50 +8,22 @@
 
 use std::{thread::sleep, time::Duration, env, fs::File, io::{BufRead, BufReader, Read}};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use chrono::Local;
+use serverstats_format::{CpuPercent, CpuStat, DiskDelta, DiskStat, MemPercent, MemStat, NetDelta, NetStat};
 
 // Increment as tol evolves
 const VERSION_NUMBER: &str = "2.1.3";
 
 // ======= DISK =======
-#[derive(Debug, Clone)]
-struct DiskStat {
-    name: String,
-    reads: u64,
-    writes: u64,
-    sectors_read: u64,
-    sectors_written: u64,
-    read_time_ms: u64,
-    write_time_ms: u64,
-    io_time_ms: u64,
-    weighted_io_time_ms: u64,
-}
-impl DiskStat {
-    fn from_line(line: &str) -> Option<Self> {
-        let cols: Vec<&str> = line.split_whitespace().collect();
-        if cols.len() < 14 { return None; }
-        Some(Self {
-            name: cols[2].to_string(),
-            reads: cols[3].parse().ok()?,
-            writes: cols[7].parse().ok()?,
-            sectors_read: cols[5].parse().ok()?,
-            sectors_written: cols[9].parse().ok()?,
-            read_time_ms: cols[6].parse().ok()?,
-            write_time_ms: cols[10].parse().ok()?,
-            io_time_ms: cols[12].parse().ok()?,
-            weighted_io_time_ms: cols[13].parse().ok()?,
-        })
-    }
-}
-
-fn run_live_disk(interval: u64, device_filter: Option<&str>) {
+fn run_live_disk(interval: u64, device_filter: Option<&str>, root: &Path) {
     let mut prev: HashMap<String, DiskStat> = HashMap::new();
     let mut printed_header = false;
     let mut output_count = 0;
 
     loop {
         let mut curr: HashMap<String, DiskStat> = HashMap::new();
-        if let Ok(file) = File::open("/proc/diskstats") {
+        if let Ok(file) = File::open(root.join("proc/diskstats")) {
             let reader = BufReader::new(file);
             for line in reader.lines().flatten() {
                 if let Some(stat) = DiskStat::from_line(&line) {
@@ -77,42 +49,13 @@ fn run_live_disk(interval: u64, device_filter: Option<&str>) {
         }
 
         for (dev, stat) in &curr {
-            if let Some(prev_stat) = prev.get(dev) {
-                let dt = interval as f64;
-
-                let d_reads = stat.reads.saturating_sub(prev_stat.reads);
-                let d_writes = stat.writes.saturating_sub(prev_stat.writes);
-                let d_sectors_read = stat.sectors_read.saturating_sub(prev_stat.sectors_read);
-                let d_sectors_written = stat.sectors_written.saturating_sub(prev_stat.sectors_written);
-                let delta_io_time_ms = stat.io_time_ms.saturating_sub(prev_stat.io_time_ms);
-                let delta_weighted_io_time_ms = stat.weighted_io_time_ms.saturating_sub(prev_stat.weighted_io_time_ms);
-
-                let r_s = d_reads as f64 / dt;
-                let w_s = d_writes as f64 / dt;
-                let rd_kbs = d_sectors_read as f64 * 512.0 / 1024.0 / dt;
-                let wr_kbs = d_sectors_written as f64 * 512.0 / 1024.0 / dt;
-
-                let total_kb = rd_kbs + wr_kbs;
-                let total_iops = r_s + w_s;
-
-                let qlen = if delta_io_time_ms > 0 {
-                    delta_weighted_io_time_ms as f64 / delta_io_time_ms as f64
-                } else { 0.0 };
-
-                let await_rd_ms = if d_reads > 0 {
-                    (stat.read_time_ms.saturating_sub(prev_stat.read_time_ms)) as f64 / d_reads as f64
-                } else {
-                    0.0
-                };
-                let await_wr_ms = if d_writes > 0 {
-                    (stat.write_time_ms.saturating_sub(prev_stat.write_time_ms)) as f64 / d_writes as f64
-                } else {
-                    0.0
-                };
-
+            if let Some(prev_stat) = prev.get(dev)
+                && let Some(d) = DiskDelta::between(prev_stat, stat, interval)
+            {
                 println!(
                     "{:<8} {:<10} {:>10.2} {:>10.2} {:>12.2} {:>12.2} {:>8.2} {:>12.2} {:>12.2} {:>12.2} {:>12.2}",
-                    now, dev, r_s, w_s, rd_kbs, wr_kbs, qlen, await_rd_ms, await_wr_ms, total_kb, total_iops
+                    now, dev, d.r_s(), d.w_s(), d.rd_kbs(), d.wr_kbs(), d.qlen(),
+                    d.await_rd(), d.await_wr(), d.kb_s(), d.io_s()
                 );
                 output_count += 1;
             }
@@ -123,51 +66,23 @@ fn run_live_disk(interval: u64, device_filter: Option<&str>) {
 }
 
 // ======= CPU =======
-fn run_live_cpu(interval: u64) {
-    let mut prev_vals: Option<Vec<u64>> = None;
-    let mut prev_guest: u64 = 0;
+fn run_live_cpu(interval: u64, root: &Path) {
+    let mut prev: Option<CpuStat> = None;
     let mut printed_header = false;
     let mut output_count = 0;
 
     loop {
         // Read /proc/stat
         let mut buf = String::new();
-        if File::open("/proc/stat").and_then(|mut f| f.read_to_string(&mut buf)).is_err() {
-            eprintln!("Failed to read /proc/stat");
+        if File::open(root.join("proc/stat")).and_then(|mut f| f.read_to_string(&mut buf)).is_err() {
+            eprintln!("Failed to read {}", root.join("proc/stat").display());
             return;
         }
-        let mut cpu_vals: Vec<u64> = Vec::new();
-        let mut running = None;
-        let mut blocked = None;
-        let mut guest = 0;
-        for line in buf.lines() {
-            if line.starts_with("cpu ") {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                cpu_vals = parts[1..=8].iter().filter_map(|v| v.parse().ok()).collect();
-                guest = parts.get(9).and_then(|v| v.parse().ok()).unwrap_or(0);
-            } else if line.starts_with("procs_running") {
-                running = line.split_whitespace().nth(1).and_then(|v| v.parse().ok());
-            } else if line.starts_with("procs_blocked") {
-                blocked = line.split_whitespace().nth(1).and_then(|v| v.parse().ok());
-            }
-        }
-
-        if let Some(last_vals) = prev_vals.as_ref() {
-            let total_diff: u64 = cpu_vals.iter().zip(last_vals.iter()).map(|(a, b)| a - b).sum::<u64>() + (guest - prev_guest);
-            if total_diff == 0 {
-                prev_vals = Some(cpu_vals);
-                prev_guest = guest;
-                sleep(Duration::from_secs(interval));
-                continue;
-            }
-            let factor = 100.0 / total_diff as f64;
-            let user   = (cpu_vals[0] - last_vals[0]) as f64 * factor;
-            let nice   = (cpu_vals[1] - last_vals[1]) as f64 * factor;
-            let sys    = (cpu_vals[2] - last_vals[2]) as f64 * factor;
-            let idle   = (cpu_vals[3] - last_vals[3]) as f64 * factor;
-            let iowait = (cpu_vals[4] - last_vals[4]) as f64 * factor;
-            let guestp = (guest - prev_guest) as f64 * factor;
+        let cur = CpuStat::from_proc_stat(&buf);
 
+        if let (Some(last), Some(stat)) = (prev.as_ref(), cur.as_ref())
+            && let Some(p) = CpuPercent::between(last, stat)
+        {
             let now = Local::now().format("%H:%M:%S").to_string();
             if !printed_header || output_count % 40 == 0 {
                 println!(
@@ -179,43 +94,28 @@ fn run_live_cpu(interval: u64) {
             }
             println!(
                 "{:<8} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>8} {:>8} {:>10.2}",
-                now, user, sys, idle, iowait, nice,
-                running.unwrap_or(0), blocked.unwrap_or(0), guestp
+                now, p.user, p.system, p.idle, p.iowait, p.nice,
+                stat.procs_running, stat.procs_blocked, p.guest
             );
             output_count += 1;
         }
-        prev_vals = Some(cpu_vals);
-        prev_guest = guest;
+        prev = cur;
         sleep(Duration::from_secs(interval));
     }
 }
 
 // ======= MEMORY =======
-fn run_live_mem(interval: u64) {
+fn run_live_mem(interval: u64, root: &Path) {
     let mut printed_header = false;
     let mut output_count = 0;
 
     loop {
         // Parse /proc/meminfo
-        let mut values = HashMap::new();
-        if let Ok(file) = File::open("/proc/meminfo") {
-            let reader = BufReader::new(file);
-            for line in reader.lines().flatten() {
-                let mut parts = line.split_whitespace();
-                if let (Some(key), Some(val)) = (parts.next(), parts.next()) {
-                    values.insert(key.trim_end_matches(':').to_string(), val.parse::<u64>().unwrap_or(0));
-                }
-            }
-        }
-        let mem_total = *values.get("MemTotal").unwrap_or(&1) as f64;
-        let mem_free  = *values.get("MemFree").unwrap_or(&0) as f64;
-        let mem_avail = *values.get("MemAvailable").unwrap_or(&0) as f64;
-        let cached    = *values.get("Cached").unwrap_or(&0) as f64;
-        let used = mem_total - mem_free;
-        let used_percent = if mem_total > 0.0 { used / mem_total * 100.0 } else { 0.0 };
-        let avail_percent = if mem_total > 0.0 { mem_avail / mem_total * 100.0 } else { 0.0 };
-        let cached_percent = if mem_total > 0.0 { cached / mem_total * 100.0 } else { 0.0 };
-        let free_percent = if mem_total > 0.0 { mem_free / mem_total * 100.0 } else { 0.0 };
+        let text = std::fs::read_to_string(root.join("proc/meminfo")).unwrap_or_default();
+        let stat = MemStat::from_meminfo(&text);
+        let pct = MemPercent::of(&stat);
+        let kb = |key: &str| stat.get(key).unwrap_or(0) as f64;
+        let used = kb("MemTotal") - kb("MemFree");
 
         let now = Local::now().format("%H:%M:%S").to_string();
         if !printed_header || output_count % 40 == 0 {
@@ -228,7 +128,7 @@ fn run_live_mem(interval: u64) {
         println!(
             "{:<8} {:>10.0} {:>10.0} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>10.0}",
             now,
-            used / 1024.0, mem_free / 1024.0, used_percent, avail_percent, cached_percent, free_percent, cached / 1024.0
+            used / 1024.0, kb("MemFree") / 1024.0, pct.used, pct.avail, pct.cached, pct.free, kb("Cached") / 1024.0
         );
         output_count += 1;
         sleep(Duration::from_secs(interval));
@@ -236,28 +136,18 @@ fn run_live_mem(interval: u64) {
 }
 
 // ======= NETWORK =======
-fn run_live_net(interval: u64) {
-    let mut prev: HashMap<String, [u64; 8]> = HashMap::new();
+fn run_live_net(interval: u64, root: &Path) {
+    let mut prev: HashMap<String, NetStat> = HashMap::new();
     let mut printed_header = false;
     let mut output_count = 0;
 
     loop {
-        let mut curr: HashMap<String, [u64; 8]> = HashMap::new();
-        if let Ok(file) = File::open("/proc/net/dev") {
+        let mut curr: HashMap<String, NetStat> = HashMap::new();
+        if let Ok(file) = File::open(root.join("proc/net/dev")) {
             let reader = BufReader::new(file);
-            for line in reader.lines().flatten().skip(2) {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 17 {
-                    let iface = parts[0].trim_end_matches(':').to_string();
-                    let rx_bytes   = parts[1].parse().unwrap_or(0);
-                    let rx_packets = parts[2].parse().unwrap_or(0);
-                    let rx_errors  = parts[3].parse().unwrap_or(0);
-                    let rx_dropped = parts[4].parse().unwrap_or(0);
-                    let tx_bytes   = parts[9].parse().unwrap_or(0);
-                    let tx_packets = parts[10].parse().unwrap_or(0);
-                    let tx_errors  = parts[11].parse().unwrap_or(0);
-                    let tx_dropped = parts[12].parse().unwrap_or(0);
-                    curr.insert(iface, [rx_bytes, tx_bytes, rx_packets, tx_packets, rx_errors, tx_errors, rx_dropped, tx_dropped]);
+            for line in reader.lines().map_while(Result::ok).skip(2) {
+                if let Some(stat) = NetStat::from_net_dev_line(&line) {
+                    curr.insert(stat.iface.clone(), stat);
                 }
             }
         }
@@ -269,23 +159,15 @@ fn run_live_net(interval: u64) {
             );
             printed_header = true;
         }
-        for (iface, vals) in &curr {
-            if let Some(prev_vals) = prev.get(iface) {
-                let drx_bytes = vals[0].saturating_sub(prev_vals[0]);
-                let dtx_bytes = vals[1].saturating_sub(prev_vals[1]);
-                let drx_packets = vals[2].saturating_sub(prev_vals[2]);
-                let dtx_packets = vals[3].saturating_sub(prev_vals[3]);
-                let drx_errs = vals[4].saturating_sub(prev_vals[4]);
-                let dtx_errs = vals[5].saturating_sub(prev_vals[5]);
-                let drx_drop = vals[6].saturating_sub(prev_vals[6]);
-                let dtx_drop = vals[7].saturating_sub(prev_vals[7]);
+        for (iface, stat) in &curr {
+            if let Some(prev_stat) = prev.get(iface)
+                && let Some(d) = NetDelta::between(prev_stat, stat, interval)
+            {
                 println!(
                     "{:<8} {:<10} {:>10.2} {:>10.2} {:>10} {:>10} {:>10} {:>10} {:>10}",
-                    now, iface,
-                    drx_bytes as f64 / interval as f64 / 1024.0,
-                    dtx_bytes as f64 / interval as f64 / 1024.0,
-                    drx_packets / interval, dtx_packets / interval,
-                    drx_errs / interval, dtx_errs / interval, (drx_drop + dtx_drop) / interval
+                    now, iface, d.rx_kbs(), d.tx_kbs(),
+                    d.rx_packets / interval, d.tx_packets / interval,
+                    d.rx_errs / interval, d.tx_errs / interval, (d.rx_drop + d.tx_drop) / interval
                 );
                 output_count += 1;
             }
@@ -304,6 +186,7 @@ fn usage() {
     eprintln!("  livestats_view -g <interval_seconds> -pM            # Memory stats");
     eprintln!("  livestats_view -g <interval_seconds> -pN            # Network stats");
     eprintln!("  livestats_view -g <interval_seconds> -pD [-d DEV]   # Disk stats (optional device filter)");
+    eprintln!("  --root <dir>                                        # Read <dir>/proc instead of /proc (e.g. /host in a container)");
     eprintln!("Example:");
     eprintln!("  livestats_view -g 1 -pD -d nvme     # Only nvme devices");
 }
@@ -314,6 +197,7 @@ fn main() {
     let mut interval: u64 = 1;
     let mut mode: Option<String> = None;
     let mut device_filter: Option<String> = None;
+    let mut root = PathBuf::from("/");
 
     let mut i = 0;
     while i < args.len() {
@@ -344,6 +228,16 @@ fn main() {
             mode = Some(arg.clone());
             i += 1;
             continue;
+        } else if arg == "--root" {
+            if i + 1 < args.len() {
+                root = PathBuf::from(&args[i + 1]);
+                i += 2;
+                continue;
+            } else {
+                eprintln!("Error: --root must be followed by a directory.");
+                usage();
+                std::process::exit(1);
+            }
         } else if arg == "-d" {
             if i + 1 < args.len() {
                 device_filter = Some(args[i + 1].clone());
@@ -366,10 +260,10 @@ fn main() {
     }
 
     match mode.unwrap().as_str() {
-        "-pC" => run_live_cpu(interval),
-        "-pM" => run_live_mem(interval),
-        "-pN" => run_live_net(interval),
-        "-pD" => run_live_disk(interval, device_filter.as_deref()),
+        "-pC" => run_live_cpu(interval, &root),
+        "-pM" => run_live_mem(interval, &root),
+        "-pN" => run_live_net(interval, &root),
+        "-pD" => run_live_disk(interval, device_filter.as_deref(), &root),
         _ => {
             usage();
             std::process::exit(1);
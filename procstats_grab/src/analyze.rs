@@ -24,8 +24,10 @@
     - Outputs top-N tables and generates SVG/PNG bar charts.
     - Provides playback (timestamped view) with delta columns.
 
-    Use `run_analysis(filename)` for dashboard/tables/charts, and
-    `run_playback(filename)` for delta view by timestamp.
+    Use `run_analysis(filename, tz)` for dashboard/tables/charts, and
+    `run_playback(filename, wide, ..., out)` for delta view by timestamp, as text,
+    CSV, JSON or NDJSON (`--format`) with optional `--columns`.
+    Times are shown in the capture host's timezone unless `--tz`/`--utc` is given.
 */
 
 use std::collections::HashMap;
@@ -34,6 +36,10 @@ use std::io::Write;
 use std::path::Path;
 use serde::Deserialize;
 use std::error::Error;
+use serverstats_format::follow::Follow;
+use serverstats_format::output::{Cell, Col, OutputOpts, Table};
+
+use crate::timeutil::{self, DisplayTz, TimeCtx, TimeWindow};
 
 // plotters
 use plotters::prelude::*;
@@ -44,31 +50,51 @@ use plotters::coord::Shift;
 // PLAYBACK MODE (delta by timestamp)
 // -------------------------------------------------------
 
-pub fn run_playback(filename: &str, wide: bool) -> Result<(), Box<dyn std::error::Error>> {
+pub fn run_playback(
+    filename: &str,
+    wide: bool,
+    ctx: &TimeCtx,
+    window: &TimeWindow,
+    out: &OutputOpts,
+    follow: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
 
-    use chrono::{DateTime, Local, Utc};
-    let mut rdr = csv::ReaderBuilder::new()
-        .has_headers(true)
-        .from_path(filename)?;
+    // Wider columns, COMM up to 24 chars for modern processes; CMDLINE only with -wide
+    let cmdline = Col::new("cmdline", "CMDLINE").sep("  ");
+    let cols = [
+        Col::new("time", "Time").l(8),
+        Col::new("pid", "PID").r(7),
+        Col::new("comm", "COMM").l(24).sep("  ").trunc(),
+        Col::new("utime", "utime").r(8),
+        Col::new("stime", "stime").r(8),
+        Col::new("read_bytes", "read_bytes").r(12),
+        Col::new("write_bytes", "write_bytes").r(12),
+        if wide { cmdline } else { cmdline.hidden() },
+    ];
+    let mut table = Table::new(&cols, out)?;
+    // --follow keeps reading as the gatherer appends (and across its restarts)
+    let src: Box<dyn std::io::Read> = if follow { Box::new(Follow::open(filename)?.skip_header_on_rotate()) } else { Box::new(File::open(filename)?) };
+    let mut rdr = csv_reader(src);
     let mut prev_map: std::collections::HashMap<(u32, String), (u64, u64, u64, u64)> = std::collections::HashMap::new();
 
-    // Wider columns, COMM up to 24 chars for modern processes
-    if wide {
-        println!(
-            "{:<8} {:>7}  {:<24} {:>8} {:>8} {:>12} {:>12}  {}",
-            "Time", "PID", "COMM", "utime", "stime", "read_bytes", "write_bytes", "CMDLINE"
-        );
-    } else {
-        println!(
-            "{:<8} {:>7}  {:<24} {:>8} {:>8} {:>12} {:>12}",
-            "Time", "PID", "COMM", "utime", "stime", "read_bytes", "write_bytes"
-        );
-    }
-
+    table.header();
     for result in rdr.deserialize() {
-        let row: ProcSample = result?;
+        let row: ProcSample = match result {
+            Ok(row) => row,
+            // A row cut off by a gatherer restart must not end --follow
+            Err(e) if follow => {
+                eprintln!("WARN: skipping unreadable row: {}", e);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if window.is_past(row.ts_epoch) { break; }
         if row.pid != row.tid || row.comm.trim().is_empty() { continue; }
         let key = (row.pid, row.comm.clone());
+        if !window.contains(row.ts_epoch) {
+            prev_map.insert(key, (row.utime, row.stime, row.read_bytes, row.write_bytes));
+            continue;
+        }
 
         let (du, ds, dr, dw) = if let Some((pu, ps, pr, pw)) = prev_map.get(&key) {
             (
@@ -81,34 +107,15 @@ pub fn run_playback(filename: &str, wide: bool) -> Result<(), Box<dyn std::error
             (0, 0, 0, 0)
         };
 
-        // Convert epoch to HH:MM:SS
-        let tstr = DateTime::<Utc>::from_timestamp(row.ts_epoch as i64, 0)
-            .map(|dt| dt.with_timezone(&Local).format("%H:%M:%S").to_string())
-            .unwrap_or_else(|| row.ts_epoch.to_string());
-
-        // COMM column: truncate but always 24 chars (adds '…' if needed)
-        let comm = if row.comm.chars().count() > 23 {
-            let mut s: String = row.comm.chars().take(23).collect();
-            s.push('…');
-            s
-        } else {
-            format!("{:<24}", row.comm)
-        };
-
-        if wide {
-            println!(
-             "{:<8} {:>7}  {:<24} {:>8} {:>8} {:>12} {:>12}  {}",
-             tstr, row.pid, comm, du, ds, dr, dw, row.cmdline
-        );
-       } else {
-            println!(
-            "{:<8} {:>7}  {:<24} {:>8} {:>8} {:>12} {:>12}",
-            tstr, row.pid, comm, du, ds, dr, dw
-             );
-         }
+        // Time in the display timezone; COMM truncated to 24 chars (with '…') in text output
+        table.row(Some(ctx), &[
+            Cell::Time(row.ts_epoch), u64::from(row.pid).into(), row.comm.as_str().into(),
+            du.into(), ds.into(), dr.into(), dw.into(), row.cmdline.as_str().into(),
+        ]);
 
         prev_map.insert(key, (row.utime, row.stime, row.read_bytes, row.write_bytes));
     }
+    table.finish("");
     Ok(())
 }
 
@@ -166,17 +173,18 @@ struct TableDef<'a> {
 // MAIN ANALYSIS
 // =======================================================
 
-pub fn run_analysis(filename: &str) -> Result<(), Box<dyn Error>> {
+pub fn run_analysis(filename: &str, tz: DisplayTz) -> Result<(), Box<dyn Error>> {
+    let ctx = timeutil::for_capture(filename, tz)?;
     // Collect all samples for line plotting
         let samples: Vec<ProcSample> = {
-        let mut rdr = csv::Reader::from_path(filename)?;
+        let mut rdr = open_csv(filename)?;
         rdr.deserialize().filter_map(Result::ok).collect()
         };
 
     let output_dir = output_dir_for_datafile(filename);
     fs::create_dir_all(&output_dir)?;
 
-    let mut rdr = csv::Reader::from_path(filename)?;
+    let mut rdr = open_csv(filename)?;
     let mut prev_map: HashMap<(u32, String, String), (u64, u64, u64, u64)> = HashMap::new();
     let mut proc_map: HashMap<(u32, String, String), ProcSummary> = HashMap::new();
     let mut all_samples: Vec<ProcSample> = Vec::new();
@@ -313,8 +321,8 @@ pub fn run_analysis(filename: &str) -> Result<(), Box<dyn Error>> {
     _ => |_| 0,
     };
 
-    plot_line_chart_svg(&line_svg, &samples, &top5, sample_metric, t.title)?;
-    plot_line_chart_png(&line_png, &samples, &top5, sample_metric, t.title)?;
+    plot_line_chart_svg(&line_svg, &ctx, &samples, &top5, sample_metric, t.title)?;
+    plot_line_chart_png(&line_png, &ctx, &samples, &top5, sample_metric, t.title)?;
 
     // --- Push all paths to chart_list
     chart_list.push((t.fname.to_string(), t.title.to_string(),
@@ -448,28 +456,31 @@ use plotters::element::PathElement;
 
 fn plot_line_chart_svg(
     filename: &str,
+    ctx: &TimeCtx,
     samples: &[ProcSample],
     top5: &[&ProcSummary],
     metric: fn(&ProcSample) -> u64,
     title: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let root = SVGBackend::new(filename, (900, 320)).into_drawing_area();
-    draw_lines(root, samples, top5, metric, title)
+    draw_lines(root, ctx, samples, top5, metric, title)
 }
 
 fn plot_line_chart_png(
     filename: &str,
+    ctx: &TimeCtx,
     samples: &[ProcSample],
     top5: &[&ProcSummary],
     metric: fn(&ProcSample) -> u64,
     title: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let root = BitMapBackend::new(filename, (900, 320)).into_drawing_area();
-    draw_lines(root, samples, top5, metric, title)
+    draw_lines(root, ctx, samples, top5, metric, title)
 }
 
 fn draw_lines<DB: DrawingBackend>(
     root: DrawingArea<DB, Shift>,
+    ctx: &TimeCtx,
     samples: &[ProcSample],
     top5: &[&ProcSummary],
     metric: fn(&ProcSample) -> u64,
@@ -490,6 +501,8 @@ where
 
     let min_ts = samples.iter().map(|s| s.ts_epoch as i64).min().unwrap_or(0);
     let max_ts = samples.iter().map(|s| s.ts_epoch as i64).max().unwrap_or(1);
+    // Multi-day captures get the date on the axis
+    let time_fmt = if max_ts - min_ts > 86400 { "%m-%d %H:%M" } else { "%H:%M:%S" };
 
     // Determine if this is a CPU metric (needs HZ conversion)
     let title_lc = title.to_lowercase();
@@ -545,14 +558,9 @@ where
         .configure_mesh()
         .x_labels(10)
         .x_label_style(("sans-serif", 12).into_font())
-        .x_label_formatter(&|ts| {
-            let dt_utc = chrono::DateTime::<chrono::Utc>::from_timestamp(*ts as i64, 0)
-                .unwrap_or_else(|| chrono::DateTime::<chrono::Utc>::from_timestamp(0, 0).unwrap());
-            let dt_local = dt_utc.with_timezone(&chrono::Local);
-            dt_local.format("%H:%M:%S").to_string()
-        })
+        .x_label_formatter(&|ts| ctx.format((*ts).max(0) as u64, time_fmt))
         .y_desc(if is_cpu { "Delta (seconds)" } else { "Delta" })
-        .x_desc("Time")
+        .x_desc(format!("Time ({})", ctx.label()))
         .draw()?;
 
     for (ix, (comm, data)) in series.iter().enumerate() {
@@ -629,6 +637,18 @@ img.chart {{ max-width:95vw; margin:1em 0; border:1px solid #ccc; }}
 // UTILS
 // =======================================================
 
+/// Opens a gather CSV, skipping `#` comment lines (disk guard events).
+fn open_csv(filename: &str) -> Result<csv::Reader<File>, csv::Error> {
+    Ok(csv_reader(File::open(filename)?))
+}
+
+fn csv_reader<R: std::io::Read>(src: R) -> csv::Reader<R> {
+    csv::ReaderBuilder::new()
+        .has_headers(true)
+        .comment(Some(b'#'))
+        .from_reader(src)
+}
+
 fn output_dir_for_datafile(datafile: &str) -> String {
     Path::new(datafile)
         .file_stem()
@@ -26,13 +26,17 @@
 
     # Usage
 
-    Call [`run_gather(interval_secs)`] to start gathering at the given interval (seconds).
+    Call [`run_gather(&GatherOpts)`] to start gathering at the given interval (seconds).
+    `/proc` is read under `GatherOpts::root` (e.g. `/host` in a container, or a fixture tree in tests).
+    Free-space guard events are written as `#EVENT,<ts>,diskguard,<kind>,<detail>` comment lines.
+    The host timezone is written as `#TZ,<ts>,<utc_offset_secs>,<zone_name>` at start and on DST changes.
 
     CSV file output will be named: `procstats_gather-<hostname>-<YYYYMMDD-HHMMSS>.csv`
 */
 
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::thread;
 
@@ -42,6 +46,9 @@ extern crate chrono;
 use chrono::{Datelike, Timelike, Local};
 use std::thread::spawn;
 
+use serverstats_format::diskguard::{DiskGuard, SpaceAction, Verdict};
+use crate::timeutil;
+
 #[derive(serde::Serialize)]
 struct CsvRow {
     ts_epoch: u64,
@@ -60,19 +67,54 @@ struct CsvRow {
     cmdline: String,
 }
 
+/// Gather mode settings.
+pub struct GatherOpts {
+    /// sampling interval in seconds
+    pub interval_secs: u64,
+    /// directory the CSV is written to
+    pub output_dir: PathBuf,
+    /// /proc is read as `<root>/proc`
+    pub root: PathBuf,
+    /// stop after this many samples, None runs until interrupted
+    pub count: Option<u64>,
+    /// free space / inode percentages below which the guard acts
+    pub min_free: f64,
+    pub min_free_inodes: f64,
+    /// what the guard does when the output filesystem runs low
+    pub action: SpaceAction,
+}
+
+impl Default for GatherOpts {
+    fn default() -> Self {
+        GatherOpts {
+            interval_secs: 60,
+            output_dir: PathBuf::from("."),
+            root: PathBuf::from("/"),
+            count: None,
+            min_free: 5.0,
+            min_free_inodes: 5.0,
+            action: SpaceAction::Pause,
+        }
+    }
+}
+
 /// Gather process stats at a specified interval and write to a CSV file.
-/// Loops forever until interrupted.
-///
-/// # Arguments
-/// * `interval_secs` - sampling interval in seconds
-pub fn run_gather(interval_secs: u64) -> std::io::Result<()> {
+/// Loops until interrupted, or for `opts.count` samples. Returns the CSV path.
+pub fn run_gather(opts: &GatherOpts) -> std::io::Result<PathBuf> {
+    let interval_secs = opts.interval_secs;
+    let proc_dir = opts.root.join("proc");
     let hostname = get_hostname();
     let time_str = get_time_string();
-    let output_file = format!("procstats_gather-{}-{}.csv", hostname, time_str);
+    let output_path = opts.output_dir.join(format!("procstats_gather-{}-{}.csv", hostname, time_str));
+    let output_file = output_path.to_string_lossy().to_string();
+    let mut guard = DiskGuard::new(&output_file, "procstats_gather-", ".csv", opts.min_free, opts.min_free_inodes, opts.action);
 
+    let csv_file = File::create(&output_file)?;
+    // Second handle on the same open file (shared offset) for raw #EVENT/#TZ comment lines
+    let mut event_out = csv_file.try_clone()?;
     let mut wtr = csv::WriterBuilder::new()
     .has_headers(false)
-    .from_writer(File::create(&output_file)?);
+    .from_writer(csv_file);
 
     // CSV header (exactly 14 fields)
     wtr.write_record(&[
@@ -87,11 +129,51 @@ pub fn run_gather(interval_secs: u64) -> std::io::Result<()> {
         output_file, interval_secs
     );
 
+    if opts.root != Path::new("/") {
+        println!("Reading /proc under: {}", opts.root.display());
+    }
+
+    let tz_name = timeutil::host_tz_name();
+    let mut last_offset: Option<i32> = None;
+    let mut samples = 0u64;
+
     loop {
+        if opts.count.is_some_and(|c| samples >= c) {
+            return Ok(output_path);
+        }
+        samples += 1;
         let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        // ---- Host timezone, again whenever the UTC offset changes (DST) ----
+        let offset = timeutil::host_utc_offset();
+        if last_offset != Some(offset) {
+            wtr.flush()?;
+            writeln!(event_out, "#TZ,{},{},{}", now, offset, tz_name)?;
+            last_offset = Some(offset);
+        }
+
+        // ---- Disk space guard ----
+        let (verdict, events) = guard.check();
+        if !events.is_empty() {
+            // Best effort: the filesystem may already be full
+            let _ = wtr.flush();
+            for ev in &events {
+                let _ = writeln!(event_out, "#EVENT,{},diskguard,{},{}", now, ev.kind, ev.detail.replace(',', ";"));
+            }
+            let _ = event_out.flush();
+        }
+        match verdict {
+            Verdict::Write => {}
+            Verdict::Skip => {
+                thread::sleep(Duration::from_secs(interval_secs));
+                continue;
+            }
+            Verdict::Stop => return Ok(output_path),
+        }
+
         println!("Gathering new interval at ts_epoch={}", now);
 
-        for entry in fs::read_dir("/proc")? {
+        for entry in fs::read_dir(&proc_dir)? {
             let entry = match entry {
                 Ok(e) => e,
                 Err(_) => continue,
@@ -103,8 +185,9 @@ pub fn run_gather(interval_secs: u64) -> std::io::Result<()> {
             };
 
             // ---- SAFE PROC INFO READ (with timeout) ----
+            let pid_dir = proc_dir.join(pid.to_string());
             let proc_info_opt = timeout_retry(
-                move || gather_proc_info(pid),
+                move || gather_proc_info(&pid_dir),
                 Duration::from_secs(2),
                 2,
             );
@@ -136,7 +219,7 @@ pub fn run_gather(interval_secs: u64) -> std::io::Result<()> {
             })?;
 
             // ---- Threads ----
-            let task_path = format!("/proc/{}/task", pid);
+            let task_path = proc_dir.join(pid.to_string()).join("task");
             if let Ok(task_dir) = fs::read_dir(&task_path) {
                 for task_entry in task_dir {
                     let task_entry = match task_entry {
@@ -152,8 +235,9 @@ pub fn run_gather(interval_secs: u64) -> std::io::Result<()> {
                         _ => continue,
                     };
 
+                    let tid_dir = task_path.join(tid.to_string());
                     let thread_info_opt = timeout_retry(
-                        move || gather_thread_info(pid, tid),
+                        move || gather_thread_info(&tid_dir),
                         Duration::from_secs(2),
                         2,
                     );
@@ -187,6 +271,9 @@ pub fn run_gather(interval_secs: u64) -> std::io::Result<()> {
         }
 
         wtr.flush()?; // ensure all buffered rows are written each interval
+        if opts.count.is_some_and(|c| samples >= c) {
+            return Ok(output_path);
+        }
         println!("Sleeping {} seconds...", interval_secs);
         thread::sleep(Duration::from_secs(interval_secs));
     }
@@ -280,8 +367,8 @@ fn parse_stat_line(stat_line: &str) -> Option<(String, Vec<String>)> {
     Some((comm, rest))
 }
 
-fn gather_proc_info(pid: u32) -> Option<ProcInfo> {
-    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+fn gather_proc_info(pid_dir: &Path) -> Option<ProcInfo> {
+    let stat = fs::read_to_string(pid_dir.join("stat")).ok()?;
     let (comm, fields) = parse_stat_line(&stat)?;
 
     if fields.len() < 22 { return None; }
@@ -300,7 +387,7 @@ fn gather_proc_info(pid: u32) -> Option<ProcInfo> {
 
     let mut read_bytes = 0;
     let mut write_bytes = 0;
-    if let Ok(file) = File::open(format!("/proc/{}/io", pid)) {
+    if let Ok(file) = File::open(pid_dir.join("io")) {
         for line in BufReader::new(file).lines().flatten() {
             if let Some(val) = line.strip_prefix("read_bytes:") {
                 read_bytes = val.trim().parse().unwrap_or(0);
@@ -311,7 +398,7 @@ fn gather_proc_info(pid: u32) -> Option<ProcInfo> {
         }
     }
 
-    let cmdline = fs::read(format!("/proc/{}/cmdline", pid))
+    let cmdline = fs::read(pid_dir.join("cmdline"))
         .ok()
         .and_then(|data| {
             if data.is_empty() { None }
@@ -333,8 +420,8 @@ fn gather_proc_info(pid: u32) -> Option<ProcInfo> {
     })
 }
 
-fn gather_thread_info(pid: u32, tid: u32) -> Option<ThreadInfo> {
-    let stat = fs::read_to_string(format!("/proc/{}/task/{}/stat", pid, tid)).ok()?;
+fn gather_thread_info(tid_dir: &Path) -> Option<ThreadInfo> {
+    let stat = fs::read_to_string(tid_dir.join("stat")).ok()?;
     let (comm, fields) = parse_stat_line(&stat)?;
     if fields.len() < 14 { return None; }
     Some(ThreadInfo {
@@ -353,3 +440,77 @@ fn page_size_kb() -> Option<u64> {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serverstats_format::testdir::TempDir;
+
+    fn fixture_root() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/root")
+    }
+
+    /// Runs run_gather for `count` samples against the fixture /proc, returns the CSV rows.
+    fn gather_fixture(name: &str, count: u64) -> Vec<Vec<String>> {
+        let dir = TempDir::new(&format!("procstats_grab-test-{}", name));
+        let opts = GatherOpts {
+            interval_secs: 0,
+            output_dir: dir.path().to_path_buf(),
+            root: fixture_root(),
+            count: Some(count),
+            min_free: 0.0,
+            min_free_inodes: 0.0,
+            ..GatherOpts::default()
+        };
+        let path = run_gather(&opts).unwrap();
+        assert!(path.starts_with(dir.path()));
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .comment(Some(b'#'))
+            .from_path(&path)
+            .unwrap();
+        rdr.records().map(|r| r.unwrap().iter().map(|f| f.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn gathers_processes_and_threads_from_root() {
+        let rows = gather_fixture("basic", 2);
+        // pid 1 (one task), pid 1234 plus its thread 1240, per sample
+        assert_eq!(rows.len(), 6);
+        let ts: Vec<&str> = rows.iter().map(|r| r[0].as_str()).collect();
+        assert_eq!(ts[0], ts[2]);
+
+        let mut first: Vec<&Vec<String>> = rows.iter().take(3).collect();
+        first.sort_by_key(|r| r[3].parse::<u32>().unwrap());
+        let systemd = first[0];
+        assert_eq!(&systemd[1..9], ["1", "0", "1", "systemd", "S", "1520", "2210", "1"]);
+        assert_eq!(&systemd[11..], ["40960", "81920", "/usr/lib/systemd/systemd --switched-root --system --deserialize 31"]);
+
+        let page_kb = page_size_kb().unwrap();
+        let pg = first[1];
+        assert_eq!(&pg[1..9], ["1234", "1", "1234", "postgres", "R", "52000", "8100", "2"]);
+        assert_eq!(pg[9].parse::<u64>().unwrap(), 51200 * page_kb);
+        assert_eq!(pg[10], (2298478592u64 / 1024).to_string());
+        assert_eq!(pg[13], "postgres: checkpointer");
+
+        // Thread rows carry the process I/O and no memory/cmdline
+        let thread = first[2];
+        assert_eq!(&thread[1..9], ["1234", "1", "1240", "bgwriter", "S", "310", "90", ""]);
+        assert_eq!(&thread[9..], ["", "", "10485760", "52428800", ""]);
+    }
+
+    #[test]
+    fn missing_proc_root_is_an_error() {
+        let dir = TempDir::new("procstats_grab-test-missing");
+        let opts = GatherOpts {
+            interval_secs: 0,
+            output_dir: dir.path().to_path_buf(),
+            root: fixture_root().join("does-not-exist"),
+            count: Some(1),
+            min_free: 0.0,
+            min_free_inodes: 0.0,
+            ..GatherOpts::default()
+        };
+        assert!(run_gather(&opts).is_err());
+    }
+}
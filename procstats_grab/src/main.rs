@@ -36,8 +36,15 @@ const VERSION_NUMBER: &str = "2.1.1";
 
 mod analyze;
 mod gather;
+mod timeutil;
 
 use std::env;
+use std::path::PathBuf;
+
+use serverstats_format::diskguard::SpaceAction;
+use serverstats_format::output::{Format, OutputOpts};
+use gather::GatherOpts;
+use timeutil::{DisplayTz, TimeWindow};
 
 /// Prints usage/help for procstats_grab.
 fn print_usage(prog: &str) {
@@ -46,7 +53,23 @@ fn print_usage(prog: &str) {
     println!("  {} -a <procstats_gather.csv>        # Analyze mode: tables & charts", prog);
     println!("  {} -p <procstats_gather.csv>        # Playback mode: print sample deltas", prog);
     println!("  {} -p <procstats_gather.csv> -wide  # Playback (wide): show full args at end", prog);
+    println!("  {} -p <procstats_gather.csv> [--from <time>] [--to <time>]", prog);
+    println!("                                      # Playback time window: HH:MM[:SS], 2025-03-14T13:15:00[Z|+HH:MM],");
+    println!("                                      # +30m / +1h30m from capture start, or @epoch");
+    println!("  {} -p <procstats_gather.csv> [--format text|csv|json|ndjson] [--columns <list>]", prog);
+    println!("                                      # Playback output format and columns (by key or heading),");
+    println!("                                      # e.g. --format csv --columns time,pid,comm,read_bytes");
+    println!("  {} -p <procstats_gather.csv> --follow", prog);
+    println!("                                      # Keep printing rows as the gatherer appends (tail -F),");
+    println!("                                      # moving on to the next CSV when the gatherer restarts");
+    println!("  -a / -p also take --tz host|local|UTC|+HH:MM|<Area/City> or --utc");
+    println!("                                      # Display timezone (default: capture host timezone)");
     println!("  {} -g <interval_secs>               # Gather mode (default: 60s)", prog);
+    println!("  {} -g <interval_secs> [--min-free PCT] [--min-free-inodes PCT] [--on-low-space stop|pause|prune]", prog);
+    println!("                                      # Free-space guard (default: 5% / 5% / pause)");
+    println!("  {} -g <interval_secs> [-o <dir>] [--root <root>] [--count N]", prog);
+    println!("                                      # Write the CSV to <dir>, read <root>/proc (e.g. /host in a");
+    println!("                                      # container), stop after N samples");
     println!("  {} -h                               # Show this help/usage", prog);
     println!();
     println!("After running the -a analyze option you can cd to the directory 
@@ -57,6 +80,27 @@ fn print_usage(prog: &str) {
     This can be safely ignored, it is how stdout works with Rust.");
 
 }
+/// Parses a 0-100 percentage for the disk guard options.
+fn parse_pct(prog: &str, s: &str) -> f64 {
+    match s.trim_end_matches('%').parse::<f64>() {
+        Ok(v) if (0.0..=100.0).contains(&v) => v,
+        _ => {
+            eprintln!("ERROR: '{}' is not a percentage between 0 and 100", s);
+            print_usage(prog);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parses --tz / --utc, exiting with usage on a bad zone.
+fn parse_tz(prog: &str, s: &str) -> DisplayTz {
+    DisplayTz::parse(s).unwrap_or_else(|e| {
+        eprintln!("ERROR: --tz: {}", e);
+        print_usage(prog);
+        std::process::exit(1);
+    })
+}
+
 /// Entry point for procstats_grab.
 ///
 /// Parses command-line arguments and dispatches to the selected mode:
@@ -73,27 +117,143 @@ fn main() -> std::io::Result<()> {
     match args[1].as_str() {
          "-a" => {
             if args.len() < 3 {
-                eprintln!("Usage: {} -a <procstats_gather.csv>", args[0]);
+                eprintln!("Usage: {} -a <procstats_gather.csv> [--tz <zone>|--utc]", args[0]);
                 std::process::exit(1);
             }
-            analyze::run_analysis(&args[2]).expect("Failed to analyze CSV");
+            let mut tz = DisplayTz::Host;
+            let mut i = 3;
+            while i < args.len() {
+                match args[i].as_str() {
+                    "--tz" if i + 1 < args.len() => {
+                        tz = parse_tz(&args[0], &args[i + 1]);
+                        i += 2;
+                    }
+                    "--utc" => {
+                        tz = parse_tz(&args[0], "UTC");
+                        i += 1;
+                    }
+                    _ => i += 1,
+                }
+            }
+            analyze::run_analysis(&args[2], tz).expect("Failed to analyze CSV");
          }
          "-p" => {
             if args.len() < 3 {
-                eprintln!("Usage: {} -p <procstats_gather.csv> [-wide]", args[0]);
+                eprintln!("Usage: {} -p <procstats_gather.csv> [-wide] [--from <time>] [--to <time>] [--tz <zone>|--utc] [--follow] \
+                           [--format text|csv|json|ndjson] [--columns <list>]", args[0]);
+                std::process::exit(1);
+            }
+            let mut wide = false;
+            let mut from = None;
+            let mut to = None;
+            let mut tz = DisplayTz::Host;
+            let mut out = OutputOpts::default();
+            let mut follow = false;
+            let mut i = 3;
+            while i < args.len() {
+                match args[i].as_str() {
+                    "--format" if i + 1 < args.len() => {
+                        out.format = Format::parse(&args[i + 1]).unwrap_or_else(|| {
+                            eprintln!("ERROR: --format must be one of text, csv, json, ndjson");
+                            print_usage(&args[0]);
+                            std::process::exit(1);
+                        });
+                        i += 2;
+                    }
+                    "--columns" if i + 1 < args.len() => {
+                        out.columns = Some(args[i + 1].clone());
+                        i += 2;
+                    }
+                    "-wide" | "--wide" | "-pwide" => {
+                        wide = true;
+                        i += 1;
+                    }
+                    "--from" if i + 1 < args.len() => {
+                        from = Some(args[i + 1].clone());
+                        i += 2;
+                    }
+                    "--to" if i + 1 < args.len() => {
+                        to = Some(args[i + 1].clone());
+                        i += 2;
+                    }
+                    "--tz" if i + 1 < args.len() => {
+                        tz = parse_tz(&args[0], &args[i + 1]);
+                        i += 2;
+                    }
+                    "--utc" => {
+                        tz = parse_tz(&args[0], "UTC");
+                        i += 1;
+                    }
+                    "--follow" => {
+                        follow = true;
+                        i += 1;
+                    }
+                    _ => i += 1,
+                }
+            }
+            let ctx = timeutil::for_capture(&args[2], tz)?;
+            let window = TimeWindow::resolve(from.as_deref(), to.as_deref(), &ctx).unwrap_or_else(|e| {
+                eprintln!("ERROR: {}", e);
+                print_usage(&args[0]);
+                std::process::exit(1);
+            });
+            if let Some(note) = ctx.host_tz_note() {
+                eprintln!("{}", note);
+            }
+            if follow && out.format == Format::Json {
+                eprintln!("WARN: --follow never closes the JSON array, --format ndjson suits streaming better");
+            }
+            if let Err(e) = analyze::run_playback(&args[2], wide, &ctx, &window, &out, follow) {
+                eprintln!("ERROR: playback: {}", e);
                 std::process::exit(1);
             }
-            let wide = args.len() > 3 && (args[3] == "-wide" || args[3] == "--wide" || args[3] == "-pwide");
-            analyze::run_playback(&args[2], wide).expect("Failed to play back CSV");
          }
          "-g" => {
+            let mut opts = GatherOpts::default();
             // Parse gather interval if given (-g <seconds>)
-            let interval_secs = if args.len() >= 3 {
-                args[2].parse().unwrap_or(60u64)
-            } else {
-                60u64
-            };
-            gather::run_gather(interval_secs)?;
+            if args.len() >= 3 {
+                opts.interval_secs = args[2].parse().unwrap_or(60u64);
+            }
+            // Optional output, root and disk guard settings after the interval
+            let mut i = 3;
+            while i < args.len() {
+                match args[i].as_str() {
+                    "-o" if i + 1 < args.len() => {
+                        opts.output_dir = PathBuf::from(&args[i + 1]);
+                        i += 2;
+                    }
+                    "--root" if i + 1 < args.len() => {
+                        opts.root = PathBuf::from(&args[i + 1]);
+                        i += 2;
+                    }
+                    "--count" if i + 1 < args.len() => {
+                        opts.count = Some(args[i + 1].parse().unwrap_or_else(|_| {
+                            eprintln!("ERROR: --count must be a number of samples");
+                            print_usage(&args[0]);
+                            std::process::exit(1);
+                        }));
+                        i += 2;
+                    }
+                    "--min-free" if i + 1 < args.len() => {
+                        opts.min_free = parse_pct(&args[0], &args[i + 1]);
+                        i += 2;
+                    }
+                    "--min-free-inodes" if i + 1 < args.len() => {
+                        opts.min_free_inodes = parse_pct(&args[0], &args[i + 1]);
+                        i += 2;
+                    }
+                    "--on-low-space" if i + 1 < args.len() => {
+                        opts.action = SpaceAction::parse(&args[i + 1]).unwrap_or_else(|| {
+                            eprintln!("ERROR: --on-low-space must be one of stop, pause, prune");
+                            print_usage(&args[0]);
+                            std::process::exit(1);
+                        });
+                        i += 2;
+                    }
+                    _ => i += 1,
+                }
+            }
+            gather::run_gather(&opts)?;
          }
         _ => {
             print_usage(&args[0]);
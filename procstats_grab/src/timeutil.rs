@@ -0,0 +1,84 @@
+// procstats_grab - Time Handling
+// Copyright (C) 2025 Laurence Oberman
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+/*!
+    # timeutil.rs
+
+    Reads the time context of a gather CSV for the shared timezone and time window
+    handling (`serverstats_format::timeutil`, see there for --from/--to and --tz).
+
+    - `ts_epoch` in the CSV is UTC epoch seconds.
+    - The gatherer writes `#TZ,<ts>,<utc_offset_secs>,<zone_name>` comment lines at start
+      and whenever the host's UTC offset changes, so times are shown in the capture
+      host's wall clock no matter where the CSV is analyzed.
+*/
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+pub use serverstats_format::timeutil::{host_tz_name, host_utc_offset, DisplayTz, TimeCtx, TimeWindow};
+
+/// Scans a gather CSV for `#TZ` comment lines and the first sample timestamp.
+/// The whole file is read (line scan only) so DST changes mid-capture are known up front.
+pub fn for_capture(path: &str, display: DisplayTz) -> std::io::Result<TimeCtx> {
+    let mut ctx = TimeCtx::new(display);
+    let reader = BufReader::new(File::open(path)?);
+    for line in reader.lines().map_while(Result::ok) {
+        if let Some(comment) = line.strip_prefix('#') {
+            note_tz_line(&mut ctx, comment);
+            continue;
+        }
+        if ctx.start_ts.is_none() {
+            // ts_epoch is the first column; the header row does not parse
+            ctx.start_ts = line.split(',').next().and_then(|v| v.parse::<u64>().ok());
+        }
+    }
+    Ok(ctx)
+}
+
+/// Consumes a `TZ,<ts>,<offset>,<name>` comment; other comments are ignored.
+fn note_tz_line(ctx: &mut TimeCtx, line: &str) {
+    let Some(rest) = line.strip_prefix("TZ,") else { return };
+    let fields: Vec<&str> = rest.split(',').collect();
+    let ts = fields.first().and_then(|v| v.parse::<u64>().ok());
+    let offset = fields.get(1).and_then(|v| v.parse::<i32>().ok());
+    if let (Some(ts), Some(offset)) = (ts, offset) {
+        ctx.note_host_tz(ts, offset, fields.get(2).copied().unwrap_or(""));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serverstats_format::testdir::TempDir;
+
+    #[test]
+    fn tz_comments_and_first_sample() {
+        let dir = TempDir::with_files("procstats_grab-test-timeutil", &[("gather.csv",
+            "#TZ,1741958100,-14400,America/New_York\n\
+             ts_epoch,pid,ppid,tid,comm\n\
+             1741958105,1,0,1,systemd\n\
+             #TZ,bad\n\
+             #TZ,1741960000,-18000,\n\
+             1741960005,1,0,1,systemd\n")]);
+        let ctx = for_capture(dir.join("gather.csv").to_str().unwrap(), DisplayTz::Host).unwrap();
+        assert_eq!(ctx.start_ts, Some(1741958105));
+        assert_eq!(ctx.label(), "host UTC-04:00 America/New_York");
+        // The later offset applies from its TZ line on, the zone name is kept
+        assert_eq!(ctx.hms(1741960005), "08:46:45");
+        assert_eq!(ctx.parse_spec("09:30", None), Ok(1741959000));
+    }
+}
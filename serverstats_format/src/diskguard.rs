@@ -0,0 +1,320 @@
+/*!
+ * Disk Space Guard Module
+ * -----------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Watches free space and free inodes on the filesystem that holds the capture
+ * file. A long running gather on a CoreOS node writes into the `core` user's
+ * home on the root filesystem, so once the configured threshold is crossed the
+ * gatherer either stops, pauses until space comes back, or prunes the oldest
+ * captures from the output directory.
+ *
+ * Shared by serverstats_grab (`.dat`) and procstats_grab (`.csv`). Pruning only
+ * removes captures last written before this gather started, so a capture that
+ * another gatherer instance is still writing in the same directory is kept.
+ */
+
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// What to do when free space or free inodes drop below the threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpaceAction {
+    /// Record the event and exit gather mode.
+    Stop,
+    /// Stop writing samples until space is available again.
+    Pause,
+    /// Delete the oldest captures in the output directory, then pause if that is not enough.
+    Prune,
+}
+
+impl SpaceAction {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "stop" => Some(SpaceAction::Stop),
+            "pause" => Some(SpaceAction::Pause),
+            "prune" => Some(SpaceAction::Prune),
+            _ => None,
+        }
+    }
+}
+
+/// Free space snapshot for one filesystem (statvfs).
+#[derive(Debug, Clone, Copy)]
+pub struct FsUsage {
+    pub free_pct: f64,
+    pub free_inodes_pct: f64,
+    pub avail_bytes: u64,
+}
+
+/// Result of a guard check, consumed by the gather loop.
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    /// Enough space, write the sample.
+    Write,
+    /// Below threshold, skip this sample.
+    Skip,
+    /// Below threshold and the action is `stop`.
+    Stop,
+}
+
+/// State change worth logging and recording in the capture.
+#[derive(Debug)]
+pub struct GuardEvent {
+    pub kind: &'static str,
+    pub detail: String,
+}
+
+/// Free-space watchdog for the gatherer output filesystem.
+pub struct DiskGuard {
+    current: PathBuf,
+    prefix: String,
+    suffix: String,
+    min_free_pct: f64,
+    min_free_inodes_pct: f64,
+    action: SpaceAction,
+    paused: bool,
+    /// Captures modified since then may be in use, they are never pruned
+    started: SystemTime,
+}
+
+impl DiskGuard {
+    /// `current` is the capture being written; `prefix`/`suffix` select which
+    /// sibling files are ours and may be pruned.
+    pub fn new(
+        current: &str,
+        prefix: &str,
+        suffix: &str,
+        min_free_pct: f64,
+        min_free_inodes_pct: f64,
+        action: SpaceAction,
+    ) -> Self {
+        DiskGuard {
+            current: PathBuf::from(current),
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            min_free_pct,
+            min_free_inodes_pct,
+            action,
+            paused: false,
+            started: SystemTime::now(),
+        }
+    }
+
+    fn dir(&self) -> PathBuf {
+        match self.current.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    fn is_low(&self, u: &FsUsage) -> bool {
+        u.free_pct < self.min_free_pct || u.free_inodes_pct < self.min_free_inodes_pct
+    }
+
+    fn describe(&self, u: &FsUsage) -> String {
+        format!(
+            "free={:.1}% inodes_free={:.1}% avail_mb={} min_free={:.1}% min_inodes={:.1}%",
+            u.free_pct,
+            u.free_inodes_pct,
+            u.avail_bytes / (1024 * 1024),
+            self.min_free_pct,
+            self.min_free_inodes_pct
+        )
+    }
+
+    /// Check the output filesystem and decide whether this sample may be written.
+    /// Any events returned should be logged and written to the capture.
+    pub fn check(&mut self) -> (Verdict, Vec<GuardEvent>) {
+        self.check_with(fs_usage)
+    }
+
+    /// `check` with the statvfs call passed in, so the decisions can be tested.
+    fn check_with(&mut self, mut usage_of: impl FnMut(&Path) -> io::Result<FsUsage>) -> (Verdict, Vec<GuardEvent>) {
+        let dir = self.dir();
+        let mut events = Vec::new();
+        let mut usage = match usage_of(&dir) {
+            Ok(u) => u,
+            Err(e) => {
+                // Cannot tell, keep gathering rather than lose data.
+                eprintln!("WARN: diskguard: statvfs({}) failed: {}", dir.display(), e);
+                return (Verdict::Write, events);
+            }
+        };
+
+        if self.is_low(&usage) && self.action == SpaceAction::Prune {
+            while self.is_low(&usage) {
+                let Some(victim) = self.oldest_capture(&dir) else { break };
+                match fs::remove_file(&victim) {
+                    Ok(()) => {
+                        let detail = format!("removed {} {}", victim.display(), self.describe(&usage));
+                        eprintln!("WARN: diskguard: low space, {}", detail);
+                        events.push(GuardEvent { kind: "pruned", detail });
+                    }
+                    Err(e) => {
+                        eprintln!("WARN: diskguard: cannot remove {}: {}", victim.display(), e);
+                        break;
+                    }
+                }
+                usage = match usage_of(&dir) {
+                    Ok(u) => u,
+                    Err(_) => break,
+                };
+            }
+        }
+
+        if !self.is_low(&usage) {
+            if self.paused {
+                self.paused = false;
+                let detail = self.describe(&usage);
+                eprintln!("diskguard: space recovered, resuming capture ({})", detail);
+                events.push(GuardEvent { kind: "resumed", detail });
+            }
+            return (Verdict::Write, events);
+        }
+
+        let detail = self.describe(&usage);
+        if self.action == SpaceAction::Stop {
+            eprintln!("ERROR: diskguard: low space on {}, stopping capture ({})", dir.display(), detail);
+            events.push(GuardEvent { kind: "stopped", detail });
+            return (Verdict::Stop, events);
+        }
+        if !self.paused {
+            self.paused = true;
+            eprintln!("WARN: diskguard: low space on {}, pausing capture ({})", dir.display(), detail);
+            events.push(GuardEvent { kind: "paused", detail });
+        }
+        (Verdict::Skip, events)
+    }
+
+    /// Oldest (by mtime) capture file of ours in `dir` not written since this gather started,
+    /// never the current one.
+    fn oldest_capture(&self, dir: &Path) -> Option<PathBuf> {
+        let current_name = self.current.file_name()?;
+        let mut candidates: Vec<(std::time::SystemTime, PathBuf)> = fs::read_dir(dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .filter(|e| {
+                let name = e.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with(&self.prefix) && name.ends_with(&self.suffix) && e.file_name() != current_name
+            })
+            .filter_map(|e| {
+                let meta = e.metadata().ok()?;
+                let modified = meta.modified().ok()?;
+                if !meta.is_file() || modified >= self.started { return None; }
+                Some((modified, e.path()))
+            })
+            .collect();
+        candidates.sort();
+        candidates.into_iter().next().map(|(_, p)| p)
+    }
+}
+
+/// statvfs() wrapper returning free space and inode percentages for `path`.
+// The statvfs field widths differ between 32 and 64 bit targets.
+#[allow(clippy::unnecessary_cast)]
+pub fn fs_usage(path: &Path) -> io::Result<FsUsage> {
+    let cpath = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::statvfs(cpath.as_ptr(), &mut st) };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let pct = |avail: u64, total: u64| if total > 0 { avail as f64 / total as f64 * 100.0 } else { 100.0 };
+    let frsize = if st.f_frsize > 0 { st.f_frsize as u64 } else { st.f_bsize as u64 };
+    Ok(FsUsage {
+        free_pct: pct(st.f_bavail as u64, st.f_blocks as u64),
+        // Filesystems without fixed inode tables (btrfs, xfs with imaxpct=0) report 0 total.
+        free_inodes_pct: pct(st.f_favail as u64, st.f_files as u64),
+        avail_bytes: st.f_bavail as u64 * frsize,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TempDir;
+    use std::fs::File;
+    use std::time::Duration;
+
+    const LOW: FsUsage = FsUsage { free_pct: 2.0, free_inodes_pct: 50.0, avail_bytes: 1 << 30 };
+    const LOW_INODES: FsUsage = FsUsage { free_pct: 50.0, free_inodes_pct: 1.0, avail_bytes: 1 << 30 };
+    const OK: FsUsage = FsUsage { free_pct: 40.0, free_inodes_pct: 50.0, avail_bytes: 1 << 34 };
+
+    fn guard(action: SpaceAction) -> DiskGuard {
+        DiskGuard::new("/var/tmp/serverstats_grab-h1-2025-03-14_13-15-00.dat", "serverstats_grab-", ".dat", 5.0, 5.0, action)
+    }
+
+    fn kinds(events: &[GuardEvent]) -> Vec<&str> {
+        events.iter().map(|e| e.kind).collect()
+    }
+
+    #[test]
+    fn enough_space_writes() {
+        let mut g = guard(SpaceAction::Stop);
+        let (v, ev) = g.check_with(|_| Ok(OK));
+        assert_eq!((v, ev.len()), (Verdict::Write, 0));
+        // statvfs failing must not lose data
+        let (v, ev) = g.check_with(|_| Err(io::Error::from(io::ErrorKind::PermissionDenied)));
+        assert_eq!((v, ev.len()), (Verdict::Write, 0));
+    }
+
+    #[test]
+    fn stop_on_low_space_or_inodes() {
+        for usage in [LOW, LOW_INODES] {
+            let (v, ev) = guard(SpaceAction::Stop).check_with(|_| Ok(usage));
+            assert_eq!((v, kinds(&ev)), (Verdict::Stop, vec!["stopped"]));
+        }
+    }
+
+    #[test]
+    fn pause_until_space_recovers() {
+        let mut g = guard(SpaceAction::Pause);
+        let (v, ev) = g.check_with(|_| Ok(LOW));
+        assert_eq!((v, kinds(&ev)), (Verdict::Skip, vec!["paused"]));
+        // Logged once per pause, not every interval
+        let (v, ev) = g.check_with(|_| Ok(LOW_INODES));
+        assert_eq!((v, ev.len()), (Verdict::Skip, 0));
+        let (v, ev) = g.check_with(|_| Ok(OK));
+        assert_eq!((v, kinds(&ev)), (Verdict::Write, vec!["resumed"]));
+        assert!(ev[0].detail.contains("free=40.0%"));
+    }
+
+    #[test]
+    fn prune_removes_only_our_older_captures() {
+        let dir = TempDir::new("diskguard-test");
+        let now = SystemTime::now();
+        let make = |name: &str, age_secs: u64| {
+            let p = dir.join(name);
+            File::create(&p).unwrap().set_modified(now - Duration::from_secs(age_secs)).unwrap();
+            p
+        };
+        let oldest = make("serverstats_grab-h1-2025-03-14_09-00-00.dat", 7200);
+        let older = make("serverstats_grab-h1-2025-03-14_11-00-00.dat", 3600);
+        let foreign = make("notes-2025-03-14.dat", 9000);
+        let current = make("serverstats_grab-h1-2025-03-14_13-15-00.dat", 7200);
+        // Another gatherer instance writing into the same directory
+        let active = make("serverstats_grab-h2-2025-03-14_08-00-00.dat", 0);
+
+        let mut g = DiskGuard::new(current.to_str().unwrap(), "serverstats_grab-", ".dat", 5.0, 5.0, SpaceAction::Prune);
+        g.started = now - Duration::from_secs(60);
+
+        // Space comes back once the oldest capture is gone
+        let (v, ev) = g.check_with(|_| Ok(if oldest.exists() { LOW } else { OK }));
+        assert_eq!((v, kinds(&ev)), (Verdict::Write, vec!["pruned"]));
+        assert!(ev[0].detail.contains("09-00-00.dat"));
+        assert!(!oldest.exists() && older.exists());
+
+        // Still low after everything prunable is gone: pause, never touch the rest
+        let (v, ev) = g.check_with(|_| Ok(LOW));
+        assert_eq!((v, kinds(&ev)), (Verdict::Skip, vec!["pruned", "paused"]));
+        assert!(!older.exists());
+        assert!(foreign.exists() && current.exists() && active.exists());
+    }
+}
@@ -0,0 +1,319 @@
+/*!
+ * Row Filter Module
+ * -----------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * `--where` predicates for playback tables, to find the few bad seconds in a
+ * day of output without grep/awk:
+ *
+ *   --where 'await_wr>20 || qlen>32'     (-pD)
+ *   --where 'iowait>10'                  (-pC)
+ *   --where 'util_pct>=80 && rx_err>0'   (-pN)
+ *
+ * A predicate compares a column (by key or heading, as for --columns) with a
+ * number using > >= < <= == !=, combined with && and || (&& binds tighter) and
+ * parentheses. A key may be given without its unit suffix when that is
+ * unambiguous (await_wr for await_wr_ms). Columns shown as "-" never match.
+ *
+ * `--context N` also prints the N rows before and after each match. Context is
+ * per device/interface in -pD/-pN, so it shows what that device did around the
+ * spike; "--" marks a gap in the text table, like grep.
+ */
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::output::{Cell, Col};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Cmp { col: usize, op: Op, value: f64 },
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Name(String),
+    Num(f64),
+    Op(Op),
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Tok>, String> {
+    let mut toks = Vec::new();
+    let mut rest = src.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let (tok, len) = match c {
+            '(' => (Tok::Open, 1),
+            ')' => (Tok::Close, 1),
+            '&' if rest.starts_with("&&") => (Tok::And, 2),
+            '|' if rest.starts_with("||") => (Tok::Or, 2),
+            '>' | '<' | '=' | '!' => {
+                let two = rest.get(..2).unwrap_or("");
+                match (c, two) {
+                    (_, ">=") => (Tok::Op(Op::Ge), 2),
+                    (_, "<=") => (Tok::Op(Op::Le), 2),
+                    (_, "==") => (Tok::Op(Op::Eq), 2),
+                    (_, "!=") => (Tok::Op(Op::Ne), 2),
+                    ('>', _) => (Tok::Op(Op::Gt), 1),
+                    ('<', _) => (Tok::Op(Op::Lt), 1),
+                    ('=', _) => (Tok::Op(Op::Eq), 1),
+                    _ => return Err(format!("unexpected '{}'", c)),
+                }
+            }
+            c if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '+'))
+                    .unwrap_or(rest.len());
+                let num = rest[..len].parse::<f64>().map_err(|_| format!("bad number '{}'", &rest[..len]))?;
+                (Tok::Num(num), len)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' || c == '%' => {
+                let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '%'))
+                    .unwrap_or(rest.len());
+                (Tok::Name(rest[..len].to_string()), len)
+            }
+            _ => return Err(format!("unexpected '{}'", c)),
+        };
+        toks.push(tok);
+        rest = rest[len..].trim_start();
+    }
+    Ok(toks)
+}
+
+/// Column for a predicate name: key or heading, or a key with a unit suffix (`await_wr` for `await_wr_ms`).
+fn column(name: &str, cols: &[Col]) -> Result<usize, String> {
+    if let Some(i) = cols.iter().position(|c| c.key.eq_ignore_ascii_case(name) || c.title.eq_ignore_ascii_case(name)) {
+        return Ok(i);
+    }
+    let prefix = format!("{}_", name.to_ascii_lowercase());
+    let mut found = cols.iter().enumerate().filter(|(_, c)| c.key.starts_with(&prefix));
+    match (found.next(), found.next()) {
+        (Some((i, _)), None) => Ok(i),
+        _ => {
+            let keys: Vec<&str> = cols.iter().map(|c| c.key).collect();
+            Err(format!("unknown column '{}', available: {}", name, keys.join(",")))
+        }
+    }
+}
+
+struct Parser<'a> {
+    toks: Vec<Tok>,
+    pos: usize,
+    cols: &'a [Col],
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<Tok> {
+        let t = self.toks.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.and()?;
+        while self.toks.get(self.pos) == Some(&Tok::Or) {
+            self.pos += 1;
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.term()?;
+        while self.toks.get(self.pos) == Some(&Tok::And) {
+            self.pos += 1;
+            lhs = Expr::And(Box::new(lhs), Box::new(self.term()?));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Tok::Open) => {
+                let e = self.or()?;
+                match self.next() {
+                    Some(Tok::Close) => Ok(e),
+                    _ => Err("missing ')'".to_string()),
+                }
+            }
+            Some(Tok::Name(name)) => {
+                let col = column(&name, self.cols)?;
+                let Some(Tok::Op(op)) = self.next() else {
+                    return Err(format!("expected a comparison after '{}'", name));
+                };
+                let Some(Tok::Num(value)) = self.next() else {
+                    return Err(format!("expected a number after '{}'", name));
+                };
+                Ok(Expr::Cmp { col, op, value })
+            }
+            _ => Err("expected a column name or '('".to_string()),
+        }
+    }
+}
+
+impl Expr {
+    /// Evaluates every comparison (no short cut) so all exceeding columns are reported in `hits`.
+    fn eval(&self, cells: &[Cell], hits: &mut Vec<usize>) -> bool {
+        match self {
+            Expr::Cmp { col, op, value } => {
+                let v = match &cells[*col] {
+                    Cell::Int(v) | Cell::Time(v) => *v as f64,
+                    Cell::Float(v) => *v,
+                    Cell::Str(_) | Cell::Missing => return false,
+                };
+                let ok = match op {
+                    Op::Gt => v > *value,
+                    Op::Ge => v >= *value,
+                    Op::Lt => v < *value,
+                    Op::Le => v <= *value,
+                    Op::Eq => v == *value,
+                    Op::Ne => v != *value,
+                };
+                if ok && !hits.contains(col) {
+                    hits.push(*col);
+                }
+                ok
+            }
+            Expr::And(a, b) => {
+                let a = a.eval(cells, hits);
+                b.eval(cells, hits) && a
+            }
+            Expr::Or(a, b) => {
+                let a = a.eval(cells, hits);
+                b.eval(cells, hits) || a
+            }
+        }
+    }
+}
+
+/// A row to print: its cells, the columns to highlight, and whether rows were skipped before it.
+pub struct Shown {
+    pub cells: Vec<Cell>,
+    pub hits: Vec<usize>,
+    pub gap: bool,
+}
+
+/// Per device/interface state for --context.
+#[derive(Default)]
+struct Track {
+    seq: u64,
+    before: VecDeque<(u64, Vec<Cell>)>,
+    after: usize,
+    last_shown: Option<u64>,
+}
+
+/// `--where`/`--context` for one table.
+pub struct RowFilter {
+    expr: Expr,
+    context: usize,
+    /// First column names the device/interface (not the time)
+    per_entity: bool,
+    tracks: HashMap<String, Track>,
+}
+
+impl RowFilter {
+    pub fn new(src: &str, context: usize, cols: &[Col]) -> Result<Self, String> {
+        let mut p = Parser { toks: tokenize(src)?, pos: 0, cols };
+        let expr = p.or()?;
+        if p.pos < p.toks.len() {
+            return Err("unexpected text after the expression".to_string());
+        }
+        Ok(RowFilter { expr, context, per_entity: cols[0].key != "time", tracks: HashMap::new() })
+    }
+
+    /// Takes one row in capture order and returns the rows to print now (context rows first).
+    pub fn offer(&mut self, cells: &[Cell]) -> Vec<Shown> {
+        let entity = match (&cells[0], self.per_entity) {
+            (Cell::Str(s), true) => s.clone(),
+            _ => String::new(),
+        };
+        let t = self.tracks.entry(entity).or_default();
+        let seq = t.seq;
+        t.seq += 1;
+        let mut hits = Vec::new();
+        let mut out = Vec::new();
+        let mut show = |t: &mut Track, seq: u64, cells: Vec<Cell>, hits: Vec<usize>| {
+            let gap = t.last_shown.is_some_and(|l| l + 1 != seq);
+            t.last_shown = Some(seq);
+            out.push(Shown { cells, hits, gap });
+        };
+        if self.expr.eval(cells, &mut hits) {
+            for (s, c) in std::mem::take(&mut t.before) {
+                show(t, s, c, Vec::new());
+            }
+            show(t, seq, cells.to_vec(), hits);
+            t.after = self.context;
+        } else if t.after > 0 {
+            t.after -= 1;
+            show(t, seq, cells.to_vec(), Vec::new());
+        } else if self.context > 0 {
+            if t.before.len() == self.context {
+                t.before.pop_front();
+            }
+            t.before.push_back((seq, cells.to_vec()));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLS: &[Col] = &[
+        Col::new("device", "Device"),
+        Col::new("qlen", "Qlen"),
+        Col::new("await_rd_ms", "await_rd(ms)"),
+        Col::new("await_wr_ms", "await_wr(ms)"),
+    ];
+
+    fn row(dev: &str, qlen: f64, wr: Option<f64>) -> Vec<Cell> {
+        vec![dev.into(), qlen.into(), 1.0.into(), wr.into()]
+    }
+
+    #[test]
+    fn expressions() {
+        let f = RowFilter::new("await_wr > 20 || qlen>=32 && await_rd<0.5", 0, COLS).unwrap();
+        let mut hits = Vec::new();
+        assert!(f.expr.eval(&row("sda", 1.0, Some(25.0)), &mut hits));
+        assert_eq!(hits, [3]);
+        // && binds tighter: qlen>=32 alone is not enough
+        assert!(!f.expr.eval(&row("sda", 40.0, Some(1.0)), &mut Vec::new()));
+        // "-" never matches
+        assert!(!f.expr.eval(&row("sda", 1.0, None), &mut Vec::new()));
+        let f = RowFilter::new("(qlen>32 || await_wr>20) && Qlen!=0", 0, COLS).unwrap();
+        assert!(f.expr.eval(&row("sda", 40.0, None), &mut Vec::new()));
+
+        for bad in ["await>1", "qlen 5", "qlen>", "(qlen>1", "qlen>1 x", "bogus<2", "qlen>1 & qlen<2"] {
+            assert!(RowFilter::new(bad, 0, COLS).is_err(), "{}", bad);
+        }
+        assert!(RowFilter::new("await>1", 0, COLS).err().unwrap().contains("available: device,qlen"));
+    }
+
+    #[test]
+    fn context_is_per_device() {
+        let mut f = RowFilter::new("qlen>10", 1, COLS).unwrap();
+        let mut shown = Vec::new();
+        for (i, q) in [1.0, 2.0, 50.0, 3.0, 4.0, 5.0, 60.0].into_iter().enumerate() {
+            // sdb interleaved, never matching
+            f.offer(&row("sdb", 0.0, None));
+            for s in f.offer(&row("sda", q, None)) {
+                shown.push((i, s.gap, s.hits));
+            }
+        }
+        assert_eq!(shown, [(2, false, vec![]), (2, false, vec![1]), (3, false, vec![]), (6, true, vec![]), (6, false, vec![1])]);
+    }
+}
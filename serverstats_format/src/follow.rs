@@ -0,0 +1,246 @@
+/*!
+ * Follow (tail -F) Module
+ * -----------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * `--follow` reads a capture to the end, then keeps reading as the gatherer
+ * appends, like `tail -F`:
+ *
+ *   - reads block at end of file and poll for more data, so a partly written
+ *     last line is only returned once its newline has been written
+ *   - a truncated or replaced file is read again from the start
+ *   - when the gatherer restarts (serverstats_grab_restart.timer) and a newer
+ *     capture of the same host appears in the directory, reading moves on to it
+ *   - `skip_header_on_rotate()` drops the first line of every file switched to,
+ *     so procstats CSV rows continue as one stream under a single header
+ *
+ * `records()` also reports "caught up" once each time the end of the data is
+ * reached and stays quiet for a poll, as a `WouldBlock` error between whole
+ * lines, so playback modes that group records by interval can print the last
+ * interval without waiting for the next sample. `.flatten()` simply skips it.
+ */
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::Reader;
+
+const POLL: Duration = Duration::from_millis(500);
+
+/// Capture name timestamps: serverstats `2025-03-14_13-15-00`, procstats `20250314-131500`.
+const STAMPS: [&str; 2] = ["dddd-dd-dd_dd-dd-dd", "dddddddd-dddddd"];
+
+/// Splits `<prefix><timestamp>.<ext>` so later captures of the same host can be found.
+fn stamp_split(name: &str) -> Option<(&str, &str, &str)> {
+    let (stem, ext) = name.rsplit_once('.')?;
+    STAMPS.iter().find_map(|tpl| {
+        let at = stem.len().checked_sub(tpl.len())?;
+        let stamp = stem.get(at..)?;
+        let ok = stamp.bytes().zip(tpl.bytes())
+            .all(|(c, t)| if t == b'd' { c.is_ascii_digit() } else { c == t });
+        ok.then(|| (&stem[..at], stamp, ext))
+    })
+}
+
+/// The next capture after `path` in its directory: same prefix and extension, later timestamp.
+fn next_capture(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let (prefix, stamp, ext) = stamp_split(name)?;
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty());
+    fs::read_dir(dir.unwrap_or(Path::new("."))).ok()?
+        .flatten()
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|n| stamp_split(n).is_some_and(|(p, s, e)| p == prefix && e == ext && s.len() == stamp.len() && s > stamp))
+        .min()
+        .map(|n| dir.map_or_else(|| PathBuf::from(&n), |d| d.join(&n)))
+}
+
+/// A file read like `tail -F`.
+pub struct Follow {
+    path: PathBuf,
+    file: File,
+    pos: u64,
+    /// Last byte returned was a newline (or nothing returned yet)
+    at_line_start: bool,
+    notify_idle: bool,
+    skip_header: bool,
+    /// Dropping the header line of a file switched to
+    skipping: bool,
+    /// End of data seen once since the last read returned data
+    quiet: bool,
+    idle_sent: bool,
+}
+
+impl Follow {
+    pub fn open(path: &str) -> io::Result<Self> {
+        Ok(Follow {
+            path: PathBuf::from(path),
+            file: File::open(path)?,
+            pos: 0,
+            at_line_start: true,
+            notify_idle: false,
+            skip_header: false,
+            skipping: false,
+            quiet: false,
+            idle_sent: false,
+        })
+    }
+
+    /// Return a `WouldBlock` error once each time the reader catches up (between lines).
+    pub fn notify_idle(mut self) -> Self {
+        self.notify_idle = true;
+        self
+    }
+
+    /// Drop the first line (a CSV header) of each file switched to.
+    pub fn skip_header_on_rotate(mut self) -> Self {
+        self.skip_header = true;
+        self
+    }
+
+    /// The file to continue with at end of data, if it was truncated, replaced or rotated.
+    fn moved(&self) -> Option<PathBuf> {
+        let ours = self.file.metadata().ok()?;
+        match fs::metadata(&self.path) {
+            Ok(m) if m.ino() != ours.ino() || m.dev() != ours.dev() => return Some(self.path.clone()),
+            Ok(m) if m.len() < self.pos => return Some(self.path.clone()),
+            _ => {}
+        }
+        next_capture(&self.path)
+    }
+
+    fn switch(&mut self, path: PathBuf) -> io::Result<()> {
+        self.file = File::open(&path)?;
+        if path != self.path {
+            eprintln!("NOTE: following {}", path.display());
+        }
+        self.path = path;
+        self.pos = 0;
+        self.skipping = self.skip_header;
+        Ok(())
+    }
+}
+
+impl Read for Follow {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() { return Ok(0); }
+        loop {
+            let mut n = self.file.read(buf)?;
+            self.pos += n as u64;
+            if self.skipping && n > 0 {
+                let Some(nl) = buf[..n].iter().position(|&b| b == b'\n') else { continue };
+                self.skipping = false;
+                buf.copy_within(nl + 1..n, 0);
+                n -= nl + 1;
+                if n == 0 { continue; }
+            }
+            if n > 0 {
+                self.at_line_start = buf[n - 1] == b'\n';
+                self.quiet = false;
+                self.idle_sent = false;
+                return Ok(n);
+            }
+            if let Some(next) = self.moved() {
+                self.switch(next)?;
+                // Never join a cut-off last line onto the first line of the next file
+                if !self.at_line_start {
+                    buf[0] = b'\n';
+                    self.at_line_start = true;
+                    return Ok(1);
+                }
+                continue;
+            }
+            // Caught up only after a quiet poll, so a sample being written is not split
+            if self.notify_idle && self.at_line_start && self.quiet && !self.idle_sent {
+                self.idle_sent = true;
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, "caught up with capture"));
+            }
+            self.quiet = true;
+            sleep(POLL);
+        }
+    }
+}
+
+/// Capture records; with `follow` they never end and "caught up" is reported (see `caught_up`).
+pub fn records(path: &str, follow: bool) -> io::Result<Reader<Box<dyn BufRead>>> {
+    if follow {
+        Ok(Reader::new(Box::new(BufReader::new(Follow::open(path)?.notify_idle()))))
+    } else {
+        Ok(Reader::new(Box::new(BufReader::new(File::open(path)?))))
+    }
+}
+
+/// True for the "caught up" marker returned by `records()` in follow mode.
+pub fn caught_up(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::WouldBlock
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TempDir;
+    use std::io::Write;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn capture_names() {
+        assert_eq!(stamp_split("serverstats_grab-db01-2025-03-14_13-15-00.dat"),
+                   Some(("serverstats_grab-db01-", "2025-03-14_13-15-00", "dat")));
+        assert_eq!(stamp_split("procstats_gather-db01-20250314-131500.csv"),
+                   Some(("procstats_gather-db01-", "20250314-131500", "csv")));
+        assert_eq!(stamp_split("capture.dat"), None);
+    }
+
+    #[test]
+    fn follows_partial_lines_and_rotation() {
+        let dir = TempDir::new("serverstats_grab-follow");
+        let first = dir.join("serverstats_grab-h1-2025-03-14_13-15-00.dat");
+        let second = dir.join("serverstats_grab-h1-2025-03-14_14-15-00.dat");
+        dir.write("serverstats_grab-h2-2025-03-14_15-00-00.dat", "CPU,9,other host\n");
+        let mut f = File::create(&first).unwrap();
+        write!(f, "CPU,1,a\nCPU,2,b").unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let path = first.to_str().unwrap().to_string();
+        thread::spawn(move || {
+            for line in BufReader::new(Follow::open(&path).unwrap()).lines() {
+                if tx.send(line.unwrap()).is_err() { break; }
+            }
+        });
+        let next = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(next(), "CPU,1,a");
+        // The partial line is held back until it is complete
+        assert!(rx.recv_timeout(Duration::from_millis(700)).is_err());
+        write!(f, "0\nCPU,3,c").unwrap();
+        assert_eq!(next(), "CPU,2,b0");
+        // Gatherer restart: the cut-off line ends, then the newer capture of the same host follows
+        fs::write(&second, "#TYPE,ts_epoch,<fields...>\nCPU,4,d\n").unwrap();
+        assert_eq!(next(), "CPU,3,c");
+        assert_eq!(next(), "#TYPE,ts_epoch,<fields...>");
+        assert_eq!(next(), "CPU,4,d");
+    }
+    #[test]
+    fn rotation_drops_the_new_header() {
+        let dir = TempDir::new("procstats_grab-follow");
+        let first = dir.write("procstats_gather-h1-20250314-131500.csv", "ts_epoch,pid\n1,10\n2,1");
+
+        let (tx, rx) = mpsc::channel();
+        let path = first.to_str().unwrap().to_string();
+        thread::spawn(move || {
+            for line in BufReader::new(Follow::open(&path).unwrap().skip_header_on_rotate()).lines() {
+                if tx.send(line.unwrap()).is_err() { break; }
+            }
+        });
+        let next = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(next(), "ts_epoch,pid");
+        assert_eq!(next(), "1,10");
+        dir.write("procstats_gather-h1-20250314-141500.csv", "ts_epoch,pid\n3,30\n");
+        assert_eq!(next(), "2,1");
+        assert_eq!(next(), "3,30");
+    }
+}
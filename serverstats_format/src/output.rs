@@ -0,0 +1,456 @@
+/*!
+ * Playback Output Module
+ * ----------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Playback modes describe their columns once and hand each row to a `Table`,
+ * which prints it as the fixed-width text table (default), CSV, a JSON array
+ * or NDJSON (one object per line). `--columns` selects and orders columns by
+ * key or by text heading, in any format.
+ *
+ * Machine formats use the column keys as field names, full ISO 8601 times in
+ * the display timezone, and empty/null where the text table shows "-".
+ *
+ * `--where`/`--context` (see filter.rs) pick the rows to print, in any format;
+ * the text table shows the columns that matched in bold red with `--color`.
+ *
+ * Shared by the serverstats_grab and procstats_grab playback modes.
+ */
+
+use std::io::{self, Write};
+
+use crate::filter::{RowFilter, Shown};
+use crate::timeutil::TimeCtx;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Csv,
+    Json,
+    Ndjson,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Some(Format::Text),
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            "ndjson" | "jsonl" => Some(Format::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// One output column: machine key, text heading and text layout.
+#[derive(Debug, Clone, Copy)]
+pub struct Col {
+    pub key: &'static str,
+    pub title: &'static str,
+    pub width: usize,
+    pub align: Align,
+    /// Decimal places for float cells
+    pub prec: usize,
+    /// Shown in the default text table (machine formats always get every column)
+    pub text: bool,
+    /// Separator printed before the column in text output
+    pub sep: &'static str,
+    /// Truncate to the column width with '…' in text output
+    pub trunc: bool,
+}
+
+impl Col {
+    pub const fn new(key: &'static str, title: &'static str) -> Self {
+        Col { key, title, width: 0, align: Align::Left, prec: 0, text: true, sep: " ", trunc: false }
+    }
+    pub const fn l(mut self, width: usize) -> Self {
+        self.width = width;
+        self.align = Align::Left;
+        self
+    }
+    pub const fn r(mut self, width: usize) -> Self {
+        self.width = width;
+        self.align = Align::Right;
+        self
+    }
+    pub const fn prec(mut self, prec: usize) -> Self {
+        self.prec = prec;
+        self
+    }
+    pub const fn hidden(mut self) -> Self {
+        self.text = false;
+        self
+    }
+    pub const fn sep(mut self, sep: &'static str) -> Self {
+        self.sep = sep;
+        self
+    }
+    pub const fn trunc(mut self) -> Self {
+        self.trunc = true;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Str(String),
+    Int(u64),
+    Float(f64),
+    /// Epoch seconds: HH:MM:SS in text, ISO 8601 in machine formats
+    Time(u64),
+    /// Not available: "-" in text, empty in CSV, null in JSON
+    Missing,
+}
+
+impl From<&str> for Cell {
+    fn from(s: &str) -> Self { Cell::Str(s.to_string()) }
+}
+impl From<String> for Cell {
+    fn from(s: String) -> Self { Cell::Str(s) }
+}
+impl From<u64> for Cell {
+    fn from(v: u64) -> Self { Cell::Int(v) }
+}
+impl From<f64> for Cell {
+    fn from(v: f64) -> Self { Cell::Float(v) }
+}
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(v: Option<T>) -> Self { v.map(Into::into).unwrap_or(Cell::Missing) }
+}
+
+/// `--format`, `--columns`, `--where`/`--context` and `--color` as given on the command line.
+#[derive(Debug, Clone)]
+pub struct OutputOpts {
+    pub format: Format,
+    pub columns: Option<String>,
+    pub filter: Option<String>,
+    pub context: usize,
+    /// Highlight matched columns (resolved from --color auto|always|never)
+    pub color: bool,
+}
+
+impl Default for OutputOpts {
+    fn default() -> Self {
+        OutputOpts { format: Format::Text, columns: None, filter: None, context: 0, color: false }
+    }
+}
+
+impl OutputOpts {
+    /// True for plain text without `--columns` or `--where`, where modes may keep their own layout.
+    pub fn is_default_text(&self) -> bool {
+        self.format == Format::Text && self.columns.is_none() && self.filter.is_none()
+    }
+}
+
+pub struct Table<'a, W: Write = io::StdoutLock<'static>> {
+    cols: &'a [Col],
+    sel: Vec<usize>,
+    format: Format,
+    header_done: bool,
+    rows: usize,
+    filter: Option<RowFilter>,
+    color: bool,
+    /// The last row offered was printed (notes under hidden rows are dropped)
+    shown: bool,
+    out: W,
+}
+
+impl<'a> Table<'a> {
+    /// Table on stdout. Resolves `--columns` and `--where` against `cols`; unknown
+    /// names are an error listing the valid keys.
+    pub fn new(cols: &'a [Col], opts: &OutputOpts) -> Result<Self, String> {
+        Table::with_writer(cols, opts, io::stdout().lock())
+    }
+}
+
+impl<'a, W: Write> Table<'a, W> {
+    pub fn with_writer(cols: &'a [Col], opts: &OutputOpts, out: W) -> Result<Self, String> {
+        let sel = match &opts.columns {
+            Some(list) => list
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|name| {
+                    cols.iter()
+                        .position(|c| c.key.eq_ignore_ascii_case(name) || c.title.eq_ignore_ascii_case(name))
+                        .ok_or_else(|| {
+                            let keys: Vec<&str> = cols.iter().map(|c| c.key).collect();
+                            format!("--columns: unknown column '{}', available: {}", name, keys.join(","))
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None if opts.format == Format::Text => (0..cols.len()).filter(|&i| cols[i].text).collect(),
+            None => (0..cols.len()).collect(),
+        };
+        if sel.is_empty() {
+            return Err("--columns selects no columns".to_string());
+        }
+        let filter = match &opts.filter {
+            Some(src) => Some(RowFilter::new(src, opts.context, cols).map_err(|e| format!("--where: {}", e))?),
+            None => None,
+        };
+        let color = opts.color && opts.format == Format::Text;
+        Ok(Table { cols, sel, format: opts.format, header_done: false, rows: 0, filter, color, shown: true, out })
+    }
+
+    /// Prints the text/CSV header now instead of before the first row.
+    pub fn header(&mut self) {
+        if self.header_done { return; }
+        self.header_done = true;
+        let line = match self.format {
+            Format::Text => self.text_line(self.sel.iter().map(|&i| self.cols[i].title.to_string()).collect(), &[]),
+            Format::Csv => self.sel.iter().map(|&i| self.cols[i].key).collect::<Vec<_>>().join(","),
+            Format::Json | Format::Ndjson => return,
+        };
+        let _ = writeln!(self.out, "{}", line);
+    }
+
+    /// Prints one row (or, with `--where`, the rows it selects); `cells` are in the
+    /// order of the full column list.
+    pub fn row(&mut self, ctx: Option<&TimeCtx>, cells: &[Cell]) {
+        let Some(filter) = self.filter.as_mut() else {
+            self.write_row(ctx, cells, &[]);
+            return;
+        };
+        let rows = filter.offer(cells);
+        // When anything is printed, this row is the last of it
+        self.shown = !rows.is_empty();
+        for Shown { cells, hits, gap } in rows {
+            if gap && self.format == Format::Text {
+                self.header();
+                let _ = writeln!(self.out, "--");
+            }
+            self.write_row(ctx, &cells, &hits);
+        }
+    }
+
+    fn write_row(&mut self, ctx: Option<&TimeCtx>, cells: &[Cell], hits: &[usize]) {
+        self.header();
+        let line = match self.format {
+            Format::Text => {
+                let texts = self.sel.iter().map(|&i| text_value(&cells[i], &self.cols[i], ctx)).collect();
+                self.text_line(texts, hits)
+            }
+            Format::Csv => self.sel.iter()
+                .map(|&i| csv_field(&machine_value(&cells[i], &self.cols[i], ctx)))
+                .collect::<Vec<_>>()
+                .join(","),
+            Format::Json | Format::Ndjson => {
+                let fields: Vec<String> = self.sel.iter()
+                    .map(|&i| format!("{}:{}", json_str(self.cols[i].key), json_value(&cells[i], &self.cols[i], ctx)))
+                    .collect();
+                let obj = format!("{{{}}}", fields.join(","));
+                match (self.format, self.rows) {
+                    (Format::Json, 0) => format!("[\n  {}", obj),
+                    (Format::Json, _) => format!(",\n  {}", obj),
+                    _ => obj,
+                }
+            }
+        };
+        self.rows += 1;
+        if self.format == Format::Json {
+            let _ = write!(self.out, "{}", line);
+        } else {
+            let _ = writeln!(self.out, "{}", line);
+        }
+    }
+
+    /// Free-form text line (e.g. flagged events) for the row just printed, only in the default text table.
+    pub fn note(&mut self, line: &str) {
+        if self.shown && self.format == Format::Text && self.sel.iter().all(|&i| self.cols[i].text) {
+            let _ = writeln!(self.out, "{}", line);
+        }
+    }
+
+    /// Closes a JSON array; prints `empty_msg` (stdout for text, stderr otherwise) when no rows were written.
+    pub fn finish(&mut self, empty_msg: &str) {
+        match self.format {
+            Format::Json if self.rows > 0 => { let _ = writeln!(self.out, "\n]"); }
+            Format::Json => { let _ = writeln!(self.out, "[]"); }
+            _ => {}
+        }
+        let empty_msg = if self.filter.is_some() && !empty_msg.is_empty() { "No rows match --where." } else { empty_msg };
+        if self.rows == 0 && !empty_msg.is_empty() {
+            if self.format == Format::Text {
+                let _ = writeln!(self.out, "{}", empty_msg);
+            } else {
+                eprintln!("{}", empty_msg);
+            }
+        }
+        let _ = self.out.flush();
+    }
+
+    /// Writes out rows still buffered, e.g. before `--follow` waits for more data.
+    pub fn flush(&mut self) {
+        let _ = self.out.flush();
+    }
+
+    /// Pads (and for `trunc` columns, truncates) the selected column texts to their widths;
+    /// `hits` columns are highlighted with --color.
+    fn text_line(&self, texts: Vec<String>, hits: &[usize]) -> String {
+        let mut line = String::new();
+        for (n, (&i, mut s)) in self.sel.iter().zip(texts).enumerate() {
+            let col = &self.cols[i];
+            if col.trunc && col.width > 0 && s.chars().count() > col.width - 1 {
+                s = s.chars().take(col.width - 1).collect();
+                s.push('…');
+            }
+            if n > 0 { line.push_str(col.sep); }
+            let padded = match col.align {
+                Align::Left => format!("{:<w$}", s, w = col.width),
+                Align::Right => format!("{:>w$}", s, w = col.width),
+            };
+            if self.color && hits.contains(&i) {
+                line.push_str(&format!("\x1b[1;31m{}\x1b[0m", padded));
+            } else {
+                line.push_str(&padded);
+            }
+        }
+        line
+    }
+}
+
+fn text_value(cell: &Cell, col: &Col, ctx: Option<&TimeCtx>) -> String {
+    match cell {
+        Cell::Str(s) => s.clone(),
+        Cell::Int(v) => v.to_string(),
+        Cell::Float(v) => format!("{:.*}", col.prec, v),
+        Cell::Time(ts) => ctx.map(|c| c.hms(*ts)).unwrap_or_else(|| ts.to_string()),
+        Cell::Missing => "-".to_string(),
+    }
+}
+
+/// Cell as plain text for CSV: numbers as in text, Missing as empty.
+fn machine_value(cell: &Cell, col: &Col, ctx: Option<&TimeCtx>) -> String {
+    match cell {
+        Cell::Str(s) => s.clone(),
+        Cell::Int(v) => v.to_string(),
+        Cell::Float(v) if v.is_finite() => format!("{:.*}", col.prec, v),
+        Cell::Float(_) | Cell::Missing => String::new(),
+        Cell::Time(ts) => iso_time(*ts, ctx),
+    }
+}
+
+fn iso_time(ts: u64, ctx: Option<&TimeCtx>) -> String {
+    ctx.map(|c| c.format(ts, "%Y-%m-%dT%H:%M:%S%:z")).unwrap_or_else(|| ts.to_string())
+}
+
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json_value(cell: &Cell, col: &Col, ctx: Option<&TimeCtx>) -> String {
+    match cell {
+        Cell::Str(s) => json_str(s),
+        Cell::Int(v) => v.to_string(),
+        Cell::Float(v) if v.is_finite() => format!("{:.*}", col.prec, v),
+        Cell::Float(_) | Cell::Missing => "null".to_string(),
+        Cell::Time(ts) => json_str(&iso_time(*ts, ctx)),
+    }
+}
+
+pub fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLS: &[Col] = &[
+        Col::new("dev", "Device").l(6),
+        Col::new("r_s", "r/s").r(6).prec(2),
+        Col::new("flush_s", "flush/s").r(8).prec(1),
+        Col::new("note", "Note").hidden(),
+    ];
+
+    fn render(format: Format, columns: Option<&str>) -> String {
+        let opts = OutputOpts { format, columns: columns.map(str::to_string), ..Default::default() };
+        let mut buf = Vec::new();
+        let mut t = Table::with_writer(COLS, &opts, &mut buf).unwrap();
+        t.row(None, &["sda".into(), 1.5.into(), Cell::Missing, "a,\"b\"".into()]);
+        t.row(None, &["nvme0n1".into(), 0.0.into(), Some(2.0).into(), Cell::Missing]);
+        t.finish("No data.");
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(render(Format::Text, None),
+            "Device    r/s  flush/s\nsda      1.50        -\nnvme0n1   0.00      2.0\n");
+        assert_eq!(render(Format::Csv, None),
+            "dev,r_s,flush_s,note\nsda,1.50,,\"a,\"\"b\"\"\"\nnvme0n1,0.00,2.0,\n");
+        assert_eq!(render(Format::Ndjson, Some("R/S,dev")),
+            "{\"r_s\":1.50,\"dev\":\"sda\"}\n{\"r_s\":0.00,\"dev\":\"nvme0n1\"}\n");
+        assert_eq!(render(Format::Json, Some("note")),
+            "[\n  {\"note\":\"a,\\\"b\\\"\"},\n  {\"note\":null}\n]\n");
+    }
+
+    #[test]
+    fn truncated_and_separated_columns() {
+        const PROC: &[Col] = &[Col::new("pid", "PID").r(5), Col::new("comm", "COMM").l(6).sep("  ").trunc()];
+        let mut buf = Vec::new();
+        let mut t = Table::with_writer(PROC, &OutputOpts::default(), &mut buf).unwrap();
+        t.row(None, &[42u64.into(), "kworker/0:1".into()]);
+        t.row(None, &[7u64.into(), "bash".into()]);
+        t.finish("");
+        assert_eq!(String::from_utf8(buf).unwrap(), "  PID  COMM  \n   42  kwork…\n    7  bash  \n");
+    }
+
+    #[test]
+    fn column_errors_and_empty_output() {
+        let opts = OutputOpts { format: Format::Text, columns: Some("r/s,bogus".to_string()), ..Default::default() };
+        let err = Table::with_writer(COLS, &opts, Vec::new()).err().unwrap();
+        assert_eq!(err, "--columns: unknown column 'bogus', available: dev,r_s,flush_s,note");
+
+        let mut buf = Vec::new();
+        let opts = OutputOpts { format: Format::Json, ..Default::default() };
+        Table::with_writer(COLS, &opts, &mut buf).unwrap().finish("No data.");
+        assert_eq!(buf, b"[]\n");
+    }
+
+    #[test]
+    fn where_context_and_color() {
+        let opts = OutputOpts { filter: Some("r_s>1".to_string()), context: 0, color: true, ..Default::default() };
+        let mut buf = Vec::new();
+        let mut t = Table::with_writer(COLS, &opts, &mut buf).unwrap();
+        for (r, note) in [(0.5, "hidden"), (2.0, "shown"), (0.1, "hidden"), (3.0, "")] {
+            t.row(None, &["sda".into(), r.into(), Cell::Missing, Cell::Missing]);
+            if !note.is_empty() { t.note(note); }
+        }
+        t.finish("No data.");
+        assert_eq!(String::from_utf8(buf).unwrap(), "Device    r/s  flush/s\n\
+            sda    \x1b[1;31m  2.00\x1b[0m        -\nshown\n--\nsda    \x1b[1;31m  3.00\x1b[0m        -\n");
+
+        let opts = OutputOpts { format: Format::Csv, filter: Some("flush_s>=0".to_string()), ..Default::default() };
+        let mut buf = Vec::new();
+        let mut t = Table::with_writer(COLS, &opts, &mut buf).unwrap();
+        t.row(None, &["sda".into(), 1.0.into(), Cell::Missing, Cell::Missing]);
+        t.finish("No data.");
+        assert!(buf.is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+/*!
+ * Deltas and Rates
+ * ----------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Per-interval deltas between two samples of the same device/interface and the
+ * rates derived from them (IOPS, KB/s, await, queue depth, CPU and memory %).
+ * Counters that go backwards (reboot, wrap) give a delta of 0.
+ */
+
+use crate::record::{CpuStat, DiskStat, MemStat, NetStat};
+
+/// Counter deltas of one disk over `dt` seconds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiskDelta {
+    pub dt: u64,
+    pub reads: u64,
+    pub reads_merged: u64,
+    pub sectors_read: u64,
+    pub read_time_ms: u64,
+    pub writes: u64,
+    pub writes_merged: u64,
+    pub sectors_written: u64,
+    pub write_time_ms: u64,
+    pub io_time_ms: u64,
+    pub weighted_io_time_ms: u64,
+    pub discards: u64,
+    pub discards_merged: u64,
+    pub sectors_discarded: u64,
+    pub discard_time_ms: u64,
+    /// None unless both samples have flush counters
+    pub flushes: Option<u64>,
+    pub flush_time_ms: Option<u64>,
+}
+
+fn per_op(time_ms: u64, ops: u64) -> f64 {
+    if ops > 0 { time_ms as f64 / ops as f64 } else { 0.0 }
+}
+
+fn opt_delta(cur: Option<u64>, prev: Option<u64>) -> Option<u64> {
+    Some(cur?.saturating_sub(prev?))
+}
+
+impl DiskDelta {
+    /// Delta from `prev` to `cur`, `dt` seconds apart. None when dt is 0.
+    pub fn between(prev: &DiskStat, cur: &DiskStat, dt: u64) -> Option<Self> {
+        if dt == 0 { return None; }
+        Some(Self {
+            dt,
+            reads: cur.reads.saturating_sub(prev.reads),
+            reads_merged: cur.reads_merged.saturating_sub(prev.reads_merged),
+            sectors_read: cur.sectors_read.saturating_sub(prev.sectors_read),
+            read_time_ms: cur.read_time_ms.saturating_sub(prev.read_time_ms),
+            writes: cur.writes.saturating_sub(prev.writes),
+            writes_merged: cur.writes_merged.saturating_sub(prev.writes_merged),
+            sectors_written: cur.sectors_written.saturating_sub(prev.sectors_written),
+            write_time_ms: cur.write_time_ms.saturating_sub(prev.write_time_ms),
+            io_time_ms: cur.io_time_ms.saturating_sub(prev.io_time_ms),
+            weighted_io_time_ms: cur.weighted_io_time_ms.saturating_sub(prev.weighted_io_time_ms),
+            discards: cur.discards.saturating_sub(prev.discards),
+            discards_merged: cur.discards_merged.saturating_sub(prev.discards_merged),
+            sectors_discarded: cur.sectors_discarded.saturating_sub(prev.sectors_discarded),
+            discard_time_ms: cur.discard_time_ms.saturating_sub(prev.discard_time_ms),
+            flushes: opt_delta(cur.flushes, prev.flushes),
+            flush_time_ms: opt_delta(cur.flush_time_ms, prev.flush_time_ms),
+        })
+    }
+
+    /// Count per second over the interval.
+    pub fn rate(&self, count: u64) -> f64 {
+        count as f64 / self.dt as f64
+    }
+
+    pub fn r_s(&self) -> f64 { self.rate(self.reads) }
+    pub fn w_s(&self) -> f64 { self.rate(self.writes) }
+    /// Read + write IOPS
+    pub fn io_s(&self) -> f64 { self.r_s() + self.w_s() }
+    pub fn rd_kbs(&self) -> f64 { self.rate(self.sectors_read) * 512.0 / 1024.0 }
+    pub fn wr_kbs(&self) -> f64 { self.rate(self.sectors_written) * 512.0 / 1024.0 }
+    /// Read + write KB/s
+    pub fn kb_s(&self) -> f64 { self.rd_kbs() + self.wr_kbs() }
+
+    /// Weighted I/O time over elapsed time: average requests in flight over the interval.
+    pub fn avg_queue_depth(&self) -> f64 {
+        self.weighted_io_time_ms as f64 / (self.dt as f64 * 1000.0)
+    }
+
+    /// Weighted I/O time over busy time (collectl style queue length).
+    pub fn qlen(&self) -> f64 {
+        per_op(self.weighted_io_time_ms, self.io_time_ms)
+    }
+
+    /// Busy time per completed read/write (ms).
+    pub fn svctim(&self) -> f64 {
+        per_op(self.io_time_ms, self.reads + self.writes)
+    }
+
+    pub fn await_rd(&self) -> f64 { per_op(self.read_time_ms, self.reads) }
+    pub fn await_wr(&self) -> f64 { per_op(self.write_time_ms, self.writes) }
+
+    pub fn discards_s(&self) -> f64 { self.rate(self.discards) }
+    pub fn discards_merged_s(&self) -> f64 { self.rate(self.discards_merged) }
+    pub fn sectors_discarded_s(&self) -> f64 { self.rate(self.sectors_discarded) }
+    pub fn discard_kbs(&self) -> f64 { self.sectors_discarded_s() * 512.0 / 1024.0 }
+    pub fn await_discard(&self) -> f64 { per_op(self.discard_time_ms, self.discards) }
+
+    /// Flushes/s, None when the kernel has no flush counters.
+    pub fn flush_s(&self) -> Option<f64> {
+        self.flushes.map(|f| self.rate(f))
+    }
+
+    /// Flush await (ms), None when the kernel has no flush counters.
+    pub fn await_flush(&self) -> Option<f64> {
+        Some(per_op(self.flush_time_ms?, self.flushes?))
+    }
+}
+
+/// CPU time split between two CPU samples, as % of all ticks in the interval.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CpuPercent {
+    pub user: f64,
+    pub nice: f64,
+    pub system: f64,
+    pub idle: f64,
+    pub iowait: f64,
+    pub steal: f64,
+    pub guest: f64,
+}
+
+impl CpuPercent {
+    /// None when no ticks elapsed between the samples.
+    pub fn between(prev: &CpuStat, cur: &CpuStat) -> Option<Self> {
+        let d = |c: u64, p: u64| c.saturating_sub(p);
+        let user = d(cur.user, prev.user);
+        let nice = d(cur.nice, prev.nice);
+        let system = d(cur.system, prev.system);
+        let idle = d(cur.idle, prev.idle);
+        let iowait = d(cur.iowait, prev.iowait);
+        let steal = d(cur.steal, prev.steal);
+        let guest = d(cur.guest, prev.guest);
+        let total = user + nice + system + idle + iowait
+            + d(cur.irq, prev.irq) + d(cur.softirq, prev.softirq) + steal + guest;
+        if total == 0 { return None; }
+        let pct = |v: u64| v as f64 * 100.0 / total as f64;
+        Some(Self {
+            user: pct(user),
+            nice: pct(nice),
+            system: pct(system),
+            idle: pct(idle),
+            iowait: pct(iowait),
+            steal: pct(steal),
+            guest: pct(guest),
+        })
+    }
+}
+
+/// Memory use as % of MemTotal.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemPercent {
+    /// MemTotal - MemFree
+    pub used: f64,
+    pub avail: f64,
+    pub cached: f64,
+    pub free: f64,
+}
+
+impl MemPercent {
+    pub fn of(stat: &MemStat) -> Self {
+        let total = stat.get("MemTotal").unwrap_or(0) as f64;
+        if total <= 0.0 { return Self::default(); }
+        let free = stat.get("MemFree").unwrap_or(0) as f64;
+        let pct = |v: f64| v / total * 100.0;
+        Self {
+            used: pct(total - free),
+            avail: pct(stat.get("MemAvailable").unwrap_or(0) as f64),
+            cached: pct(stat.get("Cached").unwrap_or(0) as f64),
+            free: pct(free),
+        }
+    }
+}
+
+/// Counter deltas of one interface over `dt` seconds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetDelta {
+    pub dt: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errs: u64,
+    pub tx_errs: u64,
+    pub rx_drop: u64,
+    pub tx_drop: u64,
+}
+
+impl NetDelta {
+    /// Delta from `prev` to `cur`, `dt` seconds apart. None when dt is 0.
+    pub fn between(prev: &NetStat, cur: &NetStat, dt: u64) -> Option<Self> {
+        if dt == 0 { return None; }
+        Some(Self {
+            dt,
+            rx_bytes: cur.rx_bytes.saturating_sub(prev.rx_bytes),
+            tx_bytes: cur.tx_bytes.saturating_sub(prev.tx_bytes),
+            rx_packets: cur.rx_packets.saturating_sub(prev.rx_packets),
+            tx_packets: cur.tx_packets.saturating_sub(prev.tx_packets),
+            rx_errs: cur.rx_errs.saturating_sub(prev.rx_errs),
+            tx_errs: cur.tx_errs.saturating_sub(prev.tx_errs),
+            rx_drop: cur.rx_drop.saturating_sub(prev.rx_drop),
+            tx_drop: cur.tx_drop.saturating_sub(prev.tx_drop),
+        })
+    }
+
+    /// Count per second over the interval.
+    pub fn rate(&self, count: u64) -> f64 {
+        count as f64 / self.dt as f64
+    }
+
+    pub fn rx_kbs(&self) -> f64 { self.rate(self.rx_bytes) / 1024.0 }
+    pub fn tx_kbs(&self) -> f64 { self.rate(self.tx_bytes) / 1024.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk(line: &str) -> DiskStat {
+        DiskStat::from_line(line).unwrap()
+    }
+
+    #[test]
+    fn disk_rates() {
+        let a = disk("8 0 sda 100 0 800 100 50 0 400 500 0 1000 2000 0 0 0 0 10 20");
+        let b = disk("8 0 sda 200 5 2800 300 150 0 1400 1500 2 2000 6000 0 0 0 0 20 60");
+        let d = DiskDelta::between(&a, &b, 10).unwrap();
+        assert_eq!((d.r_s(), d.w_s(), d.io_s()), (10.0, 10.0, 20.0));
+        assert_eq!((d.rd_kbs(), d.wr_kbs()), (100.0, 50.0));
+        assert_eq!((d.await_rd(), d.await_wr()), (2.0, 10.0));
+        assert_eq!((d.avg_queue_depth(), d.qlen(), d.svctim()), (0.4, 4.0, 5.0));
+        assert_eq!((d.flush_s(), d.await_flush()), (Some(1.0), Some(4.0)));
+        assert!(DiskDelta::between(&a, &b, 0).is_none());
+
+        // No flush counters on one side: not available rather than 0
+        let old = disk("8 0 sda 200 5 2800 300 150 0 1400 1500 2 2000 6000");
+        let d = DiskDelta::between(&a, &old, 10).unwrap();
+        assert_eq!(d.flush_s(), None);
+        // Counter reset
+        assert_eq!(DiskDelta::between(&b, &a, 10).unwrap().reads, 0);
+    }
+
+    #[test]
+    fn cpu_mem_net() {
+        let a = CpuStat { user: 100, idle: 100, ..Default::default() };
+        let b = CpuStat { user: 150, system: 25, idle: 125, ..Default::default() };
+        let p = CpuPercent::between(&a, &b).unwrap();
+        assert_eq!((p.user, p.system, p.idle), (50.0, 25.0, 25.0));
+        assert!(CpuPercent::between(&a, &a).is_none());
+
+        let m = MemStat::from_fields(&["1000", "250", "500", "0", "100"]);
+        let p = MemPercent::of(&m);
+        assert_eq!((p.used, p.avail, p.cached, p.free), (75.0, 50.0, 10.0, 25.0));
+        assert_eq!(MemPercent::of(&MemStat::default()), MemPercent::default());
+
+        let a = NetStat { iface: "eth0".into(), rx_bytes: 0, tx_bytes: 2048, ..Default::default() };
+        let b = NetStat { iface: "eth0".into(), rx_bytes: 10240, tx_bytes: 4096, ..Default::default() };
+        let d = NetDelta::between(&a, &b, 5).unwrap();
+        assert_eq!((d.rx_kbs(), d.tx_kbs()), (2.0, 0.4));
+    }
+}
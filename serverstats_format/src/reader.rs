@@ -0,0 +1,60 @@
+/*!
+ * Capture Reader
+ * --------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Streams records from a capture one line at a time, so week-long captures
+ * are never held in memory. Comments and malformed lines are skipped.
+ */
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Lines};
+use std::path::Path;
+
+use crate::record::Record;
+
+pub struct Reader<R> {
+    lines: Lines<R>,
+}
+
+impl Reader<BufReader<File>> {
+    /// Opens a capture file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Reader::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Reader { lines: inner.lines() }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.lines.next()? {
+                Ok(line) => {
+                    if let Some(rec) = Record::parse(&line) {
+                        return Some(Ok(rec));
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_header_and_malformed_lines() {
+        let text = "#TYPE,ts_epoch,<fields...>\nTZ,10,0,UTC\nDISK,10,8,0,sda\n\nCPU,10,1,2,3,4,5,6,7,8,9,1,0\nFC,10,host5,0x1,Online\nSCSIDEV,10,sdb,5:0:0:1\nXFUTURE,10,a\n";
+        let kinds: Vec<String> = Reader::new(text.as_bytes()).flatten().map(|r| r.kind().to_string()).collect();
+        assert_eq!(kinds, ["TZ", "CPU", "SCSIDEV", "XFUTURE"]);
+    }
+}
@@ -0,0 +1,619 @@
+/*!
+ * Capture Records
+ * ---------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Typed DISK, CPU, MEM and NET samples, parsed from /proc (gather) or from
+ * capture lines (playback/analysis), and the `Record` enum for one capture line.
+ */
+
+use std::fmt;
+
+use crate::state::{DrbdStatus, FcHost, IscsiSession, MdStatus, NetExt};
+
+/// Represents a single sample from /proc/diskstats for one block device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskStat {
+    /// Device major number (kernel driver family)
+    pub major: u32,
+    /// Device minor number (unique per device)
+    pub minor: u32,
+    /// Device name (e.g., sda, nvme0n1, dm-0)
+    pub name: String,
+    /// Reads completed successfully
+    pub reads: u64,
+    /// Reads merged
+    pub reads_merged: u64,
+    /// Sectors read
+    pub sectors_read: u64,
+    /// Time spent reading (ms)
+    pub read_time_ms: u64,
+    /// Writes completed
+    pub writes: u64,
+    /// Writes merged
+    pub writes_merged: u64,
+    /// Sectors written
+    pub sectors_written: u64,
+    /// Time spent writing (ms)
+    pub write_time_ms: u64,
+    /// I/Os currently in progress
+    pub io_in_progress: u64,
+    /// Time spent doing I/Os (ms)
+    pub io_time_ms: u64,
+    /// Weighted time spent doing I/Os (ms)
+    pub weighted_io_time_ms: u64,
+    /// Discard counters (kernel 4.18+, 0 on 14-field kernels)
+    pub discards: u64,
+    pub discards_merged: u64,
+    pub sectors_discarded: u64,
+    pub discard_time_ms: u64,
+    /// Flush requests completed (kernel 5.5+, None on older kernels)
+    pub flushes: Option<u64>,
+    /// Time spent flushing (ms, kernel 5.5+)
+    pub flush_time_ms: Option<u64>,
+    /// Field count of the source diskstats layout: 14 (RHEL7), 18 (4.18+) or 20 (5.5+)
+    pub layout: u8,
+}
+
+/// Field count of a `/proc/diskstats` line: 14 before kernel 4.18 (RHEL7),
+/// 18 with discard counters, 20 with flush counters (5.5+).
+/// Lines with only some of the discard counters (collectl drops discard time) count as 18.
+pub fn diskstats_layout(ncols: usize) -> u8 {
+    match ncols {
+        n if n >= 20 => 20,
+        n if n >= 15 => 18,
+        _ => 14,
+    }
+}
+
+impl DiskStat {
+    /// Parses a line from `/proc/diskstats` into a `DiskStat`.
+    /// Accepts the 14, 18 and 20 field layouts; missing discard counters read as 0.
+    pub fn from_line(line: &str) -> Option<Self> {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        if cols.len() < 14 { return None; }
+        Some(Self {
+            major: cols[0].parse().ok()?,
+            minor: cols[1].parse().ok()?,
+            name: cols[2].to_string(),
+            reads: cols[3].parse().ok()?,
+            reads_merged: cols[4].parse().ok()?,
+            sectors_read: cols[5].parse().ok()?,
+            read_time_ms: cols[6].parse().ok()?,
+            writes: cols[7].parse().ok()?,
+            writes_merged: cols[8].parse().ok()?,
+            sectors_written: cols[9].parse().ok()?,
+            write_time_ms: cols[10].parse().ok()?,
+            io_in_progress: cols[11].parse().ok()?,
+            io_time_ms: cols[12].parse().ok()?,
+            weighted_io_time_ms: cols[13].parse().ok()?,
+            discards: cols.get(14).and_then(|v| v.parse().ok()).unwrap_or(0),
+            discards_merged: cols.get(15).and_then(|v| v.parse().ok()).unwrap_or(0),
+            sectors_discarded: cols.get(16).and_then(|v| v.parse().ok()).unwrap_or(0),
+            discard_time_ms: cols.get(17).and_then(|v| v.parse().ok()).unwrap_or(0),
+            flushes: cols.get(18).and_then(|v| v.parse().ok()),
+            flush_time_ms: cols.get(19).and_then(|v| v.parse().ok()),
+            layout: diskstats_layout(cols.len()),
+        })
+    }
+
+    /// Parses a CSV field slice (from capture file, after `DISK,<ts>`) into a `DiskStat`.
+    /// DISK records always carry the four discard columns (zero-padded on 14-field
+    /// kernels), so the layout here is 18 or 20; DISKLAYOUT records say which was real.
+    pub fn from_csv_fields(fields: &[&str]) -> Option<Self> {
+        if fields.len() < 18 { return None; }
+        Some(Self {
+            major: fields[0].parse().ok()?,
+            minor: fields[1].parse().ok()?,
+            name: fields[2].to_string(),
+            reads: fields[3].parse().ok()?,
+            reads_merged: fields[4].parse().ok()?,
+            sectors_read: fields[5].parse().ok()?,
+            read_time_ms: fields[6].parse().ok()?,
+            writes: fields[7].parse().ok()?,
+            writes_merged: fields[8].parse().ok()?,
+            sectors_written: fields[9].parse().ok()?,
+            write_time_ms: fields[10].parse().ok()?,
+            io_in_progress: fields[11].parse().ok()?,
+            io_time_ms: fields[12].parse().ok()?,
+            weighted_io_time_ms: fields[13].parse().ok()?,
+            discards: fields[14].parse().ok()?,
+            discards_merged: fields[15].parse().ok()?,
+            sectors_discarded: fields[16].parse().ok()?,
+            discard_time_ms: fields[17].parse().ok()?,
+            flushes: fields.get(18).and_then(|v| v.parse().ok()),
+            flush_time_ms: fields.get(19).and_then(|v| v.parse().ok()),
+            layout: if fields.len() >= 20 { 20 } else { 18 },
+        })
+    }
+}
+
+/// Aggregate `cpu` line of /proc/stat (USER_HZ ticks) plus the run queue counters.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CpuStat {
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    pub steal: u64,
+    /// Time running guests; already included in `user`
+    pub guest: u64,
+    pub procs_running: u64,
+    pub procs_blocked: u64,
+}
+
+impl CpuStat {
+    /// Parses a `cpu  user nice system ...` line (from /proc/stat or collectl).
+    /// procs_running/procs_blocked are left at 0.
+    pub fn from_cpu_line(line: &str) -> Option<Self> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.first() != Some(&"cpu") || parts.len() < 10 { return None; }
+        let v = |i: usize| parts[i].parse::<u64>().ok();
+        Some(Self {
+            user: v(1)?,
+            nice: v(2)?,
+            system: v(3)?,
+            idle: v(4)?,
+            iowait: v(5)?,
+            irq: v(6)?,
+            softirq: v(7)?,
+            steal: v(8)?,
+            guest: v(9)?,
+            procs_running: 0,
+            procs_blocked: 0,
+        })
+    }
+
+    /// Parses the contents of /proc/stat.
+    pub fn from_proc_stat(text: &str) -> Option<Self> {
+        let mut stat = text.lines().find_map(Self::from_cpu_line)?;
+        for line in text.lines() {
+            let mut parts = line.split_whitespace();
+            let (Some(key), Some(val)) = (parts.next(), parts.next()) else { continue };
+            match key {
+                "procs_running" => stat.procs_running = val.parse().unwrap_or(0),
+                "procs_blocked" => stat.procs_blocked = val.parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+        Some(stat)
+    }
+
+    /// Parses the fields after `CPU,<ts>`: nine cpu counters, then running and blocked last.
+    pub fn from_fields(fields: &[&str]) -> Option<Self> {
+        if fields.len() < 11 { return None; }
+        let v = |i: usize| fields[i].parse::<u64>().ok();
+        Some(Self {
+            user: v(0)?,
+            nice: v(1)?,
+            system: v(2)?,
+            idle: v(3)?,
+            iowait: v(4)?,
+            irq: v(5)?,
+            softirq: v(6)?,
+            steal: v(7)?,
+            guest: v(8)?,
+            procs_running: v(fields.len() - 2).unwrap_or(0),
+            procs_blocked: v(fields.len() - 1).unwrap_or(0),
+        })
+    }
+}
+
+/// /proc/meminfo keys captured in MEM records, in record order (values in kB).
+pub const MEM_KEYS: [&str; 14] = [
+    "MemTotal", "MemFree", "MemAvailable", "Buffers", "Cached", "SwapTotal", "SwapFree",
+    "Dirty", "Writeback", "Active(file)", "Inactive(file)", "Slab", "KReclaimable", "SReclaimable",
+];
+
+/// One MEM sample: the `MEM_KEYS` values from /proc/meminfo, missing keys are 0.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemStat {
+    pub values: [u64; 14],
+}
+
+impl MemStat {
+    /// Value of a /proc/meminfo key, None if it is not one of `MEM_KEYS`.
+    pub fn get(&self, key: &str) -> Option<u64> {
+        MEM_KEYS.iter().position(|k| *k == key).map(|i| self.values[i])
+    }
+
+    /// Sets a /proc/meminfo key; returns false for keys not in `MEM_KEYS`.
+    pub fn set(&mut self, key: &str, value: u64) -> bool {
+        match MEM_KEYS.iter().position(|k| *k == key) {
+            Some(i) => {
+                self.values[i] = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Parses the contents of /proc/meminfo.
+    pub fn from_meminfo(text: &str) -> Self {
+        let mut stat = Self::default();
+        for line in text.lines() {
+            let mut parts = line.split_whitespace();
+            if let (Some(key), Some(val)) = (parts.next(), parts.next()) {
+                stat.set(key.trim_end_matches(':'), val.parse().unwrap_or(0));
+            }
+        }
+        stat
+    }
+
+    /// Parses the fields after `MEM,<ts>`.
+    pub fn from_fields(fields: &[&str]) -> Self {
+        let mut stat = Self::default();
+        for (slot, v) in stat.values.iter_mut().zip(fields) {
+            *slot = v.parse().unwrap_or(0);
+        }
+        stat
+    }
+}
+
+/// One NET sample for an interface, from /proc/net/dev.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetStat {
+    pub iface: String,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errs: u64,
+    pub tx_errs: u64,
+    pub rx_drop: u64,
+    pub tx_drop: u64,
+}
+
+impl NetStat {
+    /// Parses an interface line of /proc/net/dev (`eth0: rx_bytes rx_packets ...`).
+    pub fn from_net_dev_line(line: &str) -> Option<Self> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 13 || !parts[0].ends_with(':') { return None; }
+        let v = |i: usize| parts[i].parse::<u64>().unwrap_or(0);
+        Some(Self {
+            iface: parts[0].trim_end_matches(':').to_string(),
+            rx_bytes: v(1),
+            rx_packets: v(2),
+            rx_errs: v(3),
+            rx_drop: v(4),
+            tx_bytes: v(9),
+            tx_packets: v(10),
+            tx_errs: v(11),
+            tx_drop: v(12),
+        })
+    }
+
+    /// Parses the fields after `NET,<ts>`: iface, rx/tx bytes, packets, errors, drops.
+    pub fn from_fields(fields: &[&str]) -> Option<Self> {
+        let iface = fields.first()?.to_string();
+        let v = |i: usize| fields.get(i).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+        Some(Self {
+            iface,
+            rx_bytes: v(1),
+            tx_bytes: v(2),
+            rx_packets: v(3),
+            tx_packets: v(4),
+            rx_errs: v(5),
+            tx_errs: v(6),
+            rx_drop: v(7),
+            tx_drop: v(8),
+        })
+    }
+}
+
+/// One line of a capture file.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Record {
+    /// Capture host UTC offset (seconds) and zone name, written at start and on DST changes
+    Tz { ts: u64, offset: i32, zone: String },
+    /// diskstats field count (14/18/20), written at start and when it changes
+    DiskLayout { ts: u64, fields: u8 },
+    /// Capture host name, kernel release, machine (uname -m) and CPU count, written at start
+    Host { ts: u64, host: String, release: String, arch: String, cpus: u32 },
+    Disk { ts: u64, stat: DiskStat },
+    Cpu { ts: u64, stat: CpuStat },
+    Mem { ts: u64, stat: MemStat },
+    Net { ts: u64, stat: NetStat },
+    /// Extended counters and link state of `iface`, written just before its NET record
+    NetExt { ts: u64, iface: String, stat: NetExt },
+    Md { ts: u64, stat: MdStatus },
+    Drbd { ts: u64, stat: DrbdStatus },
+    Fc { ts: u64, stat: FcHost },
+    Iscsi { ts: u64, stat: IscsiSession },
+    /// SCSI H:C:T:L address of an sd device, written at start and when the device set changes
+    ScsiDev { ts: u64, dev: String, hctl: String },
+    /// Gatherer event, e.g. source `diskguard`, action `paused`; commas in `detail` are written as ';'
+    Event { ts: u64, source: String, action: String, detail: String },
+    /// Any other record type, fields after the timestamp kept as text
+    Other { kind: String, ts: u64, fields: Vec<String> },
+}
+
+impl Record {
+    /// Parses one capture line. Comments, blank lines and malformed records give None.
+    pub fn parse(line: &str) -> Option<Self> {
+        if line.is_empty() || line.starts_with('#') { return None; }
+        let mut cols = line.split(',');
+        let kind = cols.next()?;
+        let ts = cols.next()?.parse::<u64>().ok()?;
+        let fields: Vec<&str> = cols.collect();
+        Some(match kind {
+            "TZ" => Record::Tz {
+                ts,
+                offset: fields.first()?.parse().ok()?,
+                zone: fields.get(1).unwrap_or(&"").to_string(),
+            },
+            "DISKLAYOUT" => Record::DiskLayout { ts, fields: fields.first()?.parse().ok()? },
+            "HOST" => Record::Host {
+                ts,
+                host: fields.first()?.to_string(),
+                release: fields.get(1)?.to_string(),
+                arch: fields.get(2)?.to_string(),
+                cpus: fields.get(3)?.parse().ok()?,
+            },
+            "DISK" => Record::Disk { ts, stat: DiskStat::from_csv_fields(&fields)? },
+            "CPU" => Record::Cpu { ts, stat: CpuStat::from_fields(&fields)? },
+            "MEM" => Record::Mem { ts, stat: MemStat::from_fields(&fields) },
+            "NET" => Record::Net { ts, stat: NetStat::from_fields(&fields)? },
+            "NETX" => Record::NetExt {
+                ts,
+                iface: fields.first()?.to_string(),
+                stat: NetExt::from_fields(&fields[1..])?,
+            },
+            "MD" => Record::Md { ts, stat: MdStatus::from_fields(&fields)? },
+            "DRBD" => Record::Drbd { ts, stat: DrbdStatus::from_fields(&fields)? },
+            "FC" => Record::Fc { ts, stat: FcHost::from_fields(&fields)? },
+            "ISCSI" => Record::Iscsi { ts, stat: IscsiSession::from_fields(&fields)? },
+            "SCSIDEV" => Record::ScsiDev { ts, dev: fields.first()?.to_string(), hctl: fields.get(1)?.to_string() },
+            "EVENT" => Record::Event {
+                ts,
+                source: fields.first()?.to_string(),
+                action: fields.get(1)?.to_string(),
+                detail: fields.get(2..).unwrap_or_default().join(","),
+            },
+            _ => Record::Other {
+                kind: kind.to_string(),
+                ts,
+                fields: fields.iter().map(|f| f.to_string()).collect(),
+            },
+        })
+    }
+
+    /// Epoch seconds of the sample.
+    pub fn ts(&self) -> u64 {
+        match self {
+            Record::Tz { ts, .. }
+            | Record::DiskLayout { ts, .. }
+            | Record::Host { ts, .. }
+            | Record::Disk { ts, .. }
+            | Record::Cpu { ts, .. }
+            | Record::Mem { ts, .. }
+            | Record::Net { ts, .. }
+            | Record::NetExt { ts, .. }
+            | Record::Md { ts, .. }
+            | Record::Drbd { ts, .. }
+            | Record::Fc { ts, .. }
+            | Record::Iscsi { ts, .. }
+            | Record::ScsiDev { ts, .. }
+            | Record::Event { ts, .. }
+            | Record::Other { ts, .. } => *ts,
+        }
+    }
+
+    /// Record type as written in the capture (DISK, CPU, ...).
+    pub fn kind(&self) -> &str {
+        match self {
+            Record::Tz { .. } => "TZ",
+            Record::DiskLayout { .. } => "DISKLAYOUT",
+            Record::Host { .. } => "HOST",
+            Record::Disk { .. } => "DISK",
+            Record::Cpu { .. } => "CPU",
+            Record::Mem { .. } => "MEM",
+            Record::Net { .. } => "NET",
+            Record::NetExt { .. } => "NETX",
+            Record::Md { .. } => "MD",
+            Record::Drbd { .. } => "DRBD",
+            Record::Fc { .. } => "FC",
+            Record::Iscsi { .. } => "ISCSI",
+            Record::ScsiDev { .. } => "SCSIDEV",
+            Record::Event { .. } => "EVENT",
+            Record::Other { kind, .. } => kind,
+        }
+    }
+}
+
+/// Formats the record as a capture line (without the newline).
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.kind(), self.ts())?;
+        match self {
+            Record::Tz { offset, zone, .. } => write!(f, ",{},{}", offset, zone),
+            Record::DiskLayout { fields, .. } => write!(f, ",{}", fields),
+            Record::Host { host, release, arch, cpus, .. } => write!(f, ",{},{},{},{}", host, release, arch, cpus),
+            Record::Disk { stat: s, .. } => {
+                write!(
+                    f,
+                    ",{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                    s.major, s.minor, s.name,
+                    s.reads, s.reads_merged, s.sectors_read, s.read_time_ms,
+                    s.writes, s.writes_merged, s.sectors_written, s.write_time_ms,
+                    s.io_in_progress, s.io_time_ms, s.weighted_io_time_ms,
+                    s.discards, s.discards_merged, s.sectors_discarded, s.discard_time_ms
+                )?;
+                // Flush counters only on kernels that have them (5.5+)
+                if let (Some(flushes), Some(flush_ms)) = (s.flushes, s.flush_time_ms) {
+                    write!(f, ",{},{}", flushes, flush_ms)?;
+                }
+                Ok(())
+            }
+            Record::Cpu { stat: s, .. } => write!(
+                f,
+                ",{},{},{},{},{},{},{},{},{},{},{}",
+                s.user, s.nice, s.system, s.idle, s.iowait, s.irq, s.softirq, s.steal, s.guest,
+                s.procs_running, s.procs_blocked
+            ),
+            Record::Mem { stat, .. } => {
+                for v in &stat.values {
+                    write!(f, ",{}", v)?;
+                }
+                Ok(())
+            }
+            Record::Net { stat: s, .. } => write!(
+                f,
+                ",{},{},{},{},{},{},{},{},{}",
+                s.iface, s.rx_bytes, s.tx_bytes, s.rx_packets, s.tx_packets, s.rx_errs, s.tx_errs, s.rx_drop, s.tx_drop
+            ),
+            Record::NetExt { iface, stat: s, .. } => {
+                write!(
+                    f,
+                    ",{},{},{},{},{},{},{},{},{},{}",
+                    iface, s.rx_crc_errors, s.rx_fifo_errors, s.rx_missed_errors, s.collisions, s.multicast,
+                    s.speed_mbps, s.mtu, s.operstate, s.carrier_changes
+                )?;
+                if !s.duplex.is_empty() {
+                    write!(f, ",{}", s.duplex)?;
+                }
+                Ok(())
+            }
+            Record::Md { stat: s, .. } => write!(
+                f,
+                ",{},{},{},{},{},{},{},{},{},{},{}",
+                s.name, s.level, s.array_state, s.degraded, s.raid_disks, s.sync_action,
+                s.sync_done, s.sync_total, s.sync_speed_kb, s.mismatch_cnt, s.members.join(";")
+            ),
+            Record::Drbd { stat: s, .. } => write!(
+                f,
+                ",{},{},{},{},{},{},{},{},{},{:.1},{}",
+                s.name, s.cs, s.ro, s.ds, s.ns, s.nr, s.dw, s.dr, s.oos, s.sync_pct, s.speed_kb
+            ),
+            Record::Fc { stat: s, .. } => write!(
+                f,
+                ",{},{},{},{},{},{},{},{},{},{},{}",
+                s.host, s.port_name, s.port_state, s.speed,
+                s.link_failure_count, s.loss_of_sync_count, s.loss_of_signal_count, s.invalid_crc_count,
+                s.error_frames, s.tx_frames, s.rx_frames
+            ),
+            Record::Iscsi { stat: s, .. } => write!(
+                f,
+                ",{},{},{},{},{},{}",
+                s.session, s.host, s.target, s.state, s.conn_state, s.address
+            ),
+            Record::ScsiDev { dev, hctl, .. } => write!(f, ",{},{}", dev, hctl),
+            Record::Event { source, action, detail, .. } => write!(f, ",{},{},{}", source, action, detail.replace(',', ";")),
+            Record::Other { fields, .. } => {
+                for v in fields {
+                    write!(f, ",{}", v)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_round_trip() {
+        for line in [
+            "TZ,1741958100,-14400,America/New_York",
+            "DISKLAYOUT,1741958100,20",
+            "HOST,1741958100,db01.lab,5.14.0-427.13.1.el9_4.x86_64,x86_64,64",
+            "DISK,1741958100,8,16,sdb,52110,0,4168800,31022,1200,3,96000,880,0,31900,31902,310,0,2480,12",
+            "DISK,1741958100,259,0,nvme0n1,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,88012,41220",
+            "CPU,1741958100,1822035,1205,610233,48211022,30122,0,12011,0,0,3,1",
+            "MEM,1741958100,32617020,912204,20110204,0,0,0,0,0,0,0,0,0,0,0",
+            "NET,1741958100,eth0,9912203344,4410220112,1,2,3,4,5,6",
+            "NETX,1741958100,eth0,3,0,1,0,1203,25000,9000,up,4,full",
+            "NETX,1741958100,eth1,0,0,0,0,0,10000,1500,up,0",
+            "NETX,1741958100,veth1,0,0,0,0,0,-1,1500,down,0",
+            "MD,1741958100,md0,raid1,clean,0,2,resync,460800,2093056,11520,0,sdd;sda1",
+            "DRBD,1741958100,drbd0,SyncSource,Primary/Secondary,UpToDate/Inconsistent,1,2,3,4,524288,50.0,12288",
+            "FC,1741958100,host5,0x21000024ff7a1b2c,Online,16 Gbit,3,0,0,26,1,9912,8812",
+            "ISCSI,1741958100,session1,host3,iqn.2001-05.com.example:storage.lun1,LOGGED_IN,up,192.168.10.20:3260",
+            "SCSIDEV,1741958100,sdb,5:0:0:1",
+            "EVENT,1741958100,diskguard,pause,free 4.1%",
+            "XFUTURE,1741958100,a,b",
+        ] {
+            let rec = Record::parse(line).unwrap();
+            assert_eq!(rec.to_string(), line);
+            assert_eq!(rec.ts(), 1741958100);
+        }
+    }
+
+    #[test]
+    fn typed_fields() {
+        let Some(Record::Disk { stat, .. }) = Record::parse("DISK,1,8,0,sda,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15") else { panic!() };
+        assert_eq!((stat.name.as_str(), stat.weighted_io_time_ms, stat.flushes, stat.layout), ("sda", 11, None, 18));
+        let Some(Record::Mem { stat, .. }) = Record::parse("MEM,1,100,20,50") else { panic!() };
+        assert_eq!((stat.get("MemTotal"), stat.get("Cached"), stat.get("Bogus")), (Some(100), Some(0), None));
+        let Some(Record::Cpu { stat, .. }) = Record::parse("CPU,1,1,2,3,4,5,6,7,8,9,10,11") else { panic!() };
+        assert_eq!((stat.guest, stat.procs_running, stat.procs_blocked), (9, 10, 11));
+        assert!(Record::parse("#TYPE,ts_epoch,<fields...>").is_none());
+        assert!(Record::parse("DISK,1,8,0,sda,1,2").is_none());
+    }
+
+    #[test]
+    fn typed_state_records() {
+        let Some(Record::NetExt { iface, stat, .. }) = Record::parse("NETX,1,eth0,3,0,1,0,1203,-1,9000,down,4") else { panic!() };
+        assert_eq!((iface.as_str(), stat.rx_crc_errors, stat.speed_mbps, stat.operstate.as_str()), ("eth0", 3, -1, "down"));
+        assert_eq!(stat.speed_bytes(), None);
+        let Some(Record::Md { stat, .. }) = Record::parse("MD,1,md0,raid1,clean,1,2,recover,50,200,900,0,sda1;sdb1") else { panic!() };
+        assert_eq!((stat.members.len(), stat.is_syncing(), stat.sync_pct()), (2, true, Some(25.0)));
+        let Some(Record::Drbd { stat, .. }) = Record::parse("DRBD,1,drbd0,Connected,Primary/Secondary,UpToDate/UpToDate,0,0,0,0,0,0.0,0") else { panic!() };
+        assert!(!stat.is_syncing());
+        let Some(Record::ScsiDev { dev, hctl, .. }) = Record::parse("SCSIDEV,1,sdc,6:0:0:1") else { panic!() };
+        assert_eq!((dev.as_str(), hctl.as_str()), ("sdc", "6:0:0:1"));
+        // Commas in an event detail are written as ';' so the line stays one record
+        let ev = Record::Event { ts: 1, source: "diskguard".into(), action: "pruned".into(), detail: "removed a,b".into() };
+        assert_eq!(ev.to_string(), "EVENT,1,diskguard,pruned,removed a;b");
+        // Short state records are malformed, not passed through
+        let Some(Record::Host { host, release, arch, cpus, .. }) = Record::parse("HOST,1,db01,4.18.0-553.el8_10.ppc64le,ppc64le,8") else { panic!() };
+        assert_eq!((host.as_str(), release.as_str(), arch.as_str(), cpus), ("db01", "4.18.0-553.el8_10.ppc64le", "ppc64le", 8));
+        for bad in ["HOST,1,db01,5.14.0,x86_64", "HOST,1,db01,5.14.0,x86_64,many", "NETX,1,eth0,1,2", "MD,1,md0,raid1", "DRBD,1,drbd0", "FC,1,host5", "ISCSI,1,session1", "SCSIDEV,1,sdb", "EVENT,1,diskguard"] {
+            assert!(Record::parse(bad).is_none(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn disk_flush_fields_are_optional() {
+        let old = DiskStat::from_line("   8       0 sda 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15").unwrap();
+        assert_eq!(old.discard_time_ms, 15);
+        assert_eq!((old.flushes, old.flush_time_ms), (None, None));
+        let new = DiskStat::from_line("   8       0 sda 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17").unwrap();
+        assert_eq!((new.flushes, new.flush_time_ms), (Some(16), Some(17)));
+
+        // Captures from older kernels have no flush columns in the DISK record
+        let fields: Vec<&str> = "8,0,sda,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15".split(',').collect();
+        assert_eq!(DiskStat::from_csv_fields(&fields).unwrap().flushes, None);
+    }
+
+    #[test]
+    fn diskstats_layout_is_detected_per_line() {
+        // RHEL7: 14 fields, discard counters read as 0
+        let rhel7 = DiskStat::from_line("   8       0 sda 1 2 3 4 5 6 7 8 9 10 11").unwrap();
+        assert_eq!(rhel7.layout, 14);
+        assert_eq!(rhel7.weighted_io_time_ms, 11);
+        assert_eq!((rhel7.discards, rhel7.discard_time_ms, rhel7.flushes), (0, 0, None));
+        let rhel8 = DiskStat::from_line("   8       0 sda 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15").unwrap();
+        assert_eq!(rhel8.layout, 18);
+        let flush = DiskStat::from_line("   8       0 sda 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17").unwrap();
+        assert_eq!(flush.layout, 20);
+        assert!(DiskStat::from_line("   8       0 sda 1 2 3 4 5 6 7 8 9 10").is_none());
+    }
+
+    #[test]
+    fn proc_parsers() {
+        let stat = CpuStat::from_proc_stat("cpu  10 1 5 100 2 0 3 0 0 0\ncpu0 10 1 5 100 2 0 3 0 0 0\nprocs_running 4\nprocs_blocked 1\n").unwrap();
+        assert_eq!((stat.user, stat.softirq, stat.procs_running, stat.procs_blocked), (10, 3, 4, 1));
+        let mem = MemStat::from_meminfo("MemTotal:       32617020 kB\nMemFree:          912204 kB\nHugePages_Total: 0\n");
+        assert_eq!(mem.values[..2], [32617020, 912204]);
+        let net = NetStat::from_net_dev_line("  eth0: 100 10 1 2 0 0 0 0 200 20 3 4 0 0 0 0").unwrap();
+        assert_eq!((net.iface.as_str(), net.rx_bytes, net.tx_bytes, net.rx_drop, net.tx_errs), ("eth0", 100, 200, 2, 3));
+        assert!(NetStat::from_net_dev_line("Inter-|   Receive").is_none());
+    }
+}
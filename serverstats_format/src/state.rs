@@ -0,0 +1,305 @@
+/*!
+ * State Records
+ * -------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Typed NETX, MD, DRBD, FC and ISCSI samples. serverstats_grab reads them from
+ * sysfs and /proc at gather time; here they are parsed from and written as
+ * capture fields, so playback and analysis never split these lines by hand.
+ *
+ *    NETX,ts,iface,rx_crc_errors,rx_fifo_errors,rx_missed_errors,collisions,multicast,speed_mbps,mtu,operstate,carrier_changes[,duplex]
+ *    MD,ts,name,level,array_state,degraded,raid_disks,sync_action,sync_done,sync_total,sync_speed_kb,mismatch_cnt,members
+ *    DRBD,ts,name,cs,ro,ds,ns,nr,dw,dr,oos,sync_pct,speed_kb
+ *    FC,ts,host,port_name,port_state,speed,link_failure_count,loss_of_sync_count,loss_of_signal_count,invalid_crc_count,error_frames,tx_frames,rx_frames
+ *    ISCSI,ts,session,host,target,session_state,conn_state,address
+ *
+ * speed_mbps is -1 when the driver does not report it (virtual interfaces, link down).
+ * duplex is full, half or unknown; captures from before it was recorded leave it out.
+ * MD `members` is a ';' separated list of member block devices (e.g. sda1;sdb1),
+ * `sync_done`/`sync_total` are sectors from sync_completed, 0/0 when idle.
+ */
+
+/// Extended counters and link state for one interface (NETX).
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetExt {
+    pub rx_crc_errors: u64,
+    pub rx_fifo_errors: u64,
+    pub rx_missed_errors: u64,
+    pub collisions: u64,
+    pub multicast: u64,
+    pub speed_mbps: i64,
+    pub mtu: u32,
+    pub operstate: String,
+    pub carrier_changes: u64,
+    /// full, half or unknown; empty when the capture did not record it
+    pub duplex: String,
+}
+
+impl NetExt {
+    /// Parses the fields after `NETX,ts,iface,`.
+    pub fn from_fields(f: &[&str]) -> Option<Self> {
+        if f.len() < 9 { return None; }
+        Some(NetExt {
+            rx_crc_errors: f[0].parse().unwrap_or(0),
+            rx_fifo_errors: f[1].parse().unwrap_or(0),
+            rx_missed_errors: f[2].parse().unwrap_or(0),
+            collisions: f[3].parse().unwrap_or(0),
+            multicast: f[4].parse().unwrap_or(0),
+            speed_mbps: f[5].parse().unwrap_or(-1),
+            mtu: f[6].parse().unwrap_or(0),
+            operstate: f[7].to_string(),
+            carrier_changes: f[8].parse().unwrap_or(0),
+            duplex: f.get(9).map_or_else(String::new, |d| d.to_string()),
+        })
+    }
+
+    /// Link speed in bytes/sec, if known.
+    pub fn speed_bytes(&self) -> Option<f64> {
+        (self.speed_mbps > 0).then(|| self.speed_mbps as f64 * 1_000_000.0 / 8.0)
+    }
+
+    /// Human readable link changes between two samples: flaps, operstate, speed, duplex and MTU.
+    pub fn link_changes(&self, prev: &NetExt) -> Vec<String> {
+        let mut out = Vec::new();
+        let flaps = self.carrier_changes.saturating_sub(prev.carrier_changes);
+        if flaps > 0 {
+            out.push(format!("link flap: carrier_changes +{}", flaps));
+        }
+        if self.operstate != prev.operstate {
+            out.push(format!("operstate {} -> {}", prev.operstate, self.operstate));
+        }
+        if self.speed_mbps != prev.speed_mbps {
+            out.push(format!("speed {} -> {}", fmt_speed(prev.speed_mbps), fmt_speed(self.speed_mbps)));
+        }
+        if self.duplex != prev.duplex && !self.duplex.is_empty() && !prev.duplex.is_empty() {
+            out.push(format!("duplex {} -> {}", prev.duplex, self.duplex));
+        }
+        if self.mtu != prev.mtu {
+            out.push(format!("mtu {} -> {}", prev.mtu, self.mtu));
+        }
+        out
+    }
+}
+
+/// "25000Mb/s" or "unknown".
+pub fn fmt_speed(mbps: i64) -> String {
+    if mbps > 0 { format!("{}Mb/s", mbps) } else { "unknown".to_string() }
+}
+
+/// One md array sample (MD).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MdStatus {
+    pub name: String,
+    pub level: String,
+    pub array_state: String,
+    pub degraded: u32,
+    pub raid_disks: u32,
+    pub sync_action: String,
+    pub sync_done: u64,
+    pub sync_total: u64,
+    pub sync_speed_kb: u64,
+    pub mismatch_cnt: u64,
+    pub members: Vec<String>,
+}
+
+impl MdStatus {
+    /// True while the array is resyncing, recovering, reshaping or checking.
+    pub fn is_syncing(&self) -> bool {
+        !matches!(self.sync_action.as_str(), "idle" | "frozen" | "" | "-")
+    }
+
+    /// Sync progress in percent, if a sync is running.
+    pub fn sync_pct(&self) -> Option<f64> {
+        if self.sync_total > 0 {
+            Some(self.sync_done as f64 / self.sync_total as f64 * 100.0)
+        } else {
+            None
+        }
+    }
+
+    /// Parses the fields after `MD,ts,`.
+    pub fn from_fields(f: &[&str]) -> Option<Self> {
+        if f.len() < 11 { return None; }
+        Some(MdStatus {
+            name: f[0].to_string(),
+            level: f[1].to_string(),
+            array_state: f[2].to_string(),
+            degraded: f[3].parse().unwrap_or(0),
+            raid_disks: f[4].parse().unwrap_or(0),
+            sync_action: f[5].to_string(),
+            sync_done: f[6].parse().unwrap_or(0),
+            sync_total: f[7].parse().unwrap_or(0),
+            sync_speed_kb: f[8].parse().unwrap_or(0),
+            mismatch_cnt: f[9].parse().unwrap_or(0),
+            members: f[10].split(';').filter(|m| !m.is_empty()).map(|m| m.to_string()).collect(),
+        })
+    }
+}
+
+/// One DRBD resource sample (DRBD).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrbdStatus {
+    /// drbd<minor>, matching the DISK record name
+    pub name: String,
+    /// Connection state, e.g. Connected, SyncSource, SyncTarget, StandAlone
+    pub cs: String,
+    /// Roles local/peer, e.g. Primary/Secondary
+    pub ro: String,
+    /// Disk states local/peer, e.g. UpToDate/Inconsistent
+    pub ds: String,
+    pub ns: u64,
+    pub nr: u64,
+    pub dw: u64,
+    pub dr: u64,
+    /// Out of sync KiB
+    pub oos: u64,
+    pub sync_pct: f64,
+    pub speed_kb: u64,
+}
+
+impl DrbdStatus {
+    /// True while this resource is resyncing or verifying.
+    pub fn is_syncing(&self) -> bool {
+        self.cs.starts_with("Sync") || self.cs.starts_with("Verify") || self.cs.starts_with("PausedSync")
+    }
+
+    /// Parses the fields after `DRBD,ts,`.
+    pub fn from_fields(f: &[&str]) -> Option<Self> {
+        if f.len() < 11 { return None; }
+        Some(DrbdStatus {
+            name: f[0].to_string(),
+            cs: f[1].to_string(),
+            ro: f[2].to_string(),
+            ds: f[3].to_string(),
+            ns: f[4].parse().unwrap_or(0),
+            nr: f[5].parse().unwrap_or(0),
+            dw: f[6].parse().unwrap_or(0),
+            dr: f[7].parse().unwrap_or(0),
+            oos: f[8].parse().unwrap_or(0),
+            sync_pct: f[9].parse().unwrap_or(0.0),
+            speed_kb: f[10].parse().unwrap_or(0),
+        })
+    }
+}
+
+/// One fc_host sample (FC).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FcHost {
+    /// hostN
+    pub host: String,
+    pub port_name: String,
+    pub port_state: String,
+    pub speed: String,
+    pub link_failure_count: u64,
+    pub loss_of_sync_count: u64,
+    pub loss_of_signal_count: u64,
+    pub invalid_crc_count: u64,
+    pub error_frames: u64,
+    pub tx_frames: u64,
+    pub rx_frames: u64,
+}
+
+impl FcHost {
+    /// Parses the fields after `FC,ts,`.
+    pub fn from_fields(f: &[&str]) -> Option<Self> {
+        if f.len() < 11 { return None; }
+        let n = |i: usize| f[i].parse::<u64>().unwrap_or(0);
+        Some(FcHost {
+            host: f[0].to_string(),
+            port_name: f[1].to_string(),
+            port_state: f[2].to_string(),
+            speed: f[3].to_string(),
+            link_failure_count: n(4),
+            loss_of_sync_count: n(5),
+            loss_of_signal_count: n(6),
+            invalid_crc_count: n(7),
+            error_frames: n(8),
+            tx_frames: n(9),
+            rx_frames: n(10),
+        })
+    }
+
+    /// Error counters worth flagging when they move.
+    pub fn error_counters(&self) -> [(&'static str, u64); 5] {
+        [
+            ("link_failure", self.link_failure_count),
+            ("loss_of_sync", self.loss_of_sync_count),
+            ("loss_of_signal", self.loss_of_signal_count),
+            ("invalid_crc", self.invalid_crc_count),
+            ("error_frames", self.error_frames),
+        ]
+    }
+}
+
+/// One iSCSI session with its first connection (ISCSI).
+#[derive(Debug, Clone, PartialEq)]
+pub struct IscsiSession {
+    /// sessionN
+    pub session: String,
+    /// SCSI host the session is attached to (hostN), empty if unknown
+    pub host: String,
+    pub target: String,
+    pub state: String,
+    pub conn_state: String,
+    /// portal address:port
+    pub address: String,
+}
+
+impl IscsiSession {
+    /// Parses the fields after `ISCSI,ts,`.
+    pub fn from_fields(f: &[&str]) -> Option<Self> {
+        if f.len() < 6 { return None; }
+        Some(IscsiSession {
+            session: f[0].to_string(),
+            host: f[1].to_string(),
+            target: f[2].to_string(),
+            state: f[3].to_string(),
+            conn_state: f[4].to_string(),
+            address: f[5].to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn netx(carrier_changes: u64, operstate: &str, speed_mbps: i64, duplex: &str, mtu: u32) -> NetExt {
+        NetExt {
+            rx_crc_errors: 0, rx_fifo_errors: 0, rx_missed_errors: 0, collisions: 0, multicast: 0,
+            speed_mbps, mtu, operstate: operstate.to_string(), carrier_changes, duplex: duplex.to_string(),
+        }
+    }
+
+    #[test]
+    fn link_changes_between_samples() {
+        let up = netx(4, "up", 25000, "full", 9000);
+        let cases: &[(&str, NetExt, &[&str])] = &[
+            ("steady", up.clone(), &[]),
+            ("flap, link back up", netx(6, "up", 25000, "full", 9000), &["link flap: carrier_changes +2"]),
+            // Counters reset when the driver reloads; that is not a flap
+            ("counter reset", netx(0, "up", 25000, "full", 9000), &[]),
+            ("link down", netx(5, "down", -1, "unknown", 9000),
+             &["link flap: carrier_changes +1", "operstate up -> down", "speed 25000Mb/s -> unknown", "duplex full -> unknown"]),
+            ("renegotiated", netx(6, "up", 1000, "half", 9000),
+             &["link flap: carrier_changes +2", "speed 25000Mb/s -> 1000Mb/s", "duplex full -> half"]),
+            ("mtu", netx(4, "up", 25000, "full", 1500), &["mtu 9000 -> 1500"]),
+            // Older captures have no duplex field
+            ("no duplex recorded", netx(4, "up", 25000, "", 9000), &[]),
+        ];
+        for (what, cur, want) in cases {
+            assert_eq!(cur.link_changes(&up), *want, "{}", what);
+        }
+    }
+
+    #[test]
+    fn netx_fields() {
+        let f = ["3", "0", "1", "0", "1203", "x", "9000", "up", "4"];
+        let old = NetExt::from_fields(&f).unwrap();
+        assert_eq!((old.speed_mbps, old.speed_bytes(), old.duplex.as_str()), (-1, None, ""));
+        let new = NetExt::from_fields(&[&f[..5], &["10000", "9000", "up", "4", "full"]].concat()).unwrap();
+        assert_eq!((new.speed_bytes(), new.duplex.as_str()), (Some(1_250_000_000.0), "full"));
+        assert!(NetExt::from_fields(&f[..8]).is_none());
+        assert_eq!((fmt_speed(25000), fmt_speed(0), fmt_speed(-1)), ("25000Mb/s".to_string(), "unknown".to_string(), "unknown".to_string()));
+    }
+}
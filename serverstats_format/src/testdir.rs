@@ -0,0 +1,89 @@
+/*!
+ * Test Directories
+ * ----------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Scratch directories for the tests of this crate and the tools built on it
+ * (feature `test-util`): filled from (path, text) pairs and removed on drop,
+ * so a failing assertion does not leave them behind in the temp directory.
+ */
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory under the system temp directory, removed when dropped.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// An empty directory named after `name`, this process and a sequence number.
+    pub fn new(name: &str) -> Self {
+        let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("{}-{}-{}", name, std::process::id(), seq));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    /// A directory holding `files`, paths relative to it.
+    pub fn with_files(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = TempDir::new(name);
+        for (rel, text) in files {
+            dir.write(rel, text);
+        }
+        dir
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// `<dir>/<rel>`
+    pub fn join(&self, rel: impl AsRef<Path>) -> PathBuf {
+        self.path.join(rel)
+    }
+
+    /// Writes `contents` to `rel`, creating its parent directories; returns the full path.
+    pub fn write(&self, rel: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.path.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// Creates the directory `rel` and its parents; returns the full path.
+    pub fn mkdir(&self, rel: &str) -> PathBuf {
+        let path = self.path.join(rel);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_on_drop_even_after_a_panic() {
+        let dir = TempDir::with_files("testdir", &[("proc/mdstat", "md0 : active\n"), ("a", "")]);
+        assert_eq!(fs::read_to_string(dir.join("proc/mdstat")).unwrap(), "md0 : active\n");
+        assert_ne!(TempDir::new("testdir").path(), dir.path());
+        let path = dir.path().to_path_buf();
+        let r = std::panic::catch_unwind(move || {
+            let _dir = dir;
+            panic!("assertion failed");
+        });
+        assert!(r.is_err() && !path.exists());
+    }
+}
@@ -0,0 +1,442 @@
+/*!
+ * Time Handling Module
+ * --------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Capture timestamps are UTC epoch seconds. The gatherer also writes TZ records
+ * (`TZ,<ts>,<utc_offset_secs>,<zone_name>`) at start and whenever the host's UTC
+ * offset changes, so playback and analysis show the capture host's wall clock
+ * no matter which timezone the analyst is sitting in.
+ *
+ * Time specifications accepted by --from / --to:
+ *    HH:MM[:SS]                          first occurrence at or after the capture start
+ *                                        (for --to: at or after --from)
+ *    YYYY-MM-DD[THH:MM[:SS]][Z|+HH:MM]   absolute date-time, display timezone unless an offset is given
+ *    +30s  +30m  +2h  +1d  +1h30m        relative to the capture start
+ *    @<epoch>                            raw epoch seconds
+ *
+ * Display timezone (--tz / --utc):
+ *    host (default), local, UTC, +05:30, or an IANA zone name such as Europe/Berlin
+ *
+ * Shared by serverstats_grab and procstats_grab; procstats reads the same TZ
+ * records from `#TZ` comment lines of its CSV and feeds them to `note_host_tz`.
+ */
+
+use std::fs;
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use crate::{Reader, Record};
+
+/// Timezone used to display and interpret wall-clock times.
+#[derive(Debug, Clone)]
+pub enum DisplayTz {
+    /// Timezone recorded by the gatherer on the capture host (falls back to local).
+    Host,
+    /// Timezone of the machine running the playback/analysis.
+    Local,
+    /// Fixed UTC offset (includes UTC itself).
+    Fixed(FixedOffset),
+    /// IANA zone, DST aware.
+    Named(Tz),
+}
+
+impl DisplayTz {
+    /// Parses a --tz argument: host, local, UTC, +HH:MM / -HHMM, or an IANA zone name.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "host" => return Ok(DisplayTz::Host),
+            "local" => return Ok(DisplayTz::Local),
+            "UTC" | "utc" | "Z" | "GMT" => return Ok(DisplayTz::Fixed(FixedOffset::east_opt(0).unwrap())),
+            _ => {}
+        }
+        if s.starts_with('+') || s.starts_with('-') {
+            return parse_offset(s)
+                .map(DisplayTz::Fixed)
+                .ok_or_else(|| format!("invalid UTC offset '{}' (expected +HH:MM)", s));
+        }
+        s.parse::<Tz>()
+            .map(DisplayTz::Named)
+            .map_err(|_| format!("unknown timezone '{}'", s))
+    }
+}
+
+/// Parses "+05:30", "-0800", "+5" into a FixedOffset.
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let sign = if s.starts_with('-') { -1 } else { 1 };
+    let body = s.get(1..)?;
+    // Digits and ':' only, so the slicing below stays on char boundaries
+    if body.is_empty() || !body.bytes().all(|b| b.is_ascii_digit() || b == b':') { return None; }
+    let (h, m) = if let Some((h, m)) = body.split_once(':') {
+        (h.parse::<i32>().ok()?, m.parse::<i32>().ok()?)
+    } else if body.len() == 4 {
+        (body[..2].parse::<i32>().ok()?, body[2..].parse::<i32>().ok()?)
+    } else {
+        (body.parse::<i32>().ok()?, 0)
+    };
+    if h > 23 || m > 59 { return None; }
+    FixedOffset::east_opt(sign * (h * 3600 + m * 60))
+}
+
+/// Formats a UTC offset as UTC+HH:MM.
+fn offset_label(secs: i32) -> String {
+    let sign = if secs < 0 { '-' } else { '+' };
+    let a = secs.abs();
+    format!("UTC{}{:02}:{:02}", sign, a / 3600, (a % 3600) / 60)
+}
+
+/// Display timezone plus what we know about the capture (host offsets, start time).
+#[derive(Debug, Clone)]
+pub struct TimeCtx {
+    display: DisplayTz,
+    /// (ts, utc offset seconds) transitions from TZ records, in capture order.
+    host_offsets: Vec<(u64, i32)>,
+    host_name: String,
+    /// Timestamp of the first data record in the capture.
+    pub start_ts: Option<u64>,
+}
+
+impl TimeCtx {
+    pub fn new(display: DisplayTz) -> Self {
+        TimeCtx { display, host_offsets: Vec::new(), host_name: String::new(), start_ts: None }
+    }
+
+    /// Reads the head of a capture: TZ records written before the first sample
+    /// and the timestamp of that first sample.
+    pub fn for_capture(path: &str, display: DisplayTz) -> std::io::Result<Self> {
+        let mut ctx = TimeCtx::new(display);
+        for rec in Reader::open(path)?.map_while(Result::ok) {
+            match rec {
+                Record::Tz { ts, offset, zone } => ctx.note_host_tz(ts, offset, &zone),
+                Record::Event { .. } | Record::DiskLayout { .. } | Record::Host { .. } => {}
+                rec => {
+                    ctx.start_ts = Some(rec.ts());
+                    break;
+                }
+            }
+        }
+        Ok(ctx)
+    }
+
+    /// Records the capture host's UTC offset (seconds) and zone name from `ts` on.
+    pub fn note_host_tz(&mut self, ts: u64, offset: i32, zone: &str) {
+        if !zone.is_empty() {
+            self.host_name = zone.to_string();
+        }
+        if self.host_offsets.last().map(|(_, o)| *o) != Some(offset) {
+            self.host_offsets.push((ts, offset));
+        }
+    }
+
+    /// True when the capture carried TZ records.
+    pub fn has_host_tz(&self) -> bool {
+        !self.host_offsets.is_empty()
+    }
+
+    fn host_offset_at(&self, ts: u64) -> Option<i32> {
+        let idx = self.host_offsets.partition_point(|(t, _)| *t <= ts);
+        let (_, off) = self.host_offsets.get(idx.saturating_sub(1))?;
+        Some(*off)
+    }
+
+    /// UTC offset used to display `ts`.
+    pub fn offset_at(&self, ts: u64) -> FixedOffset {
+        let utc = DateTime::<Utc>::from_timestamp(ts as i64, 0).unwrap_or_default();
+        match &self.display {
+            DisplayTz::Host => match self.host_offset_at(ts) {
+                Some(off) => FixedOffset::east_opt(off).unwrap_or(utc.with_timezone(&Local).offset().fix()),
+                None => utc.with_timezone(&Local).offset().fix(),
+            },
+            DisplayTz::Local => utc.with_timezone(&Local).offset().fix(),
+            DisplayTz::Fixed(off) => *off,
+            DisplayTz::Named(tz) => utc.with_timezone(tz).offset().fix(),
+        }
+    }
+
+    /// Capture timestamp as a date-time in the display timezone.
+    pub fn datetime(&self, ts: u64) -> DateTime<FixedOffset> {
+        let utc = DateTime::<Utc>::from_timestamp(ts as i64, 0).unwrap_or_default();
+        utc.with_timezone(&self.offset_at(ts))
+    }
+
+    /// HH:MM:SS in the display timezone.
+    pub fn hms(&self, ts: u64) -> String {
+        self.datetime(ts).format("%H:%M:%S").to_string()
+    }
+
+    /// Arbitrary strftime format in the display timezone.
+    pub fn format(&self, ts: u64, fmt: &str) -> String {
+        self.datetime(ts).format(fmt).to_string()
+    }
+
+    /// Human readable name of the display timezone, e.g. "host UTC+01:00 Europe/London".
+    pub fn label(&self) -> String {
+        let off = offset_label(self.offset_at(self.start_ts.unwrap_or(0)).local_minus_utc());
+        match &self.display {
+            DisplayTz::Host if self.has_host_tz() => {
+                if self.host_name.is_empty() { format!("host {}", off) } else { format!("host {} {}", off, self.host_name) }
+            }
+            DisplayTz::Host | DisplayTz::Local => format!("local {}", off),
+            DisplayTz::Fixed(_) => off,
+            DisplayTz::Named(tz) => format!("{} {}", tz.name(), off),
+        }
+    }
+
+    /// Note for stderr when host times differ from the analyst's local clock.
+    pub fn host_tz_note(&self) -> Option<String> {
+        let ts = self.start_ts?;
+        if !matches!(self.display, DisplayTz::Host) || !self.has_host_tz() { return None; }
+        let local = self.datetime(ts).with_timezone(&Local).offset().fix();
+        if local == self.offset_at(ts) { return None; }
+        Some(format!("NOTE: times shown in capture host timezone ({}); use --tz local or --utc to change", self.label()))
+    }
+
+    /// Interprets a naive wall-clock date-time in the display timezone.
+    fn localize(&self, naive: NaiveDateTime) -> Option<u64> {
+        let ts = match &self.display {
+            DisplayTz::Host => match self.host_offset_at(self.start_ts.unwrap_or(0)) {
+                Some(start_off) => {
+                    let at = |off| FixedOffset::east_opt(off)?.from_local_datetime(&naive).earliest().map(|d| d.timestamp());
+                    // Then with the offset recorded at that time, so times after a DST change are not off by the shift
+                    let first = at(start_off)?;
+                    let off = u64::try_from(first).ok().and_then(|t| self.host_offset_at(t)).unwrap_or(start_off);
+                    at(off)?
+                }
+                None => Local.from_local_datetime(&naive).earliest()?.timestamp(),
+            },
+            DisplayTz::Local => Local.from_local_datetime(&naive).earliest()?.timestamp(),
+            DisplayTz::Fixed(off) => off.from_local_datetime(&naive).earliest()?.timestamp(),
+            DisplayTz::Named(tz) => tz.from_local_datetime(&naive).earliest()?.timestamp(),
+        };
+        u64::try_from(ts).ok()
+    }
+
+    /// Resolves one --from/--to specification to an epoch. `anchor` is the
+    /// earliest acceptable time for bare HH:MM:SS values.
+    pub fn parse_spec(&self, spec: &str, anchor: Option<u64>) -> Result<u64, String> {
+        let s = spec.trim();
+        if let Some(rel) = s.strip_prefix('+') {
+            let start = self.start_ts.ok_or("relative time needs a capture with data")?;
+            return parse_duration(rel)
+                .and_then(|d| start.checked_add(d))
+                .ok_or_else(|| format!("invalid relative time '{}' (e.g. +30m, +2h, +1h30m)", spec));
+        }
+        if let Some(epoch) = s.strip_prefix('@') {
+            return epoch.parse::<u64>().map_err(|_| format!("invalid epoch '{}'", spec));
+        }
+        if s.len() >= 10 && s.as_bytes()[4] == b'-' {
+            return self.parse_datetime(s).ok_or_else(|| {
+                format!("invalid date-time '{}' (expected YYYY-MM-DD[THH:MM[:SS]][Z|+HH:MM])", spec)
+            });
+        }
+        // Bare time of day
+        let t = NaiveTime::parse_from_str(s, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M"))
+            .map_err(|_| format!("Time must be in HH:MM:SS format (e.g. 13:15:01), got '{}'", spec))?;
+        let anchor = anchor.or(self.start_ts).ok_or("time of day needs a capture with data")?;
+        let day = self.datetime(anchor).date_naive();
+        let mut ts = self.localize(day.and_time(t)).ok_or_else(|| format!("'{}' does not exist on {}", spec, day))?;
+        if ts < anchor {
+            let next = day.succ_opt().ok_or("date out of range")?;
+            ts = self.localize(next.and_time(t)).ok_or_else(|| format!("'{}' does not exist on {}", spec, next))?;
+        }
+        Ok(ts)
+    }
+
+    fn parse_datetime(&self, s: &str) -> Option<u64> {
+        let s = s.replacen(' ', "T", 1);
+        // Explicit offset or Z
+        for fmt in ["%Y-%m-%dT%H:%M:%S%:z", "%Y-%m-%dT%H:%M:%S%z", "%Y-%m-%dT%H:%M%:z", "%Y-%m-%dT%H:%M%z"] {
+            if let Ok(dt) = DateTime::parse_from_str(&s, fmt) {
+                return u64::try_from(dt.timestamp()).ok();
+            }
+        }
+        if let Some(body) = s.strip_suffix('Z') {
+            let naive = parse_naive(body)?;
+            return u64::try_from(naive.and_utc().timestamp()).ok();
+        }
+        self.localize(parse_naive(&s)?)
+    }
+}
+
+fn parse_naive(s: &str) -> Option<NaiveDateTime> {
+    for fmt in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Some(dt);
+        }
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").ok().map(|d| d.and_time(NaiveTime::MIN))
+}
+
+/// Parses "30m", "2h", "1h30m", "90s", "1d" into seconds.
+pub fn parse_duration(s: &str) -> Option<u64> {
+    let mut total = 0u64;
+    let mut num = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            num.push(c);
+            continue;
+        }
+        let n: u64 = num.parse().ok()?;
+        num.clear();
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return None,
+        };
+        total = total.checked_add(n.checked_mul(unit)?)?;
+    }
+    if !num.is_empty() {
+        // Bare number means minutes ("+30")
+        total = total.checked_add(num.parse::<u64>().ok()?.checked_mul(60)?)?;
+    }
+    if s.is_empty() { None } else { Some(total) }
+}
+
+/// Inclusive [from, to] epoch window for playback and analysis.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeWindow {
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+}
+
+impl TimeWindow {
+    pub fn resolve(from: Option<&str>, to: Option<&str>, ctx: &TimeCtx) -> Result<Self, String> {
+        let from = from.map(|s| ctx.parse_spec(s, None)).transpose()?;
+        let to = to.map(|s| ctx.parse_spec(s, from)).transpose()?;
+        if let (Some(f), Some(t)) = (from, to) && t < f {
+            return Err("--to is before --from".to_string());
+        }
+        Ok(TimeWindow { from, to })
+    }
+
+    pub fn contains(&self, ts: u64) -> bool {
+        self.from.is_none_or(|f| ts >= f) && self.to.is_none_or(|t| ts <= t)
+    }
+
+    /// True once `ts` is past the end of the window (the rest of the capture can be skipped).
+    pub fn is_past(&self, ts: u64) -> bool {
+        self.to.is_some_and(|t| ts > t)
+    }
+}
+
+/// Timezone name of this host: $TZ, /etc/localtime link target, or /etc/timezone.
+pub fn host_tz_name() -> String {
+    if let Ok(tz) = std::env::var("TZ") {
+        let tz = tz.trim_start_matches(':');
+        if !tz.is_empty() { return tz.to_string(); }
+    }
+    if let Ok(target) = fs::read_link("/etc/localtime") {
+        let t = target.to_string_lossy();
+        if let Some(idx) = t.find("zoneinfo/") {
+            return t[idx + "zoneinfo/".len()..].to_string();
+        }
+    }
+    fs::read_to_string("/etc/timezone")
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Current UTC offset of this host in seconds.
+pub fn host_utc_offset() -> i32 {
+    Local::now().offset().local_minus_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2025-03-14T13:15:00Z
+    const START: u64 = 1741958100;
+
+    fn ctx(display: DisplayTz) -> TimeCtx {
+        let mut ctx = TimeCtx::new(display);
+        ctx.start_ts = Some(START);
+        ctx
+    }
+
+    fn utc() -> TimeCtx {
+        ctx(DisplayTz::parse("UTC").unwrap())
+    }
+
+    #[test]
+    fn offsets() {
+        let secs = |s| parse_offset(s).map(|o| o.local_minus_utc());
+        assert_eq!(secs("+05:30"), Some(19800));
+        assert_eq!(secs("-0800"), Some(-28800));
+        assert_eq!(secs("+5"), Some(18000));
+        assert_eq!(secs("-00:00"), Some(0));
+        for bad in ["+", "-", "+24", "+05:60", "+-5", "+5:", "+1é1", "+é", "+0é30", "+05:3é"] {
+            assert_eq!(secs(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("90s"), Some(90));
+        assert_eq!(parse_duration("30m"), Some(1800));
+        assert_eq!(parse_duration("1h30m"), Some(5400));
+        assert_eq!(parse_duration("1d"), Some(86400));
+        assert_eq!(parse_duration("30"), Some(1800));
+        assert_eq!(parse_duration("1h30"), Some(5400));
+        for bad in ["", "h", "5x", "1h x", "5é", "99999999999999999999s", "5124095576030432h", "307445734561825861", "1d18446744073709551615s"] {
+            assert_eq!(parse_duration(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn specs() {
+        let c = utc();
+        assert_eq!(c.parse_spec("+30m", None), Ok(START + 1800));
+        assert_eq!(c.parse_spec("@1741960800", None), Ok(1741960800));
+        assert_eq!(c.parse_spec("2025-03-14T14:00:00Z", None), Ok(1741960800));
+        assert_eq!(c.parse_spec("2025-03-14T15:00:00+01:00", None), Ok(1741960800));
+        assert_eq!(c.parse_spec("2025-03-14 14:00", None), Ok(1741960800));
+        assert_eq!(c.parse_spec("2025-03-14", None), Ok(1741910400));
+        // Time of day after the anchor is the same day, before it the next day
+        assert_eq!(c.parse_spec("14:00", None), Ok(1741960800));
+        assert_eq!(c.parse_spec("13:00:00", None), Ok(1742043600));
+        assert_eq!(c.parse_spec("13:00", Some(1741957200)), Ok(1741957200));
+        // Display timezone applies to wall-clock times
+        let berlin = ctx(DisplayTz::parse("Europe/Berlin").unwrap());
+        assert_eq!(berlin.parse_spec("15:00", None), Ok(1741960800));
+        for bad in ["+", "+5x", "+18446744073709551615s", "@abc", "@-1", "2025-13-40", "2025-03-14T25:00", "25:00", "1é:00", "soon"] {
+            assert!(c.parse_spec(bad, None).is_err(), "{}", bad);
+        }
+        assert!(TimeCtx::new(DisplayTz::Local).parse_spec("+30m", None).is_err());
+    }
+
+    #[test]
+    fn display_labels() {
+        assert!(matches!(DisplayTz::parse("host"), Ok(DisplayTz::Host)));
+        assert!(matches!(DisplayTz::parse("local"), Ok(DisplayTz::Local)));
+        for bad in ["+1é1", "+25:00", "Mars/Olympus", "", "é"] {
+            assert!(DisplayTz::parse(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(utc().label(), "UTC+00:00");
+        assert_eq!(ctx(DisplayTz::parse("+05:30").unwrap()).label(), "UTC+05:30");
+        assert_eq!(ctx(DisplayTz::parse("-0800").unwrap()).label(), "UTC-08:00");
+        assert_eq!(ctx(DisplayTz::parse("Europe/Berlin").unwrap()).label(), "Europe/Berlin UTC+01:00");
+        // Host without TZ records falls back to the local clock
+        assert!(ctx(DisplayTz::Host).label().starts_with("local UTC"));
+        let mut host = ctx(DisplayTz::Host);
+        host.note_host_tz(START, -14400, "America/New_York");
+        assert_eq!(host.label(), "host UTC-04:00 America/New_York");
+        assert_eq!(host.parse_spec("10:00", None), Ok(1741960800));
+    }
+
+    #[test]
+    fn host_window_after_dst_change() {
+        // Berlin capture from 2025-03-29T12:00Z; clocks went to CEST at 2025-03-30T01:00Z
+        let mut host = TimeCtx::new(DisplayTz::Host);
+        host.start_ts = Some(1743249600);
+        host.note_host_tz(1743249600, 3600, "Europe/Berlin");
+        host.note_host_tz(1743296400, 7200, "Europe/Berlin");
+        assert_eq!(host.parse_spec("2025-03-29T23:00", None), Ok(1743285600));
+        assert_eq!(host.parse_spec("2025-03-30T10:00", None), Ok(1743321600));
+        assert_eq!(host.parse_spec("10:00", None), Ok(1743321600));
+        assert_eq!(host.hms(1743321600), "10:00:00");
+    }
+}
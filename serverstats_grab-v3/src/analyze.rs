@@ -26,398 +26,430 @@
  *  All output is self-contained in a new output directory.
  *  - Disk graphs: per device & metric (SVG + PNG)
  *  - CPU and Memory: single chart each (SVG + PNG)
- *  - "Top 50" tables for disk metrics (avg/peak)
+ *  - "Top 50" tables for disk metrics (avg/peak, and ranked by p95/p99 with
+ *    the full distribution), and a percentile summary per device
+ *  - FC/iSCSI events over per-host path I/O, when the capture has them
+ *  - Findings (high await, queue saturation, iowait, memory/swap,
+ *    network drops/errors, throughput collapse) as the first dashboard
+ *    tab and findings.txt / findings.json (see findings.rs)
+ *  - Time-lagged correlation between disk, network, CPU and memory
+ *    series with overlay charts (see correlate.rs)
+ *  - Discard/flush metrics are marked "not available" when the capture
+ *    kernel's diskstats did not have them (DISKLAYOUT records)
+ *  - Optional scope: --from/--to window and device/interface filters,
+ *    shown in the dashboard header
+ *  - One streaming pass with bounded memory, graphs rendered in parallel,
+ *    with progress and timing (see stream.rs)
  *  - Dynamic index.html for browsing
  */
 
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufReader, Write};
 use std::collections::HashMap;
+use std::time::Instant;
 use plotters::prelude::*;
-use crate::DiskStat;
-use chrono::TimeZone;
+use rayon::prelude::*;
+use serverstats_format::{CpuPercent, CpuStat, DiskDelta, DiskStat, MemPercent, MemStat, NetDelta, NetStat, Reader, Record};
+use serverstats_format::output::json_str;
+use serverstats_format::timeutil::{TimeCtx, TimeWindow};
+use crate::bucket::{self, Agg};
+use crate::devselect::{DevSelect, IfaceSelect};
+use crate::correlate;
+use crate::findings;
+use crate::netext::NetExt;
+use crate::compare::{Capture, Summary};
+use crate::percentile::{self, DistAcc, Distribution};
+use crate::raid::{SyncTracker, SyncWindow};
+use crate::report::CPU_CHARTS;
+use crate::stream::{self, CountingReader, MemTracker, Progress, Tracker};
+use crate::transport::{host_of_hctl, TransportEvent, TransportTracker};
 
 // ==================== Structs ====================
 
 /// Per-interval computed disk metrics for plotting/stats
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct IntervalDiskMetrics {
-    ts: u64,
+    pub(crate) ts: u64,
     pub(crate) rps: f64,       // Read IOPS/sec
     pub(crate) wps: f64,       // Write IOPS/sec
     pub(crate) io_sec: f64,    // Total IO/sec
     pub(crate) rd_kbs: f64,    // Read KB/sec
     pub(crate) wr_kbs: f64,    // Write KB/sec
     pub(crate) kb_sec: f64,    // Total KB/sec
-    avg_queue_depth: f64, // <-- Rename this from qlen: for your interval-based calculation
-    qlen: f64,      // <-- New: collectl/iostat-style (delta_weighted_io_time / delta_io_time)
-    svctim: f64,    // calclated service time
-    await_rd: f64,  // Average read await (ms)
-    await_wr: f64,  // Average write await (ms)
-    discards_s: f64,         // New: Discard IOs/sec
-    discards_merged_s: f64,  // New: Discard merges/sec
-    sectors_discarded_s: f64,// New: Discard sectors/sec (or KB/sec if you like)
-    discard_kbs: f64,
-    await_discard_ms: f64,   // New: Discard await time (ms)
+    pub(crate) avg_queue_depth: f64, // <-- Rename this from qlen: for your interval-based calculation
+    pub(crate) qlen: f64,      // <-- New: collectl/iostat-style (delta_weighted_io_time / delta_io_time)
+    pub(crate) svctim: f64,    // calclated service time
+    pub(crate) await_rd: f64,  // Average read await (ms)
+    pub(crate) await_wr: f64,  // Average write await (ms)
+    pub(crate) discards_s: f64,         // New: Discard IOs/sec
+    pub(crate) discards_merged_s: f64,  // New: Discard merges/sec
+    pub(crate) sectors_discarded_s: f64,// New: Discard sectors/sec (or KB/sec if you like)
+    pub(crate) discard_kbs: f64,
+    pub(crate) await_discard_ms: f64,   // New: Discard await time (ms)
+    pub(crate) flush_s: f64,            // Flush requests/sec (kernel 5.5+)
+    pub(crate) await_flush_ms: f64,     // Flush await time (ms)
+}
+
+impl IntervalDiskMetrics {
+    /// Rates for the interval ending at `ts`. Flush rates are 0 when the kernel has no flush counters.
+    pub(crate) fn from_delta(ts: u64, d: &DiskDelta) -> Self {
+        IntervalDiskMetrics {
+            ts,
+            rps: d.r_s(),
+            wps: d.w_s(),
+            io_sec: d.io_s(),
+            rd_kbs: d.rd_kbs(),
+            wr_kbs: d.wr_kbs(),
+            kb_sec: d.kb_s(),
+            avg_queue_depth: d.avg_queue_depth(),
+            qlen: d.qlen(),
+            svctim: d.svctim(),
+            await_rd: d.await_rd(),
+            await_wr: d.await_wr(),
+            discards_s: d.discards_s(),
+            discards_merged_s: d.discards_merged_s(),
+            sectors_discarded_s: d.sectors_discarded_s(),
+            discard_kbs: d.discard_kbs(),
+            await_discard_ms: d.await_discard(),
+            flush_s: d.flush_s().unwrap_or(0.0),
+            await_flush_ms: d.await_flush().unwrap_or(0.0),
+        }
+    }
 }
 
 /// Per-interval CPU utilization summary
-#[derive(Debug, Clone)]
-struct CpuMetrics {
-    ts: u64,
-    user: f64,
-    sys: f64,
-    idle: f64,
-    iowait: f64,
-    guest: f64,
-    running: Option<u64>, 
-    blocked: Option<u64>,
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CpuMetrics {
+    pub(crate) ts: u64,
+    pub(crate) user: f64,
+    pub(crate) sys: f64,
+    pub(crate) idle: f64,
+    pub(crate) iowait: f64,
+    pub(crate) guest: f64,
+    pub(crate) running: Option<u64>, 
+    pub(crate) blocked: Option<u64>,
 }
 
 /// Per-interval Memory usage summary (percentages)
-#[derive(Debug, Clone)]
-struct MemMetrics {
-    ts: u64,
-    used_percent: f64,
-    avail_percent: f64,
-    cached_percent: f64,
-    free_percent: f64,
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MemMetrics {
+    pub(crate) ts: u64,
+    pub(crate) used_percent: f64,
+    pub(crate) avail_percent: f64,
+    pub(crate) cached_percent: f64,
+    pub(crate) free_percent: f64,
+    pub(crate) swap_used_kb: f64,  // SwapTotal - SwapFree
+    pub(crate) dirty_kb: f64,
+    pub(crate) writeback_kb: f64,
 }
 
 /// Per-interval computed NET metrics for plotting/stats
-#[derive(Debug, Clone)]
-struct IntervalNetMetrics {
-    ts: u64,
-    rx_bytes: f64,
-    tx_bytes: f64,
-    rx_pkts: f64,
-    tx_pkts: f64,
-    rx_errs: f64,
-    tx_errs: f64,
-    rx_drop: f64,
-    tx_drop: f64,
-}
-#[derive(Debug, Clone)]
-struct NetStat {
-    rx_bytes: u64,
-    rx_pkts: u64,
-    rx_errs: u64,
-    rx_drop: u64,
-    tx_bytes: u64,
-    tx_pkts: u64,
-    tx_errs: u64,
-    tx_drop: u64,
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IntervalNetMetrics {
+    pub(crate) ts: u64,
+    pub(crate) rx_bytes: f64,
+    pub(crate) tx_bytes: f64,
+    pub(crate) rx_pkts: f64,
+    pub(crate) tx_pkts: f64,
+    pub(crate) rx_errs: f64,
+    pub(crate) tx_errs: f64,
+    pub(crate) rx_drop: f64,
+    pub(crate) tx_drop: f64,
+    // From NETX records (0 when the capture has none)
+    pub(crate) rx_util: f64,   // RX bytes/sec as % of link speed
+    pub(crate) tx_util: f64,   // TX bytes/sec as % of link speed
+    pub(crate) rx_crc_errs: f64,
+    pub(crate) rx_fifo_errs: f64,
+    pub(crate) rx_missed_errs: f64,
 }
 
+/// One chart to render, run on the rayon pool.
+type Plot<'a> = Box<dyn Fn() -> std::io::Result<()> + Send + Sync + 'a>;
+
+/// Chart key, label and value of one interval.
+pub(crate) type Chart<M> = (&'static str, &'static str, fn(&M) -> f64);
+
+/// Disk chart metrics: (key, label, accessor), in dashboard order.
+pub(crate) const DISK_CHARTS: &[Chart<IntervalDiskMetrics>] = &[
+    ("rps", "Read IOPS/sec", |m| m.rps),
+    ("wps", "Write IOPS/sec", |m| m.wps),
+    ("io_sec", "IO/sec (Total)", |m| m.io_sec),
+    ("rd_kbs", "Read KB/sec", |m| m.rd_kbs),
+    ("wr_kbs", "Write KB/sec", |m| m.wr_kbs),
+    ("kb_sec", "KB/sec (Total)", |m| m.kb_sec),
+    ("avg_queue_depth", "AvgQDepth (interval-avg)", |m| m.avg_queue_depth),
+    ("qlen", "QueueLen (collectl/iostat style)", |m| m.qlen),
+    ("svctim", "Service Time (ms)", |m| m.svctim),
+    ("await_rd", "Read Await (ms)", |m| m.await_rd),
+    ("await_wr", "Write Await (ms)", |m| m.await_wr),
+    ("discards_s", "Discards/sec", |m| m.discards_s),
+    ("discards_merged_s", "Discard Merges/sec", |m| m.discards_merged_s),
+    ("sectors_discarded_s", "Discard Sectors/sec", |m| m.sectors_discarded_s),
+    ("await_discard_ms", "Discard Await (ms)", |m| m.await_discard_ms),
+    ("discard_kbs", "Discard KB/sec", |m| m.discard_kbs),
+    ("flush_s", "Flushes/sec", |m| m.flush_s),
+    ("await_flush_ms", "Flush Await (ms)", |m| m.await_flush_ms),
+];
+
+/// Network chart metrics: (key, label, accessor).
+pub(crate) const NET_CHARTS: &[Chart<IntervalNetMetrics>] = &[
+    ("rx_bytes", "RX Bytes/sec", |m| m.rx_bytes),
+    ("tx_bytes", "TX Bytes/sec", |m| m.tx_bytes),
+    ("rx_pkts", "RX Packets/sec", |m| m.rx_pkts),
+    ("tx_pkts", "TX Packets/sec", |m| m.tx_pkts),
+    ("rx_errs", "RX Errors/sec", |m| m.rx_errs),
+    ("tx_errs", "TX Errors/sec", |m| m.tx_errs),
+    ("rx_drop", "RX Drops/sec", |m| m.rx_drop),
+    ("tx_drop", "TX Drops/sec", |m| m.tx_drop),
+    ("rx_util", "RX Link Utilization (% of speed)", |m| m.rx_util),
+    ("tx_util", "TX Link Utilization (% of speed)", |m| m.tx_util),
+    ("rx_crc_errs", "RX CRC Errors/sec", |m| m.rx_crc_errs),
+    ("rx_fifo_errs", "RX FIFO Errors/sec", |m| m.rx_fifo_errs),
+    ("rx_missed_errs", "RX Missed Errors/sec", |m| m.rx_missed_errs),
+];
+
+/// Memory metrics with distributions (memory is a gauge, every sample counts).
+pub(crate) const MEM_STATS: &[Chart<MemMetrics>] = &[
+    ("avail", "Available %", |m| m.avail_percent),
+    ("used", "Used %", |m| m.used_percent),
+    ("cached", "Cached %", |m| m.cached_percent),
+    ("swap_used_kb", "Swap Used (KB)", |m| m.swap_used_kb),
+    ("dirty_kb", "Dirty (KB)", |m| m.dirty_kb),
+    ("writeback_kb", "Writeback (KB)", |m| m.writeback_kb),
+];
+
 // ==================== Main Analyze Entrypoint ====================
 
 /// Analyze a serverstats_grab telemetry file and write all graphs + dashboard.
 /// All output goes into a new directory (named after your capture file stem).
-pub fn analyze(file_path: &str) -> std::io::Result<()> {
-    let output_dir = output_dir_for_datafile(file_path);
+/// Chart times are shown in `ctx`'s display timezone (default: the capture host's timezone).
+/// Only intervals ending inside `window` and the devices/interfaces picked by `sel`/`ifaces` are analyzed.
+/// With `bucket` (or automatically for long captures) charts plot one point per time bucket.
+pub fn analyze(
+    file_path: &str,
+    ctx: TimeCtx,
+    window: &TimeWindow,
+    sel: &DevSelect,
+    ifaces: &IfaceSelect,
+    bucket: Option<u64>,
+) -> std::io::Result<()> {
+    analyze_into(file_path, &output_dir_for_datafile(file_path), ctx, window, sel, ifaces, bucket).map(|_| ())
+}
+
+/// analyze, writing into `output_dir` (fleet mode puts each host's dashboard under the fleet's).
+/// Also returns the chart series, distributions and findings of the pass, for the fleet
+/// tables and timelines; None when the window holds no samples.
+pub(crate) fn analyze_into(
+    file_path: &str,
+    output_dir: &str,
+    mut ctx: TimeCtx,
+    window: &TimeWindow,
+    sel: &DevSelect,
+    ifaces: &IfaceSelect,
+    bucket: Option<u64>,
+) -> std::io::Result<Option<Capture>> {
+    let output_dir = output_dir.to_string();
     println!("Analyzing serverstats: {}\nOutput dir: {output_dir}", file_path);
     fs::create_dir_all(&output_dir)?;
-    
-    // ========== Step 1: Parse all rows into Vecs ==========
-
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
-
-    let mut per_device: HashMap<String, Vec<(u64, DiskStat)>> = HashMap::new();
-    let mut cpu_vec: Vec<(u64, Vec<u64>, Option<u64>, Option<u64>)> = Vec::new();
-    let mut mem_vec: Vec<(u64, HashMap<String, u64>)> = Vec::new();
-    let mut per_net: HashMap<String, Vec<(u64, NetStat)>> = HashMap::new();
-    for line in reader.lines().flatten() {
-        if line.starts_with('#') { continue; }
-        let mut cols = line.split(',');
-        let typ = cols.next().unwrap_or("");
-        if typ == "DISK" {
-            let ts = cols.next().unwrap().parse::<u64>().unwrap_or(0);
-            let fields: Vec<&str> = cols.collect();
-            if let Some(stat) = parse_disk_from_fields(&fields) {
-                per_device.entry(stat.name.clone()).or_default().push((ts, stat));
+    let started = Instant::now();
+
+    // ========== Step 1: One streaming pass over the capture ==========
+
+    // Chart bucket chosen up front, so only the chart points are kept during the pass
+    let chart_bucket = stream::chart_bucket(file_path, window, bucket)?;
+    let mut disk_trackers: HashMap<String, Tracker<DiskStat, IntervalDiskMetrics>> = HashMap::new();
+    let mut cpu_tracker = Tracker::new(chart_bucket, cpu_interval, CPU_CHARTS, findings::cpu_detector());
+    let mut mem_tracker = MemTracker::new(chart_bucket);
+    let mut mem_dists: Vec<DistAcc> = MEM_STATS.iter().map(|_| DistAcc::new(None)).collect();
+    let mut mem_prev: Option<u64> = None;
+    let mut net_trackers: HashMap<String, Tracker<NetRow, IntervalNetMetrics>> = HashMap::new();
+    // Latest NETX per iface, written just before its NET row
+    let mut pending_netx: HashMap<String, (u64, NetExt)> = HashMap::new();
+    let mut sync_tracker = SyncTracker::default();
+    let mut transport_tracker = TransportTracker::default();
+    let mut transport_events: Vec<TransportEvent> = Vec::new();
+    let mut transport_hosts: Vec<String> = Vec::new();
+    let mut scsi_hosts: HashMap<String, String> = HashMap::new();
+    let mut min_disk_layout: Option<u8> = None;
+    let mut flush_counters = false;
+    let (mut start, mut end): (Option<u64>, u64) = (None, 0);
+    let size = fs::metadata(file_path)?.len();
+    let parse = Progress::new("Parsing", size, stream::fmt_bytes);
+    let mut records: u64 = 0;
+    for rec in Reader::new(BufReader::new(CountingReader::new(File::open(file_path)?, &parse))).flatten() {
+        let ts = rec.ts();
+        if window.is_past(ts) { break; }
+        records += 1;
+        if window.contains(ts) && matches!(rec, Record::Disk { .. } | Record::Cpu { .. } | Record::Mem { .. } | Record::Net { .. }) {
+            start.get_or_insert(ts);
+            end = ts;
+        }
+        match rec {
+            Record::Tz { ts, offset, zone } => ctx.note_host_tz(ts, offset, &zone),
+            Record::DiskLayout { fields, .. } => {
+                min_disk_layout = Some(min_disk_layout.map_or(fields, |l| l.min(fields)));
             }
-        } else if typ == "CPU" {
-            let ts = cols.next().unwrap().parse::<u64>().unwrap_or(0);
-            // Get ALL remaining numeric fields, so we can reliably pick running/blocked from the end
-            let values: Vec<u64> = cols.filter_map(|v| v.parse::<u64>().ok()).collect();
-            // Defensive: expect at least 12 values (in your format there are 8 cpu fields + 2 zeros + running + blocked)
-            if values.len() >= 10 {
-                let vals = values.clone();   // Keep ALL CPU counters
-                let running = values.get(values.len() - 2).copied();
-                let blocked = values.get(values.len() - 1).copied();
-                cpu_vec.push((ts, vals, running, blocked));
+            Record::Disk { ts, stat } if sel.wants(&stat.name) => {
+                flush_counters |= stat.flushes.is_some();
+                disk_trackers.entry(stat.name.clone())
+                    .or_insert_with(|| Tracker::new(chart_bucket, disk_interval, DISK_CHARTS, findings::disk_detector(&stat.name)))
+                    .push(ts, stat, window, &ctx);
             }
-        }
-        else if typ == "MEM" {
-            let ts = cols.next().unwrap().parse::<u64>().unwrap_or(0);
-            let keys = [
-                "MemTotal","MemFree","MemAvailable","Buffers","Cached",
-                "SwapTotal","SwapFree","Dirty","Writeback","Active(file)","Inactive(file)","Slab","KReclaimable","SReclaimable"
-            ];
-            let mut vals = HashMap::new();
-            for (k, v) in keys.iter().zip(cols) {
-                vals.insert((*k).to_string(), v.parse::<u64>().unwrap_or(0));
+            Record::Cpu { ts, stat } => cpu_tracker.push(ts, stat, window, &ctx),
+            // Memory is not a delta, no baseline row
+            Record::Mem { ts, stat } if window.contains(ts) => {
+                let m = mem_point(ts, &stat);
+                let dt = mem_prev.map_or(0, |p| ts.saturating_sub(p));
+                mem_prev = Some(ts);
+                for ((_, _, f), acc) in MEM_STATS.iter().zip(&mut mem_dists) {
+                    acc.add(f(&m), dt);
+                }
+                mem_tracker.push(m, &ctx);
             }
-            mem_vec.push((ts, vals));
-        }
-
-
-else if typ == "NET" {
-    let ts = cols.next().unwrap().parse::<u64>().unwrap_or(0);
-    let iface = cols.next().unwrap_or("").to_string();
-    // Now next 8 fields in order: rx_bytes, tx_bytes, rx_pkts, tx_pkts, rx_errs, tx_errs, rx_drop, tx_drop
-    let rx_bytes = cols.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-    let tx_bytes = cols.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-    let rx_pkts  = cols.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-    let tx_pkts  = cols.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-    let rx_errs  = cols.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-    let tx_errs  = cols.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-    let rx_drop  = cols.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-    let tx_drop  = cols.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-
-    per_net.entry(iface.clone()).or_default().push((ts, NetStat {
-        rx_bytes,
-        tx_bytes,
-        rx_pkts,
-        tx_pkts,
-        rx_errs,
-        tx_errs,
-        rx_drop,
-        tx_drop,
-    }));
-}
-
-    }
-
-
-    // ========== Step 2: Process raw Vecs into per-interval metric Vecs ==========
-
-    // --- Disk Metrics ---
-    let mut disk_metrics: HashMap<String, Vec<IntervalDiskMetrics>> = HashMap::new();
-    for (dev, rows) in &per_device {
-        let mut prev: Option<(u64, &DiskStat)> = None;
-        let mut out = Vec::new();
-        for (ts, stat) in rows {
-            if let Some((last_ts, last_stat)) = prev {
-                let dt = (*ts).saturating_sub(last_ts);
-                if dt == 0 { prev = Some((*ts, stat)); continue; }
-                let d_reads = stat.reads.saturating_sub(last_stat.reads);
-                let d_writes = stat.writes.saturating_sub(last_stat.writes);
-                let d_sectors_read = stat.sectors_read.saturating_sub(last_stat.sectors_read);
-                let d_sectors_written = stat.sectors_written.saturating_sub(last_stat.sectors_written);
-                let delta_weighted_io_time_ms = stat.weighted_io_time_ms.saturating_sub(last_stat.weighted_io_time_ms);
-                let avg_queue_depth = delta_weighted_io_time_ms as f64 / (dt as f64 * 1000.0);
-                let delta_io_time_ms = stat.io_time_ms.saturating_sub(last_stat.io_time_ms);
-                let qlen = if delta_io_time_ms > 0 {
-                    delta_weighted_io_time_ms as f64 / delta_io_time_ms as f64
-                } else {
-                    0.0
-                };
-                let delta_io_time_ms = stat.io_time_ms.saturating_sub(last_stat.io_time_ms);
-                let total_ios = d_reads + d_writes;
-                let svctim = if total_ios > 0 {
-                    delta_io_time_ms as f64 / total_ios as f64
-                } else {
-                    0.0
-                };
-                let rps = d_reads as f64 / dt as f64;
-                let wps = d_writes as f64 / dt as f64;
-                let io_sec = rps + wps;
-                let rd_kbs = d_sectors_read as f64 * 512.0 / 1024.0 / dt as f64;
-                let wr_kbs = d_sectors_written as f64 * 512.0 / 1024.0 / dt as f64;
-                let kb_sec = rd_kbs + wr_kbs;
-                let await_read_ms = if d_reads > 0 {
-                    (stat.read_time_ms.saturating_sub(last_stat.read_time_ms)) as f64 / d_reads as f64
-                } else { 0.0 };
-                let await_write_ms = if d_writes > 0 {
-                    (stat.write_time_ms.saturating_sub(last_stat.write_time_ms)) as f64 / d_writes as f64
-                } else { 0.0 };
-                    let d_discards = stat.discards.saturating_sub(last_stat.discards);
-                    let d_discards_merged = stat.discards_merged.saturating_sub(last_stat.discards_merged);
-                    let d_sectors_discarded = stat.sectors_discarded.saturating_sub(last_stat.sectors_discarded);
-                    let d_discard_time_ms = stat.discard_time_ms.saturating_sub(last_stat.discard_time_ms);
-                    let sectors_discarded_s = d_sectors_discarded as f64 / dt as f64;
-                    let discards_s = d_discards as f64 / dt as f64;
-                    let discards_merged_s = d_discards_merged as f64 / dt as f64;
-                    // If you want KB/sec for discards, multiply by 0.5 (just like you do for reads/writes if 512B sectors)
-                    let discard_kbs = d_sectors_discarded as f64 * 0.5 / dt as f64;
-                    let await_discard_ms = if d_discards > 0 {
-                        d_discard_time_ms as f64 / d_discards as f64
-                    } else { 0.0 };
-
-                    out.push(IntervalDiskMetrics {
-                        ts: *ts,
-                        rps,
-                        wps,
-                        io_sec,
-                        rd_kbs,
-                        wr_kbs,
-                        kb_sec,
-                        avg_queue_depth,
-                        qlen,
-                        svctim,
-                        await_rd: await_read_ms,
-                        await_wr: await_write_ms,
-                        discards_s,
-                        discards_merged_s,
-                        sectors_discarded_s,
-                        await_discard_ms,
-                        discard_kbs,
-                    });
+            Record::Net { ts, stat } if ifaces.wants(&stat.iface) => {
+                let ext = pending_netx.remove(&stat.iface).filter(|(t, _)| *t == ts).map(|(_, e)| e);
+                net_trackers.entry(stat.iface.clone())
+                    .or_insert_with(|| Tracker::new(chart_bucket, net_row_interval, NET_CHARTS, findings::net_detector(&stat.iface)))
+                    .push(ts, (stat, ext), window, &ctx);
             }
-            prev = Some((*ts, stat));
-        }
-        if !out.is_empty() {
-            disk_metrics.insert(dev.clone(), out);
-        }
-    }
-        // CPU Metrics
-        let mut cpu_metrics: Vec<CpuMetrics> = Vec::new();
-        let mut prev: Option<(u64, Vec<u64>, Option<u64>, Option<u64>)> = None;
-        for (ts, vals, running, blocked) in cpu_vec {
-            if let Some((_last_ts, last_vals, _, _)) = &prev {
-                let total = vals.iter().zip(last_vals.iter()).map(|(v, lv)| v - lv).sum::<u64>() as f64;
-                if total == 0.0 { prev = Some((ts, vals, running, blocked)); continue; }
-                let user   = (vals[0] - last_vals[0]) as f64 / total * 100.0;
-                let nice   = (vals[1] - last_vals[1]) as f64 / total * 100.0;
-                let sys    = (vals[2] - last_vals[2]) as f64 / total * 100.0;
-                let idle   = (vals[3] - last_vals[3]) as f64 / total * 100.0;
-                let iowait = (vals[4] - last_vals[4]) as f64 / total * 100.0;
-                let guest  = (vals[8] - last_vals[8]) as f64 / total * 100.0;
-                cpu_metrics.push(CpuMetrics {
-                    ts,
-                    user: user + nice,
-                    sys,
-                    idle,
-                    iowait,
-                    guest,
-                    running,
-                    blocked,
-                });
+            Record::NetExt { ts, iface, stat } => {
+                pending_netx.insert(iface, (ts, stat));
+            }
+            Record::Md { ts, stat: md } if window.contains(ts) => {
+                let mut devs = vec![md.name.clone()];
+                devs.extend(md.members.iter().cloned());
+                sync_tracker.sample(ts, &md.name, md.is_syncing(), &md.sync_action, &devs);
+            }
+            Record::Drbd { ts, stat: d } if window.contains(ts) => {
+                sync_tracker.sample(ts, &d.name, d.is_syncing(), &d.cs, std::slice::from_ref(&d.name));
             }
-            prev = Some((ts, vals, running, blocked));
+            Record::ScsiDev { dev, hctl, .. } => {
+                scsi_hosts.insert(dev, host_of_hctl(&hctl));
+            }
+            Record::Fc { ts, stat: fc } => {
+                if !transport_hosts.contains(&fc.host) { transport_hosts.push(fc.host.clone()); }
+                transport_events.extend(transport_tracker.fc(ts, fc).into_iter().filter(|e| window.contains(e.ts)));
+            }
+            Record::Iscsi { ts, stat: session } => {
+                if !session.host.is_empty() && !transport_hosts.contains(&session.host) {
+                    transport_hosts.push(session.host.clone());
+                }
+                transport_events.extend(transport_tracker.iscsi(ts, session).into_iter().filter(|e| window.contains(e.ts)));
+            }
+            _ => {}
         }
-    // --- Memory Metrics ---
-    let mut mem_metrics: Vec<MemMetrics> = Vec::new();
-    for (ts, vals) in &mem_vec {
-        let mem_total = *vals.get("MemTotal").unwrap_or(&1) as f64;
-        let mem_free  = *vals.get("MemFree").unwrap_or(&0) as f64;
-        let mem_avail = *vals.get("MemAvailable").unwrap_or(&0) as f64;
-        let cached    = *vals.get("Cached").unwrap_or(&0) as f64;
-        let used = mem_total - mem_free;
-        let used_percent = if mem_total > 0.0 { used / mem_total * 100.0 } else { 0.0 };
-        let avail_percent = if mem_total > 0.0 { mem_avail / mem_total * 100.0 } else { 0.0 };
-        let cached_percent = if mem_total > 0.0 { cached / mem_total * 100.0 } else { 0.0 };
-        let free_percent = if mem_total > 0.0 { mem_free / mem_total * 100.0 } else { 0.0 };
-        mem_metrics.push(MemMetrics {
-            ts: *ts,
-            used_percent,
-            avail_percent,
-            cached_percent,
-            free_percent,
-        });
     }
-
-    // --- NET Metrics ---
-        let mut net_metrics: HashMap<String, Vec<IntervalNetMetrics>> = HashMap::new();
-        for (iface, rows) in &per_net {
-            let mut prev: Option<(u64, &NetStat)> = None;
-            let mut out = Vec::new();
-            for (ts, stat) in rows {
-            if let Some((last_ts, last_stat)) = prev {
-                let dt = (*ts).saturating_sub(last_ts);
-                if dt == 0 { prev = Some((*ts, stat)); continue; }
-                let rx_bytes = (stat.rx_bytes.saturating_sub(last_stat.rx_bytes)) as f64 / dt as f64;
-                let tx_bytes = (stat.tx_bytes.saturating_sub(last_stat.tx_bytes)) as f64 / dt as f64;
-                let rx_pkts  = (stat.rx_pkts.saturating_sub(last_stat.rx_pkts)) as f64 / dt as f64;
-                let tx_pkts  = (stat.tx_pkts.saturating_sub(last_stat.tx_pkts)) as f64 / dt as f64;
-                let rx_errs  = (stat.rx_errs.saturating_sub(last_stat.rx_errs)) as f64 / dt as f64;
-                let tx_errs  = (stat.tx_errs.saturating_sub(last_stat.tx_errs)) as f64 / dt as f64;
-                let rx_drop  = (stat.rx_drop.saturating_sub(last_stat.rx_drop)) as f64 / dt as f64;
-                let tx_drop  = (stat.tx_drop.saturating_sub(last_stat.tx_drop)) as f64 / dt as f64;
-                out.push(IntervalNetMetrics {
-                    ts: *ts, rx_bytes, tx_bytes, rx_pkts, tx_pkts, rx_errs, tx_errs, rx_drop, tx_drop,
-                });
-            }
-        prev = Some((*ts, stat));
+    let parse_secs = parse.secs();
+    println!("Parsed {} records ({}) in {:.1}s", records, stream::fmt_bytes(size), parse_secs);
+    let scope = scope_line(&ctx, window, sel, ifaces);
+    println!("Scope: {}", scope);
+
+    // ========== Step 2: Chart series, distributions and findings of each series ==========
+
+    let mut found: Vec<findings::Finding> = Vec::new();
+    let mut disk_chart: HashMap<String, Vec<IntervalDiskMetrics>> = HashMap::new();
+    let mut disk_dists: Vec<(String, stream::Dists)> = Vec::new();
+    for (dev, tracker) in disk_trackers {
+        let done = tracker.finish();
+        // No interval in the window
+        if done.dists.iter().all(|(_, d)| d.is_none()) { continue; }
+        // --no-idle: no I/O anywhere in the window
+        if sel.no_idle && done.dists.iter().all(|(_, d)| d.as_ref().is_none_or(|d| d.max == 0.0)) { continue; }
+        found.extend(done.findings);
+        disk_dists.push((dev.clone(), done.dists));
+        disk_chart.insert(dev, done.chart);
+    }
+    let cpu_done = cpu_tracker.finish();
+    found.extend(cpu_done.findings);
+    let cpu_metrics = cpu_done.chart;
+    let (mem_metrics, mem_found) = mem_tracker.finish();
+    found.extend(mem_found);
+    let mut net_metrics: HashMap<String, Vec<IntervalNetMetrics>> = HashMap::new();
+    let mut net_dists: HashMap<String, stream::Dists> = HashMap::new();
+    for (iface, tracker) in net_trackers {
+        let done = tracker.finish();
+        found.extend(done.findings);
+        if !done.chart.is_empty() {
+            net_dists.insert(iface.clone(), done.dists);
+            net_metrics.insert(iface, done.chart);
         }
-    if !out.is_empty() {
-        net_metrics.insert(iface.clone(), out);
     }
-}
+    findings::rank(&mut found);
 
 
     // ========== Step 3: Generate all graphs ==========
 
     // --- Disk: per device, per metric ---
-    let disk_metric_defs: &[(&str, &str, Box<dyn Fn(&IntervalDiskMetrics) -> f64>)] = &[
-    ("rps", "Read IOPS/sec", Box::new(|m: &IntervalDiskMetrics| m.rps)),
-    ("wps", "Write IOPS/sec", Box::new(|m: &IntervalDiskMetrics| m.wps)),
-    ("io_sec", "IO/sec (Total)", Box::new(|m: &IntervalDiskMetrics| m.io_sec)),
-    ("rd_kbs", "Read KB/sec", Box::new(|m: &IntervalDiskMetrics| m.rd_kbs)),
-    ("wr_kbs", "Write KB/sec", Box::new(|m: &IntervalDiskMetrics| m.wr_kbs)),
-    ("kb_sec", "KB/sec (Total)", Box::new(|m: &IntervalDiskMetrics| m.kb_sec)),
-    ("avg_queue_depth", "AvgQDepth (interval-avg)", Box::new(|m: &IntervalDiskMetrics| m.avg_queue_depth)),
-    ("qlen", "QueueLen (collectl/iostat style)", Box::new(|m: &IntervalDiskMetrics| m.qlen)),
-    ("svctim", "Service Time (ms)", Box::new(|m: &IntervalDiskMetrics| m.svctim)),   // <--- HERE!
-    ("await_rd", "Read Await (ms)", Box::new(|m: &IntervalDiskMetrics| m.await_rd)),
-    ("await_wr", "Write Await (ms)", Box::new(|m: &IntervalDiskMetrics| m.await_wr)),
-    ("discards_s", "Discards/sec", Box::new(|m: &IntervalDiskMetrics| m.discards_s)),
-    ("discards_merged_s", "Discard Merges/sec", Box::new(|m: &IntervalDiskMetrics| m.discards_merged_s)),
-    ("sectors_discarded_s", "Discard Sectors/sec", Box::new(|m: &IntervalDiskMetrics| m.sectors_discarded_s)),
-    ("await_discard_ms", "Discard Await (ms)", Box::new(|m: &IntervalDiskMetrics| m.await_discard_ms)),
-    ("discard_kbs", "Discard KB/sec", Box::new(|m: &IntervalDiskMetrics| m.discard_kbs)),
-];
 
-    println!("Writing disk graphs...");
-    for (dev, series) in &disk_metrics {
-        for (key, label, func) in disk_metric_defs.iter() {
-            plot_disk_metric(&output_dir, dev, series, &**func, label, key)?;
+    // Metrics the capture kernel never reported: no charts, "not available" in tables and dashboard
+    let unavailable = unavailable_disk_metrics(min_disk_layout, flush_counters);
+    let unavailable_reason = |key: &str| unavailable.iter().find(|(k, _)| *k == key).map(|(_, r)| r.as_str());
+
+    // md/DRBD resync windows, shaded on the array and member disk charts
+    let sync_windows = sync_tracker.finish();
+    let mut dev_windows: HashMap<&str, Vec<&SyncWindow>> = HashMap::new();
+    for w in &sync_windows {
+        for d in &w.devices {
+            dev_windows.entry(d.as_str()).or_default().push(w);
         }
     }
 
-    // --- CPU (all lines on one chart) ---
+    // Every chart is its own SVG/PNG pair, rendered in parallel
+    println!("Chart times: {}", ctx.label());
+    let mut plots: Vec<Plot> = Vec::new();
+    for (dev, series) in &disk_chart {
+        let shade = dev_windows.get(dev.as_str()).map(|v| v.as_slice()).unwrap_or(&[]);
+        for (key, label, func) in DISK_CHARTS {
+            // An all-zero series gets no chart, and is not counted as one
+            if unavailable_reason(key).is_some() || series.iter().all(|m| func(m) == 0.0) { continue; }
+            let (output_dir, ctx) = (&output_dir, &ctx);
+            plots.push(Box::new(move || plot_disk_metric(output_dir, ctx, dev, series, shade, func, label, key)));
+        }
+    }
     if !cpu_metrics.is_empty() {
-        plot_cpu(&output_dir, &cpu_metrics)?;
-        plot_running_blocked(&output_dir, &cpu_metrics)?;
+        plots.push(Box::new(|| plot_cpu(&output_dir, &ctx, &cpu_metrics)));
+        plots.push(Box::new(|| plot_running_blocked(&output_dir, &ctx, &cpu_metrics)));
     }
-    // --- MEM (all lines on one chart) ---
     if !mem_metrics.is_empty() {
-        plot_mem(&output_dir, &mem_metrics)?;
+        plots.push(Box::new(|| plot_mem(&output_dir, &ctx, &mem_metrics)));
     }
-// ===> INSERT NET GRAPHS HERE <===
-let net_metric_defs: &[(&str, &str, Box<dyn Fn(&IntervalNetMetrics) -> f64>)] = &[
-    ("rx_bytes", "RX Bytes/sec", Box::new(|m: &IntervalNetMetrics| m.rx_bytes)),
-    ("tx_bytes", "TX Bytes/sec", Box::new(|m: &IntervalNetMetrics| m.tx_bytes)),
-    ("rx_pkts", "RX Packets/sec", Box::new(|m: &IntervalNetMetrics| m.rx_pkts)),
-    ("tx_pkts", "TX Packets/sec", Box::new(|m: &IntervalNetMetrics| m.tx_pkts)),
-    ("rx_errs", "RX Errors/sec", Box::new(|m: &IntervalNetMetrics| m.rx_errs)),
-    ("tx_errs", "TX Errors/sec", Box::new(|m: &IntervalNetMetrics| m.tx_errs)),
-    ("rx_drop", "RX Drops/sec", Box::new(|m: &IntervalNetMetrics| m.rx_drop)),
-    ("tx_drop", "TX Drops/sec", Box::new(|m: &IntervalNetMetrics| m.tx_drop)),
-];
-    println!("Writing net graphs...");
     for (iface, series) in &net_metrics {
-        for (key, label, func) in net_metric_defs.iter() {
-            // Debug print of actual values being plotted
-            //let vals: Vec<f64> = series.iter().map(|m| func(m)).collect();
-            //println!("Net graph: iface={} metric={} first5={:?}", iface, key, &vals[..std::cmp::min(5, vals.len())]);
-            plot_net_metric(&output_dir, iface, series, &**func, label, key)?;
+        for (key, label, func) in NET_CHARTS {
+            if series.iter().all(|m| func(m) == 0.0) { continue; }
+            let (output_dir, ctx) = (&output_dir, &ctx);
+            plots.push(Box::new(move || plot_net_metric(output_dir, ctx, iface, series, func, label, key)));
         }
     }
+    println!("Writing {} graphs on {} threads...", plots.len(), rayon::current_num_threads());
+    let render = Progress::new("Graphs", plots.len() as u64, stream::fmt_count);
+    plots.par_iter().try_for_each(|plot| {
+        plot()?;
+        render.add(1);
+        Ok::<(), std::io::Error>(())
+    })?;
+    let render_secs = render.secs();
+    drop(plots);
 
     // ========== Step 3.5: Write Top 50 Device Tables ==========
 
-    // Compute summary for each device/metric
-    let mut metrics_summary: HashMap<&str, Vec<(String, f64, f64)>> = HashMap::new();
-    for (dev, series) in &disk_metrics {
-        for (key, _label, func) in disk_metric_defs.iter() {
-            let avg = mean(series, &**func);
-            let max = max(series, &**func);
-            metrics_summary.entry(key).or_default().push((dev.clone(), avg, max));
+    // Distribution of each device/metric over every interval
+    let mut dists: HashMap<&str, Vec<(String, Distribution)>> = HashMap::new();
+    let mut device_pctl: Vec<String> = Vec::new();
+    disk_dists.sort_by(|a, b| a.0.cmp(&b.0));
+    for (dev, metric_dists) in &disk_dists {
+        let mut refs: Vec<(&str, &Distribution)> = Vec::new();
+        for (key, d) in metric_dists {
+            let Some(d) = d else { continue };
+            if unavailable_reason(key).is_none() {
+                refs.push((key, d));
+            }
+            dists.entry(key).or_default().push((dev.clone(), d.clone()));
         }
+        device_pctl.push(format!("{}:{}", json_str(dev), percentile::device_json(&refs)));
     }
+    let metrics_summary: HashMap<&str, Vec<(String, f64, f64)>> = dists.iter()
+        .map(|(key, v)| (*key, v.iter().map(|(dev, d)| (dev.clone(), d.mean, d.max)).collect()))
+        .collect();
 
     // Write top50 txt tables
     let mut tables: Vec<String> = Vec::new();
@@ -437,13 +469,20 @@ let net_metric_defs: &[(&str, &str, Box<dyn Fn(&IntervalNetMetrics) -> f64>)] =
 
         // Table headings
         writeln!(f_avg, "{fname}\nMetric: {metric} (average)\n", fname=avg_fname, metric=metric)?;
+        writeln!(f_max, "{fname}\nMetric: {metric} (peak)\n", fname=max_fname, metric=metric)?;
+        if let Some(reason) = unavailable_reason(metric) {
+            writeln!(f_avg, "{}", reason)?;
+            writeln!(f_max, "{}", reason)?;
+            tables.push(avg_fname);
+            tables.push(max_fname);
+            continue;
+        }
         writeln!(f_avg, "{:<5} {:<16} {:>12} {:>12}", "Rank", "Device", "Average", "Peak")?;
         writeln!(f_avg, "{}", "-".repeat(5 + 1 + 16 + 1 + 12 + 1 + 12))?;
         for (idx, (dev, avg, peak)) in by_avg.iter().take(50).enumerate() {
             writeln!(f_avg, "{:<5} {:<16} {:>12.2} {:>12.2}", idx+1, dev, avg, peak)?;
         }
 
-        writeln!(f_max, "{fname}\nMetric: {metric} (peak)\n", fname=max_fname, metric=metric)?;
         writeln!(f_max, "{:<5} {:<16} {:>12} {:>12}", "Rank", "Device", "Average", "Peak")?;
         writeln!(f_max, "{}", "-".repeat(5 + 1 + 16 + 1 + 12 + 1 + 12))?;
         for (idx, (dev, avg, peak)) in by_max.iter().take(50).enumerate() {
@@ -454,42 +493,166 @@ let net_metric_defs: &[(&str, &str, Box<dyn Fn(&IntervalNetMetrics) -> f64>)] =
         tables.push(max_fname);
     }
 
+    // Percentile-ranked tables: a device that is slow all day rather than for one interval
+    let mut pctl_metrics: Vec<&&str> = dists.keys().collect();
+    pctl_metrics.sort();
+    for metric in pctl_metrics {
+        for rank_by in ["p95", "p99"] {
+            let fname = format!("top50_{}_{}.txt", metric, rank_by);
+            let body = match unavailable_reason(metric) {
+                Some(reason) => format!("{fname}\nMetric: {metric} (ranked by {rank_by})\n\n{reason}\n"),
+                None => {
+                    let mut rows: Vec<(&str, &Distribution)> = dists[metric].iter().map(|(dev, d)| (dev.as_str(), d)).collect();
+                    rows.sort_by(|a, b| b.1.at(rank_by).total_cmp(&a.1.at(rank_by)).then(a.0.cmp(b.0)));
+                    percentile::ranked_table(&fname, metric, rank_by, &rows)
+                }
+            };
+            fs::write(format!("{}/{}", output_dir, fname), body)?;
+            tables.push(fname);
+        }
+    }
+
+    // ========== Step 3.6: md/DRBD resync windows ==========
+
+    let mut raid_html = String::new();
+    if !sync_windows.is_empty() {
+        let fname = "md_sync_windows.txt";
+        let mut f = File::create(format!("{}/{}", output_dir, fname))?;
+        writeln!(f, "{fname}\nmd RAID / DRBD resync and rebuild windows ({})\n", ctx.label())?;
+        writeln!(f, "{:<10} {:<20} {:<19} {:<19} {:>9}  Devices", "Array", "Action", "Start", "End", "Duration")?;
+        writeln!(f, "{}", "-".repeat(100))?;
+        raid_html.push_str("<table border=\"1\" cellpadding=\"4\"><tr><th>Array</th><th>Action</th><th>Start</th><th>End</th><th>Duration</th><th>Devices</th></tr>\n");
+        for w in &sync_windows {
+            let start = ctx.format(w.start, "%Y-%m-%d %H:%M:%S");
+            let end = ctx.format(w.end, "%Y-%m-%d %H:%M:%S");
+            let dur = w.end.saturating_sub(w.start);
+            let dur = format!("{}:{:02}:{:02}", dur / 3600, (dur % 3600) / 60, dur % 60);
+            writeln!(f, "{:<10} {:<20} {:<19} {:<19} {:>9}  {}", w.array, w.action, start, end, dur, w.devices.join(" "))?;
+            raid_html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                w.array, w.action, start, end, dur, w.devices.join(" ")
+            ));
+        }
+        raid_html.push_str("</table>\n<p>These windows are shaded on the array and member disk charts.</p>\n");
+        tables.push(fname.to_string());
+        println!("Found {} md/DRBD resync window(s), see {}", sync_windows.len(), fname);
+    }
+
+    // ========== Step 3.7: FC/iSCSI events and per-host path I/O ==========
+
+    let mut transport_html = String::new();
+    if !transport_hosts.is_empty() {
+        let fname = "transport_events.txt";
+        let mut f = File::create(format!("{}/{}", output_dir, fname))?;
+        writeln!(f, "{fname}\nFC/iSCSI transport events ({})\n", ctx.label())?;
+        writeln!(f, "{:<19} {:<8} {:<6} {:<10}  Event", "Time", "Host", "Source", "Object")?;
+        writeln!(f, "{}", "-".repeat(100))?;
+        transport_html.push_str("<table border=\"1\" cellpadding=\"4\"><tr><th>Time</th><th>Host</th><th>Source</th><th>Object</th><th>Event</th></tr>\n");
+        for e in &transport_events {
+            let when = ctx.format(e.ts, "%Y-%m-%d %H:%M:%S");
+            writeln!(f, "{:<19} {:<8} {:<6} {:<10}  {}", when, e.host, e.source, e.object, e.detail)?;
+            transport_html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                when, e.host, e.source, e.object, e.detail
+            ));
+        }
+        transport_html.push_str("</table>\n");
+        if transport_events.is_empty() {
+            writeln!(f, "(no port/session state changes or error counter increases)")?;
+            transport_html.push_str("<p>No port/session state changes or error counter increases.</p>\n");
+        }
+        tables.push(fname.to_string());
+
+        println!("Writing transport path I/O graphs...");
+        for host in &transport_hosts {
+            // Sum the sd paths behind this host per interval
+            let mut per_ts: std::collections::BTreeMap<u64, (f64, f64)> = std::collections::BTreeMap::new();
+            let mut paths: Vec<&str> = Vec::new();
+            for (dev, h) in &scsi_hosts {
+                if h != host { continue; }
+                paths.push(dev);
+                for m in disk_chart.get(dev).map(|v| v.as_slice()).unwrap_or(&[]) {
+                    let e = per_ts.entry(m.ts).or_default();
+                    e.0 += m.io_sec;
+                    e.1 += m.kb_sec;
+                }
+            }
+            paths.sort();
+            let series: Vec<(u64, f64, f64)> = per_ts.into_iter().map(|(ts, (io, kb))| (ts, io, kb)).collect();
+            let marks: Vec<u64> = transport_events.iter().filter(|e| &e.host == host).map(|e| e.ts).collect();
+            transport_html.push_str(&format!("<h3>{} paths: {}</h3>\n", host, if paths.is_empty() { "-".to_string() } else { paths.join(" ") }));
+            if series.len() >= 2 {
+                plot_path_io(&output_dir, &ctx, host, &series, &marks)?;
+                transport_html.push_str(&format!(
+                    "<img class=\"graph\" src=\"transport_{host}.svg\" onerror=\"this.src='transport_{host}.png';\">\n"
+                ));
+            } else {
+                transport_html.push_str("<p>No path I/O captured for this host.</p>\n");
+            }
+        }
+        println!("Found {} FC/iSCSI event(s), see {}", transport_events.len(), fname);
+    }
+
+    // ========== Step 3.8: Findings ==========
+
+    // The rules ran on every interval during the pass (Step 2), not on the chart buckets
+    fs::write(format!("{}/findings.txt", output_dir), findings::to_text(&found, &ctx, "findings.txt"))?;
+    fs::write(format!("{}/findings.json", output_dir), findings::to_json(&found, &ctx))?;
+    tables.insert(0, "findings.json".to_string());
+    tables.insert(0, "findings.txt".to_string());
+    let findings_html = findings::to_html(&found, &ctx);
+    let critical = found.iter().filter(|f| f.severity == findings::Severity::Critical).count();
+    println!("Found {} finding(s), {} critical, see findings.txt", found.len(), critical);
+
+    // ========== Step 3.9: Cross-subsystem correlation ==========
+
+    // On the chart series, so the overlay charts line up with the other charts
+    let mut chart_disks: Vec<(&str, &[IntervalDiskMetrics])> = disk_chart.iter().map(|(d, v)| (d.as_str(), v.as_slice())).collect();
+    chart_disks.sort_by_key(|d| d.0);
+    let mut chart_nets: Vec<(&str, &[IntervalNetMetrics])> = net_metrics.iter().map(|(i, v)| (i.as_str(), v.as_slice())).collect();
+    chart_nets.sort_by_key(|n| n.0);
+    let correlation_html = correlate::write(&output_dir, &ctx, &chart_disks, &cpu_metrics, &mem_metrics, &chart_nets)?;
+    tables.insert(2, "correlations.txt".to_string());
+
     // ========== Step 4: HTML dashboard ==========
 
-    let devices: Vec<String> = disk_metrics.keys().cloned().collect();
+    let devices: Vec<String> = disk_chart.keys().cloned().collect();
     let net_ifaces: Vec<String> = net_metrics.keys().cloned().collect();
-    write_index_html(&output_dir, &devices, &net_ifaces, &tables, "cpu", "mem")?;
+    write_index_html(&output_dir, &devices, &net_ifaces, &tables, &device_pctl, &findings_html, &correlation_html, &raid_html, &transport_html, &unavailable, &scope, "cpu", "mem")?;
+    println!("Timing: parse {:.1}s, graphs {:.1}s, total {:.1}s", parse_secs, render_secs, started.elapsed().as_secs_f64());
     println!("Analysis complete. See {}/ for results.", output_dir);
-    Ok(())
+
+    let Some(start) = start else { return Ok(None) };
+    let disks = disk_dists.into_iter().map(|(dev, mut dists)| {
+        // Zeros the kernel never counted are not a measurement
+        for (key, d) in dists.iter_mut() {
+            if unavailable_reason(key).is_some() { *d = None; }
+        }
+        let chart = disk_chart.remove(&dev).unwrap_or_default();
+        (dev, Summary { chart, dists })
+    }).collect();
+    let nets = net_metrics.into_iter()
+        .map(|(iface, chart)| { let dists = net_dists.remove(&iface).unwrap_or_default(); (iface, Summary { chart, dists }) })
+        .collect();
+    Ok(Some(Capture {
+        path: file_path.to_string(),
+        ctx,
+        start,
+        end,
+        disks,
+        cpu: Summary { chart: cpu_metrics, dists: cpu_done.dists },
+        mem: Summary {
+            chart: mem_metrics,
+            dists: MEM_STATS.iter().zip(mem_dists).map(|((key, _, _), acc)| (*key, acc.finish())).collect(),
+        },
+        nets,
+        findings: found,
+        unavailable,
+    }))
 }
 
 // ==================== Helpers: Parsing ====================
 
-/// Parse DiskStat from split CSV field slice (see DISK lines)
-fn parse_disk_from_fields(fields: &[&str]) -> Option<DiskStat> {
-    if fields.len() < 18 { return None; }
-    Some(DiskStat {
-        major: fields[0].parse().ok()?,
-        minor: fields[1].parse().ok()?,
-        name: fields[2].to_string(),
-        reads: fields[3].parse().ok()?,
-        reads_merged: fields[4].parse().ok()?,
-        sectors_read: fields[5].parse().ok()?,
-        read_time_ms: fields[6].parse().ok()?,
-        writes: fields[7].parse().ok()?,
-        writes_merged: fields[8].parse().ok()?,
-        sectors_written: fields[9].parse().ok()?,
-        write_time_ms: fields[10].parse().ok()?,
-        io_in_progress: fields[11].parse().ok()?,
-        io_time_ms: fields[12].parse().ok()?,
-        weighted_io_time_ms: fields[13].parse().ok()?,
-        discards: fields[14].parse().ok()?,
-        discards_merged: fields[15].parse().ok()?,
-        sectors_discarded: fields[16].parse().ok()?,
-        discard_time_ms: fields[17].parse().ok()?,
-    })
-}
-
 /// Compute output directory (stem of .dat file)
 fn output_dir_for_datafile(datafile: &str) -> String {
     let path = std::path::Path::new(datafile);
@@ -497,13 +660,224 @@ fn output_dir_for_datafile(datafile: &str) -> String {
     stem.to_string()
 }
 
+/// Disk metrics the capture kernel never reported, with the reason: discards need an 18+ field
+/// diskstats (the smallest DISKLAYOUT seen), flushes a DISK record that had flush counters.
+/// Captures without DISKLAYOUT records predate it and always had discards.
+pub(crate) fn unavailable_disk_metrics(min_disk_layout: Option<u8>, flush_counters: bool) -> Vec<(&'static str, String)> {
+    let mut unavailable = Vec::new();
+    if min_disk_layout.is_some_and(|l| l < 18) {
+        for key in ["discards_s", "discards_merged_s", "sectors_discarded_s", "await_discard_ms", "discard_kbs"] {
+            unavailable.push((key, "not available (kernel diskstats has 14 fields, no discard counters)".to_string()));
+        }
+    }
+    if !flush_counters {
+        let reason = match min_disk_layout {
+            Some(l) => format!("not available (kernel diskstats has {} fields, no flush counters)", l),
+            None => "not available (capture has no flush counters)".to_string(),
+        };
+        for key in ["flush_s", "await_flush_ms"] {
+            unavailable.push((key, reason.clone()));
+        }
+    }
+    unavailable
+}
+
+/// Dashboard header line: time window, devices and interfaces analyzed.
+fn scope_line(ctx: &TimeCtx, window: &TimeWindow, sel: &DevSelect, ifaces: &IfaceSelect) -> String {
+    let when = |ts: Option<u64>, edge: &str| ts.map_or(edge.to_string(), |t| ctx.format(t, "%Y-%m-%d %H:%M:%S"));
+    let time = if window.from.is_none() && window.to.is_none() {
+        "whole capture".to_string()
+    } else {
+        format!("{} to {} ({})", when(window.from, "capture start"), when(window.to, "capture end"), ctx.label())
+    };
+    let devices = match sel.describe() {
+        d if d.is_empty() => "all devices".to_string(),
+        d => format!("devices {}", d),
+    };
+    let nets = match ifaces.describe() {
+        d if d.is_empty() => "all interfaces".to_string(),
+        d => format!("interfaces {}", d),
+    };
+    format!("{}; {}; {}", time, devices, nets)
+}
+
+// ==================== Helpers: Series ====================
+
+/// Disk metrics for the interval between two rows of one device.
+pub(crate) fn disk_interval(last: &(u64, DiskStat), cur: &(u64, DiskStat)) -> Option<IntervalDiskMetrics> {
+    DiskDelta::between(&last.1, &cur.1, cur.0.saturating_sub(last.0)).map(|d| IntervalDiskMetrics::from_delta(cur.0, &d))
+}
+
+/// CPU metrics for the interval between two CPU rows.
+pub(crate) fn cpu_interval(last: &(u64, CpuStat), cur: &(u64, CpuStat)) -> Option<CpuMetrics> {
+    let (ts, stat) = cur;
+    let p = CpuPercent::between(&last.1, stat)?;
+    Some(CpuMetrics {
+        ts: *ts,
+        user: p.user + p.nice,
+        sys: p.system,
+        idle: p.idle,
+        iowait: p.iowait,
+        guest: p.guest,
+        running: Some(stat.procs_running),
+        blocked: Some(stat.procs_blocked),
+    })
+}
+
+/// NET metrics for the interval between two rows of one interface, with the NETX
+/// records of both rows (current, last) when the capture has them.
+pub(crate) fn net_interval(last: (u64, &NetStat), cur: (u64, &NetStat), ext: Option<(&NetExt, &NetExt)>) -> Option<IntervalNetMetrics> {
+    let (ts, stat) = cur;
+    let d = NetDelta::between(last.1, stat, ts.saturating_sub(last.0))?;
+    let rx_bytes = d.rate(d.rx_bytes);
+    let tx_bytes = d.rate(d.tx_bytes);
+    // Link utilization and extended error rates from NETX
+    let (mut rx_util, mut tx_util, mut rx_crc_errs, mut rx_fifo_errs, mut rx_missed_errs) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    if let Some((e, le)) = ext {
+        if let Some(speed) = e.speed_bytes() {
+            rx_util = rx_bytes / speed * 100.0;
+            tx_util = tx_bytes / speed * 100.0;
+        }
+        rx_crc_errs = d.rate(e.rx_crc_errors.saturating_sub(le.rx_crc_errors));
+        rx_fifo_errs = d.rate(e.rx_fifo_errors.saturating_sub(le.rx_fifo_errors));
+        rx_missed_errs = d.rate(e.rx_missed_errors.saturating_sub(le.rx_missed_errors));
+    }
+    Some(IntervalNetMetrics {
+        ts,
+        rx_bytes,
+        tx_bytes,
+        rx_pkts: d.rate(d.rx_packets),
+        tx_pkts: d.rate(d.tx_packets),
+        rx_errs: d.rate(d.rx_errs),
+        tx_errs: d.rate(d.tx_errs),
+        rx_drop: d.rate(d.rx_drop),
+        tx_drop: d.rate(d.tx_drop),
+        rx_util, tx_util, rx_crc_errs, rx_fifo_errs, rx_missed_errs,
+    })
+}
+
+/// A NET row with the NETX record written just before it, when there was one.
+pub(crate) type NetRow = (NetStat, Option<NetExt>);
+
+/// net_interval for two NetRows.
+pub(crate) fn net_row_interval(last: &(u64, NetRow), cur: &(u64, NetRow)) -> Option<IntervalNetMetrics> {
+    net_interval((last.0, &last.1.0), (cur.0, &cur.1.0), cur.1.1.as_ref().zip(last.1.1.as_ref()))
+}
+
+/// Memory percentages of one MEM row.
+pub(crate) fn mem_point(ts: u64, stat: &MemStat) -> MemMetrics {
+    let p = MemPercent::of(stat);
+    let swap_used = stat.get("SwapTotal").unwrap_or(0).saturating_sub(stat.get("SwapFree").unwrap_or(0));
+    MemMetrics {
+        ts,
+        used_percent: p.used,
+        avail_percent: p.avail,
+        cached_percent: p.cached,
+        free_percent: p.free,
+        swap_used_kb: swap_used as f64,
+        dirty_kb: stat.get("Dirty").unwrap_or(0) as f64,
+        writeback_kb: stat.get("Writeback").unwrap_or(0) as f64,
+    }
+}
+
+/// Memory gauges averaged per bucket, weighted by the time each sample covers, one
+/// sample at a time. Each point is placed at the last sample of its bucket, like the
+/// counter series.
+pub(crate) struct MemBuckets {
+    bucket: u64,
+    aggs: [Agg; 7],
+    /// (bucket start, last ts)
+    cur: Option<(u64, u64)>,
+    prev_ts: Option<u64>,
+}
+
+impl MemBuckets {
+    pub(crate) fn new(bucket: u64) -> Self {
+        MemBuckets { bucket, aggs: Default::default(), cur: None, prev_ts: None }
+    }
+
+    /// Adds a sample; returns the previous bucket's point when this sample starts a new bucket.
+    pub(crate) fn push(&mut self, m: &MemMetrics, ctx: &TimeCtx) -> Option<MemMetrics> {
+        let start = bucket::bucket_start(m.ts, self.bucket, ctx);
+        let mut done = None;
+        if let Some((s, last)) = self.cur && s != start {
+            done = self.flush(last);
+        }
+        self.cur = Some((start, m.ts));
+        // The first sample covers no time; weight it 1s so a one-sample capture still charts
+        let dt = self.prev_ts.map_or(1, |p| m.ts.saturating_sub(p)) as f64;
+        self.prev_ts = Some(m.ts);
+        for (agg, v) in self.aggs.iter_mut().zip([m.used_percent, m.avail_percent, m.cached_percent, m.free_percent, m.swap_used_kb, m.dirty_kb, m.writeback_kb]) {
+            agg.add(v, dt);
+        }
+        done
+    }
+
+    /// The last bucket's point.
+    pub(crate) fn finish(&mut self) -> Option<MemMetrics> {
+        let (_, last) = self.cur.take()?;
+        self.flush(last)
+    }
+
+    fn flush(&mut self, ts: u64) -> Option<MemMetrics> {
+        let v: Vec<Option<f64>> = self.aggs.iter().map(|a| a.stats().map(|s| s.mean)).collect();
+        self.aggs = Default::default();
+        let [Some(used), Some(avail), Some(cached), Some(free), Some(swap), Some(dirty), Some(writeback)] = v[..] else { return None };
+        Some(MemMetrics {
+            ts,
+            used_percent: used,
+            avail_percent: avail,
+            cached_percent: cached,
+            free_percent: free,
+            swap_used_kb: swap,
+            dirty_kb: dirty,
+            writeback_kb: writeback,
+        })
+    }
+}
+
+/// Chart bucket for captures with more than CHART_POINTS samples and no --bucket:
+/// the smallest standard bucket that keeps the charts at or under CHART_POINTS points.
+const CHART_POINTS: u64 = 2000;
+
+pub(crate) fn auto_bucket(samples: usize, span: u64) -> Option<u64> {
+    if (samples as u64) <= CHART_POINTS { return None; }
+    [60, 300, 900, 3600, 21600, 86400]
+        .into_iter()
+        .find(|b| span / b <= CHART_POINTS)
+        .or(Some(86400))
+}
+
+pub(crate) fn fmt_bucket(secs: u64) -> String {
+    match secs {
+        s if s % 86400 == 0 => format!("{}d", s / 86400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
 // ==================== Plotters (Graphing) ====================
 
-/// Plot one disk metric for a device (SVG + PNG)
+/// X axis labels in the display timezone; captures spanning more than a day get the date too.
+pub(crate) fn time_axis(ctx: &TimeCtx, times: &[u64]) -> (Vec<String>, String) {
+    let span = match (times.first(), times.last()) {
+        (Some(first), Some(last)) => last.saturating_sub(*first),
+        _ => 0,
+    };
+    let (fmt, desc) = if span > 86400 { ("%m-%d %H:%M", "MM-DD HH:MM") } else { ("%H:%M:%S", "HH:MM:SS") };
+    let labels = times.iter().map(|ts| ctx.format(*ts, fmt)).collect();
+    (labels, format!("Time ({}, {})", desc, ctx.label()))
+}
+
+/// Plot one disk metric for a device (SVG + PNG), shading any resync windows
+#[allow(clippy::too_many_arguments)]
 fn plot_disk_metric<F>(
     output_dir: &str,
+    ctx: &TimeCtx,
     dev: &str,
     series: &[IntervalDiskMetrics],
+    shade: &[&SyncWindow],
     metric: F,
     ylabel: &str,
     fname: &str,
@@ -512,17 +886,25 @@ where
     F: Fn(&IntervalDiskMetrics) -> f64,
 {
     let times: Vec<u64> = series.iter().map(|m| m.ts).collect();
-    let time_labels: Vec<String> = times.iter().map(|epoch| {
-        chrono::Local.timestamp_opt(*epoch as i64, 0)
-            .single()
-            .unwrap_or_else(|| chrono::Local.timestamp_opt(0, 0).single().unwrap())
-            .format("%H:%M:%S").to_string()
-    }).collect();
+    let (time_labels, x_desc) = time_axis(ctx, &times);
     let values: Vec<f64> = series.iter().map(|m| metric(m)).collect();
-    if values.iter().all(|&v| v == 0.0) { return Ok(()); }
     let y_min = values.iter().cloned().fold(f64::INFINITY, f64::min).min(0.0);
     let y_max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max).max(1.0);
 
+    // Resync/rebuild windows as x index ranges
+    let spans: Vec<(usize, usize)> = shade.iter().filter_map(|w| {
+        let first = series.iter().position(|m| m.ts >= w.start && m.ts <= w.end)?;
+        let last = series.iter().rposition(|m| m.ts >= w.start && m.ts <= w.end)?;
+        Some((first, last.max(first + 1).min(series.len() - 1)))
+    }).collect();
+    let caption = if spans.is_empty() {
+        format!("{} - {}", dev, ylabel)
+    } else {
+        let mut names: Vec<String> = shade.iter().map(|w| format!("{} {}", w.array, w.action)).collect();
+        names.dedup();
+        format!("{} - {} (shaded: {})", dev, ylabel, names.join(", "))
+    };
+
     // SVG
     let svg_path = format!("{}/{}_{}.svg", output_dir, dev, fname);
     {
@@ -530,7 +912,7 @@ where
         let root = backend.into_drawing_area();
         root.fill(&WHITE).unwrap();
         let mut chart = ChartBuilder::on(&root)
-            .caption(format!("{} - {}", dev, ylabel), ("sans-serif", 22))
+            .caption(&caption, ("sans-serif", 22))
             .margin(12)
             .x_label_area_size(30)
             .y_label_area_size(60)
@@ -540,10 +922,15 @@ where
             .configure_mesh()
             .x_labels(8)
             .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
-            .x_desc("Time (HH:MM:SS)")
+            .x_desc(x_desc.as_str())
             .y_desc(ylabel)
             .draw()
             .unwrap();
+        chart
+            .draw_series(spans.iter().map(|(a, b)| {
+                Rectangle::new([(*a, y_min), (*b, y_max)], RGBColor(255, 165, 0).mix(0.25).filled())
+            }))
+            .unwrap();
         chart
             .draw_series(LineSeries::new(
                 (0..values.len()).map(|i| (i, values[i])),
@@ -559,7 +946,7 @@ where
         let root = backend.into_drawing_area();
         root.fill(&WHITE).unwrap();
         let mut chart = ChartBuilder::on(&root)
-            .caption(format!("{} - {}", dev, ylabel), ("sans-serif", 22))
+            .caption(&caption, ("sans-serif", 22))
             .margin(12)
             .x_label_area_size(30)
             .y_label_area_size(60)
@@ -569,10 +956,15 @@ where
             .configure_mesh()
             .x_labels(8)
             .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
-            .x_desc("Time (HH:MM:SS)")
+            .x_desc(x_desc.as_str())
             .y_desc(ylabel)
             .draw()
             .unwrap();
+        chart
+            .draw_series(spans.iter().map(|(a, b)| {
+                Rectangle::new([(*a, y_min), (*b, y_max)], RGBColor(255, 165, 0).mix(0.25).filled())
+            }))
+            .unwrap();
         chart
             .draw_series(LineSeries::new(
                 (0..values.len()).map(|i| (i, values[i])),
@@ -583,8 +975,59 @@ where
     }
     Ok(())
 }
+/// Plot total path IO/sec behind one SCSI host (SVG + PNG), with a red line at each transport event
+fn plot_path_io(output_dir: &str, ctx: &TimeCtx, host: &str, series: &[(u64, f64, f64)], events: &[u64]) -> std::io::Result<()> {
+    let svg_path = format!("{}/transport_{}.svg", output_dir, host);
+    draw_path_io(SVGBackend::new(&svg_path, (900, 300)).into_drawing_area(), ctx, host, series, events);
+    let png_path = format!("{}/transport_{}.png", output_dir, host);
+    draw_path_io(BitMapBackend::new(&png_path, (900, 300)).into_drawing_area(), ctx, host, series, events);
+    Ok(())
+}
+
+fn draw_path_io<DB: DrawingBackend>(
+    root: DrawingArea<DB, plotters::coord::Shift>,
+    ctx: &TimeCtx,
+    host: &str,
+    series: &[(u64, f64, f64)],
+    events: &[u64],
+) {
+    let times: Vec<u64> = series.iter().map(|s| s.0).collect();
+    let (time_labels, x_desc) = time_axis(ctx, &times);
+    let y_max = series.iter().map(|s| s.1).fold(1.0, f64::max);
+    // Events as x indexes: the first interval at or after the event
+    let marks: Vec<usize> = events
+        .iter()
+        .filter_map(|ts| series.iter().position(|s| s.0 >= *ts))
+        .collect();
+
+    root.fill(&WHITE).unwrap();
+    let mut chart = ChartBuilder::on(&root)
+        .caption(format!("{} - path IO/sec", host), ("sans-serif", 22))
+        .margin(12)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0..(time_labels.len() - 1), 0.0..y_max)
+        .unwrap();
+    chart
+        .configure_mesh()
+        .x_labels(8)
+        .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
+        .x_desc(x_desc.as_str())
+        .y_desc("IO/sec")
+        .draw()
+        .unwrap();
+    chart
+        .draw_series(marks.iter().map(|x| PathElement::new(vec![(*x, 0.0), (*x, y_max)], RED.stroke_width(2))))
+        .unwrap();
+    chart
+        .draw_series(LineSeries::new(series.iter().enumerate().map(|(i, s)| (i, s.1)), &BLUE))
+        .unwrap();
+    root.present().unwrap();
+}
+
 fn plot_net_metric<F>(
     output_dir: &str,
+    ctx: &TimeCtx,
     iface: &str,
     series: &[IntervalNetMetrics],
     metric: F,
@@ -595,14 +1038,8 @@ where
     F: Fn(&IntervalNetMetrics) -> f64,
 {
     let times: Vec<u64> = series.iter().map(|m| m.ts).collect();
-    let time_labels: Vec<String> = times.iter().map(|epoch| {
-        chrono::Local.timestamp_opt(*epoch as i64, 0)
-            .single()
-            .unwrap_or_else(|| chrono::Local.timestamp_opt(0, 0).single().unwrap())
-            .format("%H:%M:%S").to_string()
-    }).collect();
+    let (time_labels, x_desc) = time_axis(ctx, &times);
     let values: Vec<f64> = series.iter().map(|m| metric(m)).collect();
-    if values.iter().all(|&v| v == 0.0) { return Ok(()); }
     let y_min = values.iter().cloned().fold(f64::INFINITY, f64::min).min(0.0);
     let y_max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max).max(1.0);
 
@@ -623,7 +1060,7 @@ where
             .configure_mesh()
             .x_labels(8)
             .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
-            .x_desc("Time (HH:MM:SS)")
+            .x_desc(x_desc.as_str())
             .y_desc(ylabel)
             .draw()
             .unwrap();
@@ -652,7 +1089,7 @@ where
             .configure_mesh()
             .x_labels(8)
             .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
-            .x_desc("Time (HH:MM:SS)")
+            .x_desc(x_desc.as_str())
             .y_desc(ylabel)
             .draw()
             .unwrap();
@@ -671,14 +1108,9 @@ where
 /// Plot all CPU metrics (user/sys/idle/iowait) in one chart, SVG + PNG
 
 /// Plot all CPU metrics (user/sys/idle/iowait/guest) in one chart, SVG + PNG
-fn plot_cpu(output_dir: &str, series: &[CpuMetrics]) -> std::io::Result<()> {
+fn plot_cpu(output_dir: &str, ctx: &TimeCtx, series: &[CpuMetrics]) -> std::io::Result<()> {
     let times: Vec<u64> = series.iter().map(|m| m.ts).collect();
-    let time_labels: Vec<String> = times.iter().map(|epoch| {
-        chrono::Local.timestamp_opt(*epoch as i64, 0)
-            .single()
-            .unwrap_or_else(|| chrono::Local.timestamp_opt(0, 0).single().unwrap())
-            .format("%H:%M:%S").to_string()
-    }).collect();
+    let (time_labels, x_desc) = time_axis(ctx, &times);
 
     let user:   Vec<f64> = series.iter().map(|m| m.user).collect();
     let sys:    Vec<f64> = series.iter().map(|m| m.sys).collect();
@@ -707,7 +1139,7 @@ fn plot_cpu(output_dir: &str, series: &[CpuMetrics]) -> std::io::Result<()> {
             chart.configure_mesh()
                 .x_labels(8)
                 .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
-                .x_desc("Time (HH:MM:SS)")
+                .x_desc(x_desc.as_str())
                 .y_desc("CPU %")
                 .draw()
                 .unwrap();
@@ -754,7 +1186,7 @@ fn plot_cpu(output_dir: &str, series: &[CpuMetrics]) -> std::io::Result<()> {
             chart.configure_mesh()
                 .x_labels(8)
                 .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
-                .x_desc("Time (HH:MM:SS)")
+                .x_desc(x_desc.as_str())
                 .y_desc("CPU %")
                 .draw()
                 .unwrap();
@@ -790,14 +1222,9 @@ fn plot_cpu(output_dir: &str, series: &[CpuMetrics]) -> std::io::Result<()> {
 }
 
 
-fn plot_running_blocked(output_dir: &str, series: &[CpuMetrics]) -> std::io::Result<()> {
+fn plot_running_blocked(output_dir: &str, ctx: &TimeCtx, series: &[CpuMetrics]) -> std::io::Result<()> {
     let times: Vec<u64> = series.iter().map(|m| m.ts).collect();
-    let time_labels: Vec<String> = times.iter().map(|epoch| {
-        chrono::Local.timestamp_opt(*epoch as i64, 0)
-            .single()
-            .unwrap_or_else(|| chrono::Local.timestamp_opt(0, 0).single().unwrap())
-            .format("%H:%M:%S").to_string()
-    }).collect();
+    let (time_labels, x_desc) = time_axis(ctx, &times);
     let running: Vec<f64> = series.iter().map(|m| m.running.unwrap_or(0) as f64).collect();
     let blocked: Vec<f64> = series.iter().map(|m| m.blocked.unwrap_or(0) as f64).collect();
 
@@ -822,7 +1249,7 @@ fn plot_running_blocked(output_dir: &str, series: &[CpuMetrics]) -> std::io::Res
             .configure_mesh()
             .x_labels(8)
             .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
-            .x_desc("Time (HH:MM:SS)")
+            .x_desc(x_desc.as_str())
             .y_desc(label)
             .draw()
             .unwrap();
@@ -849,7 +1276,7 @@ fn plot_running_blocked(output_dir: &str, series: &[CpuMetrics]) -> std::io::Res
             .configure_mesh()
             .x_labels(8)
             .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
-            .x_desc("Time (HH:MM:SS)")
+            .x_desc(x_desc.as_str())
             .y_desc(label)
             .draw()
             .unwrap();
@@ -865,14 +1292,9 @@ fn plot_running_blocked(output_dir: &str, series: &[CpuMetrics]) -> std::io::Res
 }
 
 /// Plot all Memory metrics (% used/avail/cached) in one chart, SVG + PNG
-fn plot_mem(output_dir: &str, series: &[MemMetrics]) -> std::io::Result<()> {
+fn plot_mem(output_dir: &str, ctx: &TimeCtx, series: &[MemMetrics]) -> std::io::Result<()> {
     let times: Vec<u64> = series.iter().map(|m| m.ts).collect();
-    let time_labels: Vec<String> = times.iter().map(|epoch| {
-        chrono::Local.timestamp_opt(*epoch as i64, 0)
-            .single()
-            .unwrap_or_else(|| chrono::Local.timestamp_opt(0, 0).single().unwrap())
-            .format("%H:%M:%S").to_string()
-    }).collect();
+    let (time_labels, x_desc) = time_axis(ctx, &times);
     let used: Vec<f64> = series.iter().map(|m| m.used_percent).collect();
     let avail: Vec<f64> = series.iter().map(|m| m.avail_percent).collect();
     let cached: Vec<f64> = series.iter().map(|m| m.cached_percent).collect();
@@ -897,7 +1319,7 @@ fn plot_mem(output_dir: &str, series: &[MemMetrics]) -> std::io::Result<()> {
                 .configure_mesh()
                 .x_labels(8)
                 .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
-                .x_desc("Time (HH:MM:SS)")
+                .x_desc(x_desc.as_str())
                 .y_desc("Memory %")
                 .draw()
                 .unwrap();
@@ -930,7 +1352,7 @@ fn plot_mem(output_dir: &str, series: &[MemMetrics]) -> std::io::Result<()> {
                 .configure_mesh()
                 .x_labels(8)
                 .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
-                .x_desc("Time (HH:MM:SS)")
+                .x_desc(x_desc.as_str())
                 .y_desc("Memory %")
                 .draw()
                 .unwrap();
@@ -953,11 +1375,19 @@ fn plot_mem(output_dir: &str, series: &[MemMetrics]) -> std::io::Result<()> {
 
 
 // ==================== HTML Dashboard ====================
+#[allow(clippy::too_many_arguments)]
 fn write_index_html(
     output_dir: &str,
     devices: &[String],
     netifaces: &[String],
     tables: &[String],
+    device_pctl: &[String],
+    findings_html: &str,
+    correlation_html: &str,
+    raid_html: &str,
+    transport_html: &str,
+    unavailable: &[(&str, String)],
+    scope: &str,
     _cpu: &str,
     _mem: &str,
 ) -> std::io::Result<()> {
@@ -971,6 +1401,25 @@ fn write_index_html(
         "window.TABLES = [{}];",
         tables.iter().map(|t| format!("\"{}\"", t)).collect::<Vec<_>>().join(", ")
     );
+    // RAID tab only when the capture saw a resync/rebuild
+    let raid_tab = if raid_html.is_empty() {
+        String::new()
+    } else {
+        "<span class=\"section-tab\" onclick=\"showSection('raid')\">RAID</span>".to_string()
+    };
+    let transport_tab = if transport_html.is_empty() {
+        String::new()
+    } else {
+        "<span class=\"section-tab\" onclick=\"showSection('transport')\">Transport</span>".to_string()
+    };
+    // Disk metrics the capture kernel did not report, with the reason shown instead of a chart
+    let unavailable_js = format!(
+        "window.UNAVAILABLE = {{{}}};",
+        unavailable.iter().map(|(k, r)| format!("\"{}\": \"{}\"", k, r)).collect::<Vec<_>>().join(", ")
+    );
+    // Per-device percentile summary, shown above the disk charts
+    let pctl_js = format!("window.PCTL = {{{}}};", device_pctl.join(", "));
+    let scope = scope.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
     let netifaces_js = format!(
         "window.NETIFACES = [{}];",
         netifaces.iter().map(|n| format!("\"{}\"", n)).collect::<Vec<_>>().join(", ")
@@ -1000,21 +1449,35 @@ fn write_index_html(
     .table-link {{ font-size: 0.95em; margin: 0.5em 0; display: block; }}
     h2 {{ margin-top: 2em; }}
     #deviceSearch {{ width: 180px; margin-right: 1em; }}
+    tr.sev-critical td:first-child {{ background: #f4b0b0; font-weight: bold; }}
+    tr.sev-warning td:first-child {{ background: #f8e0a0; }}
+    p.scope {{ color: #555; margin-top: -0.5em; }}
   </style>
 </head>
 
 <body>
   <h1>{}</h1>
+  <p class="scope">Scope: {scope}</p>
   <!-- TAB BAR -->
   <div id="tabs">
-    <span class="section-tab active" onclick="showSection('disk')">Disk</span>
+    <span class="section-tab active" onclick="showSection('findings')">Findings</span>
+    <span class="section-tab" onclick="showSection('disk')">Disk</span>
     <span class="section-tab" onclick="showSection('cpu')">CPU</span>
     <span class="section-tab" onclick="showSection('mem')">Memory</span>
     <span class="section-tab" onclick="showSection('net')">Network</span>
+    <span class="section-tab" onclick="showSection('correlation')">Correlation</span>
+    {raid_tab}
+    {transport_tab}
+  </div>
+
+  <!-- FINDINGS SECTION -->
+  <div id="findings" class="section-content active">
+    <h2>Findings</h2>
+    {findings_html}
   </div>
 
   <!-- DISK SECTION -->
-  <div id="disk" class="section-content active">
+  <div id="disk" class="section-content">
     <div id="controls">
       <label for="deviceSearch"><b>Device:</b></label>
       <input type="text" id="deviceSearch" placeholder="Type to filter disks...">
@@ -1036,8 +1499,11 @@ fn write_index_html(
         <label class="metric"><input type="checkbox" class="metric-cb" value="sectors_discarded_s">Discard Sectors/sec</label>
         <label class="metric"><input type="checkbox" class="metric-cb" value="discard_kbs">Discard KB/sec</label>
         <label class="metric"><input type="checkbox" class="metric-cb" value="await_discard_ms">Discard Await (ms)</label>
+        <label class="metric"><input type="checkbox" class="metric-cb" value="flush_s">Flushes/sec</label>
+        <label class="metric"><input type="checkbox" class="metric-cb" value="await_flush_ms">Flush Await (ms)</label>
       </span>
     </div>
+    <div id="pctl"></div>
     <div id="graphs"></div>
     <hr>
     <h2>Stats Tables</h2>
@@ -1074,14 +1540,40 @@ fn write_index_html(
         <label class="metric"><input type="checkbox" class="net-metric-cb" value="tx_errs">TX Errors/sec</label>
         <label class="metric"><input type="checkbox" class="net-metric-cb" value="rx_drop">RX Drops/sec</label>
         <label class="metric"><input type="checkbox" class="net-metric-cb" value="tx_drop">TX Drops/sec</label>
+        <label class="metric"><input type="checkbox" class="net-metric-cb" value="rx_util" checked>RX Link Util %</label>
+        <label class="metric"><input type="checkbox" class="net-metric-cb" value="tx_util" checked>TX Link Util %</label>
+        <label class="metric"><input type="checkbox" class="net-metric-cb" value="rx_crc_errs">RX CRC Errors/sec</label>
+        <label class="metric"><input type="checkbox" class="net-metric-cb" value="rx_fifo_errs">RX FIFO Errors/sec</label>
+        <label class="metric"><input type="checkbox" class="net-metric-cb" value="rx_missed_errs">RX Missed/sec</label>
       </span>
     </div>
     <div id="net-graphs"></div>
   </div>
 
+  <!-- CORRELATION SECTION -->
+  <div id="correlation" class="section-content">
+    <h2>Cross-subsystem correlation</h2>
+    {correlation_html}
+  </div>
+
+  <!-- RAID SECTION -->
+  <div id="raid" class="section-content">
+    <h2>md RAID / DRBD resync and rebuild windows</h2>
+    {raid_html}
+  </div>
+
+  <!-- TRANSPORT SECTION -->
+  <div id="transport" class="section-content">
+    <h2>FC / iSCSI events and path I/O per SCSI host</h2>
+    <p>Red lines mark port/session state changes and error counter increases.</p>
+    {transport_html}
+  </div>
+
 <script>
 {devices_js}
 {tables_js}
+{unavailable_js}
+{pctl_js}
 {netifaces_js}
 
 function showSection(sec) {{
@@ -1128,12 +1620,49 @@ function populateTableLinks() {{
   }});
 }}
 
+// Percentile summary of the selected device: every interval, not the chart buckets
+function showPercentiles(device) {{
+  const div = document.getElementById('pctl');
+  div.innerHTML = "";
+  const rows = (window.PCTL || {{}})[device];
+  if (!rows) return;
+  const table = document.createElement('table');
+  table.border = "1";
+  table.cellPadding = "4";
+  const head = table.insertRow();
+  ["Metric", "Mean", "StdDev", "P50", "P90", "P95", "P99", "P99.9", "Max", "Time above threshold"].forEach(h => {{
+    const th = document.createElement('th');
+    th.textContent = h;
+    head.appendChild(th);
+  }});
+  Object.entries(rows).forEach(([metric, v]) => {{
+    const tr = table.insertRow();
+    [metric, ...v].forEach((x, i) => {{
+      const td = tr.insertCell();
+      td.textContent = x;
+      if (i > 0) td.style.textAlign = "right";
+    }});
+  }});
+  const title = document.createElement('h3');
+  title.textContent = `${{device}} percentiles`;
+  div.appendChild(title);
+  div.appendChild(table);
+}}
+
 function showGraphs() {{
   const device = document.getElementById('deviceSelect').value;
+  showPercentiles(device);
   const checked = Array.from(document.querySelectorAll('.metric-cb:checked')).map(cb => cb.value);
   const graphsDiv = document.getElementById('graphs');
   graphsDiv.innerHTML = "";
   checked.forEach(metric => {{
+    const reason = (window.UNAVAILABLE || {{}})[metric];
+    if (reason) {{
+      const p = document.createElement('p');
+      p.textContent = `${{device}} ${{metric}}: ${{reason}}`;
+      graphsDiv.appendChild(p);
+      return;
+    }}
     const img = document.createElement('img');
     img.className = "graph";
     img.src = `${{device}}_${{metric}}.svg`;
@@ -1192,93 +1721,20 @@ window.addEventListener('DOMContentLoaded', () => {{
 
 // ========== Stats helpers ==========
 
-/// Mean of a Vec<IntervalDiskMetrics> by accessor
-fn mean<F>(data: &[IntervalDiskMetrics], f: F) -> f64
-where
-    F: Fn(&IntervalDiskMetrics) -> f64,
-{
-    let sum: f64 = data.iter().map(|x| f(x)).sum();
-    let count = data.len() as f64;
-    if count == 0.0 { 0.0 } else { sum / count }
-}
-
-/// Max of a Vec<IntervalDiskMetrics> by accessor
-fn max<F>(data: &[IntervalDiskMetrics], f: F) -> f64
-where
-    F: Fn(&IntervalDiskMetrics) -> f64,
-{
-    data.iter().map(|x| f(x)).fold(0.0, |acc, v| acc.max(v))
-}
-
-
-/// Helper to load disk metrics (for use by multipath module)
-pub fn get_disk_metrics_map(file_path: &str) -> std::io::Result<std::collections::HashMap<String, Vec<IntervalDiskMetrics>>> {
-    // Essentially copy your parsing up to the per-device metrics map in analyze.rs
-    // (Same as in analyze())
-    use std::collections::HashMap;
-    use std::fs::File;
-    use std::io::{BufRead, BufReader};
-
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
-    let mut per_device: HashMap<String, Vec<(u64, crate::DiskStat)>> = HashMap::new();
-
-    for line in reader.lines().flatten() {
-        if line.starts_with('#') { continue; }
-        let mut cols = line.split(',');
-        let typ = cols.next().unwrap_or("");
-        if typ == "DISK" {
-            let ts = cols.next().unwrap().parse::<u64>().unwrap_or(0);
-            let fields: Vec<&str> = cols.collect();
-            if let Some(stat) = crate::analyze::parse_disk_from_fields(&fields) {
-                per_device.entry(stat.name.clone()).or_default().push((ts, stat));
-            }
-        }
-    }
-    // Convert to per-device Vec<IntervalDiskMetrics>
+/// Helper to load disk metrics (for use by multipath module).
+/// Only intervals ending inside `window` are returned.
+pub fn get_disk_metrics_map(file_path: &str, window: &TimeWindow) -> std::io::Result<HashMap<String, Vec<IntervalDiskMetrics>>> {
+    let mut prev: HashMap<String, (u64, DiskStat)> = HashMap::new();
     let mut out: HashMap<String, Vec<IntervalDiskMetrics>> = HashMap::new();
-    for (dev, rows) in per_device {
-        let mut prev: Option<(u64, crate::DiskStat)> = None;
-        let mut metrics = Vec::new();
-        for (ts, stat) in rows {
-            if let Some((last_ts, last_stat)) = &prev {
-                let dt = ts.saturating_sub(*last_ts);
-                if dt == 0 { prev = Some((ts, stat.clone())); continue; }
-                let d_reads = stat.reads.saturating_sub(last_stat.reads);
-                let d_writes = stat.writes.saturating_sub(last_stat.writes);
-                let d_sectors_read = stat.sectors_read.saturating_sub(last_stat.sectors_read);
-                let d_sectors_written = stat.sectors_written.saturating_sub(last_stat.sectors_written);
-                let delta_weighted_io_time_ms = stat.weighted_io_time_ms.saturating_sub(last_stat.weighted_io_time_ms);
-                let avg_queue_depth = delta_weighted_io_time_ms as f64 / (dt as f64 * 1000.0);
-                let delta_io_time_ms = stat.io_time_ms.saturating_sub(last_stat.io_time_ms);
-                let qlen = if delta_io_time_ms > 0 {
-                    delta_weighted_io_time_ms as f64 / delta_io_time_ms as f64
-                } else { 0.0 };
-                let rps = d_reads as f64 / dt as f64;
-                let wps = d_writes as f64 / dt as f64;
-                let rd_kbs = d_sectors_read as f64 * 512.0 / 1024.0 / dt as f64;
-                let wr_kbs = d_sectors_written as f64 * 512.0 / 1024.0 / dt as f64;
-
-                metrics.push(IntervalDiskMetrics {
-                    ts,
-                    rps,
-                    wps,
-                    io_sec: rps + wps,
-                    rd_kbs,
-                    wr_kbs,
-                    kb_sec: rd_kbs + wr_kbs,
-                    avg_queue_depth,
-                    qlen,
-                    svctim: 0.0, // Not used in summary
-                    await_rd: 0.0, await_wr: 0.0, discards_s: 0.0, discards_merged_s: 0.0, sectors_discarded_s: 0.0, await_discard_ms: 0.0, discard_kbs: 0.0,
-                });
-            }
-            prev = Some((ts, stat));
-        }
-        if !metrics.is_empty() {
-            out.insert(dev, metrics);
+    for rec in Reader::open(file_path)?.flatten() {
+        let Record::Disk { ts, stat } = rec else { continue };
+        if let Some((last_ts, last_stat)) = prev.get(&stat.name)
+            && window.contains(ts)
+            && let Some(d) = DiskDelta::between(last_stat, &stat, ts.saturating_sub(*last_ts))
+        {
+            out.entry(stat.name.clone()).or_default().push(IntervalDiskMetrics::from_delta(ts, &d));
         }
+        prev.insert(stat.name.clone(), (ts, stat));
     }
     Ok(out)
 }
-
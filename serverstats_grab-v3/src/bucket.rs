@@ -0,0 +1,365 @@
+/*!
+ * Time Bucket Aggregation Module
+ * ------------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * `--bucket 1m|5m|1h` folds capture intervals into fixed time buckets, aligned to
+ * the display timezone (1h buckets start on the hour, 1d at midnight).
+ *
+ * Playback reports mean, min, max and p95 of each rate per bucket. Every interval
+ * is weighted: rates and percentages by its elapsed time, per-I/O latencies
+ * (await, svctim) by its I/O count and qlen by its busy time, so a bucket mean
+ * is exactly total/total over the bucket (e.g. total read time / total reads).
+ * An interval is counted in the bucket it ends in.
+ *
+ * Analysis resamples the raw counters at bucket ends instead, so each chart
+ * point is the exact mean over its bucket.
+ */
+
+use std::collections::HashMap;
+
+use serverstats_format::{CpuPercent, CpuStat, DiskDelta, MemPercent, NetDelta};
+use serverstats_format::output::{Cell, Col, OutputOpts, Table};
+use serverstats_format::timeutil::{self, TimeCtx};
+
+
+/// Parses a bucket length (`30s`, `1m`, `5m`, `1h`, `1d`).
+pub fn parse_bucket(s: &str) -> Option<u64> {
+    timeutil::parse_duration(s).filter(|&secs| secs > 0)
+}
+
+/// Start of the bucket holding `ts`, aligned in the display timezone.
+pub fn bucket_start(ts: u64, bucket: u64, ctx: &TimeCtx) -> u64 {
+    let offset = ctx.offset_at(ts).local_minus_utc() as i64;
+    let local = ts as i64 + offset;
+    (local - local.rem_euclid(bucket as i64) - offset) as u64
+}
+
+/// Baseline row plus the last row of each bucket, for resampling counters.
+/// Deltas between consecutive kept rows are then exact per-bucket means. The
+/// streaming charts (stream::Series) keep the same rows; this is their reference.
+#[cfg(test)]
+pub fn bucket_ends<'a, T>(rows: &'a [(u64, T)], bucket: u64, ctx: &TimeCtx) -> Vec<&'a (u64, T)> {
+    let mut out: Vec<&(u64, T)> = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        let last_in_bucket = rows.get(i + 1)
+            .is_none_or(|next| bucket_start(next.0, bucket, ctx) != bucket_start(row.0, bucket, ctx));
+        if i == 0 || last_in_bucket {
+            out.push(row);
+        }
+    }
+    out
+}
+
+/// Weighted samples of one metric within a bucket.
+#[derive(Debug, Default, Clone)]
+pub struct Agg {
+    samples: Vec<(f64, f64)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    pub p95: f64,
+}
+
+impl Agg {
+    /// Adds a value with its weight; zero-weight samples (e.g. await with no I/O) are ignored.
+    pub fn add(&mut self, value: f64, weight: f64) {
+        if weight > 0.0 && value.is_finite() {
+            self.samples.push((value, weight));
+        }
+    }
+
+    pub fn stats(&self) -> Option<Stats> {
+        let total: f64 = self.samples.iter().map(|(_, w)| w).sum();
+        if total <= 0.0 { return None; }
+        let mean = self.samples.iter().map(|(v, w)| v * w).sum::<f64>() / total;
+        let mut sorted = self.samples.clone();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // Weighted p95: smallest value with at least 95% of the weight at or below it
+        let mut cum = 0.0;
+        let mut p95 = sorted[sorted.len() - 1].0;
+        for (v, w) in &sorted {
+            cum += w;
+            if cum >= 0.95 * total {
+                p95 = *v;
+                break;
+            }
+        }
+        Some(Stats { mean, min: sorted[0].0, max: sorted[sorted.len() - 1].0, p95 })
+    }
+}
+
+/// A metric value with its weight for one interval, None when not available.
+pub type Sample = Option<(f64, f64)>;
+
+pub const DISK_METRICS: &[&str] = &[
+    "r_s", "w_s", "rd_kbs", "wr_kbs", "avg_qdepth", "qlen", "svctim", "await_rd_ms", "await_wr_ms",
+    "discards_s", "discard_kbs", "await_discard_ms", "flush_s", "await_flush_ms",
+];
+
+/// Disk samples in DISK_METRICS order; discard metrics need an 18+ field diskstats layout.
+pub fn disk_samples(d: &DiskDelta, layout: u8) -> Vec<Sample> {
+    let dt = d.dt as f64;
+    let discard = |v: f64, w: f64| (layout >= 18).then_some((v, w));
+    vec![
+        Some((d.r_s(), dt)), Some((d.w_s(), dt)), Some((d.rd_kbs(), dt)), Some((d.wr_kbs(), dt)),
+        Some((d.avg_queue_depth(), dt)),
+        Some((d.qlen(), d.io_time_ms as f64)),
+        Some((d.svctim(), (d.reads + d.writes) as f64)),
+        Some((d.await_rd(), d.reads as f64)),
+        Some((d.await_wr(), d.writes as f64)),
+        discard(d.discards_s(), dt), discard(d.discard_kbs(), dt),
+        discard(d.await_discard(), d.discards as f64),
+        d.flush_s().map(|v| (v, dt)),
+        d.await_flush().zip(d.flushes).map(|(v, n)| (v, n as f64)),
+    ]
+}
+
+pub const CPU_METRICS: &[&str] = &["user", "system", "idle", "iowait", "nice", "guest", "running", "blocked"];
+
+pub fn cpu_samples(p: &CpuPercent, stat: &CpuStat, dt: u64) -> Vec<Sample> {
+    let dt = dt as f64;
+    [p.user, p.system, p.idle, p.iowait, p.nice, p.guest, stat.procs_running as f64, stat.procs_blocked as f64]
+        .iter()
+        .map(|&v| Some((v, dt)))
+        .collect()
+}
+
+pub const MEM_METRICS: &[&str] = &["used_pct", "avail_pct", "cached_pct", "free_pct"];
+
+/// Memory is a gauge: each sample is weighted by the time since the previous one.
+pub fn mem_samples(p: &MemPercent, dt: u64) -> Vec<Sample> {
+    let dt = dt as f64;
+    [p.used, p.avail, p.cached, p.free].iter().map(|&v| Some((v, dt))).collect()
+}
+
+pub const NET_METRICS: &[&str] = &[
+    "rx_kbs", "tx_kbs", "rx_pkts_s", "tx_pkts_s", "rx_err_s", "tx_err_s", "drop_s", "util_pct",
+];
+
+/// Net samples in NET_METRICS order; `util` is the link utilization % when NETX records are present.
+pub fn net_samples(d: &NetDelta, util: Option<f64>) -> Vec<Sample> {
+    let dt = d.dt as f64;
+    vec![
+        Some((d.rx_kbs(), dt)), Some((d.tx_kbs(), dt)),
+        Some((d.rate(d.rx_packets), dt)), Some((d.rate(d.tx_packets), dt)),
+        Some((d.rate(d.rx_errs), dt)), Some((d.rate(d.tx_errs), dt)),
+        Some((d.rate(d.rx_drop + d.tx_drop), dt)),
+        util.map(|u| (u, dt)),
+    ]
+}
+
+/// Bucket table columns for per-device, per-interface and system-wide playback.
+pub const DEVICE_COLS: &[Col] = &[
+    Col::new("device", "Device").l(10),
+    Col::new("time", "Time").l(8),
+    Col::new("epoch", "Epoch").l(10),
+    Col::new("secs", "Secs").r(6),
+    Col::new("metric", "Metric").l(16),
+    Col::new("mean", "Mean").r(12).prec(2),
+    Col::new("min", "Min").r(12).prec(2),
+    Col::new("max", "Max").r(12).prec(2),
+    Col::new("p95", "P95").r(12).prec(2),
+];
+pub const IFACE_COLS: &[Col] = &[
+    Col::new("iface", "Iface").l(10),
+    Col::new("time", "Time").l(8),
+    Col::new("epoch", "Epoch").l(10),
+    Col::new("secs", "Secs").r(6),
+    Col::new("metric", "Metric").l(16),
+    Col::new("mean", "Mean").r(12).prec(2),
+    Col::new("min", "Min").r(12).prec(2),
+    Col::new("max", "Max").r(12).prec(2),
+    Col::new("p95", "P95").r(12).prec(2),
+];
+pub const SYSTEM_COLS: &[Col] = &[
+    Col::new("time", "Time").l(8),
+    Col::new("epoch", "Epoch").l(10),
+    Col::new("secs", "Secs").r(6),
+    Col::new("metric", "Metric").l(16),
+    Col::new("mean", "Mean").r(12).prec(2),
+    Col::new("min", "Min").r(12).prec(2),
+    Col::new("max", "Max").r(12).prec(2),
+    Col::new("p95", "P95").r(12).prec(2),
+];
+
+/// Streams bucketed playback rows: one row per bucket, entity and metric.
+/// `--columns` names that are metrics pick the metrics; the rest pick table columns.
+pub struct Bucketer<'a> {
+    bucket: u64,
+    metrics: Vec<(usize, &'static str)>,
+    with_entity: bool,
+    start: Option<u64>,
+    order: Vec<String>,
+    aggs: HashMap<String, (u64, Vec<Agg>)>,
+    table: Table<'a>,
+}
+
+impl<'a> Bucketer<'a> {
+    pub fn new(bucket: u64, metrics: &[&'static str], cols: &'a [Col], out: &OutputOpts) -> Result<Self, String> {
+        let mut picked: Vec<(usize, &'static str)> = Vec::new();
+        let mut rest: Vec<&str> = Vec::new();
+        for name in out.columns.as_deref().unwrap_or("").split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match metrics.iter().position(|m| m.eq_ignore_ascii_case(name)) {
+                Some(i) => picked.push((i, metrics[i])),
+                None => rest.push(name),
+            }
+        }
+        if picked.is_empty() {
+            picked = metrics.iter().copied().enumerate().collect();
+        }
+        let table_opts = OutputOpts {
+            format: out.format,
+            columns: if rest.is_empty() { None } else { Some(rest.join(",")) },
+            ..Default::default()
+        };
+        let table = Table::new(cols, &table_opts).map_err(|e| format!("{}; metrics: {}", e, metrics.join(",")))?;
+        Ok(Bucketer {
+            bucket,
+            metrics: picked,
+            with_entity: cols[0].key != "time",
+            start: None,
+            order: Vec::new(),
+            aggs: HashMap::new(),
+            table,
+        })
+    }
+
+    /// Adds one interval of `dt` seconds ending at `ts`. `values` are (value, weight)
+    /// in metric order, None where the metric is not available for this interval.
+    pub fn add(&mut self, ctx: &TimeCtx, ts: u64, entity: &str, dt: u64, values: &[Sample]) {
+        let start = bucket_start(ts, self.bucket, ctx);
+        if self.start.is_some_and(|s| s != start) {
+            self.flush(ctx);
+        }
+        self.start = Some(start);
+        if !self.aggs.contains_key(entity) {
+            self.order.push(entity.to_string());
+        }
+        let (secs, aggs) = self.aggs.entry(entity.to_string())
+            .or_insert_with(|| (0, vec![Agg::default(); values.len()]));
+        *secs += dt;
+        for (agg, v) in aggs.iter_mut().zip(values) {
+            if let Some((value, weight)) = v {
+                agg.add(*value, *weight);
+            }
+        }
+    }
+
+    fn flush(&mut self, ctx: &TimeCtx) {
+        let Some(start) = self.start.take() else { return };
+        for entity in std::mem::take(&mut self.order) {
+            let Some((secs, aggs)) = self.aggs.remove(&entity) else { continue };
+            for &(i, metric) in &self.metrics {
+                let stats = aggs.get(i).and_then(Agg::stats);
+                let mut cells = vec![
+                    Cell::from(entity.as_str()), Cell::Time(start), start.into(), secs.into(), metric.into(),
+                    stats.map(|s| s.mean).into(), stats.map(|s| s.min).into(),
+                    stats.map(|s| s.max).into(), stats.map(|s| s.p95).into(),
+                ];
+                if !self.with_entity {
+                    cells.remove(0);
+                }
+                self.table.row(Some(ctx), &cells);
+            }
+        }
+    }
+
+    pub fn finish(&mut self, ctx: &TimeCtx, empty_msg: &str) {
+        self.flush(ctx);
+        self.table.finish(empty_msg);
+    }
+}
+
+/// Playback output: the per-interval table, or bucket rows with `--bucket`.
+pub enum Sink<'a> {
+    Rows(Table<'a>),
+    Buckets(Bucketer<'a>),
+}
+
+impl<'a> Sink<'a> {
+    pub fn new(bucket: Option<u64>, cols: &'a [Col], metrics: &[&'static str], bucket_cols: &'a [Col],
+               out: &OutputOpts) -> Result<Self, String> {
+        match bucket {
+            Some(b) => Ok(Sink::Buckets(Bucketer::new(b, metrics, bucket_cols, out)?)),
+            None => Ok(Sink::Rows(Table::new(cols, out)?)),
+        }
+    }
+
+    /// One interval: `cells` for the table, `samples` (metric order) for buckets.
+    pub fn interval(&mut self, ctx: &TimeCtx, ts: u64, entity: &str, dt: u64, cells: &[Cell],
+                    samples: impl FnOnce() -> Vec<Sample>) {
+        match self {
+            Sink::Rows(table) => table.row(Some(ctx), cells),
+            Sink::Buckets(b) => b.add(ctx, ts, entity, dt, &samples()),
+        }
+    }
+
+    /// Free-form text line, per-interval table only.
+    pub fn note(&mut self, line: &str) {
+        if let Sink::Rows(table) = self {
+            table.note(line);
+        }
+    }
+
+    /// Writes out what is complete so far; an open bucket stays open.
+    pub fn flush(&mut self) {
+        match self {
+            Sink::Rows(table) => table.flush(),
+            Sink::Buckets(b) => b.table.flush(),
+        }
+    }
+
+    pub fn finish(&mut self, ctx: &TimeCtx, empty_msg: &str) {
+        match self {
+            Sink::Rows(table) => table.finish(empty_msg),
+            Sink::Buckets(b) => b.finish(ctx, empty_msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serverstats_format::timeutil::DisplayTz;
+
+    #[test]
+    fn weighted_stats() {
+        let mut a = Agg::default();
+        // 10s at 100/s, 50s at 10/s: time-weighted mean 25, not the plain mean 55
+        a.add(100.0, 10.0);
+        a.add(10.0, 50.0);
+        a.add(999.0, 0.0);
+        let s = a.stats().unwrap();
+        assert_eq!((s.mean, s.min, s.max), (25.0, 10.0, 100.0));
+        assert_eq!(s.p95, 100.0);
+        assert!(Agg::default().stats().is_none());
+
+        let mut b = Agg::default();
+        for v in 1..=100 {
+            b.add(v as f64, 1.0);
+        }
+        assert_eq!(b.stats().unwrap().p95, 95.0);
+    }
+
+    #[test]
+    fn buckets_align_to_display_timezone() {
+        let utc = TimeCtx::new(DisplayTz::parse("UTC").unwrap());
+        let ist = TimeCtx::new(DisplayTz::parse("+05:30").unwrap());
+        // 2025-03-14 13:47:10 UTC
+        let ts = 1741960030;
+        assert_eq!(bucket_start(ts, 3600, &utc), 1741957200); // 13:00 UTC
+        assert_eq!(bucket_start(ts, 3600, &ist), 1741959000); // 19:00 IST = 13:30 UTC
+        assert_eq!(bucket_start(ts, 300, &utc), 1741959900);
+        assert_eq!(parse_bucket("5m"), Some(300));
+        assert_eq!(parse_bucket("0m"), None);
+
+        let rows: Vec<(u64, u32)> = [0u64, 20, 40, 60, 80, 130, 170].iter().map(|t| (1741957200 + t, 0)).collect();
+        let kept: Vec<u64> = bucket_ends(&rows, 60, &utc).iter().map(|r| r.0 - 1741957200).collect();
+        assert_eq!(kept, [0, 40, 80, 170]);
+    }
+}
@@ -0,0 +1,1029 @@
+/*!
+ * Capture Comparison Module
+ * -------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * `-c <before.dat> <after.dat>` compares two captures of the same host, e.g.
+ * before and after a tuning change, a kernel update or a firmware update,
+ * instead of two dashboards open in two browser tabs:
+ *
+ *   - devices are matched by name; with the `multipath -ll` output taken with
+ *     each capture, multipath maps are matched by WWID (dm-N names change
+ *     across reboots) and a path device whose LUN changed is not matched
+ *   - side-by-side distributions (mean, p50..p99.9, max) of every metric,
+ *     with the p95 change
+ *   - before/after overlay charts on an elapsed-time axis
+ *   - a verdict per device, interface, CPU, memory and per subsystem, from the
+ *     p95 of the latency, queue, iowait, memory and error metrics
+ *     (VERDICT_METRICS). Throughput only shows the load: it follows the
+ *     workload, not the change, so a large load change is flagged instead
+ *
+ * Disk metrics one capture's kernel did not report (discards on 14-field
+ * diskstats, flushes before 5.5, see DISKLAYOUT) show as n/a and take no part in
+ * the verdict, instead of comparing against zero.
+ *
+ * Each capture is read in one streaming pass, as for `-a` (stream.rs), with
+ * --from/--to resolved against each capture, so `--from +1h --to +3h` compares
+ * the same part of two test runs. Both are charted with the same bucket.
+ * Output goes to compare_<before>_vs_<after>/: compare.txt, compare.json,
+ * the overlay charts and index.html.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::time::Instant;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use rayon::prelude::*;
+use serverstats_format::{DiskStat, Reader, Record};
+use serverstats_format::output::json_str;
+use serverstats_format::timeutil::{TimeCtx, TimeWindow};
+
+use crate::analyze::{self, Chart, CpuMetrics, IntervalDiskMetrics, IntervalNetMetrics, MemMetrics, NetRow, DISK_CHARTS, MEM_STATS, NET_CHARTS};
+use crate::devselect::{DevSelect, IfaceSelect};
+use crate::findings::{self, Finding, Severity};
+use crate::mpath::MultipathDevice;
+use crate::netext::NetExt;
+use crate::percentile::{DistAcc, Distribution, PERCENTILES};
+use crate::report::{html_escape, CPU_CHARTS};
+use crate::stream::{self, CountingReader, Dists, MemTracker, Progress, Tracker};
+
+/// Metrics that decide a verdict: (key, higher is worse, smallest p95 change that counts).
+const VERDICT_METRICS: &[(&str, bool, f64)] = &[
+    ("await_rd", true, 1.0),
+    ("await_wr", true, 1.0),
+    ("svctim", true, 0.5),
+    ("qlen", true, 1.0),
+    ("await_discard_ms", true, 1.0),
+    ("await_flush_ms", true, 1.0),
+    ("iowait", true, 2.0),
+    ("sys", true, 2.0),
+    ("blocked", true, 1.0),
+    ("avail", false, 2.0),
+    ("swap_used_kb", true, 65536.0),
+    ("rx_drop", true, 1.0),
+    ("tx_drop", true, 1.0),
+    ("rx_errs", true, 0.1),
+    ("tx_errs", true, 0.1),
+    ("rx_crc_errs", true, 0.1),
+    ("rx_fifo_errs", true, 0.1),
+    ("rx_missed_errs", true, 0.1),
+];
+
+/// A p95 change must also be at least this fraction of the before value.
+const MIN_CHANGE: f64 = 0.10;
+/// Mean load changes beyond this fraction are flagged next to the verdict.
+const LOAD_CHANGE: f64 = 0.25;
+
+const SUBSYSTEMS: [&str; 4] = ["disk", "cpu", "memory", "net"];
+
+// ==================== One capture ====================
+
+/// Chart series and distributions of one device, interface, the CPU or memory.
+pub(crate) struct Summary<M> {
+    pub(crate) chart: Vec<M>,
+    pub(crate) dists: Dists,
+}
+
+impl<M> Summary<M> {
+    pub(crate) fn dist(&self, key: &str) -> Option<&Distribution> {
+        self.dists.iter().find(|(k, _)| *k == key).and_then(|(_, d)| d.as_ref())
+    }
+}
+
+/// What compare and fleet need of one capture, read in one pass.
+pub(crate) struct Capture {
+    pub(crate) path: String,
+    pub(crate) ctx: TimeCtx,
+    /// First and last sample in the window
+    pub(crate) start: u64,
+    pub(crate) end: u64,
+    pub(crate) disks: BTreeMap<String, Summary<IntervalDiskMetrics>>,
+    pub(crate) cpu: Summary<CpuMetrics>,
+    pub(crate) mem: Summary<MemMetrics>,
+    pub(crate) nets: BTreeMap<String, Summary<IntervalNetMetrics>>,
+    pub(crate) findings: Vec<Finding>,
+    /// Disk metrics the capture kernel did not report, with the reason (no distributions for them)
+    pub(crate) unavailable: Vec<(&'static str, String)>,
+}
+
+impl Capture {
+    /// "path (start - end, timezone)"
+    fn describe(&self) -> String {
+        format!("{} ({} - {}, {})", self.path, self.ctx.format(self.start, "%Y-%m-%d %H:%M:%S"),
+            self.ctx.format(self.end, "%Y-%m-%d %H:%M:%S"), self.ctx.label())
+    }
+
+    /// "flush_s, await_flush_ms not available (...)" lines for the report header.
+    fn unavailable_text(&self) -> Vec<String> {
+        let mut by_reason: Vec<(&str, Vec<&str>)> = Vec::new();
+        for (key, reason) in &self.unavailable {
+            match by_reason.iter_mut().find(|(r, _)| r == reason) {
+                Some((_, keys)) => keys.push(key),
+                None => by_reason.push((reason, vec![key])),
+            }
+        }
+        by_reason.into_iter().map(|(reason, keys)| format!("{} {}", keys.join(", "), reason)).collect()
+    }
+
+    /// Subsystem of a finding's subject.
+    fn subsystem_of(&self, subject: &str) -> &'static str {
+        match subject {
+            "cpu" => "cpu",
+            "memory" => "memory",
+            s if self.nets.contains_key(s) => "net",
+            _ => "disk",
+        }
+    }
+}
+
+/// Reads a capture in one streaming pass: chart series (one point per `chart_bucket` when set),
+/// distributions and findings of each selected device and interface, the CPU and memory.
+pub(crate) fn read_capture(path: &str, mut ctx: TimeCtx, window: &TimeWindow, sel: &DevSelect,
+                           ifaces: &IfaceSelect, chart_bucket: Option<u64>) -> io::Result<Capture> {
+    let mut disk_trackers: HashMap<String, Tracker<DiskStat, IntervalDiskMetrics>> = HashMap::new();
+    let mut cpu_tracker = Tracker::new(chart_bucket, analyze::cpu_interval, CPU_CHARTS, findings::cpu_detector());
+    let mut mem_tracker = MemTracker::new(chart_bucket);
+    let mut mem_dists: Vec<DistAcc> = MEM_STATS.iter().map(|_| DistAcc::new(None)).collect();
+    let mut mem_prev: Option<u64> = None;
+    let mut net_trackers: HashMap<String, Tracker<NetRow, IntervalNetMetrics>> = HashMap::new();
+    // Latest NETX per iface, written just before its NET row
+    let mut pending_netx: HashMap<String, (u64, NetExt)> = HashMap::new();
+    let (mut start, mut end): (Option<u64>, u64) = (None, 0);
+    let mut min_disk_layout: Option<u8> = None;
+    let mut flush_counters = false;
+
+    let size = fs::metadata(path)?.len();
+    let parse = Progress::new("Parsing", size, stream::fmt_bytes);
+    for rec in Reader::new(BufReader::new(CountingReader::new(File::open(path)?, &parse))).flatten() {
+        let ts = rec.ts();
+        if window.is_past(ts) { break; }
+        if window.contains(ts) && matches!(rec, Record::Disk { .. } | Record::Cpu { .. } | Record::Mem { .. } | Record::Net { .. }) {
+            start.get_or_insert(ts);
+            end = ts;
+        }
+        match rec {
+            Record::Tz { ts, offset, zone } => ctx.note_host_tz(ts, offset, &zone),
+            Record::DiskLayout { fields, .. } => {
+                min_disk_layout = Some(min_disk_layout.map_or(fields, |l| l.min(fields)));
+            }
+            Record::Disk { ts, stat } if sel.wants(&stat.name) => {
+                flush_counters |= stat.flushes.is_some();
+                disk_trackers.entry(stat.name.clone())
+                    .or_insert_with(|| Tracker::new(chart_bucket, analyze::disk_interval, DISK_CHARTS, findings::disk_detector(&stat.name)))
+                    .push(ts, stat, window, &ctx);
+            }
+            Record::Cpu { ts, stat } => cpu_tracker.push(ts, stat, window, &ctx),
+            Record::Mem { ts, stat } if window.contains(ts) => {
+                let m = analyze::mem_point(ts, &stat);
+                let dt = mem_prev.map_or(0, |p| ts.saturating_sub(p));
+                mem_prev = Some(ts);
+                for ((_, _, f), acc) in MEM_STATS.iter().zip(&mut mem_dists) {
+                    acc.add(f(&m), dt);
+                }
+                mem_tracker.push(m, &ctx);
+            }
+            Record::Net { ts, stat } if ifaces.wants(&stat.iface) => {
+                let ext = pending_netx.remove(&stat.iface).filter(|(t, _)| *t == ts).map(|(_, e)| e);
+                net_trackers.entry(stat.iface.clone())
+                    .or_insert_with(|| Tracker::new(chart_bucket, analyze::net_row_interval, NET_CHARTS, findings::net_detector(&stat.iface)))
+                    .push(ts, (stat, ext), window, &ctx);
+            }
+            Record::NetExt { ts, iface, stat } => {
+                pending_netx.insert(iface, (ts, stat));
+            }
+            _ => {}
+        }
+    }
+    println!("Parsed {} ({}) in {:.1}s", path, stream::fmt_bytes(size), parse.secs());
+    let Some(start) = start else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}: no samples in the time window", path)));
+    };
+
+    let unavailable = analyze::unavailable_disk_metrics(min_disk_layout, flush_counters);
+    let mut found: Vec<Finding> = Vec::new();
+    let mut disks = BTreeMap::new();
+    for (dev, tracker) in disk_trackers {
+        let mut done = tracker.finish();
+        // Zeros the kernel never counted are not a measurement
+        for (key, d) in done.dists.iter_mut() {
+            if unavailable.iter().any(|(k, _)| k == key) { *d = None; }
+        }
+        // No interval in the window
+        if done.dists.iter().all(|(_, d)| d.is_none()) { continue; }
+        // --no-idle: no I/O anywhere in the window
+        if sel.no_idle && done.dists.iter().all(|(_, d)| d.as_ref().is_none_or(|d| d.max == 0.0)) { continue; }
+        found.extend(done.findings);
+        disks.insert(dev, Summary { chart: done.chart, dists: done.dists });
+    }
+    let cpu = cpu_tracker.finish();
+    found.extend(cpu.findings);
+    let (mem_chart, mem_found) = mem_tracker.finish();
+    found.extend(mem_found);
+    let mut nets = BTreeMap::new();
+    for (iface, tracker) in net_trackers {
+        let done = tracker.finish();
+        found.extend(done.findings);
+        if !done.chart.is_empty() {
+            nets.insert(iface, Summary { chart: done.chart, dists: done.dists });
+        }
+    }
+    findings::rank(&mut found);
+    Ok(Capture {
+        path: path.to_string(),
+        ctx,
+        start,
+        end,
+        disks,
+        cpu: Summary { chart: cpu.chart, dists: cpu.dists },
+        mem: Summary {
+            chart: mem_chart,
+            dists: MEM_STATS.iter().zip(mem_dists).map(|((key, _, _), acc)| (*key, acc.finish())).collect(),
+        },
+        nets,
+        findings: found,
+        unavailable,
+    })
+}
+
+// ==================== Matching ====================
+
+/// Matched devices as (label, before name, after name), sorted by label, and the ones in one capture only.
+struct Matching {
+    pairs: Vec<(String, String, String)>,
+    only_before: Vec<String>,
+    only_after: Vec<String>,
+}
+
+/// Match key and label of each multipath map (by dm name) and path device of one capture.
+fn mpath_keys(maps: &[MultipathDevice]) -> HashMap<&str, (String, String)> {
+    let mut keys = HashMap::new();
+    for m in maps {
+        keys.insert(m.dm_name.as_str(), (format!("wwid:{}", m.wwid), format!("{} ({})", m.name, m.wwid)));
+        for p in &m.paths {
+            keys.insert(p.dev_name.as_str(), (format!("path:{}:{}", m.wwid, p.dev_name), p.dev_name.clone()));
+        }
+    }
+    keys
+}
+
+/// Matches devices by name, or by WWID with the `multipath -ll` output of each capture:
+/// maps by WWID, paths by WWID and name, everything else by name.
+fn match_devices(before: &[&str], after: &[&str], maps: Option<&(Vec<MultipathDevice>, Vec<MultipathDevice>)>) -> Matching {
+    let (keys_b, keys_a) = maps.map(|(b, a)| (mpath_keys(b), mpath_keys(a))).unwrap_or_default();
+    let key = |keys: &HashMap<&str, (String, String)>, name: &str| {
+        keys.get(name).cloned().unwrap_or_else(|| (format!("name:{}", name), name.to_string()))
+    };
+    let mut after_by_key: HashMap<String, &str> = after.iter().map(|n| (key(&keys_a, n).0, *n)).collect();
+    let mut pairs = Vec::new();
+    let mut only_before = Vec::new();
+    for name in before {
+        let (k, label) = key(&keys_b, name);
+        match after_by_key.remove(&k) {
+            Some(a) => pairs.push((label, name.to_string(), a.to_string())),
+            None => only_before.push(name.to_string()),
+        }
+    }
+    let mut only_after: Vec<String> = after_by_key.into_values().map(str::to_string).collect();
+    pairs.sort();
+    only_before.sort();
+    only_after.sort();
+    Matching { pairs, only_before, only_after }
+}
+
+// ==================== Verdicts ====================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    NoChange,
+    Improvement,
+    Regression,
+    Mixed,
+}
+
+impl Verdict {
+    fn label(self) -> &'static str {
+        match self {
+            Verdict::NoChange => "no change",
+            Verdict::Improvement => "improvement",
+            Verdict::Regression => "regression",
+            Verdict::Mixed => "mixed",
+        }
+    }
+
+    /// Overall verdict: mixed when some things got better and others worse.
+    fn of(verdicts: impl IntoIterator<Item = Verdict>) -> Verdict {
+        let (mut better, mut worse) = (false, false);
+        for v in verdicts {
+            better |= matches!(v, Verdict::Improvement | Verdict::Mixed);
+            worse |= matches!(v, Verdict::Regression | Verdict::Mixed);
+        }
+        match (better, worse) {
+            (true, true) => Verdict::Mixed,
+            (true, false) => Verdict::Improvement,
+            (false, true) => Verdict::Regression,
+            (false, false) => Verdict::NoChange,
+        }
+    }
+
+    /// Table order: regressions first.
+    fn order(self) -> u8 {
+        match self {
+            Verdict::Regression => 0,
+            Verdict::Mixed => 1,
+            Verdict::Improvement => 2,
+            Verdict::NoChange => 3,
+        }
+    }
+}
+
+/// Verdict of one metric from its p95 before and after; None for metrics that do not decide one
+/// and when either capture does not have the metric.
+fn judge(key: &str, before: Option<&Distribution>, after: Option<&Distribution>) -> Option<Verdict> {
+    let (_, higher_worse, floor) = VERDICT_METRICS.iter().find(|(k, _, _)| *k == key)?;
+    let (b, a) = (before?.at("p95"), after?.at("p95"));
+    let diff = a - b;
+    if diff.abs() < *floor || diff.abs() < b.abs() * MIN_CHANGE {
+        return Some(Verdict::NoChange);
+    }
+    Some(if (diff > 0.0) == *higher_worse { Verdict::Regression } else { Verdict::Improvement })
+}
+
+/// Change from `b` to `a` in percent, None when `b` is 0 and `a` is not.
+fn pct_change(b: f64, a: f64) -> Option<f64> {
+    if b == 0.0 {
+        return (a == 0.0).then_some(0.0);
+    }
+    Some((a - b) / b.abs() * 100.0)
+}
+
+fn fmt_change(b: f64, a: f64) -> String {
+    pct_change(b, a).map_or("new".to_string(), |p| format!("{:+.1}%", p))
+}
+
+/// fmt_change of two p95s, "n/a" when a capture does not have the metric.
+fn fmt_p95_change(p95: Option<(f64, f64)>) -> String {
+    p95.map_or("n/a".to_string(), |(b, a)| fmt_change(b, a))
+}
+
+/// One metric of a matched pair.
+struct MetricCmp {
+    key: &'static str,
+    label: &'static str,
+    before: Option<Distribution>,
+    after: Option<Distribution>,
+    verdict: Option<Verdict>,
+}
+
+impl MetricCmp {
+    /// p95 before and after; None when either capture does not have the metric.
+    fn p95(&self) -> Option<(f64, f64)> {
+        Some((self.before.as_ref()?.at("p95"), self.after.as_ref()?.at("p95")))
+    }
+}
+
+/// A matched device, interface, the CPU or memory.
+struct EntityCmp {
+    subsystem: &'static str,
+    label: String,
+    before: String,
+    after: String,
+    /// Chart file and anchor prefix
+    id: String,
+    metrics: Vec<MetricCmp>,
+    verdict: Verdict,
+    /// Mean load before and after: IO/s for disks, bytes/s for interfaces
+    load: Option<(f64, f64)>,
+}
+
+impl EntityCmp {
+    /// Metrics with a verdict of one kind, most changed first.
+    fn changed(&self, v: Verdict) -> Vec<&MetricCmp> {
+        let mut m: Vec<&MetricCmp> = self.metrics.iter().filter(|m| m.verdict == Some(v)).collect();
+        let size = |m: &MetricCmp| m.p95().and_then(|(b, a)| pct_change(b, a)).map_or(f64::INFINITY, f64::abs);
+        m.sort_by(|x, y| size(y).total_cmp(&size(x)));
+        m
+    }
+
+    /// "await_wr p95 +45.0%, qlen p95 +30.2%" for the metrics behind the verdict.
+    fn reason(&self) -> String {
+        let metrics = match self.verdict {
+            Verdict::Regression => self.changed(Verdict::Regression),
+            Verdict::Improvement => self.changed(Verdict::Improvement),
+            Verdict::Mixed => {
+                let mut m = self.changed(Verdict::Regression);
+                m.extend(self.changed(Verdict::Improvement));
+                m
+            }
+            Verdict::NoChange => Vec::new(),
+        };
+        let parts: Vec<String> = metrics.iter().take(3).map(|m| format!("{} p95 {}", m.key, fmt_p95_change(m.p95()))).collect();
+        parts.join(", ")
+    }
+
+    /// "IO/s +35.0% (load changed)" when the mean load moved by more than LOAD_CHANGE.
+    fn load_text(&self) -> String {
+        let Some((b, a)) = self.load else { return String::new() };
+        let what = if self.subsystem == "disk" { "IO/s" } else { "bytes/s" };
+        let flag = pct_change(b, a).is_none_or(|p| p.abs() > LOAD_CHANGE * 100.0);
+        format!("{} {}{}", what, fmt_change(b, a), if flag { " (load changed)" } else { "" })
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn compare_entity<M>(subsystem: &'static str, label: String, names: (&str, &str), id: String,
+                     before: &Summary<M>, after: &Summary<M>, charts: &[Chart<M>], load: Option<(f64, f64)>) -> EntityCmp {
+    let metrics: Vec<MetricCmp> = charts.iter().filter_map(|(key, label, _)| {
+        let (b, a) = (before.dist(key), after.dist(key));
+        // Metrics that are all zero or missing in both captures say nothing
+        if b.is_none_or(|d| d.max == 0.0) && a.is_none_or(|d| d.max == 0.0) { return None; }
+        Some(MetricCmp { key, label, before: b.cloned(), after: a.cloned(), verdict: judge(key, b, a) })
+    }).collect();
+    EntityCmp {
+        subsystem,
+        label,
+        before: names.0.to_string(),
+        after: names.1.to_string(),
+        id,
+        verdict: Verdict::of(metrics.iter().filter_map(|m| m.verdict)),
+        metrics,
+        load,
+    }
+}
+
+/// Chart file and anchor names: letters, digits, '-' and '_' only.
+fn safe_id(s: &str) -> String {
+    s.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect()
+}
+
+// ==================== Entrypoint ====================
+
+/// Compares two captures and writes the report into compare_<before>_vs_<after>/.
+/// `before`/`after` are each capture's context and window; `maps` is the `multipath -ll`
+/// output taken with each capture, to match multipath devices by WWID.
+#[allow(clippy::too_many_arguments)]
+pub fn compare(before_path: &str, after_path: &str, before: (TimeCtx, TimeWindow), after: (TimeCtx, TimeWindow),
+               maps: Option<&(Vec<MultipathDevice>, Vec<MultipathDevice>)>, sel: &DevSelect, ifaces: &IfaceSelect,
+               bucket: Option<u64>) -> io::Result<()> {
+    let stem = |p: &str| Path::new(p).file_stem().and_then(|s| s.to_str()).unwrap_or("capture").to_string();
+    let output_dir = format!("compare_{}_vs_{}", stem(before_path), stem(after_path));
+    println!("Comparing {} (before) with {} (after)\nOutput dir: {}", before_path, after_path, output_dir);
+    fs::create_dir_all(&output_dir)?;
+    let started = Instant::now();
+
+    // Same chart bucket for both, so the overlays have the same resolution
+    let (ctx_b, window_b) = before;
+    let (ctx_a, window_a) = after;
+    let chart_bucket = match bucket {
+        Some(b) => stream::chart_bucket(before_path, &window_b, Some(b))?,
+        None => stream::chart_bucket(before_path, &window_b, None)?.max(stream::chart_bucket(after_path, &window_a, None)?),
+    };
+    let cap_b = read_capture(before_path, ctx_b, &window_b, sel, ifaces, chart_bucket)?;
+    let cap_a = read_capture(after_path, ctx_a, &window_a, sel, ifaces, chart_bucket)?;
+
+    // ========== Match and compare ==========
+
+    fn names<T>(m: &BTreeMap<String, T>) -> Vec<&str> {
+        m.keys().map(String::as_str).collect()
+    }
+    let disks = match_devices(&names(&cap_b.disks), &names(&cap_a.disks), maps);
+    let nets = match_devices(&names(&cap_b.nets), &names(&cap_a.nets), None);
+    if maps.is_none() && disks.pairs.iter().any(|(l, _, _)| l.starts_with("dm-")) {
+        println!("NOTE: dm devices matched by name; give the multipath -ll output of each capture to match by WWID");
+    }
+
+    let mut entities: Vec<EntityCmp> = Vec::new();
+    let mut plots: Vec<Plot> = Vec::new();
+    for (label, b, a) in &disks.pairs {
+        let (sb, sa) = (&cap_b.disks[b], &cap_a.disks[a]);
+        let load = Some((sb.dist("io_sec").map_or(0.0, |d| d.mean), sa.dist("io_sec").map_or(0.0, |d| d.mean)));
+        let e = compare_entity("disk", label.clone(), (b, a), format!("disk_{}", safe_id(label)), sb, sa, DISK_CHARTS, load);
+        overlays(&output_dir, &e, (&cap_b, sb), (&cap_a, sa), DISK_CHARTS, |m| m.ts, &mut plots);
+        entities.push(e);
+    }
+    let e = compare_entity("cpu", "cpu".to_string(), ("cpu", "cpu"), "cpu".to_string(), &cap_b.cpu, &cap_a.cpu, CPU_CHARTS, None);
+    overlays(&output_dir, &e, (&cap_b, &cap_b.cpu), (&cap_a, &cap_a.cpu), CPU_CHARTS, |m| m.ts, &mut plots);
+    entities.push(e);
+    let e = compare_entity("memory", "memory".to_string(), ("memory", "memory"), "memory".to_string(), &cap_b.mem, &cap_a.mem, MEM_STATS, None);
+    overlays(&output_dir, &e, (&cap_b, &cap_b.mem), (&cap_a, &cap_a.mem), MEM_STATS, |m| m.ts, &mut plots);
+    entities.push(e);
+    for (label, b, a) in &nets.pairs {
+        let (sb, sa) = (&cap_b.nets[b], &cap_a.nets[a]);
+        let bytes = |s: &Summary<IntervalNetMetrics>| ["rx_bytes", "tx_bytes"].iter().filter_map(|k| s.dist(k)).map(|d| d.mean).sum::<f64>();
+        let e = compare_entity("net", label.clone(), (b, a), format!("net_{}", safe_id(label)), sb, sa, NET_CHARTS, Some((bytes(sb), bytes(sa))));
+        overlays(&output_dir, &e, (&cap_b, sb), (&cap_a, sa), NET_CHARTS, |m| m.ts, &mut plots);
+        entities.push(e);
+    }
+    entities.sort_by_key(|e| e.verdict.order());
+
+    // ========== Overlay charts, in parallel ==========
+
+    println!("Writing {} overlay graphs on {} threads...", plots.len(), rayon::current_num_threads());
+    let render = Progress::new("Graphs", plots.len() as u64, stream::fmt_count);
+    plots.par_iter().try_for_each(|plot| {
+        plot()?;
+        render.add(1);
+        Ok::<(), io::Error>(())
+    })?;
+    let render_secs = render.secs();
+    drop(plots);
+
+    // ========== Reports ==========
+
+    let subsystems: Vec<SubsystemCmp> = SUBSYSTEMS.iter().map(|s| SubsystemCmp::of(s, &entities, &cap_b, &cap_a)).collect();
+    fs::write(format!("{}/compare.txt", output_dir), to_text(&cap_b, &cap_a, &subsystems, &entities, &disks, &nets))?;
+    fs::write(format!("{}/compare.json", output_dir), to_json(&cap_b, &cap_a, &subsystems, &entities, &disks, &nets))?;
+    fs::write(format!("{}/index.html", output_dir), to_html(&cap_b, &cap_a, &subsystems, &entities, &disks, &nets))?;
+
+    for s in &subsystems {
+        println!("{:<7} {:<12} {}", s.subsystem, s.verdict.label(), s.counts());
+    }
+    println!("Timing: graphs {:.1}s, total {:.1}s", render_secs, started.elapsed().as_secs_f64());
+    println!("Comparison complete. See {}/ for results.", output_dir);
+    Ok(())
+}
+
+// ==================== Subsystem verdicts ====================
+
+struct SubsystemCmp {
+    subsystem: &'static str,
+    verdict: Verdict,
+    /// Entities with each verdict: regression, improvement, mixed, no change
+    counts: [usize; 4],
+    /// Critical and warning findings in each capture
+    findings: ((usize, usize), (usize, usize)),
+}
+
+impl SubsystemCmp {
+    fn of(subsystem: &'static str, entities: &[EntityCmp], before: &Capture, after: &Capture) -> Self {
+        let verdicts: Vec<Verdict> = entities.iter().filter(|e| e.subsystem == subsystem).map(|e| e.verdict).collect();
+        let count = |v: Verdict| verdicts.iter().filter(|x| **x == v).count();
+        let found = |cap: &Capture| {
+            let mine: Vec<&Finding> = cap.findings.iter().filter(|f| cap.subsystem_of(&f.subject) == subsystem).collect();
+            let n = |sev| mine.iter().filter(|f| f.severity == sev).count();
+            (n(Severity::Critical), n(Severity::Warning))
+        };
+        SubsystemCmp {
+            subsystem,
+            verdict: Verdict::of(verdicts.iter().copied()),
+            counts: [count(Verdict::Regression), count(Verdict::Improvement), count(Verdict::Mixed), count(Verdict::NoChange)],
+            findings: (found(before), found(after)),
+        }
+    }
+
+    fn counts(&self) -> String {
+        let [r, i, m, n] = self.counts;
+        format!("{} regressed, {} improved, {} mixed, {} unchanged", r, i, m, n)
+    }
+
+    fn findings_text(&self) -> String {
+        let ((bc, bw), (ac, aw)) = self.findings;
+        format!("{}c/{}w -> {}c/{}w", bc, bw, ac, aw)
+    }
+}
+
+// ==================== Output ====================
+
+const HEADER_NOTE: &str = "Verdicts compare the p95 of latency, queue, iowait, memory and error metrics; a change counts \
+when it is at least 10% of the before value and above the metric's floor. Throughput is shown as load only.";
+
+/// Mean, p50, p95, p99 and max, or n/a when the capture does not have the metric.
+fn dist_cells(d: Option<&Distribution>) -> [String; 5] {
+    match d {
+        Some(d) => [d.mean, d.at("p50"), d.at("p95"), d.at("p99"), d.max].map(|v| format!("{:.2}", v)),
+        None => std::array::from_fn(|_| "n/a".to_string()),
+    }
+}
+
+fn to_text(before: &Capture, after: &Capture, subsystems: &[SubsystemCmp], entities: &[EntityCmp],
+           disks: &Matching, nets: &Matching) -> String {
+    let mut s = format!("compare.txt\nBefore: {}\nAfter:  {}\n", before.describe(), after.describe());
+    for (side, cap) in [("before", before), ("after", after)] {
+        for line in cap.unavailable_text() {
+            let _ = writeln!(s, "In {}: {}", side, line);
+        }
+    }
+    let _ = writeln!(s, "{}\n", HEADER_NOTE);
+    let _ = writeln!(s, "{:<8} {:<12} {:<58} Findings (crit/warn)", "Subsys", "Verdict", "Devices");
+    let _ = writeln!(s, "{}", "-".repeat(110));
+    for sc in subsystems {
+        let _ = writeln!(s, "{:<8} {:<12} {:<58} {}", sc.subsystem, sc.verdict.label(), sc.counts(), sc.findings_text());
+    }
+
+    let _ = writeln!(s, "\n{:<8} {:<32} {:<12} {:<16} {:<28} Load", "Subsys", "Name", "Verdict", "Before/After", "Worst changes");
+    let _ = writeln!(s, "{}", "-".repeat(130));
+    for e in entities {
+        let names = if e.before == e.after { e.before.clone() } else { format!("{}/{}", e.before, e.after) };
+        let line = format!("{:<8} {:<32} {:<12} {:<16} {:<28} {}", e.subsystem, e.label, e.verdict.label(), names, e.reason(), e.load_text());
+        let _ = writeln!(s, "{}", line.trim_end());
+    }
+    for (what, m) in [("Devices", disks), ("Interfaces", nets)] {
+        if !m.only_before.is_empty() {
+            let _ = writeln!(s, "\n{} only in before: {}", what, m.only_before.join(", "));
+        }
+        if !m.only_after.is_empty() {
+            let _ = writeln!(s, "{} only in after: {}", what, m.only_after.join(", "));
+        }
+    }
+
+    // Side-by-side distributions
+    for e in entities {
+        let _ = writeln!(s, "\n== {} {} ({} -> {}): {} {}", e.subsystem, e.label, e.before, e.after, e.verdict.label(), e.load_text());
+        let _ = writeln!(s, "{:<20} | {:>9} {:>9} {:>9} {:>9} {:>9} | {:>9} {:>9} {:>9} {:>9} {:>9} | {:>9} Verdict",
+            "Metric", "Mean", "P50", "P95", "P99", "Max", "Mean", "P50", "P95", "P99", "Max", "P95 chg");
+        for m in &e.metrics {
+            let _ = write!(s, "{:<20} |", m.key);
+            for v in dist_cells(m.before.as_ref()) {
+                let _ = write!(s, " {:>9}", v);
+            }
+            let _ = write!(s, " |");
+            for v in dist_cells(m.after.as_ref()) {
+                let _ = write!(s, " {:>9}", v);
+            }
+            let _ = writeln!(s, " | {:>9} {}", fmt_p95_change(m.p95()), m.verdict.map_or("", Verdict::label));
+        }
+    }
+    s
+}
+
+fn to_json(before: &Capture, after: &Capture, subsystems: &[SubsystemCmp], entities: &[EntityCmp],
+           disks: &Matching, nets: &Matching) -> String {
+    let num = |v: f64| if v.is_finite() { format!("{:.2}", v) } else { "null".to_string() };
+    let dist = |d: Option<&Distribution>| d.map_or("null".to_string(), |d| {
+        let mut f = vec![format!("\"mean\":{}", num(d.mean)), format!("\"stddev\":{}", num(d.stddev))];
+        f.extend(PERCENTILES.iter().zip(d.pct).map(|((n, _), v)| format!("{}:{}", json_str(n), num(v))));
+        f.push(format!("\"max\":{}", num(d.max)));
+        format!("{{{}}}", f.join(","))
+    });
+    let capture = |c: &Capture| {
+        let unavailable: Vec<String> = c.unavailable.iter().map(|(k, r)| format!("{}:{}", json_str(k), json_str(r))).collect();
+        format!("{{\"path\":{},\"start_epoch\":{},\"end_epoch\":{},\"timezone\":{},\"unavailable\":{{{}}}}}",
+            json_str(&c.path), c.start, c.end, json_str(&c.ctx.label()), unavailable.join(","))
+    };
+    let list = |v: &[String]| format!("[{}]", v.iter().map(|s| json_str(s)).collect::<Vec<_>>().join(","));
+
+    let subs: Vec<String> = subsystems.iter().map(|sc| {
+        let ((bc, bw), (ac, aw)) = sc.findings;
+        format!("{{\"subsystem\":{},\"verdict\":{},\"regressed\":{},\"improved\":{},\"mixed\":{},\"unchanged\":{},\
+            \"findings_before\":{{\"critical\":{},\"warning\":{}}},\"findings_after\":{{\"critical\":{},\"warning\":{}}}}}",
+            json_str(sc.subsystem), json_str(sc.verdict.label()), sc.counts[0], sc.counts[1], sc.counts[2], sc.counts[3], bc, bw, ac, aw)
+    }).collect();
+    let ents: Vec<String> = entities.iter().map(|e| {
+        let metrics: Vec<String> = e.metrics.iter().map(|m| {
+            format!("{{\"metric\":{},\"before\":{},\"after\":{},\"p95_change_pct\":{},\"verdict\":{}}}",
+                json_str(m.key), dist(m.before.as_ref()), dist(m.after.as_ref()),
+                m.p95().and_then(|(b, a)| pct_change(b, a)).map_or("null".to_string(), num),
+                m.verdict.map_or("null".to_string(), |v| json_str(v.label())))
+        }).collect();
+        let load = e.load.map_or("null".to_string(), |(b, a)| format!("{{\"before\":{},\"after\":{}}}", num(b), num(a)));
+        format!("{{\"subsystem\":{},\"name\":{},\"before\":{},\"after\":{},\"verdict\":{},\"load\":{},\"metrics\":[{}]}}",
+            json_str(e.subsystem), json_str(&e.label), json_str(&e.before), json_str(&e.after), json_str(e.verdict.label()),
+            load, metrics.join(","))
+    }).collect();
+    format!("{{\"before\":{},\"after\":{},\"subsystems\":[\n{}\n],\"entities\":[\n{}\n],\
+        \"devices_only_before\":{},\"devices_only_after\":{},\"interfaces_only_before\":{},\"interfaces_only_after\":{}}}\n",
+        capture(before), capture(after), subs.join(",\n"), ents.join(",\n"),
+        list(&disks.only_before), list(&disks.only_after), list(&nets.only_before), list(&nets.only_after))
+}
+
+fn dist_table_html(e: &EntityCmp) -> String {
+    let mut s = String::from("<table border=\"1\" cellpadding=\"3\"><tr><th rowspan=\"2\">Metric</th>\
+        <th colspan=\"5\">Before</th><th colspan=\"5\">After</th><th rowspan=\"2\">P95 change</th><th rowspan=\"2\">Verdict</th></tr>\n<tr>");
+    for _ in 0..2 {
+        s.push_str("<th>Mean</th><th>P50</th><th>P95</th><th>P99</th><th>Max</th>");
+    }
+    s.push_str("</tr>\n");
+    for m in &e.metrics {
+        let v = m.verdict.map_or("", Verdict::label);
+        let _ = write!(s, "<tr class=\"v-{}\"><td>{}</td>", v.replace(' ', "-"), m.label);
+        for x in dist_cells(m.before.as_ref()).into_iter().chain(dist_cells(m.after.as_ref())) {
+            let _ = write!(s, "<td>{}</td>", x);
+        }
+        let _ = writeln!(s, "<td>{}</td><td class=\"verdict\">{}</td></tr>", fmt_p95_change(m.p95()), v);
+    }
+    s.push_str("</table>\n");
+    s
+}
+
+fn to_html(before: &Capture, after: &Capture, subsystems: &[SubsystemCmp], entities: &[EntityCmp],
+           disks: &Matching, nets: &Matching) -> String {
+    let mut verdict = String::from("<table border=\"1\" cellpadding=\"4\"><tr><th>Subsystem</th><th>Verdict</th>\
+        <th>Devices</th><th>Findings (crit/warn) before &rarr; after</th></tr>\n");
+    for sc in subsystems {
+        let _ = writeln!(verdict, "<tr class=\"v-{}\"><td>{}</td><td class=\"verdict\">{}</td><td>{}</td><td>{}</td></tr>",
+            sc.verdict.label().replace(' ', "-"), sc.subsystem, sc.verdict.label(), sc.counts(), sc.findings_text());
+    }
+    verdict.push_str("</table>\n<h2>Per device and interface</h2>\n<table border=\"1\" cellpadding=\"4\"><tr><th>Subsystem</th>\
+        <th>Name</th><th>Before</th><th>After</th><th>Verdict</th><th>Worst changes</th><th>Load</th></tr>\n");
+    for e in entities {
+        let _ = writeln!(verdict, "<tr class=\"v-{}\"><td>{}</td><td><a href=\"#{}\" onclick=\"openEntity('{}', '{}')\">{}</a></td>\
+            <td>{}</td><td>{}</td><td class=\"verdict\">{}</td><td>{}</td><td>{}</td></tr>",
+            e.verdict.label().replace(' ', "-"), e.subsystem, e.id, section_of(e.subsystem), e.id, html_escape(&e.label),
+            html_escape(&e.before), html_escape(&e.after), e.verdict.label(), e.reason(), e.load_text());
+    }
+    verdict.push_str("</table>\n");
+    for (what, m) in [("Devices", disks), ("Interfaces", nets)] {
+        for (side, list) in [("before", &m.only_before), ("after", &m.only_after)] {
+            if !list.is_empty() {
+                let _ = writeln!(verdict, "<p>{} only in {}: {}</p>", what, side, html_escape(&list.join(", ")));
+            }
+        }
+    }
+
+    let mut sections: BTreeMap<&str, String> = BTreeMap::new();
+    for e in entities {
+        let body = sections.entry(section_of(e.subsystem)).or_default();
+        let open = if matches!(e.subsystem, "cpu" | "memory") { " open" } else { "" };
+        let _ = writeln!(body, "<details id=\"{}\"{}><summary><span class=\"v-{}\">{}</span> {} ({} &rarr; {}) {}</summary>",
+            e.id, open, e.verdict.label().replace(' ', "-"), e.verdict.label(), html_escape(&e.label),
+            html_escape(&e.before), html_escape(&e.after), e.load_text());
+        body.push_str(&dist_table_html(e));
+        for m in &e.metrics {
+            let name = format!("{}_{}", e.id, m.key);
+            let _ = writeln!(body, "<img class=\"graph\" loading=\"lazy\" src=\"{name}.svg\" onerror=\"this.onerror=null; this.src='{name}.png';\">");
+        }
+        body.push_str("</details>\n");
+    }
+    let section = |k: &str| sections.get(k).cloned().unwrap_or_else(|| "<p>Nothing to compare.</p>".to_string());
+
+    let describe = |c: &Capture| {
+        let mut lines = vec![c.describe()];
+        lines.extend(c.unavailable_text());
+        lines.iter().map(|l| html_escape(l)).collect::<Vec<_>>().join("; ")
+    };
+    PAGE.replace("{{BEFORE}}", &describe(before))
+        .replace("{{AFTER}}", &describe(after))
+        .replace("{{NOTE}}", HEADER_NOTE)
+        .replace("{{VERDICT}}", &verdict)
+        .replace("{{DISK}}", &section("disk"))
+        .replace("{{CPU}}", &section("cpu"))
+        .replace("{{MEM}}", &section("mem"))
+        .replace("{{NET}}", &section("net"))
+}
+
+/// Dashboard tab of a subsystem.
+fn section_of(subsystem: &str) -> &'static str {
+    match subsystem {
+        "disk" => "disk",
+        "cpu" => "cpu",
+        "memory" => "mem",
+        _ => "net",
+    }
+}
+
+const PAGE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="UTF-8">
+  <title>serverstats_grab comparison</title>
+  <style>
+    body { font-family: sans-serif; margin: 2em; }
+    img.graph { display: block; margin: 1em 0; max-width: 98vw; border: 1px solid #ccc; }
+    .section-tab { cursor:pointer; display:inline-block; margin-right:1em; padding:0.5em 1em; border-radius:1em; background:#ddd; }
+    .section-tab.active { background:#333; color:#fff; }
+    .section-content { display:none; }
+    .section-content.active { display:block; }
+    details { margin: 0.5em 0; }
+    summary { cursor: pointer; }
+    p.scope { color: #555; }
+    .v-regression td.verdict, span.v-regression { background: #f4b0b0; font-weight: bold; }
+    .v-mixed td.verdict, span.v-mixed { background: #f8e0a0; }
+    .v-improvement td.verdict, span.v-improvement { background: #b8e6b8; }
+  </style>
+</head>
+<body>
+  <h1>Before / after comparison</h1>
+  <p class="scope">Before: {{BEFORE}}<br>After: {{AFTER}}</p>
+  <p class="scope">{{NOTE}}</p>
+  <div id="tabs">
+    <span class="section-tab active" onclick="showSection('verdict')">Verdict</span>
+    <span class="section-tab" onclick="showSection('disk')">Disk</span>
+    <span class="section-tab" onclick="showSection('cpu')">CPU</span>
+    <span class="section-tab" onclick="showSection('mem')">Memory</span>
+    <span class="section-tab" onclick="showSection('net')">Network</span>
+  </div>
+  <div id="verdict" class="section-content active"><h2>Verdict</h2>
+{{VERDICT}}
+  </div>
+  <div id="disk" class="section-content"><h2>Disk</h2>
+{{DISK}}
+  </div>
+  <div id="cpu" class="section-content"><h2>CPU</h2>
+{{CPU}}
+  </div>
+  <div id="mem" class="section-content"><h2>Memory</h2>
+{{MEM}}
+  </div>
+  <div id="net" class="section-content"><h2>Network</h2>
+{{NET}}
+  </div>
+<script>
+function showSection(id) {
+  document.querySelectorAll('.section-content').forEach(d => d.classList.toggle('active', d.id === id));
+  document.querySelectorAll('.section-tab').forEach(t => t.classList.toggle('active', t.getAttribute('onclick').includes("'" + id + "'")));
+}
+function openEntity(section, id) {
+  showSection(section);
+  document.getElementById(id).open = true;
+}
+</script>
+</body>
+</html>
+"##;
+
+// ==================== Overlay charts ====================
+
+/// One chart to render, run on the rayon pool.
+type Plot<'a> = Box<dyn Fn() -> io::Result<()> + Send + Sync + 'a>;
+
+/// Queues a before/after overlay chart for each compared metric of `e`.
+fn overlays<'a, M: Sync>(output_dir: &'a str, e: &EntityCmp, before: (&'a Capture, &'a Summary<M>), after: (&'a Capture, &'a Summary<M>),
+                         charts: &'static [Chart<M>], ts: fn(&M) -> u64, plots: &mut Vec<Plot<'a>>) {
+    for m in &e.metrics {
+        let Some((_, label, value)) = charts.iter().find(|c| c.0 == m.key) else { continue };
+        let fname = format!("{}_{}", e.id, m.key);
+        let caption = format!("{} - {}", e.label, label);
+        let m_key = m.key;
+        plots.push(Box::new(move || {
+            // A capture without the metric draws no line
+            let points = |(cap, s): (&Capture, &Summary<M>)| -> Vec<(f64, f64)> {
+                if s.dist(m_key).is_none() { return Vec::new(); }
+                s.chart.iter().map(|x| (ts(x).saturating_sub(cap.start) as f64, value(x))).filter(|p| p.1.is_finite()).collect()
+            };
+            let (b, a) = (points(before), points(after));
+            let legend = |cap: &Capture, side: &str| format!("{} ({})", side, cap.ctx.format(cap.start, "%Y-%m-%d %H:%M"));
+            let names = (legend(before.0, "before"), legend(after.0, "after"));
+            let svg = format!("{}/{}.svg", output_dir, fname);
+            draw_overlay(SVGBackend::new(&svg, (900, 300)).into_drawing_area(), &caption, label, &b, &a, &names);
+            let png = format!("{}/{}.png", output_dir, fname);
+            draw_overlay(BitMapBackend::new(&png, (900, 300)).into_drawing_area(), &caption, label, &b, &a, &names);
+            Ok(())
+        }));
+    }
+}
+
+/// Elapsed time axis label: H:MM:SS, or Dd HH:MM past a day.
+fn fmt_elapsed(secs: u64) -> String {
+    if secs >= 86400 {
+        format!("{}d {:02}:{:02}", secs / 86400, (secs % 86400) / 3600, (secs % 3600) / 60)
+    } else {
+        format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+    }
+}
+
+/// Before (blue) and after (red) on one chart, x = seconds since each capture's start.
+fn draw_overlay<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, caption: &str, ylabel: &str,
+                                    before: &[(f64, f64)], after: &[(f64, f64)], names: &(String, String)) {
+    let all = || before.iter().chain(after);
+    let x_max = all().map(|p| p.0).fold(1.0, f64::max);
+    let y_min = all().map(|p| p.1).fold(0.0, f64::min);
+    let y_max = all().map(|p| p.1).fold(1.0, f64::max) * 1.05;
+
+    root.fill(&WHITE).unwrap();
+    let mut chart = ChartBuilder::on(&root)
+        .caption(caption, ("sans-serif", 20))
+        .margin(12)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0.0..x_max, y_min..y_max)
+        .unwrap();
+    chart
+        .configure_mesh()
+        .x_labels(8)
+        .x_label_formatter(&|x| fmt_elapsed(*x as u64))
+        .x_desc("Elapsed since capture start")
+        .y_desc(ylabel)
+        .draw()
+        .unwrap();
+    chart
+        .draw_series(LineSeries::new(before.iter().copied(), &BLUE))
+        .unwrap()
+        .label(names.0.as_str())
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 25, y)], BLUE));
+    chart
+        .draw_series(LineSeries::new(after.iter().copied(), &RED))
+        .unwrap()
+        .label(names.1.as_str())
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 25, y)], RED));
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .unwrap();
+    root.present().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpath::MpathPath;
+
+    fn map(name: &str, wwid: &str, dm: &str, paths: &[&str]) -> MultipathDevice {
+        MultipathDevice {
+            name: name.to_string(),
+            wwid: wwid.to_string(),
+            dm_name: dm.to_string(),
+            vendor_model: String::new(),
+            size: String::new(),
+            paths: paths.iter().map(|p| MpathPath {
+                bus: String::new(), dev_name: p.to_string(), major_minor: String::new(), status: String::new(),
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn devices_match_by_wwid() {
+        // After the reboot mpatha is dm-5, and sdc is a path of another LUN
+        let maps = (
+            vec![map("mpatha", "3600a", "dm-3", &["sdc", "sdd"])],
+            vec![map("mpatha", "3600a", "dm-5", &["sdd", "sde"]), map("mpathb", "3600b", "dm-3", &["sdc"])],
+        );
+        let m = match_devices(&["dm-3", "sda", "sdc", "sdd"], &["dm-3", "dm-5", "sda", "sdc", "sdd", "sde"], Some(&maps));
+        let pairs: Vec<(&str, &str, &str)> = m.pairs.iter().map(|(l, b, a)| (l.as_str(), b.as_str(), a.as_str())).collect();
+        assert_eq!(pairs, [("mpatha (3600a)", "dm-3", "dm-5"), ("sda", "sda", "sda"), ("sdd", "sdd", "sdd")]);
+        assert_eq!((m.only_before, m.only_after), (vec!["sdc".to_string()], vec!["dm-3".to_string(), "sdc".to_string(), "sde".to_string()]));
+
+        // Without the multipath output everything is matched by name
+        let m = match_devices(&["dm-3", "sda"], &["dm-3", "sdb"], None);
+        assert_eq!(m.pairs.len(), 1);
+        assert_eq!((m.only_before, m.only_after), (vec!["sda".to_string()], vec!["sdb".to_string()]));
+    }
+
+    #[test]
+    fn verdicts() {
+        let dist = |p95: f64| Distribution { mean: p95, stddev: 0.0, max: p95, pct: [p95; 5], above_secs: None, total_secs: 60 };
+        assert_eq!(judge("await_wr", Some(&dist(10.0)), Some(&dist(15.0))), Some(Verdict::Regression));
+        assert_eq!(judge("await_wr", Some(&dist(10.0)), Some(&dist(5.0))), Some(Verdict::Improvement));
+        // Under 10%, or under the 1 ms floor
+        assert_eq!(judge("await_wr", Some(&dist(20.0)), Some(&dist(21.5))), Some(Verdict::NoChange));
+        assert_eq!(judge("await_wr", Some(&dist(0.2)), Some(&dist(0.9))), Some(Verdict::NoChange));
+        // Less memory available is worse; throughput has no verdict
+        assert_eq!(judge("avail", Some(&dist(40.0)), Some(&dist(20.0))), Some(Verdict::Regression));
+        assert_eq!(judge("kb_sec", Some(&dist(100.0)), Some(&dist(10.0))), None);
+
+        use Verdict::*;
+        assert_eq!(Verdict::of([NoChange, Regression, NoChange]), Regression);
+        assert_eq!(Verdict::of([Improvement, Regression]), Mixed);
+        assert_eq!(Verdict::of([]), NoChange);
+        assert_eq!((fmt_change(10.0, 15.0), fmt_change(0.0, 1.0)), ("+50.0%".to_string(), "new".to_string()));
+        // A capture without the metric decides nothing
+        assert_eq!(judge("await_flush_ms", Some(&dist(5.0)), None), None);
+        assert_eq!(judge("await_flush_ms", None, Some(&dist(5.0))), None);
+    }
+
+    /// Ten samples of sda under the same steady load; `fields` is the kernel's diskstats
+    /// layout (14, 18 or 20), the DISK records carry flush counters only with 20.
+    fn steady_capture(fields: u8) -> String {
+        let mut s = format!("#TYPE,ts_epoch,<fields...>\nDISKLAYOUT,100,{}\n", fields);
+        for i in 0..10u64 {
+            // 100 reads at 2 ms, 100 writes at 3 ms, 10 discards at 4 ms and 10 flushes at 5 ms per 10s
+            let (discards, flushes) = if fields >= 18 { (10 * i, 10 * i) } else { (0, 0) };
+            s += &format!("DISK,{},8,0,sda,{},0,{},{},{},0,{},{},0,{},{},{},0,{},{}",
+                100 + 10 * i, 100 * i, 800 * i, 200 * i, 100 * i, 800 * i, 300 * i, 5 * i, 500 * i,
+                discards, 8 * discards, 4 * discards);
+            if fields >= 20 {
+                s += &format!(",{},{}", flushes, 5 * flushes);
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    #[test]
+    fn mismatched_disk_layouts() {
+        let dir = serverstats_format::testdir::TempDir::new("serverstats_grab-compare");
+        let read = |name: &str, fields: u8| {
+            let path = dir.write(name, steady_capture(fields));
+            let ctx = TimeCtx::new(serverstats_format::timeutil::DisplayTz::parse("UTC").unwrap());
+            read_capture(path.to_str().unwrap(), ctx, &TimeWindow::default(), &DevSelect::default(), &IfaceSelect::default(), None).unwrap()
+        };
+        let (b20, a18, a14) = (read("b20.dat", 20), read("a18.dat", 18), read("a14.dat", 14));
+        assert!(b20.unavailable.is_empty());
+        assert_eq!(a18.unavailable.iter().map(|(k, _)| *k).collect::<Vec<_>>(), ["flush_s", "await_flush_ms"]);
+        assert_eq!(a14.unavailable.len(), 7);
+        assert!(a18.unavailable_text()[0].ends_with("(kernel diskstats has 18 fields, no flush counters)"));
+
+        // Same load on both sides: the missing counters are not an improvement
+        for (after, missing) in [(&a18, &["flush_s", "await_flush_ms"][..]),
+                                 (&a14, &["discards_s", "await_discard_ms", "flush_s", "await_flush_ms"][..])] {
+            let e = compare_entity("disk", "sda".to_string(), ("sda", "sda"), "disk_sda".to_string(),
+                                   &b20.disks["sda"], &after.disks["sda"], DISK_CHARTS, None);
+            assert_eq!(e.verdict, Verdict::NoChange, "{}", e.reason());
+            for key in missing {
+                let m = e.metrics.iter().find(|m| m.key == *key).unwrap();
+                assert_eq!((m.verdict, m.p95(), fmt_p95_change(m.p95())), (None, None, "n/a".to_string()), "{}", key);
+            }
+            let m = e.metrics.iter().find(|m| m.key == "await_rd").unwrap();
+            assert_eq!(m.verdict, Some(Verdict::NoChange));
+            let text = to_text(&b20, after, &[], &[e], &match_devices(&[], &[], None), &match_devices(&[], &[], None));
+            let row = text.lines().find(|l| l.starts_with("await_flush_ms")).unwrap();
+            assert!(row.trim_end().ends_with("|       n/a       n/a       n/a       n/a       n/a |       n/a"), "{}", row);
+            assert!(text.contains("In after: flush_s, await_flush_ms not available"));
+        }
+    }
+}
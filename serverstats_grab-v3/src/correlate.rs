@@ -0,0 +1,344 @@
+/*!
+ * Correlation Module
+ * ------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Cross-subsystem correlation for `-a`: the cause-and-effect checks we do by
+ * hand when explaining a problem to a customer, e.g.
+ *
+ *   - per-device await vs CPU iowait
+ *   - NET rx_drop vs disk await (iSCSI)
+ *   - Dirty/Writeback memory vs write KB/s
+ *
+ * Every disk, network, CPU and memory series below is paired with the series
+ * of the other subsystems (never disk with disk), and the Pearson correlation
+ * is taken with the second series shifted by up to MAX_LAG intervals either
+ * way, so "drops come first, await follows 10s later" is found too. Pairs with
+ * |r| >= STRONG are ranked in correlations.txt and the Correlation tab, each
+ * with an overlay chart of the two series (corr_NN.svg/png, two y axes).
+ *
+ * The series are the chart series, so long captures are correlated on their
+ * chart buckets. Only the busiest CORR_DEVICES disks and CORR_IFACES
+ * interfaces take part, to keep the pair count down on hosts with hundreds of
+ * multipath legs. Correlation is not causation; the lag is the hint.
+ */
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write as _;
+
+use plotters::prelude::*;
+use rayon::prelude::*;
+
+use serverstats_format::timeutil::TimeCtx;
+
+use crate::analyze::{self, CpuMetrics, IntervalDiskMetrics, IntervalNetMetrics, MemMetrics};
+
+/// Strong correlation: |r| at or above this is reported.
+const STRONG: f64 = 0.7;
+/// Largest shift tried, in intervals (each way).
+const MAX_LAG: isize = 5;
+/// A longer lag must beat a shorter one by this much to be preferred.
+const LAG_MARGIN: f64 = 0.02;
+/// Overlapping points needed for a correlation.
+const MIN_POINTS: usize = 20;
+const CORR_DEVICES: usize = 16;
+const CORR_IFACES: usize = 8;
+/// Pairs listed and charted.
+const CORR_TOP: usize = 50;
+
+/// Series name and value of one interval.
+type Metric<M> = (&'static str, fn(&M) -> f64);
+
+const DISK_SIGNALS: &[Metric<IntervalDiskMetrics>] = &[
+    ("await_rd", |m| m.await_rd),
+    ("await_wr", |m| m.await_wr),
+    ("rd_kbs", |m| m.rd_kbs),
+    ("wr_kbs", |m| m.wr_kbs),
+    ("qlen", |m| m.qlen),
+];
+
+const NET_SIGNALS: &[Metric<IntervalNetMetrics>] = &[
+    ("rx_drop", |m| m.rx_drop),
+    ("tx_drop", |m| m.tx_drop),
+    ("rx_errs", |m| m.rx_errs),
+    ("rx_bytes", |m| m.rx_bytes),
+    ("tx_bytes", |m| m.tx_bytes),
+];
+
+const CPU_SIGNALS: &[Metric<CpuMetrics>] = &[
+    ("iowait", |m| m.iowait),
+    ("sys", |m| m.sys),
+    ("user", |m| m.user),
+    ("blocked", |m| m.blocked.map_or(f64::NAN, |v| v as f64)),
+];
+
+const MEM_SIGNALS: &[Metric<MemMetrics>] = &[
+    ("dirty_kb", |m| m.dirty_kb),
+    ("writeback_kb", |m| m.writeback_kb),
+];
+
+/// One series on the common timeline.
+struct Signal {
+    name: String,
+    subsystem: &'static str,
+    values: Vec<Option<f64>>,
+}
+
+#[derive(Debug)]
+struct Correlation {
+    a: usize,
+    b: usize,
+    r: f64,
+    /// b is shifted by this many intervals: positive means b follows a
+    lag: isize,
+    points: usize,
+}
+
+fn signals_of<M>(timeline: &HashMap<u64, usize>, subsystem: &'static str, prefix: &str, series: &[M],
+                 ts: fn(&M) -> u64, metrics: &[Metric<M>], out: &mut Vec<Signal>) {
+    for (key, f) in metrics {
+        let mut values = vec![None; timeline.len()];
+        for m in series {
+            let v = f(m);
+            if v.is_finite() {
+                values[timeline[&ts(m)]] = Some(v);
+            }
+        }
+        out.push(Signal { name: format!("{} {}", prefix, key), subsystem, values });
+    }
+}
+
+/// Pearson r of a[i] with b[i + lag], over the points both have.
+fn pearson(a: &[Option<f64>], b: &[Option<f64>], lag: isize) -> Option<(f64, usize)> {
+    let (mut n, mut sa, mut sb, mut saa, mut sbb, mut sab) = (0usize, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for (i, x) in a.iter().enumerate() {
+        let j = i as isize + lag;
+        if j < 0 || j as usize >= b.len() { continue; }
+        if let (Some(x), Some(y)) = (x, b[j as usize]) {
+            n += 1;
+            sa += x;
+            sb += y;
+            saa += x * x;
+            sbb += y * y;
+            sab += x * y;
+        }
+    }
+    if n < MIN_POINTS { return None; }
+    let nf = n as f64;
+    let cov = sab - sa * sb / nf;
+    let (va, vb) = (saa - sa * sa / nf, sbb - sb * sb / nf);
+    // Flat series (all zero drops, idle disk) correlate with nothing
+    if va <= 1e-12 * nf || vb <= 1e-12 * nf { return None; }
+    Some(((cov / (va * vb).sqrt()).clamp(-1.0, 1.0), n))
+}
+
+/// Best lag for a pair: 0, 1, -1, 2, -2, ...; a longer lag has to be clearly better.
+fn best_lag(a: &[Option<f64>], b: &[Option<f64>]) -> Option<(f64, isize, usize)> {
+    let mut best: Option<(f64, isize, usize)> = None;
+    for k in 0..=MAX_LAG {
+        for lag in if k == 0 { vec![0] } else { vec![k, -k] } {
+            if let Some((r, n)) = pearson(a, b, lag)
+                && best.is_none_or(|(br, _, _)| r.abs() > br.abs() + LAG_MARGIN)
+            {
+                best = Some((r, lag, n));
+            }
+        }
+    }
+    best
+}
+
+/// The `n` series with the highest `load`, in name order.
+fn busiest<'a, M>(items: &[(&'a str, &'a [M])], load: fn(&M) -> f64, n: usize) -> Vec<(&'a str, &'a [M])> {
+    let mut ranked: Vec<(f64, &str, &[M])> = items.iter()
+        .map(|(name, s)| (s.iter().map(load).sum::<f64>() / s.len().max(1) as f64, *name, *s))
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(b.1)));
+    let mut out: Vec<_> = ranked.into_iter().take(n).map(|(_, name, s)| (name, s)).collect();
+    out.sort_by_key(|i| i.0);
+    out
+}
+
+fn lag_text(c: &Correlation, signals: &[Signal], interval: u64) -> String {
+    let secs = c.lag.unsigned_abs() as u64 * interval;
+    match c.lag {
+        0 => "same interval".to_string(),
+        l if l > 0 => format!("{} follows by {}s", signals[c.b].name, secs),
+        _ => format!("{} follows by {}s", signals[c.a].name, secs),
+    }
+}
+
+/// Correlates the chart series, writes correlations.txt and the overlay charts, and returns
+/// the dashboard section (empty when there is nothing to correlate).
+pub(crate) fn write(output_dir: &str, ctx: &TimeCtx, disks: &[(&str, &[IntervalDiskMetrics])], cpu: &[CpuMetrics],
+                    mem: &[MemMetrics], nets: &[(&str, &[IntervalNetMetrics])]) -> std::io::Result<String> {
+    let disks = busiest(disks, |m| m.kb_sec, CORR_DEVICES);
+    let nets = busiest(nets, |m| m.rx_bytes + m.tx_bytes, CORR_IFACES);
+
+    let mut all_ts: BTreeSet<u64> = BTreeSet::new();
+    all_ts.extend(disks.iter().flat_map(|(_, s)| s.iter().map(|m| m.ts)));
+    all_ts.extend(nets.iter().flat_map(|(_, s)| s.iter().map(|m| m.ts)));
+    all_ts.extend(cpu.iter().map(|m| m.ts));
+    all_ts.extend(mem.iter().map(|m| m.ts));
+    let times: Vec<u64> = all_ts.into_iter().collect();
+    let index: HashMap<u64, usize> = times.iter().enumerate().map(|(i, ts)| (*ts, i)).collect();
+
+    let mut signals: Vec<Signal> = Vec::new();
+    for (dev, s) in &disks {
+        signals_of(&index, "disk", dev, s, |m| m.ts, DISK_SIGNALS, &mut signals);
+    }
+    for (iface, s) in &nets {
+        signals_of(&index, "net", iface, s, |m| m.ts, NET_SIGNALS, &mut signals);
+    }
+    signals_of(&index, "cpu", "cpu", cpu, |m| m.ts, CPU_SIGNALS, &mut signals);
+    signals_of(&index, "mem", "mem", mem, |m| m.ts, MEM_SIGNALS, &mut signals);
+
+    let mut found: Vec<Correlation> = Vec::new();
+    for a in 0..signals.len() {
+        for b in a + 1..signals.len() {
+            if signals[a].subsystem == signals[b].subsystem { continue; }
+            if let Some((r, lag, points)) = best_lag(&signals[a].values, &signals[b].values)
+                && r.abs() >= STRONG
+            {
+                found.push(Correlation { a, b, r, lag, points });
+            }
+        }
+    }
+    found.sort_by(|x, y| y.r.abs().total_cmp(&x.r.abs()).then(x.lag.abs().cmp(&y.lag.abs())));
+    found.truncate(CORR_TOP);
+
+    // Typical interval, for lags in seconds
+    let mut steps: Vec<u64> = times.windows(2).map(|w| w[1] - w[0]).collect();
+    steps.sort_unstable();
+    let interval = steps.get(steps.len() / 2).copied().unwrap_or(1);
+
+    let fname = "correlations.txt";
+    let mut txt = format!("{fname}\nCross-subsystem correlations, |r| >= {STRONG}, lag up to {} intervals ({}s each)\n\n",
+        MAX_LAG, interval);
+    let mut html = format!("<p>Pairs of disk, network, CPU and memory series with |r| &ge; {STRONG}, \
+        best lag up to {MAX_LAG} intervals of {interval}s. Click a pair for its overlay chart.</p>\n");
+    if found.is_empty() {
+        txt.push_str("No strongly correlated pairs.\n");
+        html.push_str("<p>No strongly correlated pairs.</p>\n");
+    } else {
+        let _ = writeln!(txt, "{:<5} {:>6} {:<24} {:<24} {:>6}  Lag", "Rank", "r", "Series A", "Series B", "Points");
+        let _ = writeln!(txt, "{}", "-".repeat(100));
+        html.push_str("<table border=\"1\" cellpadding=\"4\"><tr><th>Rank</th><th>r</th><th>Series A</th><th>Series B</th><th>Points</th><th>Lag</th></tr>\n");
+        for (i, c) in found.iter().enumerate() {
+            let (a, b) = (&signals[c.a].name, &signals[c.b].name);
+            let lag = lag_text(c, &signals, interval);
+            let _ = writeln!(txt, "{:<5} {:>6.2} {:<24} {:<24} {:>6}  {}", i + 1, c.r, a, b, c.points, lag);
+            let _ = writeln!(html, "<tr><td>{}</td><td>{:.2}</td><td><a href=\"#corr_{:02}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                i + 1, c.r, i + 1, a, b, c.points, lag);
+        }
+        html.push_str("</table>\n");
+        // Each pair is its own SVG/PNG pair, rendered in parallel like the other charts
+        found.par_iter().enumerate().for_each(|(i, c)| {
+            let name = format!("corr_{:02}", i + 1);
+            let svg = format!("{}/{}.svg", output_dir, name);
+            draw_pair(SVGBackend::new(&svg, (900, 300)).into_drawing_area(), ctx, &times, &signals, c, &lag_text(c, &signals, interval));
+            let png = format!("{}/{}.png", output_dir, name);
+            draw_pair(BitMapBackend::new(&png, (900, 300)).into_drawing_area(), ctx, &times, &signals, c, &lag_text(c, &signals, interval));
+        });
+        for i in 0..found.len() {
+            let name = format!("corr_{:02}", i + 1);
+            let _ = writeln!(html, "<img class=\"graph\" id=\"{name}\" src=\"{name}.svg\" onerror=\"this.onerror=null; this.src='{name}.png';\">");
+        }
+    }
+    std::fs::write(format!("{}/{}", output_dir, fname), txt)?;
+    println!("Found {} strongly correlated pair(s), see {}", found.len(), fname);
+    Ok(html)
+}
+
+/// Both series of a pair on one chart: A on the left axis (blue), B on the right (red), unshifted.
+fn draw_pair<DB: DrawingBackend>(root: DrawingArea<DB, plotters::coord::Shift>, ctx: &TimeCtx, times: &[u64],
+                                 signals: &[Signal], c: &Correlation, lag: &str) {
+    let (a, b) = (&signals[c.a], &signals[c.b]);
+    let (time_labels, x_desc) = analyze::time_axis(ctx, times);
+    let top = |s: &Signal| s.values.iter().flatten().fold(0.0_f64, |m, v| m.max(*v)).max(1e-9) * 1.05;
+    let x_max = times.len().saturating_sub(1).max(1);
+
+    root.fill(&WHITE).unwrap();
+    let mut chart = ChartBuilder::on(&root)
+        .caption(format!("{} vs {}  (r={:.2}, {})", a.name, b.name, c.r, lag), ("sans-serif", 20))
+        .margin(12)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .right_y_label_area_size(60)
+        .build_cartesian_2d(0..x_max, 0.0..top(a))
+        .unwrap()
+        .set_secondary_coord(0..x_max, 0.0..top(b));
+    chart
+        .configure_mesh()
+        .x_labels(8)
+        .x_label_formatter(&|idx| time_labels.get(*idx).cloned().unwrap_or_default())
+        .x_desc(x_desc.as_str())
+        .y_desc(a.name.as_str())
+        .draw()
+        .unwrap();
+    chart.configure_secondary_axes().y_desc(b.name.as_str()).draw().unwrap();
+    let points = |s: &Signal| s.values.iter().enumerate().filter_map(|(i, v)| v.map(|v| (i, v))).collect::<Vec<_>>();
+    chart
+        .draw_series(LineSeries::new(points(a), &BLUE))
+        .unwrap()
+        .label(a.name.as_str())
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 25, y)], BLUE));
+    chart
+        .draw_secondary_series(LineSeries::new(points(b), &RED))
+        .unwrap()
+        .label(b.name.as_str())
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 25, y)], RED));
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .unwrap();
+    root.present().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serverstats_format::testdir::TempDir;
+
+    #[test]
+    fn finds_the_lag() {
+        // b repeats a two intervals later; c is unrelated noise
+        let wave: Vec<f64> = (0..60).map(|i| ((i * 7919) % 101) as f64).collect();
+        let a: Vec<Option<f64>> = wave.iter().map(|v| Some(*v)).collect();
+        let b: Vec<Option<f64>> = (0..60).map(|i| if i >= 2 { Some(wave[i - 2] * 3.0 + 1.0) } else { None }).collect();
+        let (r, lag, n) = best_lag(&a, &b).unwrap();
+        assert!((r - 1.0).abs() < 1e-9);
+        assert_eq!((lag, n), (2, 58));
+        let (r, lag, _) = best_lag(&b, &a).unwrap();
+        assert!((r - 1.0).abs() < 1e-9 && lag == -2);
+
+        // Flat or short series never correlate
+        assert!(pearson(&a, &vec![Some(0.0); 60], 0).is_none());
+        assert!(pearson(&a[..10], &b[..10], 0).is_none());
+    }
+
+    #[test]
+    fn only_cross_subsystem_pairs() {
+        let dir = TempDir::new("serverstats_grab-corr");
+        let load = |i: u64| ((i * 37) % 23) as f64;
+        let sda: Vec<IntervalDiskMetrics> = (0..30)
+            .map(|i| IntervalDiskMetrics { ts: i * 10, await_wr: load(i), kb_sec: 100.0, ..Default::default() })
+            .collect();
+        // sdb's await matches sda's exactly: same subsystem, not reported
+        let sdb = sda.clone();
+        let cpu: Vec<CpuMetrics> = (0..30)
+            .map(|i| CpuMetrics { ts: i * 10, iowait: load(i) / 2.0, user: 5.0, ..Default::default() })
+            .collect();
+        let ctx = TimeCtx::new(serverstats_format::timeutil::DisplayTz::parse("UTC").unwrap());
+        let html = write(dir.path().to_str().unwrap(), &ctx, &[("sda", &sda), ("sdb", &sdb)], &cpu, &[], &[]).unwrap();
+        let txt = std::fs::read_to_string(dir.join("correlations.txt")).unwrap();
+        let has_svg = dir.join("corr_01.svg").exists();
+
+        let rows: Vec<&str> = txt.lines().skip(5).collect();
+        assert_eq!(rows.len(), 2, "{}", txt);
+        assert!(rows[0].contains("sda await_wr") && rows[0].contains("cpu iowait") && rows[0].contains("same interval"));
+        assert!(rows[1].contains("sdb await_wr"));
+        assert!(html.contains("href=\"#corr_02\"") && has_svg);
+    }
+}
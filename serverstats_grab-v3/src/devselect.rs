@@ -0,0 +1,258 @@
+/*!
+ * Disk Device Selection Module
+ * ----------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Device filters and per-interval top-N for `-pD`, so hosts with hundreds of
+ * multipath legs can be read without piping through grep:
+ *
+ *   --dev <regex>          only devices matching (repeatable, any match)
+ *   --exclude-dev <regex>  drop devices matching (repeatable)
+ *   --no-partitions        drop partitions (sda1, nvme0n1p2, mmcblk0p1, ...)
+ *   --no-idle              drop devices with no I/O in the interval
+ *   --top N                only the N worst devices of each interval
+ *   --sort <key>           what "worst" means for --top (default io_s)
+ *
+ * Devices are listed in a stable order: the order they first appear in the
+ * capture, or by the --sort key (highest first) when --sort/--top is given.
+ *
+ * `-a` takes the name filters and --no-idle (devices with no I/O in the whole
+ * window), plus --iface/--exclude-iface for the network interfaces.
+ */
+
+use regex::Regex;
+use serverstats_format::DiskDelta;
+
+/// --sort keys, named like the -pD column keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    AwaitRd,
+    AwaitWr,
+    Qlen,
+    AvgQdepth,
+    Svctim,
+    KbSec,
+    RdKbs,
+    WrKbs,
+    IoS,
+    RS,
+    WS,
+    Util,
+}
+
+pub const SORT_KEYS: &str = "await_rd, await_wr, qlen, avg_qdepth, svctim, kb_sec, rd_kbs, wr_kbs, io_s, r_s, w_s, util";
+
+impl SortKey {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "await_rd" | "await_rd_ms" => Some(SortKey::AwaitRd),
+            "await_wr" | "await_wr_ms" => Some(SortKey::AwaitWr),
+            "qlen" => Some(SortKey::Qlen),
+            "avg_qdepth" | "aqu-sz" => Some(SortKey::AvgQdepth),
+            "svctim" => Some(SortKey::Svctim),
+            "kb_sec" | "kb_s" => Some(SortKey::KbSec),
+            "rd_kbs" => Some(SortKey::RdKbs),
+            "wr_kbs" => Some(SortKey::WrKbs),
+            "io_s" | "io_sec" | "iops" => Some(SortKey::IoS),
+            "r_s" => Some(SortKey::RS),
+            "w_s" => Some(SortKey::WS),
+            "util" | "util_pct" => Some(SortKey::Util),
+            _ => None,
+        }
+    }
+
+    pub fn value(self, d: &DiskDelta) -> f64 {
+        match self {
+            SortKey::AwaitRd => d.await_rd(),
+            SortKey::AwaitWr => d.await_wr(),
+            SortKey::Qlen => d.qlen(),
+            SortKey::AvgQdepth => d.avg_queue_depth(),
+            SortKey::Svctim => d.svctim(),
+            SortKey::KbSec => d.kb_s(),
+            SortKey::RdKbs => d.rd_kbs(),
+            SortKey::WrKbs => d.wr_kbs(),
+            SortKey::IoS => d.io_s(),
+            SortKey::RS => d.r_s(),
+            SortKey::WS => d.w_s(),
+            // Busy time as % of the interval
+            SortKey::Util => d.rate(d.io_time_ms) / 10.0,
+        }
+    }
+}
+
+/// Partition names as the kernel creates them: sda1, vdb2, xvda1, nvme0n1p2, mmcblk0p1, loop0p1.
+pub fn is_partition(name: &str) -> bool {
+    let digits = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    if digits.len() == name.len() { return false; }
+    if let Some(base) = digits.strip_suffix('p') {
+        // nvme0n1p2, mmcblk0p1, nbd0p1, loop0p1: "p" after a whole-disk name ending in a digit
+        return base.ends_with(|c: char| c.is_ascii_digit())
+            && ["nvme", "mmcblk", "nbd", "loop", "md"].iter().any(|p| base.starts_with(p));
+    }
+    ["sd", "vd", "xvd", "hd"].iter().any(|p| digits.len() > p.len() && digits.starts_with(p)
+        && digits[p.len()..].chars().all(|c| c.is_ascii_lowercase()))
+}
+
+#[derive(Debug, Default)]
+pub struct DevSelect {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+    pub no_partitions: bool,
+    pub no_idle: bool,
+    pub top: Option<usize>,
+    pub sort: Option<SortKey>,
+}
+
+impl DevSelect {
+    pub fn include(&mut self, pattern: &str) -> Result<(), String> {
+        self.include.push(Regex::new(pattern).map_err(|e| format!("--dev: {}", e))?);
+        Ok(())
+    }
+
+    pub fn exclude(&mut self, pattern: &str) -> Result<(), String> {
+        self.exclude.push(Regex::new(pattern).map_err(|e| format!("--exclude-dev: {}", e))?);
+        Ok(())
+    }
+
+    /// No selection options given: every device, capture order.
+    pub fn is_default(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && !self.no_partitions && !self.no_idle
+            && self.top.is_none() && self.sort.is_none()
+    }
+
+    /// Name filters (--dev, --exclude-dev, --no-partitions).
+    pub fn wants(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|r| r.is_match(name)))
+            && !self.exclude.iter().any(|r| r.is_match(name))
+            && !(self.no_partitions && is_partition(name))
+    }
+
+    /// Per-interval filter (--no-idle).
+    pub fn keep(&self, d: &DiskDelta) -> bool {
+        !self.no_idle
+            || d.reads + d.writes + d.discards + d.flushes.unwrap_or(0) > 0
+            || d.io_time_ms > 0
+    }
+
+    /// Orders one interval's rows and applies --top. `rows` are (first-seen index, delta).
+    pub fn pick<T>(&self, rows: &mut Vec<(usize, T)>, delta: impl Fn(&T) -> &DiskDelta) {
+        rows.sort_by_key(|(idx, _)| *idx);
+        let key = self.sort.or(self.top.map(|_| SortKey::IoS));
+        if let Some(key) = key {
+            // Stable sort: ties stay in first-seen order
+            rows.sort_by(|a, b| key.value(delta(&b.1)).total_cmp(&key.value(delta(&a.1))));
+        }
+        if let Some(n) = self.top {
+            rows.truncate(n);
+        }
+    }
+
+    /// Name filters and --no-idle in words, empty when every device is selected.
+    pub fn describe(&self) -> String {
+        let mut parts = patterns("matching", &self.include, "excluding", &self.exclude);
+        if self.no_partitions { parts.push("no partitions".to_string()); }
+        if self.no_idle { parts.push("no idle devices".to_string()); }
+        parts.join(", ")
+    }
+}
+
+/// Network interface filters for `-a` (--iface, --exclude-iface).
+#[derive(Debug, Default)]
+pub struct IfaceSelect {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl IfaceSelect {
+    pub fn include(&mut self, pattern: &str) -> Result<(), String> {
+        self.include.push(Regex::new(pattern).map_err(|e| format!("--iface: {}", e))?);
+        Ok(())
+    }
+
+    pub fn exclude(&mut self, pattern: &str) -> Result<(), String> {
+        self.exclude.push(Regex::new(pattern).map_err(|e| format!("--exclude-iface: {}", e))?);
+        Ok(())
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn wants(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|r| r.is_match(name)))
+            && !self.exclude.iter().any(|r| r.is_match(name))
+    }
+
+    /// Filters in words, empty when every interface is selected.
+    pub fn describe(&self) -> String {
+        patterns("matching", &self.include, "excluding", &self.exclude).join(", ")
+    }
+}
+
+/// "matching /a/ or /b/", "excluding /c/" for the scope line.
+fn patterns(inc_word: &str, include: &[Regex], exc_word: &str, exclude: &[Regex]) -> Vec<String> {
+    let list = |rs: &[Regex]| rs.iter().map(|r| format!("/{}/", r.as_str())).collect::<Vec<_>>().join(" or ");
+    let mut parts = Vec::new();
+    if !include.is_empty() { parts.push(format!("{} {}", inc_word, list(include))); }
+    if !exclude.is_empty() { parts.push(format!("{} {}", exc_word, list(exclude))); }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serverstats_format::DiskStat;
+
+    #[test]
+    fn partitions() {
+        for p in ["sda1", "sdab12", "vdb2", "xvda1", "hda3", "nvme0n1p2", "mmcblk0p1", "loop0p1"] {
+            assert!(is_partition(p), "{}", p);
+        }
+        for d in ["sda", "sdab", "nvme0n1", "mmcblk0", "dm-3", "md127", "loop0", "sr0", "dm-12"] {
+            assert!(!is_partition(d), "{}", d);
+        }
+    }
+
+    #[test]
+    fn filters_sort_and_top() {
+        let mut sel = DevSelect { no_partitions: true, no_idle: true, top: Some(2), ..Default::default() };
+        sel.include("^(sd|dm-)").unwrap();
+        sel.exclude("^sdc$").unwrap();
+        assert!(sel.include("(").is_err());
+        assert!(sel.wants("sda") && sel.wants("dm-0"));
+        assert!(!sel.wants("sda1") && !sel.wants("sdc") && !sel.wants("nvme0n1"));
+
+        let base = DiskStat::from_line("8 0 sda 0 0 0 0 0 0 0 0 0 0 0").unwrap();
+        let delta = |reads: u64, read_ms: u64| {
+            let cur = DiskStat::from_line(&format!("8 0 sda {} 0 {} {} 0 0 0 0 0 {} 0", reads, reads * 8, read_ms, reads)).unwrap();
+            DiskDelta::between(&base, &cur, 10).unwrap()
+        };
+        assert!(!sel.keep(&delta(0, 0)));
+        assert!(sel.keep(&delta(5, 10)));
+
+        // Default --top order is io_s, highest first
+        let mut rows = vec![(2, delta(10, 100)), (0, delta(30, 30)), (1, delta(20, 400))];
+        sel.pick(&mut rows, |d| d);
+        assert_eq!(rows.iter().map(|r| r.0).collect::<Vec<_>>(), [0, 1]);
+
+        sel.sort = SortKey::parse("await_rd");
+        sel.top = None;
+        let mut rows = vec![(2, delta(10, 100)), (0, delta(30, 30)), (1, delta(20, 400))];
+        sel.pick(&mut rows, |d| d);
+        assert_eq!(rows.iter().map(|r| r.0).collect::<Vec<_>>(), [1, 2, 0]);
+        assert!(SortKey::parse("bogus").is_none());
+        assert_eq!(sel.describe(), "matching /^(sd|dm-)/, excluding /^sdc$/, no partitions, no idle devices");
+    }
+
+    #[test]
+    fn iface_filters() {
+        let mut ifaces = IfaceSelect::default();
+        assert!(ifaces.wants("lo") && ifaces.describe().is_empty());
+        ifaces.include("^(eth|ens)").unwrap();
+        ifaces.include("^bond").unwrap();
+        ifaces.exclude("\\.").unwrap();
+        assert!(ifaces.wants("ens3") && ifaces.wants("bond0"));
+        assert!(!ifaces.wants("lo") && !ifaces.wants("eth0.100"));
+        assert_eq!(ifaces.describe(), "matching /^(eth|ens)/ or /^bond/, excluding /\\./");
+    }
+}
@@ -0,0 +1,204 @@
+/*!
+ * Capture Extract Module
+ * ----------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * `-t` writes a cut-down capture to share a slice of a long one: only the
+ * --from/--to window, and only the devices and interfaces picked with --dev,
+ * --exclude-dev, --no-partitions, --iface and --exclude-iface. The result is an
+ * ordinary .dat that every mode reads.
+ *
+ *   - the sample just before --from is kept, so the first interval of the
+ *     window keeps its rates
+ *   - the HOST, TZ and DISKLAYOUT records in force at that point are kept, so
+ *     times, DISK fields and the -pX banner read as in the original
+ *   - DISK, SCSIDEV, MD and DRBD records follow the device filters, NET and
+ *     NETX the interface filters; CPU, MEM, FC, ISCSI, EVENT and record types
+ *     this version does not know are always kept
+ *   - lines that are not text (a torn write) are skipped and counted, as the
+ *     other modes skip them
+ */
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+
+use serverstats_format::{Reader, Record, Writer};
+use serverstats_format::timeutil::TimeWindow;
+
+use crate::devselect::{DevSelect, IfaceSelect};
+
+/// Records written, the span of the samples among them and the unreadable lines skipped.
+#[derive(Debug, Default, PartialEq)]
+pub struct Extracted {
+    pub records: usize,
+    pub first: Option<u64>,
+    pub last: Option<u64>,
+    pub skipped: usize,
+}
+
+fn wanted(rec: &Record, sel: &DevSelect, ifaces: &IfaceSelect) -> bool {
+    match rec {
+        Record::Disk { stat, .. } => sel.wants(&stat.name),
+        Record::ScsiDev { dev, .. } => sel.wants(dev),
+        Record::Md { stat, .. } => sel.wants(&stat.name),
+        Record::Drbd { stat, .. } => sel.wants(&stat.name),
+        Record::Net { stat, .. } => ifaces.wants(&stat.iface),
+        Record::NetExt { iface, .. } => ifaces.wants(iface),
+        _ => true,
+    }
+}
+
+/// Copies the window and selected devices of `path` to a new capture at `out_path`.
+pub fn extract(path: &str, out_path: &str, window: &TimeWindow, sel: &DevSelect, ifaces: &IfaceSelect)
+    -> io::Result<Extracted> {
+    let reader = Reader::open(path)?;
+    let mut out = Writer::new(BufWriter::new(File::create(out_path)?));
+    out.write_header()?;
+    let mut done = Extracted::default();
+    let mut emit = |out: &mut Writer<BufWriter<File>>, rec: &Record| -> io::Result<()> {
+        out.write(rec)?;
+        done.records += 1;
+        if !matches!(rec, Record::Host { .. } | Record::Tz { .. } | Record::DiskLayout { .. }) {
+            done.first.get_or_insert(rec.ts());
+            done.last = Some(rec.ts());
+        }
+        Ok(())
+    };
+    // Before the window: the HOST, TZ and DISKLAYOUT in force and the records of the last sample
+    let mut host: Option<Record> = None;
+    let mut tz: Option<Record> = None;
+    let mut layout: Option<Record> = None;
+    let mut baseline: Vec<Record> = Vec::new();
+    let mut inside = false;
+    let mut skipped = 0;
+    for rec in reader {
+        let rec = match rec {
+            Ok(rec) => rec,
+            // Invalid UTF-8: the line is consumed, reading goes on with the next one
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                skipped += 1;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if !wanted(&rec, sel, ifaces) { continue; }
+        let ts = rec.ts();
+        if window.is_past(ts) { break; }
+        if !window.contains(ts) {
+            match rec {
+                Record::Host { .. } => host = Some(rec),
+                Record::Tz { .. } => tz = Some(rec),
+                Record::DiskLayout { .. } => layout = Some(rec),
+                _ => {
+                    if baseline.last().is_some_and(|r| r.ts() != ts) { baseline.clear(); }
+                    baseline.push(rec);
+                }
+            }
+            continue;
+        }
+        if !inside {
+            inside = true;
+            for r in host.iter().chain(tz.iter()).chain(layout.iter()).chain(baseline.iter()) {
+                emit(&mut out, r)?;
+            }
+        }
+        emit(&mut out, &rec)?;
+    }
+    out.flush()?;
+    done.skipped = skipped;
+    Ok(done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serverstats_format::testdir::TempDir;
+    use std::fs;
+
+    const CAPTURE: &str = "\
+#TYPE,ts_epoch,<fields...>
+HOST,100,db01,5.14.0-427.13.1.el9_4.x86_64,x86_64,64
+TZ,100,3600,Europe/Berlin
+DISKLAYOUT,100,14
+DISK,100,8,0,sda,1,0,8,1,0,0,0,0,0,1,1,0,0,0,0
+DISK,100,8,1,sda1,1,0,8,1,0,0,0,0,0,1,1,0,0,0,0
+DISK,100,253,0,dm-0,1,0,8,1,0,0,0,0,0,1,1,0,0,0,0
+CPU,100,1,0,1,10,0,0,0,0,0,1,0
+NET,100,eth0,1,1,1,1,0,0,0,0
+NET,100,lo,1,1,1,1,0,0,0,0
+DISK,110,8,0,sda,2,0,16,2,0,0,0,0,0,2,2,0,0,0,0
+DISK,110,8,1,sda1,2,0,16,2,0,0,0,0,0,2,2,0,0,0,0
+DISK,110,253,0,dm-0,2,0,16,2,0,0,0,0,0,2,2,0,0,0,0
+CPU,110,2,0,2,20,0,0,0,0,0,1,0
+NETX,110,eth0,0,0,0,0,0,1000,1500,up,1,full
+NET,110,eth0,2,2,2,2,0,0,0,0
+NET,110,lo,2,2,2,2,0,0,0,0
+EVENT,115,diskguard,pause,free 4.1%
+DISK,120,8,0,sda,3,0,24,3,0,0,0,0,0,3,3,0,0,0,0
+DISK,120,8,1,sda1,3,0,24,3,0,0,0,0,0,3,3,0,0,0,0
+DISK,120,253,0,dm-0,3,0,24,3,0,0,0,0,0,3,3,0,0,0,0
+CPU,120,3,0,3,30,0,0,0,0,0,1,0
+XFUTURE,120,a,b
+DISK,130,8,0,sda,4,0,32,4,0,0,0,0,0,4,4,0,0,0,0
+CPU,130,4,0,4,40,0,0,0,0,0,1,0
+";
+
+    fn run(name: &str, window: TimeWindow, sel: &DevSelect, ifaces: &IfaceSelect) -> (Extracted, Vec<String>) {
+        run_bytes(name, CAPTURE.as_bytes(), window, sel, ifaces)
+    }
+
+    fn run_bytes(name: &str, capture: &[u8], window: TimeWindow, sel: &DevSelect, ifaces: &IfaceSelect) -> (Extracted, Vec<String>) {
+        let dir = TempDir::new(&format!("serverstats_grab-extract-{}", name));
+        let (src, dst) = (dir.write("in.dat", capture), dir.join("out.dat"));
+        let done = extract(src.to_str().unwrap(), dst.to_str().unwrap(), &window, sel, ifaces).unwrap();
+        let text = fs::read_to_string(&dst).unwrap();
+        (done, text.lines().map(|l| l.to_string()).collect())
+    }
+
+    #[test]
+    fn whole_capture_is_copied() {
+        let (done, lines) = run("all", TimeWindow::default(), &DevSelect::default(), &IfaceSelect::default());
+        assert_eq!(lines.join("\n") + "\n", CAPTURE);
+        assert_eq!(done, Extracted { records: CAPTURE.lines().count() - 1, first: Some(100), last: Some(130), skipped: 0 });
+    }
+
+    #[test]
+    fn window_and_devices() {
+        let mut sel = DevSelect::default();
+        sel.no_partitions = true;
+        sel.exclude("^dm-").unwrap();
+        let mut ifaces = IfaceSelect::default();
+        ifaces.include("^eth").unwrap();
+        let (done, lines) = run("scoped", TimeWindow { from: Some(115), to: Some(125) }, &sel, &ifaces);
+        // HOST, TZ, DISKLAYOUT and the 110 sample come along as the baseline for the first interval
+        assert_eq!(lines, [
+            "#TYPE,ts_epoch,<fields...>",
+            "HOST,100,db01,5.14.0-427.13.1.el9_4.x86_64,x86_64,64",
+            "TZ,100,3600,Europe/Berlin",
+            "DISKLAYOUT,100,14",
+            "DISK,110,8,0,sda,2,0,16,2,0,0,0,0,0,2,2,0,0,0,0",
+            "CPU,110,2,0,2,20,0,0,0,0,0,1,0",
+            "NETX,110,eth0,0,0,0,0,0,1000,1500,up,1,full",
+            "NET,110,eth0,2,2,2,2,0,0,0,0",
+            "EVENT,115,diskguard,pause,free 4.1%",
+            "DISK,120,8,0,sda,3,0,24,3,0,0,0,0,0,3,3,0,0,0,0",
+            "CPU,120,3,0,3,30,0,0,0,0,0,1,0",
+            "XFUTURE,120,a,b",
+        ]);
+        assert_eq!(done, Extracted { records: 11, first: Some(110), last: Some(120), skipped: 0 });
+
+        let (done, lines) = run("empty", TimeWindow { from: Some(200), to: None }, &sel, &ifaces);
+        assert_eq!((done.records, lines.len()), (0, 1));
+    }
+
+    #[test]
+    fn torn_lines_are_skipped() {
+        // A torn write left a non-UTF-8 line in the middle of the 110 sample
+        let mut capture = CAPTURE.as_bytes().to_vec();
+        let at = CAPTURE.find("CPU,110").unwrap();
+        capture.splice(at..at, b"DISK,110,8,0,s\xff\xfe\n".iter().copied());
+        let (done, lines) = run_bytes("torn", &capture, TimeWindow::default(), &DevSelect::default(), &IfaceSelect::default());
+        assert_eq!(lines.join("\n") + "\n", CAPTURE);
+        assert_eq!((done.skipped, done.last), (1, Some(130)));
+    }
+}
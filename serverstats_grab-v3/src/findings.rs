@@ -0,0 +1,501 @@
+/*!
+ * Findings Module
+ * ---------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Rule-based findings for `-a`: scans the per-interval disk, CPU, memory and
+ * network series and reports what looks wrong, with a time range, the affected
+ * device and a severity:
+ *
+ *   - sustained high read/write await and queue saturation (per disk)
+ *   - iowait spikes
+ *   - memory exhaustion (MemAvailable low) and swap activity
+ *   - network drops and errors (per interface)
+ *   - sudden throughput collapse (per disk and interface), critical when I/O
+ *     is still queued on the disk, i.e. it stalled rather than went idle
+ *
+ * A run of matching intervals may include one interval below the threshold, so
+ * a flapping device gives one finding rather than dozens. Thresholds are the
+ * constants below; they are starting points, not SLAs.
+ */
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+
+use serverstats_format::output::json_str;
+use serverstats_format::timeutil::TimeCtx;
+
+use crate::analyze::{CpuMetrics, IntervalDiskMetrics, IntervalNetMetrics, MemMetrics};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Severity {
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Finding {
+    pub(crate) severity: Severity,
+    /// Start of the first interval and end of the last interval of the run
+    pub(crate) start: u64,
+    pub(crate) end: u64,
+    /// Device, interface, "cpu" or "memory"
+    pub(crate) subject: String,
+    pub(crate) rule: &'static str,
+    pub(crate) detail: String,
+}
+
+/// A threshold on one value of a series.
+struct Rule<M> {
+    rule: &'static str,
+    what: &'static str,
+    unit: &'static str,
+    value: fn(&M) -> f64,
+    /// Interval counts at all (e.g. await only when there was I/O)
+    gate: fn(&M) -> bool,
+    warn: f64,
+    crit: f64,
+    /// Low values are bad (memory available)
+    below: bool,
+    /// Intervals needed for a finding
+    min: usize,
+}
+
+/// Non-matching intervals tolerated inside a run.
+const RUN_GAP: usize = 1;
+
+const DISK_RULES: &[Rule<IntervalDiskMetrics>] = &[
+    Rule { rule: "high_await_rd", what: "read await", unit: " ms", value: |m| m.await_rd, gate: |m| m.rps >= 1.0,
+           warn: 20.0, crit: 100.0, below: false, min: 3 },
+    Rule { rule: "high_await_wr", what: "write await", unit: " ms", value: |m| m.await_wr, gate: |m| m.wps >= 1.0,
+           warn: 20.0, crit: 100.0, below: false, min: 3 },
+    Rule { rule: "queue_saturation", what: "queue length", unit: "", value: |m| m.qlen, gate: |_| true,
+           warn: 32.0, crit: 128.0, below: false, min: 3 },
+];
+
+const CPU_RULES: &[Rule<CpuMetrics>] = &[
+    Rule { rule: "iowait_spike", what: "iowait", unit: "%", value: |m| m.iowait, gate: |_| true,
+           warn: 20.0, crit: 50.0, below: false, min: 1 },
+];
+
+const MEM_RULES: &[Rule<MemMetrics>] = &[
+    Rule { rule: "memory_exhaustion", what: "MemAvailable", unit: "% of RAM", value: |m| m.avail_percent,
+           gate: |_| true, warn: 10.0, crit: 5.0, below: true, min: 3 },
+];
+
+/// Swap activity: change in swap used between MEM samples, KB/s.
+const SWAP_RULE: Rule<(u64, f64)> = Rule {
+    rule: "swap_activity", what: "swap used changing by", unit: " KB/s", value: |m| m.1, gate: |_| true,
+    warn: 100.0, crit: 10240.0, below: false, min: 2,
+};
+
+const NET_RULES: &[Rule<IntervalNetMetrics>] = &[
+    Rule { rule: "net_drops", what: "drops", unit: "/s", value: |m| m.rx_drop + m.tx_drop, gate: |_| true,
+           warn: 1.0, crit: 100.0, below: false, min: 1 },
+    Rule { rule: "net_errors", what: "errors", unit: "/s",
+           value: |m| m.rx_errs + m.tx_errs + m.rx_crc_errs + m.rx_fifo_errs + m.rx_missed_errs,
+           gate: |_| true, warn: 0.01, crit: 10.0, below: false, min: 1 },
+];
+
+/// Throughput collapse: below COLLAPSE_RATIO of the mean of the COLLAPSE_BASELINE intervals before it,
+/// for at least COLLAPSE_MIN intervals, when that mean was at least `floor`.
+const COLLAPSE_BASELINE: usize = 5;
+const COLLAPSE_RATIO: f64 = 0.1;
+const COLLAPSE_MIN: usize = 2;
+
+struct Throughput<M> {
+    value: fn(&M) -> f64,
+    /// Work still outstanding while throughput is down (a stall, not idle)
+    stalled: fn(&M) -> bool,
+    floor: f64,
+    unit: &'static str,
+}
+
+const DISK_THROUGHPUT: Throughput<IntervalDiskMetrics> =
+    Throughput { value: |m| m.kb_sec, stalled: |m| m.qlen >= 1.0, floor: 1024.0, unit: " KB/s" };
+const NET_THROUGHPUT: Throughput<IntervalNetMetrics> =
+    Throughput { value: |m| m.rx_bytes + m.tx_bytes, stalled: |_| false, floor: 1024.0 * 1024.0, unit: " B/s" };
+
+/// A throughput collapse in progress.
+struct Collapse {
+    baseline: f64,
+    /// Start of the first low interval, end of the last
+    start: u64,
+    end: u64,
+    low: f64,
+    intervals: usize,
+    stalled: bool,
+}
+
+fn fmt_value(v: f64) -> String {
+    match v.abs() {
+        a if a >= 100.0 => format!("{:.0}", v),
+        a if a >= 1.0 || a == 0.0 => format!("{:.1}", v),
+        _ => format!("{:.2}", v),
+    }
+}
+
+/// A run of intervals over a threshold in progress.
+struct Run {
+    severity: Severity,
+    /// Start of the first matching interval, end of the last
+    start: u64,
+    end: u64,
+    /// Matching intervals, their sum and the worst value
+    hits: usize,
+    sum: f64,
+    worst: f64,
+    /// Intervals below the threshold since the last match
+    misses: usize,
+}
+
+/// Runs of intervals over one rule's thresholds, fed an interval at a time.
+struct Scan<M: 'static> {
+    rule: &'static Rule<M>,
+    run: Option<Run>,
+}
+
+impl<M> Scan<M> {
+    fn new(rule: &'static Rule<M>) -> Self {
+        Scan { rule, run: None }
+    }
+
+    fn level(&self, m: &M) -> Option<Severity> {
+        let rule = self.rule;
+        let v = (rule.value)(m);
+        if !(rule.gate)(m) || !v.is_finite() { return None; }
+        let over = |t: f64| if rule.below { v <= t } else { v >= t };
+        if over(rule.crit) { Some(Severity::Critical) } else if over(rule.warn) { Some(Severity::Warning) } else { None }
+    }
+
+    fn push(&mut self, start: u64, ts: u64, m: &M, subject: &str, out: &mut Vec<Finding>) {
+        let v = (self.rule.value)(m);
+        let below = self.rule.below;
+        match (self.level(m), self.run.as_mut()) {
+            (Some(sev), Some(r)) => {
+                r.severity = r.severity.max(sev);
+                r.end = ts;
+                r.hits += 1;
+                r.sum += v;
+                r.worst = if below { r.worst.min(v) } else { r.worst.max(v) };
+                r.misses = 0;
+            }
+            (Some(severity), None) => {
+                self.run = Some(Run { severity, start, end: ts, hits: 1, sum: v, worst: v, misses: 0 });
+            }
+            (None, Some(r)) => {
+                r.misses += 1;
+                if r.misses > RUN_GAP {
+                    self.finish(subject, out);
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn finish(&mut self, subject: &str, out: &mut Vec<Finding>) {
+        let Some(run) = self.run.take() else { return };
+        let rule = self.rule;
+        if run.hits < rule.min { return; }
+        out.push(Finding {
+            severity: run.severity,
+            start: run.start,
+            end: run.end,
+            subject: subject.to_string(),
+            rule: rule.rule,
+            detail: format!("{} {} {}{}, mean {}{} over {} interval(s) ({} {}{})",
+                rule.what, if rule.below { "down to" } else { "up to" }, fmt_value(run.worst), rule.unit,
+                fmt_value(run.sum / run.hits as f64), rule.unit, run.hits,
+                if rule.below { "warning below" } else { "warning at" }, fmt_value(rule.warn), rule.unit),
+        });
+    }
+}
+
+/// Runs where throughput fell below a fraction of what it was just before, fed an interval at a time.
+struct CollapseScan<M: 'static> {
+    t: &'static Throughput<M>,
+    recent: VecDeque<f64>,
+    run: Option<Collapse>,
+}
+
+impl<M> CollapseScan<M> {
+    fn new(t: &'static Throughput<M>) -> Self {
+        CollapseScan { t, recent: VecDeque::new(), run: None }
+    }
+
+    fn push(&mut self, start: u64, ts: u64, m: &M, subject: &str, out: &mut Vec<Finding>) {
+        let t = self.t;
+        let v = (t.value)(m);
+        if let Some(c) = self.run.as_mut() {
+            if v < c.baseline * COLLAPSE_RATIO {
+                c.end = ts;
+                c.low = c.low.min(v);
+                c.intervals += 1;
+                c.stalled |= (t.stalled)(m);
+                return;
+            }
+            self.finish(subject, out);
+            self.recent.clear();
+        }
+        if self.recent.len() == COLLAPSE_BASELINE {
+            let baseline = self.recent.iter().sum::<f64>() / self.recent.len() as f64;
+            if baseline >= t.floor && v < baseline * COLLAPSE_RATIO {
+                self.run = Some(Collapse { baseline, start, end: ts, low: v, intervals: 1, stalled: (t.stalled)(m) });
+                return;
+            }
+            self.recent.pop_front();
+        }
+        self.recent.push_back(v);
+    }
+
+    fn finish(&mut self, subject: &str, out: &mut Vec<Finding>) {
+        let Some(c) = self.run.take() else { return };
+        if c.intervals < COLLAPSE_MIN { return; }
+        out.push(Finding {
+            severity: if c.stalled { Severity::Critical } else { Severity::Warning },
+            start: c.start,
+            end: c.end,
+            subject: subject.to_string(),
+            rule: "throughput_collapse",
+            detail: format!("throughput fell from {}{} to {}{} for {} interval(s){}",
+                fmt_value(c.baseline), self.t.unit, fmt_value(c.low), self.t.unit, c.intervals,
+                if c.stalled { ", with I/O still queued" } else { "" }),
+        });
+    }
+}
+
+/// All the rules for one device, interface, "cpu" or "memory", fed one interval at a time,
+/// so `-a` never holds a whole series.
+pub(crate) struct Detector<M: 'static> {
+    subject: String,
+    ts: fn(&M) -> u64,
+    scans: Vec<Scan<M>>,
+    collapse: Option<CollapseScan<M>>,
+    found: Vec<Finding>,
+}
+
+impl<M> Detector<M> {
+    fn new(subject: &str, ts: fn(&M) -> u64, rules: &'static [Rule<M>], collapse: Option<&'static Throughput<M>>) -> Self {
+        Detector {
+            subject: subject.to_string(),
+            ts,
+            scans: rules.iter().map(Scan::new).collect(),
+            collapse: collapse.map(CollapseScan::new),
+            found: Vec::new(),
+        }
+    }
+
+    /// Next interval, `dt` seconds long and ending at its timestamp.
+    pub(crate) fn push(&mut self, m: &M, dt: u64) {
+        let ts = (self.ts)(m);
+        let start = ts.saturating_sub(dt);
+        for scan in &mut self.scans {
+            scan.push(start, ts, m, &self.subject, &mut self.found);
+        }
+        if let Some(c) = self.collapse.as_mut() {
+            c.push(start, ts, m, &self.subject, &mut self.found);
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<Finding> {
+        for scan in &mut self.scans {
+            scan.finish(&self.subject, &mut self.found);
+        }
+        if let Some(c) = self.collapse.as_mut() {
+            c.finish(&self.subject, &mut self.found);
+        }
+        self.found
+    }
+}
+
+pub(crate) fn disk_detector(dev: &str) -> Detector<IntervalDiskMetrics> {
+    Detector::new(dev, |m| m.ts, DISK_RULES, Some(&DISK_THROUGHPUT))
+}
+
+pub(crate) fn cpu_detector() -> Detector<CpuMetrics> {
+    Detector::new("cpu", |m| m.ts, CPU_RULES, None)
+}
+
+pub(crate) fn net_detector(iface: &str) -> Detector<IntervalNetMetrics> {
+    Detector::new(iface, |m| m.ts, NET_RULES, Some(&NET_THROUGHPUT))
+}
+
+/// Memory rules, plus swap activity from the change between consecutive MEM samples.
+pub(crate) struct MemDetector {
+    mem: Detector<MemMetrics>,
+    swap: Detector<(u64, f64)>,
+    prev: Option<(u64, f64)>,
+}
+
+impl MemDetector {
+    pub(crate) fn new() -> Self {
+        MemDetector {
+            mem: Detector::new("memory", |m| m.ts, MEM_RULES, None),
+            swap: Detector::new("memory", |m| m.0, std::slice::from_ref(&SWAP_RULE), None),
+            prev: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, m: &MemMetrics) {
+        // A sample stands for the time since the previous one
+        self.mem.push(m, self.prev.map_or(0, |(ts, _)| m.ts.saturating_sub(ts)));
+        if let Some((ts, swap)) = self.prev && m.ts > ts {
+            self.swap.push(&(m.ts, (m.swap_used_kb - swap).abs() / (m.ts - ts) as f64), m.ts - ts);
+        }
+        self.prev = Some((m.ts, m.swap_used_kb));
+    }
+
+    pub(crate) fn finish(self) -> Vec<Finding> {
+        let mut out = self.mem.finish();
+        out.extend(self.swap.finish());
+        out
+    }
+}
+
+/// Most severe first, then by start time.
+pub(crate) fn rank(found: &mut [Finding]) {
+    found.sort_by(|a, b| b.severity.cmp(&a.severity).then(a.start.cmp(&b.start)).then(a.subject.cmp(&b.subject)));
+}
+
+/// All findings over whole series, most severe first, then by start time.
+#[cfg(test)]
+fn find(disks: &[(&str, &[IntervalDiskMetrics])], cpu: &[CpuMetrics], mem: &[MemMetrics],
+        nets: &[(&str, &[IntervalNetMetrics])]) -> Vec<Finding> {
+    // Evenly spaced series: every interval as long as the gap between the first two
+    fn feed<M>(mut d: Detector<M>, series: &[M]) -> Vec<Finding> {
+        let dt = match series {
+            [a, b, ..] => (d.ts)(b).saturating_sub((d.ts)(a)),
+            _ => 0,
+        };
+        series.iter().for_each(|m| d.push(m, dt));
+        d.finish()
+    }
+    let mut out = Vec::new();
+    for (dev, series) in disks {
+        out.extend(feed(disk_detector(dev), series));
+    }
+    out.extend(feed(cpu_detector(), cpu));
+    let mut m = MemDetector::new();
+    mem.iter().for_each(|x| m.push(x));
+    out.extend(m.finish());
+    for (iface, series) in nets {
+        out.extend(feed(net_detector(iface), series));
+    }
+    rank(&mut out);
+    out
+}
+
+fn duration(f: &Finding) -> String {
+    let d = f.end.saturating_sub(f.start);
+    format!("{}:{:02}:{:02}", d / 3600, (d % 3600) / 60, d % 60)
+}
+
+/// findings.txt: one line per finding.
+pub(crate) fn to_text(findings: &[Finding], ctx: &TimeCtx, fname: &str) -> String {
+    let mut s = format!("{fname}\nFindings ({})\n\n", ctx.label());
+    if findings.is_empty() {
+        s.push_str("No findings: nothing crossed the thresholds.\n");
+        return s;
+    }
+    let _ = writeln!(s, "{:<8} {:<19} {:<19} {:>8}  {:<16} {:<20} Detail", "Severity", "Start", "End", "Duration", "Device", "Finding");
+    let _ = writeln!(s, "{}", "-".repeat(120));
+    for f in findings {
+        let _ = writeln!(s, "{:<8} {:<19} {:<19} {:>8}  {:<16} {:<20} {}", f.severity.label(),
+            ctx.format(f.start, "%Y-%m-%d %H:%M:%S"), ctx.format(f.end, "%Y-%m-%d %H:%M:%S"), duration(f),
+            f.subject, f.rule, f.detail);
+    }
+    s
+}
+
+/// findings.json: `{"timezone":..,"findings":[{..},..]}` with ISO 8601 and epoch times.
+pub(crate) fn to_json(findings: &[Finding], ctx: &TimeCtx) -> String {
+    let iso = |ts: u64| json_str(&ctx.format(ts, "%Y-%m-%dT%H:%M:%S%:z"));
+    let items: Vec<String> = findings.iter().map(|f| format!(
+        "{{\"severity\":{},\"start\":{},\"end\":{},\"start_epoch\":{},\"end_epoch\":{},\"device\":{},\"finding\":{},\"detail\":{}}}",
+        json_str(f.severity.label()), iso(f.start), iso(f.end), f.start, f.end,
+        json_str(&f.subject), json_str(f.rule), json_str(&f.detail),
+    )).collect();
+    format!("{{\"timezone\":{},\"findings\":[\n{}\n]}}\n", json_str(&ctx.label()), items.join(",\n"))
+}
+
+/// Summary table for the dashboard.
+pub(crate) fn to_html(findings: &[Finding], ctx: &TimeCtx) -> String {
+    let esc = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let count = |sev| findings.iter().filter(|f| f.severity == sev).count();
+    let mut s = format!("<p>{} critical, {} warning ({}).</p>\n",
+        count(Severity::Critical), count(Severity::Warning), ctx.label());
+    if findings.is_empty() {
+        s.push_str("<p>No findings: nothing crossed the thresholds.</p>\n");
+        return s;
+    }
+    s.push_str("<table border=\"1\" cellpadding=\"4\"><tr><th>Severity</th><th>Start</th><th>End</th><th>Duration</th><th>Device</th><th>Finding</th><th>Detail</th></tr>\n");
+    for f in findings {
+        let _ = writeln!(s, "<tr class=\"sev-{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            f.severity.label(), f.severity.label(), ctx.format(f.start, "%Y-%m-%d %H:%M:%S"),
+            ctx.format(f.end, "%Y-%m-%d %H:%M:%S"), duration(f), esc(&f.subject), f.rule, esc(&f.detail));
+    }
+    s.push_str("</table>\n");
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk(ts: u64, kb_sec: f64, qlen: f64, await_wr: f64) -> IntervalDiskMetrics {
+        IntervalDiskMetrics { ts, kb_sec, qlen, await_wr, wps: 10.0, ..Default::default() }
+    }
+
+    #[test]
+    fn sustained_runs_tolerate_one_gap() {
+        // await_wr over 20 ms for 4 intervals with one dip, then a lone spike (too short)
+        let series: Vec<_> = [5.0, 30.0, 150.0, 10.0, 40.0, 5.0, 5.0, 90.0, 5.0]
+            .into_iter().enumerate().map(|(i, a)| disk(i as u64 * 10, 100.0, 1.0, a)).collect();
+        let found = find(&[("sda", &series)], &[], &[], &[]);
+        assert_eq!(found.len(), 1);
+        let f = &found[0];
+        assert_eq!((f.severity, f.start, f.end, f.subject.as_str(), f.rule), (Severity::Critical, 0, 40, "sda", "high_await_wr"));
+        assert!(f.detail.starts_with("write await up to 150 ms, mean 73.3 ms over 3 interval(s)"), "{}", f.detail);
+    }
+
+    #[test]
+    fn collapse_memory_and_swap() {
+        // Steady 50 MB/s, then almost nothing while requests stay queued
+        let series: Vec<_> = [50000.0, 50000.0, 50000.0, 50000.0, 50000.0, 10.0, 0.0, 0.0, 50000.0]
+            .into_iter().enumerate().map(|(i, kb)| disk(i as u64, kb, if kb < 100.0 { 4.0 } else { 0.5 }, 1.0)).collect();
+        let mem: Vec<MemMetrics> = [(50.0, 0.0), (8.0, 0.0), (4.0, 2048.0), (9.0, 4096.0), (40.0, 4096.0)]
+            .into_iter().enumerate()
+            .map(|(i, (avail, swap))| MemMetrics { ts: i as u64 * 10, avail_percent: avail, swap_used_kb: swap, ..Default::default() })
+            .collect();
+        let found = find(&[("sdb", &series)], &[], &mem, &[]);
+        let rules: Vec<_> = found.iter().map(|f| (f.rule, f.severity, f.start, f.end)).collect();
+        assert_eq!(rules, [
+            ("memory_exhaustion", Severity::Critical, 0, 30),
+            ("throughput_collapse", Severity::Critical, 4, 7),
+            ("swap_activity", Severity::Warning, 10, 30),
+        ]);
+        assert!(found[1].detail.contains("from 50000 KB/s to 0.0 KB/s for 3 interval(s), with I/O still queued"));
+    }
+
+    #[test]
+    fn one_interval_finding_lasts_one_interval() {
+        let cpu: Vec<CpuMetrics> = [1.0, 60.0, 1.0]
+            .into_iter().enumerate().map(|(i, iowait)| CpuMetrics { ts: 100 + i as u64 * 30, iowait, ..Default::default() })
+            .collect();
+        let found = find(&[], &cpu, &[], &[]);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].rule, found[0].start, found[0].end), ("iowait_spike", 100, 130));
+        assert_eq!(duration(&found[0]), "0:00:30");
+        let ctx = TimeCtx::new(serverstats_format::timeutil::DisplayTz::parse("UTC").unwrap());
+        assert!(to_text(&found, &ctx, "x.dat").contains("1970-01-01 00:01:40 1970-01-01 00:02:10  0:00:30"));
+    }
+}
@@ -0,0 +1,535 @@
+/*!
+ * Fleet Analysis Module
+ * ---------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * `-a <directory>` analyzes every `.dat` capture in a directory as one fleet,
+ * e.g. all nodes of an OpenShift or RAC cluster, instead of one `-a` per node:
+ *
+ *   - per-host summary: CPU busy, iowait p95, MemAvailable, total disk IO/s,
+ *     the worst device await and the findings, linked to the host's own `-a`
+ *     dashboard (written to fleet_<dir>/<host>/)
+ *   - cross-host top-N tables: worst await (read or write await p95 per
+ *     device), busiest devices (mean IO/s) and highest iowait (p95 per host)
+ *   - aligned timelines: the same metric of every host on one chart and one
+ *     time axis, to see which node went first
+ *
+ * The host name comes from the capture file name (serverstats_grab-<host>-<date>.dat).
+ * Hosts may record different timezones: the shared tables and timelines use one
+ * fleet zone (UTC unless --tz/--utc), each host's Start/End its own zone.
+ * Each capture is read once: its own `-a` run (analyze::analyze_into) writes the
+ * host dashboard and returns the series and distributions the fleet tables and
+ * timelines use. Disk totals count whole disks only, as in -pA.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use serverstats_format::output::json_str;
+use serverstats_format::timeutil::{DisplayTz, TimeCtx, TimeWindow};
+
+use crate::analyze::{self, IntervalDiskMetrics};
+use crate::compare::Capture;
+use crate::devselect::{is_partition, DevSelect, IfaceSelect};
+use crate::findings::Severity;
+use crate::percentile::Distribution;
+use crate::report::html_escape;
+use crate::stream;
+use crate::vmstat::is_stacked;
+
+/// Rows in each top-N table.
+const FLEET_TOP: usize = 20;
+
+/// A timeline: key, label and one host's series (ts, value).
+type Timeline = (&'static str, &'static str, fn(&Capture) -> Vec<(u64, f64)>);
+
+const TIMELINES: &[Timeline] = &[
+    ("cpu_busy", "CPU busy %", |c| c.cpu.chart.iter().map(|m| (m.ts, 100.0 - m.idle)).collect()),
+    ("iowait", "IOWait %", |c| c.cpu.chart.iter().map(|m| (m.ts, m.iowait)).collect()),
+    ("mem_avail", "MemAvailable %", |c| c.mem.chart.iter().map(|m| (m.ts, m.avail_percent)).collect()),
+    ("disk_iops", "Disk IO/s (whole disks)", |c| disk_total(c, |m| m.io_sec)),
+    ("disk_kbs", "Disk KB/s (whole disks)", |c| disk_total(c, |m| m.kb_sec)),
+    ("max_await", "Worst await (ms, any device)", disk_worst_await),
+    ("net_bytes", "Network rx+tx bytes/s (all but lo)", |c| {
+        let mut t: BTreeMap<u64, f64> = BTreeMap::new();
+        for s in c.nets.iter().filter(|(i, _)| i.as_str() != "lo").map(|(_, s)| s) {
+            for m in &s.chart {
+                *t.entry(m.ts).or_default() += m.rx_bytes + m.tx_bytes;
+            }
+        }
+        t.into_iter().collect()
+    }),
+];
+
+/// Whole disks only: partitions and stacked devices would count the same I/O twice.
+fn is_whole_disk(dev: &str) -> bool {
+    !is_partition(dev) && !is_stacked(dev)
+}
+
+/// Sum of one metric over the whole disks at each chart point.
+fn disk_total(c: &Capture, f: fn(&IntervalDiskMetrics) -> f64) -> Vec<(u64, f64)> {
+    let mut t: BTreeMap<u64, f64> = BTreeMap::new();
+    for (_, s) in c.disks.iter().filter(|(d, _)| is_whole_disk(d)) {
+        for m in &s.chart {
+            *t.entry(m.ts).or_default() += f(m);
+        }
+    }
+    t.into_iter().collect()
+}
+
+/// Highest read or write await of any device, dm and md included, at each chart point.
+fn disk_worst_await(c: &Capture) -> Vec<(u64, f64)> {
+    let mut t: BTreeMap<u64, f64> = BTreeMap::new();
+    for s in c.disks.values() {
+        for m in &s.chart {
+            let v = t.entry(m.ts).or_default();
+            *v = v.max(m.await_rd).max(m.await_wr);
+        }
+    }
+    t.into_iter().collect()
+}
+
+/// Host name from a capture file name: serverstats_grab-<host>-<YYYY-MM-DD_HH-MM-SS>.dat.
+fn host_of(path: &str) -> String {
+    let stem = Path::new(path).file_stem().and_then(|s| s.to_str()).unwrap_or(path);
+    let name = stem.strip_prefix("serverstats_grab-").unwrap_or(stem);
+    let is_stamp = |s: &str| s.len() == 19 && s.chars().enumerate().all(|(i, c)| match i {
+        4 | 7 | 13 | 16 => c == '-',
+        10 => c == '_',
+        _ => c.is_ascii_digit(),
+    });
+    match name.len().checked_sub(20) {
+        Some(i) if i > 0 && name.is_char_boundary(i) && name[i..].starts_with('-') && is_stamp(&name[i + 1..]) => name[..i].to_string(),
+        _ => name.to_string(),
+    }
+}
+
+/// One capture of the fleet.
+struct Host {
+    label: String,
+    cap: Capture,
+}
+
+// ==================== Tables ====================
+
+enum Val {
+    Text(String),
+    Num(f64),
+    /// Host label, linked to its dashboard in the HTML
+    Host(String),
+}
+
+/// A fleet table, written as text, HTML and JSON.
+struct Tab {
+    key: &'static str,
+    title: &'static str,
+    /// (JSON key, heading)
+    cols: &'static [(&'static str, &'static str)],
+    rows: Vec<Vec<Val>>,
+}
+
+impl Tab {
+    fn cell(v: &Val) -> String {
+        match v {
+            Val::Text(s) | Val::Host(s) => s.clone(),
+            Val::Num(n) if n.is_finite() => format!("{:.2}", n),
+            Val::Num(_) => "-".to_string(),
+        }
+    }
+
+    fn text(&self) -> String {
+        let cells: Vec<Vec<String>> = self.rows.iter().map(|r| r.iter().map(Self::cell).collect()).collect();
+        let widths: Vec<usize> = self.cols.iter().enumerate()
+            .map(|(i, (_, h))| cells.iter().map(|r| r[i].len()).chain([h.len()]).max().unwrap_or(0))
+            .collect();
+        let line = |vals: Vec<&str>| {
+            let parts: Vec<String> = vals.iter().zip(&widths).enumerate()
+                .map(|(i, (v, w))| if i == 0 || self.rows.first().is_some_and(|r| !matches!(r[i], Val::Num(_))) {
+                    format!("{:<w$}", v, w = w)
+                } else {
+                    format!("{:>w$}", v, w = w)
+                })
+                .collect();
+            parts.join("  ").trim_end().to_string()
+        };
+        let mut s = format!("{}\n", self.title);
+        let _ = writeln!(s, "{}", line(self.cols.iter().map(|(_, h)| *h).collect()));
+        let _ = writeln!(s, "{}", "-".repeat(widths.iter().sum::<usize>() + 2 * widths.len().saturating_sub(1)));
+        for r in &cells {
+            let _ = writeln!(s, "{}", line(r.iter().map(String::as_str).collect()));
+        }
+        if cells.is_empty() {
+            s.push_str("None.\n");
+        }
+        s
+    }
+
+    fn html(&self) -> String {
+        let mut s = format!("<h2>{}</h2>\n<table border=\"1\" cellpadding=\"4\"><tr>", self.title);
+        for (_, h) in self.cols {
+            let _ = write!(s, "<th>{}</th>", h);
+        }
+        s.push_str("</tr>\n");
+        for r in &self.rows {
+            s.push_str("<tr>");
+            for v in r {
+                match v {
+                    Val::Host(h) => {
+                        let _ = write!(s, "<td><a href=\"{}/index.html\">{}</a></td>", html_escape(h), html_escape(h));
+                    }
+                    v => {
+                        let _ = write!(s, "<td>{}</td>", html_escape(&Self::cell(v)));
+                    }
+                }
+            }
+            s.push_str("</tr>\n");
+        }
+        s.push_str("</table>\n");
+        if self.rows.is_empty() {
+            s.push_str("<p>None.</p>\n");
+        }
+        s
+    }
+
+    fn json(&self) -> String {
+        let rows: Vec<String> = self.rows.iter().map(|r| {
+            let fields: Vec<String> = self.cols.iter().zip(r).map(|((k, _), v)| {
+                let val = match v {
+                    Val::Text(s) | Val::Host(s) => json_str(s),
+                    Val::Num(n) if n.is_finite() => format!("{:.2}", n),
+                    Val::Num(_) => "null".to_string(),
+                };
+                format!("{}:{}", json_str(k), val)
+            }).collect();
+            format!("{{{}}}", fields.join(","))
+        }).collect();
+        format!("{}:[\n{}\n]", json_str(self.key), rows.join(",\n"))
+    }
+}
+
+fn mean(d: Option<&Distribution>) -> f64 {
+    d.map_or(f64::NAN, |d| d.mean)
+}
+
+fn p95(d: Option<&Distribution>) -> f64 {
+    d.map_or(f64::NAN, |d| d.at("p95"))
+}
+
+/// Worst of read and write await p95 of each device: (device, metric, distribution).
+fn device_awaits(c: &Capture) -> Vec<(&str, &'static str, &Distribution)> {
+    let mut out = Vec::new();
+    for (dev, s) in &c.disks {
+        let worst = ["await_rd", "await_wr"].into_iter()
+            .filter_map(|k| s.dist(k).map(|d| (k, d)))
+            .max_by(|a, b| a.1.at("p95").total_cmp(&b.1.at("p95")));
+        if let Some((k, d)) = worst {
+            out.push((dev.as_str(), k, d));
+        }
+    }
+    out
+}
+
+fn hosts_table(hosts: &[Host]) -> Tab {
+    let rows = hosts.iter().map(|h| {
+        let c = &h.cap;
+        let worst = device_awaits(c).into_iter().max_by(|a, b| a.2.at("p95").total_cmp(&b.2.at("p95")));
+        let iops: f64 = c.disks.iter().filter(|(d, _)| is_whole_disk(d)).map(|(_, s)| mean(s.dist("io_sec"))).filter(|v| v.is_finite()).sum();
+        let n = |sev| c.findings.iter().filter(|f| f.severity == sev).count();
+        vec![
+            Val::Host(h.label.clone()),
+            Val::Text(c.ctx.format(c.start, "%Y-%m-%d %H:%M")),
+            Val::Text(c.ctx.format(c.end, "%Y-%m-%d %H:%M")),
+            Val::Text(c.ctx.label()),
+            Val::Num(100.0 - mean(c.cpu.dist("idle"))),
+            Val::Num(p95(c.cpu.dist("iowait"))),
+            Val::Num(mean(c.mem.dist("avail"))),
+            Val::Num(iops),
+            Val::Text(worst.map_or("-".to_string(), |w| format!("{} {}", w.0, w.1))),
+            Val::Num(worst.map_or(f64::NAN, |w| w.2.at("p95"))),
+            Val::Text(format!("{}/{}", n(Severity::Critical), n(Severity::Warning))),
+        ]
+    }).collect();
+    Tab {
+        key: "hosts",
+        title: "Hosts",
+        cols: &[("host", "Host"), ("start", "Start"), ("end", "End"), ("timezone", "Timezone"), ("cpu_busy_mean", "CPU busy %"),
+                ("iowait_p95", "IOWait p95"), ("mem_avail_mean", "MemAvail %"), ("disk_iops_mean", "Disk IO/s"),
+                ("worst_await_device", "Worst await"), ("worst_await_p95", "Await p95 (ms)"), ("findings", "Findings crit/warn")],
+        rows,
+    }
+}
+
+fn top_await(hosts: &[Host]) -> Tab {
+    let mut all: Vec<(&str, &str, &str, &Distribution)> = hosts.iter()
+        .flat_map(|h| device_awaits(&h.cap).into_iter().map(|(dev, k, d)| (h.label.as_str(), dev, k, d)))
+        .filter(|r| r.3.max > 0.0)
+        .collect();
+    all.sort_by(|a, b| b.3.at("p95").total_cmp(&a.3.at("p95")));
+    Tab {
+        key: "top_await",
+        title: "Worst await (device read or write await, by p95)",
+        cols: &[("host", "Host"), ("device", "Device"), ("metric", "Metric"), ("mean", "Mean"), ("p95", "P95"), ("p99", "P99"), ("max", "Max")],
+        rows: all.into_iter().take(FLEET_TOP).map(|(h, dev, k, d)| vec![
+            Val::Host(h.to_string()), Val::Text(dev.to_string()), Val::Text(k.to_string()),
+            Val::Num(d.mean), Val::Num(d.at("p95")), Val::Num(d.at("p99")), Val::Num(d.max),
+        ]).collect(),
+    }
+}
+
+fn top_busy(hosts: &[Host]) -> Tab {
+    let mut all: Vec<(&str, &str, f64, f64, f64)> = hosts.iter()
+        .flat_map(|h| h.cap.disks.iter().map(move |(dev, s)| {
+            (h.label.as_str(), dev.as_str(), mean(s.dist("io_sec")), p95(s.dist("io_sec")), mean(s.dist("kb_sec")))
+        }))
+        .filter(|r| r.2 > 0.0)
+        .collect();
+    all.sort_by(|a, b| b.2.total_cmp(&a.2));
+    Tab {
+        key: "top_busy",
+        title: "Busiest devices (by mean IO/s)",
+        cols: &[("host", "Host"), ("device", "Device"), ("io_s_mean", "IO/s mean"), ("io_s_p95", "IO/s p95"), ("kb_s_mean", "KB/s mean")],
+        rows: all.into_iter().take(FLEET_TOP).map(|(h, dev, io, io95, kb)| vec![
+            Val::Host(h.to_string()), Val::Text(dev.to_string()), Val::Num(io), Val::Num(io95), Val::Num(kb),
+        ]).collect(),
+    }
+}
+
+fn top_iowait(hosts: &[Host]) -> Tab {
+    let mut all: Vec<(&str, &Distribution)> = hosts.iter()
+        .filter_map(|h| h.cap.cpu.dist("iowait").map(|d| (h.label.as_str(), d)))
+        .collect();
+    all.sort_by(|a, b| b.1.at("p95").total_cmp(&a.1.at("p95")));
+    Tab {
+        key: "top_iowait",
+        title: "Highest iowait (by p95)",
+        cols: &[("host", "Host"), ("mean", "Mean %"), ("p95", "P95 %"), ("p99", "P99 %"), ("max", "Max %")],
+        rows: all.into_iter().take(FLEET_TOP).map(|(h, d)| vec![
+            Val::Host(h.to_string()), Val::Num(d.mean), Val::Num(d.at("p95")), Val::Num(d.at("p99")), Val::Num(d.max),
+        ]).collect(),
+    }
+}
+
+// ==================== Entrypoint ====================
+
+/// Analyzes every `.dat` capture in `dir` as one fleet, into fleet_<dir>/.
+/// `display` is the zone of the fleet-wide tables and timelines; each host's
+/// Start/End and dashboard use the time context `resolve` gives with its window.
+pub(crate) fn analyze_fleet(dir: &str, display: DisplayTz, resolve: &dyn Fn(&str) -> io::Result<(TimeCtx, TimeWindow)>,
+                            sel: &DevSelect, ifaces: &IfaceSelect, bucket: Option<u64>) -> io::Result<()> {
+    let mut files: Vec<String> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|x| x == "dat"))
+        .filter_map(|p| p.to_str().map(str::to_string))
+        .collect();
+    files.sort();
+    if files.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{}: no .dat captures", dir)));
+    }
+    let name = Path::new(dir.trim_end_matches('/')).file_name().and_then(|s| s.to_str()).unwrap_or("captures");
+    let output_dir = format!("fleet_{}", name);
+    println!("Fleet analysis of {} captures in {}\nOutput dir: {}", files.len(), dir, output_dir);
+    fs::create_dir_all(&output_dir)?;
+    let started = Instant::now();
+
+    // Same chart bucket for every host, so the timelines line up
+    let mut windows = Vec::new();
+    for f in &files {
+        windows.push(resolve(f)?);
+    }
+    let chart_bucket = match bucket {
+        Some(b) => Some(b),
+        None => {
+            let mut b = None;
+            for (f, (_, w)) in files.iter().zip(&windows) {
+                b = b.max(stream::chart_bucket(f, w, None)?);
+            }
+            b
+        }
+    };
+
+    // Host labels: the host name, or the capture name when a host has several captures
+    let mut count: HashMap<String, usize> = HashMap::new();
+    for f in &files {
+        *count.entry(host_of(f)).or_default() += 1;
+    }
+    let mut hosts: Vec<Host> = Vec::new();
+    for (f, (ctx, window)) in files.iter().zip(windows) {
+        let host = host_of(f);
+        let label = if count[&host] > 1 {
+            Path::new(f).file_stem().and_then(|s| s.to_str()).unwrap_or(&host).to_string()
+        } else {
+            host
+        };
+        println!("\n===== {} ({}) =====", label, f);
+        match analyze::analyze_into(f, &format!("{}/{}", output_dir, label), ctx, &window, sel, ifaces, chart_bucket) {
+            Ok(Some(cap)) => hosts.push(Host { label, cap }),
+            Ok(None) => eprintln!("WARN: skipping {}: no samples in the time window", f),
+            Err(e) => eprintln!("WARN: skipping {}: {}", f, e),
+        }
+    }
+    if hosts.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}: no capture has samples in the time window", dir)));
+    }
+
+    // ========== Tables ==========
+
+    let tabs = [hosts_table(&hosts), top_await(&hosts), top_busy(&hosts), top_iowait(&hosts)];
+    let mut ctx = TimeCtx::new(display);
+    ctx.start_ts = hosts.iter().map(|h| h.cap.start).min();
+    let ctx = &ctx;
+    let mut txt = format!("fleet.txt\nFleet of {} hosts from {} (times {})\n", hosts.len(), dir, ctx.label());
+    for t in &tabs {
+        txt.push('\n');
+        txt.push_str(&t.text());
+    }
+    fs::write(format!("{}/fleet.txt", output_dir), txt)?;
+    let json: Vec<String> = tabs.iter().map(Tab::json).collect();
+    fs::write(format!("{}/fleet.json", output_dir), format!("{{{}}}\n", json.join(",\n")))?;
+
+    // ========== Aligned timelines ==========
+
+    println!("\nWriting {} fleet timelines...", TIMELINES.len());
+    let mut timelines = String::new();
+    for (key, label, series) in TIMELINES {
+        let lines: Vec<(&str, Vec<(u64, f64)>)> = hosts.iter().map(|h| (h.label.as_str(), series(&h.cap))).collect();
+        if lines.iter().all(|(_, s)| s.is_empty()) { continue; }
+        let name = format!("timeline_{}", key);
+        let svg = format!("{}/{}.svg", output_dir, name);
+        draw_timeline(SVGBackend::new(&svg, (1100, 360)).into_drawing_area(), ctx, label, &lines);
+        let png = format!("{}/{}.png", output_dir, name);
+        draw_timeline(BitMapBackend::new(&png, (1100, 360)).into_drawing_area(), ctx, label, &lines);
+        let _ = writeln!(timelines, "<img class=\"graph\" src=\"{name}.svg\" onerror=\"this.onerror=null; this.src='{name}.png';\">");
+    }
+
+    let top: String = tabs[1..].iter().map(Tab::html).collect();
+    let page = PAGE
+        .replace("{{TITLE}}", &html_escape(&format!("Fleet: {} hosts from {}", hosts.len(), dir)))
+        .replace("{{TZ}}", &html_escape(&ctx.label()))
+        .replace("{{HOSTS}}", &tabs[0].html())
+        .replace("{{TOP}}", &top)
+        .replace("{{TIMELINES}}", &timelines);
+    fs::write(format!("{}/index.html", output_dir), page)?;
+    println!("Timing: total {:.1}s", started.elapsed().as_secs_f64());
+    println!("Fleet analysis complete. See {}/ for results.", output_dir);
+    Ok(())
+}
+
+/// Every host's series on one chart and one time axis.
+fn draw_timeline<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, ctx: &TimeCtx, label: &str, lines: &[(&str, Vec<(u64, f64)>)]) {
+    let points = || lines.iter().flat_map(|(_, s)| s.iter());
+    let x_min = points().map(|p| p.0).min().unwrap_or(0);
+    let x_max = points().map(|p| p.0).max().unwrap_or(0).max(x_min + 1);
+    let y_max = points().map(|p| p.1).filter(|v| v.is_finite()).fold(1.0, f64::max) * 1.05;
+    let fmt = if x_max - x_min > 86400 { "%m-%d %H:%M" } else { "%H:%M:%S" };
+
+    root.fill(&WHITE).unwrap();
+    let mut chart = ChartBuilder::on(&root)
+        .caption(format!("{} - all hosts", label), ("sans-serif", 20))
+        .margin(12)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .build_cartesian_2d(x_min..x_max, 0.0..y_max)
+        .unwrap();
+    chart
+        .configure_mesh()
+        .x_labels(8)
+        .x_label_formatter(&|ts| ctx.format(*ts, fmt))
+        .x_desc(format!("Time ({})", ctx.label()))
+        .y_desc(label)
+        .draw()
+        .unwrap();
+    for (i, (host, series)) in lines.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart
+            .draw_series(LineSeries::new(series.iter().filter(|p| p.1.is_finite()).copied(), color))
+            .unwrap()
+            .label(*host)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 25, y)], color));
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperRight)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .unwrap();
+    root.present().unwrap();
+}
+
+const PAGE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="UTF-8">
+  <title>{{TITLE}}</title>
+  <style>
+    body { font-family: sans-serif; margin: 2em; }
+    img.graph { display: block; margin: 1em 0; max-width: 98vw; border: 1px solid #ccc; }
+    .section-tab { cursor:pointer; display:inline-block; margin-right:1em; padding:0.5em 1em; border-radius:1em; background:#ddd; }
+    .section-tab.active { background:#333; color:#fff; }
+    .section-content { display:none; }
+    .section-content.active { display:block; }
+    p.scope { color: #555; }
+  </style>
+</head>
+<body>
+  <h1>{{TITLE}}</h1>
+  <p class="scope">Times {{TZ}}. Host names link to each host's own dashboard.</p>
+  <div id="tabs">
+    <span class="section-tab active" onclick="showSection('hosts')">Hosts</span>
+    <span class="section-tab" onclick="showSection('top')">Top N</span>
+    <span class="section-tab" onclick="showSection('timelines')">Timelines</span>
+  </div>
+  <div id="hosts" class="section-content active">
+{{HOSTS}}
+  </div>
+  <div id="top" class="section-content">
+{{TOP}}
+  </div>
+  <div id="timelines" class="section-content"><h2>Aligned timelines</h2>
+{{TIMELINES}}
+  </div>
+<script>
+function showSection(id) {
+  document.querySelectorAll('.section-content').forEach(d => d.classList.toggle('active', d.id === id));
+  document.querySelectorAll('.section-tab').forEach(t => t.classList.toggle('active', t.getAttribute('onclick').includes("'" + id + "'")));
+}
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_names_from_capture_files() {
+        assert_eq!(host_of("/data/serverstats_grab-rac1.example.com-2025-03-14_10-00-00.dat"), "rac1.example.com");
+        assert_eq!(host_of("serverstats_grab-ocp-worker-2-2025-03-14_10-00-05.dat"), "ocp-worker-2");
+        // Renamed captures keep their name
+        assert_eq!(host_of("node3.dat"), "node3");
+        assert_eq!(host_of("serverstats_grab-node4-before.dat"), "node4-before");
+    }
+
+    #[test]
+    fn text_table_aligns_numbers() {
+        let t = Tab {
+            key: "t",
+            title: "T",
+            cols: &[("host", "Host"), ("v", "Value")],
+            rows: vec![
+                vec![Val::Host("a".to_string()), Val::Num(1.5)],
+                vec![Val::Host("longer".to_string()), Val::Num(f64::NAN)],
+            ],
+        };
+        assert_eq!(t.text(), "T\nHost    Value\n-------------\na        1.50\nlonger      -\n");
+        assert_eq!(t.json(), "\"t\":[\n{\"host\":\"a\",\"v\":1.50},\n{\"host\":\"longer\",\"v\":null}\n]");
+    }
+}
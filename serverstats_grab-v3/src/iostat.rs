@@ -0,0 +1,286 @@
+/*!
+ * iostat -x Playback Module
+ * -------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Replays DISK and CPU records in the sysstat `iostat -x -t` layout (sysstat 12.x
+ * column set, with the discard and flush columns), so existing iostat parsing
+ * scripts work on serverstats captures. Each interval is printed as one block:
+ * timestamp, avg-cpu, then the Device table in capture (/proc/diskstats) order.
+ * Like `iostat -y`, the since-boot first report is skipped.
+ *
+ * Columns follow sysstat's definitions: %util is busy time over the interval,
+ * aqu-sz is weighted I/O time over the interval, rareq-sz/wareq-sz are in kB.
+ * Kernels without discard or flush counters show 0.00 there, as iostat does.
+ * The banner line takes the kernel release, machine and CPU count from the
+ * capture's HOST record.
+ */
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serverstats_format::{CpuStat, DiskDelta, DiskStat, Record};
+use serverstats_format::timeutil::{TimeCtx, TimeWindow};
+
+
+/// Throughput unit: `-x`/`-xk` print kB/s, `-xm` prints MB/s.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    Kb,
+    Mb,
+}
+
+impl Unit {
+    fn label(self) -> &'static str {
+        match self {
+            Unit::Kb => "kB",
+            Unit::Mb => "MB",
+        }
+    }
+
+    fn sectors_per_unit(self) -> f64 {
+        match self {
+            Unit::Kb => 2.0,
+            Unit::Mb => 2048.0,
+        }
+    }
+}
+
+/// Minimum Device column width used by sysstat.
+const DEV_WIDTH: usize = 13;
+
+fn device_header(width: usize, unit: Unit) -> String {
+    let u = unit.label();
+    format!(
+        "{:<width$} {:>7} {:>9} {:>8} {:>6} {:>7} {:>8} {:>7} {:>9} {:>8} {:>6} {:>7} {:>8} \
+         {:>7} {:>9} {:>8} {:>6} {:>7} {:>8} {:>7} {:>7} {:>7} {:>6}",
+        "Device",
+        "r/s", format!("r{}/s", u), "rrqm/s", "%rrqm", "r_await", "rareq-sz",
+        "w/s", format!("w{}/s", u), "wrqm/s", "%wrqm", "w_await", "wareq-sz",
+        "d/s", format!("d{}/s", u), "drqm/s", "%drqm", "d_await", "dareq-sz",
+        "f/s", "f_await", "aqu-sz", "%util",
+    )
+}
+
+/// Merged requests as % of all requests of that type.
+fn pct_merged(merged: u64, ios: u64) -> f64 {
+    if merged + ios > 0 { merged as f64 * 100.0 / (merged + ios) as f64 } else { 0.0 }
+}
+
+/// Average request size in kB.
+fn req_kb(sectors: u64, ios: u64) -> f64 {
+    if ios > 0 { sectors as f64 / ios as f64 / 2.0 } else { 0.0 }
+}
+
+fn device_row(name: &str, width: usize, d: &DiskDelta, unit: Unit) -> String {
+    let per_unit = |sectors: u64| d.rate(sectors) / unit.sectors_per_unit();
+    let util = (d.rate(d.io_time_ms) / 10.0).min(100.0);
+    format!(
+        "{:<width$} {:>7.2} {:>9.2} {:>8.2} {:>6.2} {:>7.2} {:>8.2} {:>7.2} {:>9.2} {:>8.2} {:>6.2} {:>7.2} {:>8.2} \
+         {:>7.2} {:>9.2} {:>8.2} {:>6.2} {:>7.2} {:>8.2} {:>7.2} {:>7.2} {:>7.2} {:>6.2}",
+        name,
+        d.r_s(), per_unit(d.sectors_read), d.rate(d.reads_merged), pct_merged(d.reads_merged, d.reads),
+        d.await_rd(), req_kb(d.sectors_read, d.reads),
+        d.w_s(), per_unit(d.sectors_written), d.rate(d.writes_merged), pct_merged(d.writes_merged, d.writes),
+        d.await_wr(), req_kb(d.sectors_written, d.writes),
+        d.discards_s(), per_unit(d.sectors_discarded), d.discards_merged_s(), pct_merged(d.discards_merged, d.discards),
+        d.await_discard(), req_kb(d.sectors_discarded, d.discards),
+        d.flush_s().unwrap_or(0.0), d.await_flush().unwrap_or(0.0),
+        d.avg_queue_depth(), util,
+    )
+}
+
+/// avg-cpu values as sysstat computes them: guest time is already in user,
+/// %system includes irq and softirq.
+fn avg_cpu(prev: &CpuStat, cur: &CpuStat) -> Option<String> {
+    let d = |c: u64, p: u64| c.saturating_sub(p);
+    let user = d(cur.user, prev.user);
+    let nice = d(cur.nice, prev.nice);
+    let system = d(cur.system, prev.system) + d(cur.irq, prev.irq) + d(cur.softirq, prev.softirq);
+    let idle = d(cur.idle, prev.idle);
+    let iowait = d(cur.iowait, prev.iowait);
+    let steal = d(cur.steal, prev.steal);
+    let total = user + nice + system + idle + iowait + steal;
+    if total == 0 { return None; }
+    let pct = |v: u64| v as f64 * 100.0 / total as f64;
+    Some(format!(
+        "       {:>8.2}{:>8.2}{:>8.2}{:>8.2}{:>8.2}{:>8.2}",
+        pct(user.saturating_sub(d(cur.guest, prev.guest))), pct(nice), pct(system), pct(iowait), pct(steal), pct(idle)
+    ))
+}
+
+/// Host name from a `serverstats_grab-<host>-<date>_<time>.dat` capture name.
+fn capture_host(file_path: &str) -> String {
+    let stem = Path::new(file_path).file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    // "-YYYY-MM-DD_HH-MM-SS" is the last 20 characters
+    stem.strip_prefix("serverstats_grab-")
+        .and_then(|rest| rest.len().checked_sub(20).map(|n| rest.split_at(n)))
+        .filter(|(host, date)| !host.is_empty() && date.starts_with('-'))
+        .map(|(host, _)| host)
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// The capture host as its HOST record describes it.
+struct HostInfo {
+    host: String,
+    release: String,
+    arch: String,
+    cpus: u32,
+}
+
+/// First line of iostat output: `Linux <release> (<host>) \t<date> \t_<arch>_\t(<N> CPU)`.
+/// Captures without a HOST record take the host from the file name and print
+/// "unknown" and 0 CPU, so the line keeps its shape for parsers.
+fn banner(host: Option<&HostInfo>, file_path: &str, date: &str) -> String {
+    match host {
+        Some(h) => format!("Linux {} ({}) \t{} \t_{}_\t({} CPU)", h.release, h.host, date, h.arch, h.cpus),
+        None => format!("Linux unknown ({}) \t{} \t_unknown_\t(0 CPU)", capture_host(file_path), date),
+    }
+}
+
+/// One interval's worth of records, printed when the timestamp changes.
+#[derive(Default)]
+struct Block {
+    ts: u64,
+    disks: Vec<(String, DiskDelta)>,
+    cpu: Option<String>,
+}
+
+impl Block {
+    fn print(&self, ctx: &TimeCtx, unit: Unit) {
+        if self.disks.is_empty() { return; }
+        println!("{}", ctx.format(self.ts, "%m/%d/%Y %I:%M:%S %p"));
+        if let Some(cpu) = &self.cpu {
+            println!("avg-cpu:  %user   %nice %system %iowait  %steal   %idle");
+            println!("{}", cpu);
+            println!();
+        }
+        let width = self.disks.iter().map(|(n, _)| n.len()).max().unwrap_or(0).max(DEV_WIDTH);
+        println!("{}", device_header(width, unit));
+        for (name, d) in &self.disks {
+            println!("{}", device_row(name, width, d, unit));
+        }
+        println!();
+    }
+}
+
+/// Prints `block`, after the iostat banner if it is the first. Returns true if anything was printed.
+fn emit(block: &Block, host: Option<&HostInfo>, file_path: &str, ctx: &TimeCtx, unit: Unit, printed_banner: &mut bool) -> bool {
+    if block.disks.is_empty() { return false; }
+    if !*printed_banner {
+        println!("{}", banner(host, file_path, &ctx.format(block.ts, "%m/%d/%Y")));
+        println!();
+        *printed_banner = true;
+    }
+    block.print(ctx, unit);
+    true
+}
+
+/// Playback DISK (and CPU) records in `iostat -x` layout, one block per interval.
+pub fn playback_iostat(file_path: &str, ctx: &mut TimeCtx, window: &TimeWindow, unit: Unit, follow: bool) -> std::io::Result<()> {
+    let mut prev_disk: HashMap<String, (u64, DiskStat)> = HashMap::new();
+    let mut prev_cpu: Option<CpuStat> = None;
+    let mut block = Block::default();
+    let mut host: Option<HostInfo> = None;
+    let mut printed_banner = false;
+    let mut printed_any = false;
+
+    for rec in serverstats_format::follow::records(file_path, follow)? {
+        let rec = match rec {
+            Ok(rec) => rec,
+            // --follow: print the block read so far rather than wait for the next sample
+            Err(e) if serverstats_format::follow::caught_up(&e) => {
+                printed_any |= emit(&block, host.as_ref(), file_path, ctx, unit, &mut printed_banner);
+                block = Block { ts: block.ts, ..Default::default() };
+                continue;
+            }
+            Err(_) => continue,
+        };
+        if let Record::Tz { ts, offset, zone } = &rec {
+            ctx.note_host_tz(*ts, *offset, zone);
+            continue;
+        }
+        if let Record::Host { host: name, release, arch, cpus, .. } = rec {
+            host.get_or_insert(HostInfo { host: name, release, arch, cpus });
+            continue;
+        }
+        if !matches!(rec, Record::Disk { .. } | Record::Cpu { .. }) { continue; }
+        let ts = rec.ts();
+        if window.is_past(ts) { break; }
+        if ts != block.ts {
+            printed_any |= emit(&block, host.as_ref(), file_path, ctx, unit, &mut printed_banner);
+            block = Block { ts, ..Default::default() };
+        }
+        // Keep prev current outside the window so the first block in it is a true delta
+        let in_window = window.contains(ts);
+        match rec {
+            Record::Disk { ts, stat } => {
+                let key = format!("{}-{}-{}", stat.major, stat.minor, stat.name);
+                if in_window
+                    && let Some((last_ts, last)) = prev_disk.get(&key)
+                    && let Some(d) = DiskDelta::between(last, &stat, ts.saturating_sub(*last_ts))
+                {
+                    block.disks.push((stat.name.clone(), d));
+                }
+                prev_disk.insert(key, (ts, stat));
+            }
+            Record::Cpu { stat, .. } => {
+                if in_window && let Some(last) = &prev_cpu {
+                    block.cpu = avg_cpu(last, &stat);
+                }
+                prev_cpu = Some(stat);
+            }
+            _ => {}
+        }
+    }
+    printed_any |= emit(&block, host.as_ref(), file_path, ctx, unit, &mut printed_banner);
+    if !printed_any {
+        println!("No disk data found.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iostat_x_columns() {
+        // Header matches sysstat 12.x `iostat -x` byte for byte
+        assert_eq!(
+            device_header(DEV_WIDTH, Unit::Kb),
+            "Device            r/s     rkB/s   rrqm/s  %rrqm r_await rareq-sz     w/s     wkB/s   wrqm/s  %wrqm \
+             w_await wareq-sz     d/s     dkB/s   drqm/s  %drqm d_await dareq-sz     f/s f_await  aqu-sz  %util"
+        );
+        assert!(device_header(DEV_WIDTH, Unit::Mb).contains("     rMB/s"));
+
+        let a = DiskStat::from_line("8 0 sda 100 0 800 100 50 0 400 500 0 1000 2000 0 0 0 0 10 20").unwrap();
+        let b = DiskStat::from_line("8 0 sda 200 100 2800 300 150 0 1400 1500 2 2000 6000 0 0 0 0 20 60").unwrap();
+        let d = DiskDelta::between(&a, &b, 10).unwrap();
+        let row = device_row("sda", DEV_WIDTH, &d, Unit::Kb);
+        let cols: Vec<&str> = row.split_whitespace().collect();
+        // r/s rkB/s rrqm/s %rrqm r_await rareq-sz
+        assert_eq!(&cols[1..7], ["10.00", "100.00", "10.00", "50.00", "2.00", "10.00"]);
+        // f/s f_await aqu-sz %util
+        assert_eq!(&cols[19..], ["1.00", "4.00", "0.40", "10.00"]);
+        assert_eq!(row.len(), device_header(DEV_WIDTH, Unit::Kb).len());
+    }
+
+    #[test]
+    fn avg_cpu_and_host() {
+        let a = CpuStat { user: 100, idle: 100, ..Default::default() };
+        let b = CpuStat { user: 150, system: 20, irq: 5, idle: 125, guest: 10, ..Default::default() };
+        assert_eq!(avg_cpu(&a, &b).unwrap(), "          40.00    0.00   25.00    0.00    0.00   25.00");
+        assert_eq!(capture_host("/x/serverstats_grab-db01.lab-2025-03-14_13-15-00.dat"), "db01.lab");
+        assert_eq!(capture_host("capture.dat"), "unknown");
+    }
+
+    #[test]
+    fn banner_shape() {
+        let h = HostInfo { host: "db01".into(), release: "5.14.0-427.13.1.el9_4.x86_64".into(), arch: "x86_64".into(), cpus: 64 };
+        let path = "/x/serverstats_grab-db01.lab-2025-03-14_13-15-00.dat";
+        assert_eq!(banner(Some(&h), path, "03/14/2025"), "Linux 5.14.0-427.13.1.el9_4.x86_64 (db01) \t03/14/2025 \t_x86_64_\t(64 CPU)");
+        assert_eq!(banner(None, path, "03/14/2025"), "Linux unknown (db01.lab) \t03/14/2025 \t_unknown_\t(0 CPU)");
+    }
+}
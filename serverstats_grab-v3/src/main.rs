@@ -27,19 +27,31 @@
  *
  * FEATURES:
  *  - Collects `/proc/diskstats`, `/proc/stat`, and `/proc/meminfo` at user-defined intervals,
- *    writing a unified `.dat` capture file.
+ *    writing a unified `.dat` capture file. Flush counters are captured on kernel 5.5+.
+ *  - One binary for RHEL7 (14-field) and RHEL8+ (18/20-field) diskstats; the layout seen
+ *    is recorded as DISKLAYOUT records.
  *  - Playback modes for each metric with clear, human-readable output (disk IOPS, CPU%, Mem%).
  *  - Analysis mode generates per-device and system-level SVG/PNG graphs and a dynamic HTML dashboard
  *    for instant, browser-based review.
  *  - Handles sparse data, missing metrics, and idle periods gracefully.
+ *  - Captures extended NIC counters and link state (speed, mtu, operstate, carrier_changes).
+ *  - Captures md RAID and DRBD state so resync/rebuild windows show up next to member disk I/O.
+ *  - Captures FC HBA port state/error counters and iSCSI session state alongside path I/O.
+ *  - Free-space guard stops, pauses, or prunes old captures before the output filesystem fills.
  *  - Output directory is self-contained—just copy and open `index.html` in any browser.
  *
  * USAGE:
  *    serverstats_grab -g <interval_seconds>       # Gather mode (writes .dat capture)
  *    serverstats_grab -pD <capturefile>           # Playback DISK metrics
+ *    serverstats_grab -pX <capturefile>           # Playback DISK in iostat -x layout (-pXm for MB/s)
  *    serverstats_grab -pC <capturefile>           # Playback CPU metrics
  *    serverstats_grab -pM <capturefile>           # Playback MEM metrics
+ *    serverstats_grab -pR <capturefile>           # Playback md RAID / DRBD state
+ *    serverstats_grab -pT <capturefile>           # Playback FC/iSCSI events with path I/O
  *    serverstats_grab -a <capturefile>            # Analysis mode (graphs + dashboard)
+ *    serverstats_grab -a <directory>              # Fleet analysis of every capture in a directory
+ *    serverstats_grab -r <capturefile> [out.html] # Single-file interactive HTML report
+ *    serverstats_grab -c <before> <after>         # Before/after comparison with verdicts
  *
  * AUTHOR:
  *    Laurence Oberman <loberman@redhat.com>
@@ -47,158 +59,147 @@
  */
 
 mod analyze;
+mod bucket;
+mod compare;
+mod correlate;
+mod devselect;
+mod extract;
+mod findings;
+mod fleet;
+mod iostat;
 mod mpath;
+mod netext;
+mod percentile;
+mod raid;
+mod report;
+mod stream;
+mod sysroot;
+mod transport;
+mod tui;
+mod vmstat;
 
 // Increment as tool evolves
 const VERSION_NUMBER: &str = "3.0.0";
 
 use std::{
-    fs::{File, OpenOptions},
-    io::{BufRead, BufReader, Write, Read},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, IsTerminal},
     collections::HashMap,
     env,
+    path::Path,
     thread::sleep,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use chrono::{Local, TimeZone, Timelike};
+use chrono::Local;
 use hostname::get;
+use netext::NetExt;
+use bucket::Sink;
+use devselect::{DevSelect, IfaceSelect, SortKey};
+use serverstats_format::diskguard::{DiskGuard, SpaceAction, Verdict};
+use serverstats_format::follow;
+use serverstats_format::{CpuPercent, CpuStat, DiskDelta, DiskStat, MemPercent, MemStat, NetDelta, NetStat, Record, Writer};
+use serverstats_format::output::{Cell, Col, Format, OutputOpts};
+use serverstats_format::timeutil::{self, DisplayTz, TimeCtx, TimeWindow};
+use sysroot::SysRoot;
 
-/// Represents a single sample from /proc/diskstats for one block device.
-#[derive(Debug, Clone)]
-pub struct DiskStat {
-    /// Device major number (kernel driver family)
-    pub major: u32,
-    /// Device minor number (unique per device)
-    pub minor: u32,
-    /// Device name (e.g., sda, nvme0n1, dm-0)
-    pub name: String,
-    /// Reads completed successfully
-    pub reads: u64,
-    /// Reads merged
-    pub reads_merged: u64,
-    /// Sectors read
-    pub sectors_read: u64,
-    /// Time spent reading (ms)
-    pub read_time_ms: u64,
-    /// Writes completed
-    pub writes: u64,
-    /// Writes merged
-    pub writes_merged: u64,
-    /// Sectors written
-    pub sectors_written: u64,
-    /// Time spent writing (ms)
-    pub write_time_ms: u64,
-    /// I/Os currently in progress
-    pub io_in_progress: u64,
-    /// Time spent doing I/Os (ms)
-    pub io_time_ms: u64,
-    /// Weighted time spent doing I/Os (ms)
-    pub weighted_io_time_ms: u64,
-    pub discards: u64,
-    pub discards_merged: u64,
-    pub sectors_discarded: u64,
-    pub discard_time_ms: u64,
+/// `uname -m`: the machine the kernel runs on (x86_64, aarch64, ppc64le, s390x).
+fn uname_machine() -> Option<String> {
+    // SAFETY: uname fills the zeroed struct with NUL terminated strings
+    let mut u: libc::utsname = unsafe { std::mem::zeroed() };
+    if unsafe { libc::uname(&mut u) } != 0 { return None; }
+    Some(unsafe { std::ffi::CStr::from_ptr(u.machine.as_ptr()) }.to_string_lossy().into_owned())
 }
 
-impl DiskStat {
-    /// Parses a line from `/proc/diskstats` into a `DiskStat`.
-    fn from_line(line: &str) -> Option<Self> {
-        let cols: Vec<&str> = line.split_whitespace().collect();
-        if cols.len() < 18 { return None; }
-        Some(Self {
-            major: cols[0].parse().ok()?,
-            minor: cols[1].parse().ok()?,
-            name: cols[2].to_string(),
-            reads: cols[3].parse().ok()?,
-            reads_merged: cols[4].parse().ok()?,
-            sectors_read: cols[5].parse().ok()?,
-            read_time_ms: cols[6].parse().ok()?,
-            writes: cols[7].parse().ok()?,
-            writes_merged: cols[8].parse().ok()?,
-            sectors_written: cols[9].parse().ok()?,
-            write_time_ms: cols[10].parse().ok()?,
-            io_in_progress: cols[11].parse().ok()?,
-            io_time_ms: cols[12].parse().ok()?,
-            weighted_io_time_ms: cols[13].parse().ok()?,
-            discards: cols[14].parse().ok()?,
-            discards_merged: cols[15].parse().ok()?,
-            sectors_discarded: cols[16].parse().ok()?,
-            discard_time_ms: cols[17].parse().ok()?,
-        })
-    }
-    /// Parses a CSV field slice (from capture file) into a `DiskStat`.
-    fn from_csv_fields(fields: &[&str]) -> Option<Self> {
-        if fields.len() < 18 { return None; }
-        Some(Self {
-            major: fields[0].parse().ok()?,
-            minor: fields[1].parse().ok()?,
-            name: fields[2].to_string(),
-            reads: fields[3].parse().ok()?,
-            reads_merged: fields[4].parse().ok()?,
-            sectors_read: fields[5].parse().ok()?,
-            read_time_ms: fields[6].parse().ok()?,
-            writes: fields[7].parse().ok()?,
-            writes_merged: fields[8].parse().ok()?,
-            sectors_written: fields[9].parse().ok()?,
-            write_time_ms: fields[10].parse().ok()?,
-            io_in_progress: fields[11].parse().ok()?,
-            io_time_ms: fields[12].parse().ok()?,
-            weighted_io_time_ms: fields[13].parse().ok()?,
-            discards: fields[14].parse().ok()?,
-            discards_merged: fields[15].parse().ok()?,
-            sectors_discarded: fields[16].parse().ok()?,
-            discard_time_ms: fields[17].parse().ok()?,
-        })
+/// HOST record for the `-pX` banner: host name and kernel release from <root>/proc/sys/kernel,
+/// the machine (kernel.arch, or uname on kernels without it) and the cpuN lines of /proc/stat.
+fn host_record(root: &SysRoot, ts: u64) -> Record {
+    let kernel = |name: &str| std::fs::read_to_string(root.proc(&format!("sys/kernel/{}", name)))
+        .ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let cpus = std::fs::read_to_string(root.proc("stat")).map(|stat| {
+        stat.lines().filter(|l| l.strip_prefix("cpu").is_some_and(|n| n.starts_with(|c: char| c.is_ascii_digit()))).count()
+    });
+    Record::Host {
+        ts,
+        host: kernel("hostname")
+            .or_else(|| get().ok().and_then(|h| h.into_string().ok()))
+            .unwrap_or_else(|| "unknown".to_string()),
+        release: kernel("osrelease").unwrap_or_else(|| "unknown".to_string()),
+        arch: kernel("arch").or_else(uname_machine).unwrap_or_else(|| "unknown".to_string()),
+        cpus: cpus.map_or(0, |n| n as u32),
     }
 }
 
 /// Gathers disk, CPU, and memory stats at the requested interval and appends to output file.
-fn gather(interval: u64, out_path: &str) -> std::io::Result<()> {
-    let mut out = OpenOptions::new()
+/// The disk guard is checked before each sample; guard events are written as EVENT records.
+/// /proc and /sys are read under `root`; `count` stops after that many samples (forever when None).
+fn gather(interval: u64, out_path: &str, guard: &mut DiskGuard, root: &SysRoot, count: Option<u64>) -> std::io::Result<()> {
+    let file = OpenOptions::new()
         .create(true)
         .append(true)
         .open(out_path)?;
+    let mut out = Writer::new(file);
 
     // Print header only if file is empty
-    if out.metadata()?.len() == 0 {
-        writeln!(out, "#TYPE,ts_epoch,<fields...>")?;
+    if out.get_ref().metadata()?.len() == 0 {
+        out.write_header()?;
     }
+    let tz_name = timeutil::host_tz_name();
+    let mut last_offset: Option<i32> = None;
+    let mut last_scsi_devs: Vec<(String, String)> = Vec::new();
+    let mut last_layout: Option<u8> = None;
+    let mut samples = 0u64;
 
     loop {
+        if count.is_some_and(|c| samples >= c) {
+            return Ok(());
+        }
+        samples += 1;
         let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
 
+        // --- HOST: once per gather, for the iostat banner ---
+        if samples == 1 {
+            out.write(&host_record(root, now))?;
+        }
+
+        // --- TZ: host UTC offset, again whenever it changes (DST) ---
+        let offset = timeutil::host_utc_offset();
+        if last_offset != Some(offset) {
+            out.write(&Record::Tz { ts: now, offset, zone: tz_name.clone() })?;
+            last_offset = Some(offset);
+        }
+
+        // --- DISK SPACE GUARD ---
+        let (verdict, events) = guard.check();
+        for ev in &events {
+            // Best effort: the filesystem may already be full
+            let _ = out.write(&Record::Event { ts: now, source: "diskguard".to_string(), action: ev.kind.to_string(), detail: ev.detail.clone() });
+            let _ = out.flush();
+        }
+        match verdict {
+            Verdict::Write => {}
+            Verdict::Skip => {
+                sleep(Duration::from_secs(interval));
+                continue;
+            }
+            Verdict::Stop => return Ok(()),
+        }
+
         // --- DISK ---
-        let file = File::open("/proc/diskstats")?;
+        let file = File::open(root.proc("diskstats"))?;
         let reader = BufReader::new(file);
         for line in reader.lines().flatten() {
             if let Some(stat) = DiskStat::from_line(&line) {
                 if stat.name.starts_with("sd") || stat.name.starts_with("nvme") || stat.name.starts_with("dm-") || stat.name.starts_with("loop")
                     || stat.name.starts_with("emcpower") || stat.name.starts_with("vd") || stat.name.starts_with("rbd") || stat.name.starts_with("md")
+                    || stat.name.starts_with("drbd")
                 {
-                    writeln!(
-                        out,
-                        "DISK,{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
-                        now,
-                        stat.major,
-                        stat.minor,
-                        stat.name,
-                        stat.reads,
-                        stat.reads_merged,
-                        stat.sectors_read,
-                        stat.read_time_ms,
-                        stat.writes,
-                        stat.writes_merged,
-                        stat.sectors_written,
-                        stat.write_time_ms,
-                        stat.io_in_progress,
-                        stat.io_time_ms,
-                        stat.weighted_io_time_ms,
-                        stat.discards,
-                        stat.discards_merged,
-                        stat.sectors_discarded,
-                        stat.discard_time_ms
-                    )?;
+                    // --- DISKLAYOUT: diskstats field count, again whenever it changes ---
+                    if last_layout != Some(stat.layout) {
+                        out.write(&Record::DiskLayout { ts: now, fields: stat.layout })?;
+                        last_layout = Some(stat.layout);
+                    }
+                    out.write(&Record::Disk { ts: now, stat })?;
                 }
 
             }
@@ -218,92 +219,58 @@ Field	Name	Description
 the nice field.
  */
         // --- CPU ---
-        if let Ok(mut stat_file) = File::open("/proc/stat") {
-            let mut buf = String::new();
-            stat_file.read_to_string(&mut buf)?;
-            let mut procs_running: Option<u64> = None;
-            let mut procs_blocked: Option<u64> = None;
-            let mut cpu_vals: Vec<&str> = Vec::new();
-            for line in buf.lines() {
-                if line.starts_with("cpu ") {
-                    cpu_vals = line.split_whitespace().collect();
-                } else if line.starts_with("procs_running") {
-                    procs_running = line.split_whitespace().nth(1).and_then(|v| v.parse().ok());
-                } else if line.starts_with("procs_blocked") {
-                    procs_blocked = line.split_whitespace().nth(1).and_then(|v| v.parse().ok());
-                }
-            }
-            if cpu_vals.len() >= 10 {
-                writeln!(out, "CPU,{},{},{},{},{},{},{},{},{},{},{},{}",
-                    now,
-                    cpu_vals[1], cpu_vals[2], cpu_vals[3], cpu_vals[4], cpu_vals[5],
-                    cpu_vals[6], cpu_vals[7], cpu_vals[8], cpu_vals[9],
-                    procs_running.unwrap_or(0), procs_blocked.unwrap_or(0)
-                )?;
-            }
+        if let Ok(text) = std::fs::read_to_string(root.proc("stat"))
+            && let Some(stat) = CpuStat::from_proc_stat(&text)
+        {
+            out.write(&Record::Cpu { ts: now, stat })?;
         }
 
         // --- MEM ---
-        if let Ok(mem_file) = File::open("/proc/meminfo") {
-            let reader = BufReader::new(mem_file);
-            let mut values = HashMap::new();
-            for line in reader.lines().flatten() {
-                let mut parts = line.split_whitespace();
-                if let (Some(key), Some(val)) = (parts.next(), parts.next()) {
-                    values.insert(key.trim_end_matches(':').to_string(), val.to_string());
-                }
-            }
-            writeln!(out, "MEM,{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
-                now,
-                values.get("MemTotal").unwrap_or(&"0".to_string()),
-                values.get("MemFree").unwrap_or(&"0".to_string()),
-                values.get("MemAvailable").unwrap_or(&"0".to_string()),
-                values.get("Buffers").unwrap_or(&"0".to_string()),
-                values.get("Cached").unwrap_or(&"0".to_string()),
-                values.get("SwapTotal").unwrap_or(&"0".to_string()),
-                values.get("SwapFree").unwrap_or(&"0".to_string()),
-                values.get("Dirty").unwrap_or(&"0".to_string()),
-                values.get("Writeback").unwrap_or(&"0".to_string()),
-                values.get("Active(file)").unwrap_or(&"0".to_string()),
-                values.get("Inactive(file)").unwrap_or(&"0".to_string()),
-                values.get("Slab").unwrap_or(&"0".to_string()),
-                values.get("KReclaimable").unwrap_or(&"0".to_string()),
-                values.get("SReclaimable").unwrap_or(&"0".to_string()),
-            )?;
-        }
-
-                // --- NET ---
-        if let Ok(file) = File::open("/proc/net/dev") {
+        if let Ok(text) = std::fs::read_to_string(root.proc("meminfo")) {
+            out.write(&Record::Mem { ts: now, stat: MemStat::from_meminfo(&text) })?;
+        }
+
+        // --- NET ---
+        if let Ok(file) = File::open(root.proc("net/dev")) {
             let reader = BufReader::new(file);
             for line in reader.lines().flatten().skip(2) { // skip header lines
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 17 {
-                    // Format: iface: rx_bytes ... tx_bytes ... etc
-                    // E.g. ["eth0:", "123", "0", ... "456", ...]
-                    let iface = parts[0].trim_end_matches(':');
-                    let rx_bytes   = parts[1];
-                    let rx_packets = parts[2];
-                    let rx_errors  = parts[3];
-                    let rx_dropped = parts[4];
-                    let tx_bytes   = parts[9];
-                    let tx_packets = parts[10];
-                    let tx_errors  = parts[11];
-                    let tx_dropped = parts[12];
-
-                    writeln!(
-                        out,
-                        "NET,{},{},{},{},{},{},{},{},{},{}",
-                        now, iface,
-                        rx_bytes, tx_bytes,
-                        rx_packets, tx_packets,
-                        rx_errors, tx_errors,
-                        rx_dropped, tx_dropped
-                    )?;
+                let Some(stat) = NetStat::from_net_dev_line(&line) else { continue };
+                // Extended counters and link state first, so playback has them for this NET row
+                if let Some(ext) = netext::read_netext(root, &stat.iface) {
+                    out.write(&Record::NetExt { ts: now, iface: stat.iface.clone(), stat: ext })?;
                 }
+                out.write(&Record::Net { ts: now, stat })?;
             }
         }
 
+        // --- MD RAID / DRBD state (nothing written when neither is in use) ---
+        for md in raid::read_md_status(root) {
+            out.write(&Record::Md { ts: now, stat: md })?;
+        }
+        for drbd in raid::read_drbd_status(root) {
+            out.write(&Record::Drbd { ts: now, stat: drbd })?;
+        }
+
+        // --- FC / iSCSI transport state, SCSI addresses when the device set changes ---
+        let sysfs = root.sys();
+        for fc in transport::read_fc_hosts(&sysfs) {
+            out.write(&Record::Fc { ts: now, stat: fc })?;
+        }
+        for session in transport::read_iscsi_sessions(&sysfs) {
+            out.write(&Record::Iscsi { ts: now, stat: session })?;
+        }
+        let scsi_devs = transport::read_scsi_devices(&sysfs);
+        if scsi_devs != last_scsi_devs {
+            for (dev, hctl) in &scsi_devs {
+                out.write(&Record::ScsiDev { ts: now, dev: dev.clone(), hctl: hctl.clone() })?;
+            }
+            last_scsi_devs = scsi_devs;
+        }
+
         out.flush()?;
+        if count.is_some_and(|c| samples >= c) {
+            return Ok(());
+        }
         sleep(Duration::from_secs(interval));
     }
 }
@@ -312,115 +279,115 @@ the nice field.
 
 
 /// Playback disk stats from a previously captured file, printing interval-by-interval deltas.
-/// Now supports filtering output to a given time window (seconds since midnight).
-fn playback_disk(file_path: &str, from_sec: Option<u32>, to_sec: Option<u32>) -> std::io::Result<()> {
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
+/// Supports filtering output to a date-aware time window; times shown in the display timezone.
+fn playback_disk(file_path: &str, ctx: &mut TimeCtx, window: &TimeWindow, out: &OutputOpts, bucket: Option<u64>,
+                 sel: &DevSelect, follow: bool) -> std::io::Result<()> {
+    const COLS: &[Col] = &[
+        Col::new("device", "Device").l(10),
+        Col::new("time", "Time").l(8),
+        Col::new("epoch", "Epoch").l(10),
+        Col::new("dt", "Δt").l(5),
+        Col::new("reads", "ΔReads").r(10),
+        Col::new("reads_merged", "ΔReadsMerg").r(12),
+        Col::new("writes", "ΔWrites").r(10),
+        Col::new("writes_merged", "ΔWritesMerg").r(14),
+        Col::new("avg_qdepth", "AvgQDepth").r(12).prec(2),
+        Col::new("qlen", "Qlen").r(12).prec(2),
+        Col::new("r_s", "r/s").r(10).prec(2),
+        Col::new("w_s", "w/s").r(10).prec(2),
+        Col::new("rd_kbs", "rd_kB/s").r(12).prec(2),
+        Col::new("wr_kbs", "wr_kB/s").r(12).prec(2),
+        Col::new("svctim", "svctim").r(10).prec(2),
+        Col::new("await_rd_ms", "await_rd(ms)").r(12).prec(2),
+        Col::new("await_wr_ms", "await_wr(ms)").r(12).prec(2),
+        Col::new("discards", "Discards").r(10),
+        Col::new("discards_merged", "DiscardsM").r(14),
+        Col::new("sectors_discarded", "Discardssecs").r(14),
+        Col::new("discard_kbs", "DiscardsKBS").r(14).prec(2),
+        Col::new("await_discard_ms", "await_dis(ms)").r(14).prec(2),
+        Col::new("flush_s", "flush/s").r(10).prec(2),
+        Col::new("await_flush_ms", "await_fl(ms)").r(14).prec(2),
+    ];
+    let mut sink = output_sink(bucket, COLS, bucket::DISK_METRICS, bucket::DEVICE_COLS, out);
     let mut prev: HashMap<String, (u64, DiskStat)> = HashMap::new();
-    let mut printed_header = false;
-
-    for line in reader.lines().flatten() {
-        if line.starts_with("#TYPE") || line.starts_with('#') { continue; }
-        let mut cols = line.split(',');
-        let typ = cols.next().unwrap_or("");
-        if typ != "DISK" { continue; }
-        let ts = cols.next().unwrap_or("0").parse::<u64>().unwrap_or(0);
-        let fields: Vec<&str> = cols.collect();
-        if let Some(stat) = DiskStat::from_csv_fields(&fields) {
-            let key = format!("{}-{}-{}", stat.major, stat.minor, stat.name);
-            if let Some((last_ts, last_stat)) = prev.get(&key) {
-                let dt = ts.saturating_sub(*last_ts);
-                if dt == 0 { continue; }
-                let d_reads = stat.reads.saturating_sub(last_stat.reads);
-                let d_reads_merged = stat.reads_merged.saturating_sub(last_stat.reads_merged);
-                let d_writes = stat.writes.saturating_sub(last_stat.writes);
-                let d_writes_merged = stat.writes_merged.saturating_sub(last_stat.writes_merged);
-                let d_sectors_written = stat.sectors_written.saturating_sub(last_stat.sectors_written);
-                let d_sectors_read = stat.sectors_read.saturating_sub(last_stat.sectors_read);
-                let delta_weighted_io_time_ms = stat.weighted_io_time_ms.saturating_sub(last_stat.weighted_io_time_ms);
-                let avg_queue_depth = delta_weighted_io_time_ms as f64 / (dt as f64 * 1000.0);
-                let delta_io_time_ms = stat.io_time_ms.saturating_sub(last_stat.io_time_ms);
-                let d_discards = stat.discards.saturating_sub(last_stat.discards);
-                let d_discards_merged = stat.discards_merged.saturating_sub(last_stat.discards_merged);
-                let d_sectors_discarded = stat.sectors_discarded.saturating_sub(last_stat.sectors_discarded);
-                let d_discard_ms = stat.discard_time_ms.saturating_sub(last_stat.discard_time_ms);
-                let qlen = if delta_io_time_ms > 0 {
-                    delta_weighted_io_time_ms as f64 / delta_io_time_ms as f64
-                } else {
-                    0.0
-                };
-                let r_s = d_reads as f64 / dt as f64;
-                let w_s = d_writes as f64 / dt as f64;
-                let rd_sec_s = d_sectors_read as f64 / dt as f64;
-                let wr_sec_s = d_sectors_written as f64 / dt as f64;
-                let rd_kbs = rd_sec_s * 512.0 / 1024.0;
-                let wr_kbs = wr_sec_s * 512.0 / 1024.0;
-                let await_read_ms = if d_reads > 0 {
-                    (stat.read_time_ms.saturating_sub(last_stat.read_time_ms)) as f64 / d_reads as f64
-                } else { 0.0 };
-                let await_write_ms = if d_writes > 0 {
-                    (stat.write_time_ms.saturating_sub(last_stat.write_time_ms)) as f64 / d_writes as f64
-                } else { 0.0 };
-                // Service time (ms)
-                let total_ios = d_reads + d_writes;
-                let svctim = if total_ios > 0 {
-                    delta_io_time_ms as f64 / total_ios as f64
-                } else {
-                    0.0
-                };
-                let _discards_s = d_discards as f64 / dt as f64;
-                let _discards_merged_s = d_discards_merged as f64 / dt as f64;
-                let sectors_discarded_s = d_sectors_discarded as f64 / dt as f64;
-                let discard_kbs = sectors_discarded_s * 512.0 / 1024.0;
-                let await_discard_ms = if d_discards > 0 {
-                    d_discard_ms as f64 / d_discards as f64
-                } else { 0.0 };
-
-                // --- Time filter logic ---
-                let dt_obj = Local.timestamp_opt(ts as i64, 0).single().unwrap();
-                let t_hms = dt_obj.format("%H:%M:%S").to_string();
-                let secs_since_midnight = dt_obj.hour() * 3600 + dt_obj.minute() * 60 + dt_obj.second();
-
-                if let Some(start) = from_sec {
-                    if secs_since_midnight < start { continue; }
-                }
-                if let Some(end) = to_sec {
-                    if secs_since_midnight > end { continue; }
-                }
+    // Captures without DISKLAYOUT records always had discard counters
+    let mut layout: u8 = 18;
+    // Devices are listed in first-seen order; one interval is collected, then filtered/sorted
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
+    let mut interval: Vec<(usize, DiskRow)> = Vec::new();
 
-                // Print header on first output row
-                if !printed_header {
-                    println!(
-                        "{:<10} {:<8} {:<10} {:<5} {:>10} {:>12} {:>10} {:>14} \
-                         {:>12} {:>12} {:>10} {:>10} {:>12} {:>12} {:>10} {:>12} {:>12} \
-                         {:>10} {:>14} {:>14} {:>14} {:>14}",
-                        "Device", "Time", "Epoch", "Δt", "ΔReads", "ΔReadsMerg", "ΔWrites", "ΔWritesMerg",
-                        "AvgQDepth", "Qlen", "r/s", "w/s", "rd_kB/s", "wr_kB/s", "svctim", "await_rd(ms)", "await_wr(ms)",
-                        "Discards", "DiscardsM", "Discardssecs", "DiscardsKBS", "await_dis(ms)"
-                    );
-
-                    printed_header = true;
-                }
-                println!(
-                    "{:<10} {:<8} {:<10} {:<5} {:>10} {:>12} {:>10} {:>14} \
-                     {:>12.2} {:>12.2} {:>10.2} {:>10.2} {:>12.2} {:>12.2} {:>10.2} {:>12.2} {:>12.2} \
-                     {:>10} {:>14} {:>14} {:>14.2} {:>14.2}",
-                    stat.name, t_hms, ts, dt,
-                    d_reads, d_reads_merged, d_writes, d_writes_merged,
-                    avg_queue_depth, qlen,
-                    r_s, w_s, rd_kbs, wr_kbs, svctim, await_read_ms, await_write_ms,
-                    d_discards, d_discards_merged, d_sectors_discarded, discard_kbs, await_discard_ms
-                );
+    for rec in follow::records(file_path, follow)? {
+        let rec = match rec {
+            Ok(rec) => rec,
+            // --follow: print the interval read so far rather than wait for the next sample
+            Err(e) if follow::caught_up(&e) => {
+                emit_disk_interval(&mut sink, ctx, sel, &mut interval);
+                sink.flush();
+                continue;
             }
+            Err(_) => continue,
+        };
+        let (ts, stat) = match rec {
+            Record::Tz { ts, offset, zone } => { ctx.note_host_tz(ts, offset, &zone); continue; }
+            Record::DiskLayout { fields, .. } => { layout = fields; continue; }
+            Record::Disk { ts, stat } => (ts, stat),
+            _ => continue,
+        };
+        if window.is_past(ts) { break; }
+        if interval.first().is_some_and(|(_, r)| r.ts != ts) {
+            emit_disk_interval(&mut sink, ctx, sel, &mut interval);
+        }
+        let key = format!("{}-{}-{}", stat.major, stat.minor, stat.name);
+        // --- Time filter logic (keep prev current so the first delta in the window is correct) ---
+        if !window.contains(ts) || !sel.wants(&stat.name) {
             prev.insert(key, (ts, stat));
+            continue;
         }
+        if let Some((last_ts, last_stat)) = prev.get(&key)
+            && let Some(d) = DiskDelta::between(last_stat, &stat, ts.saturating_sub(*last_ts))
+            && sel.keep(&d)
+        {
+            let n = first_seen.len();
+            let idx = *first_seen.entry(stat.name.clone()).or_insert(n);
+            interval.push((idx, DiskRow { ts, name: stat.name.clone(), d, layout }));
+        }
+        prev.insert(key, (ts, stat));
     }
-    if !printed_header {
-        println!("No disk data found.");
-    }
+    emit_disk_interval(&mut sink, ctx, sel, &mut interval);
+    sink.finish(ctx, "No disk data found.");
     Ok(())
 }
 
+/// One device's delta in the interval being collected by `playback_disk`.
+struct DiskRow {
+    ts: u64,
+    name: String,
+    d: DiskDelta,
+    layout: u8,
+}
+
+/// Orders (and with --top, trims) one interval's devices and prints them.
+fn emit_disk_interval(sink: &mut Sink, ctx: &TimeCtx, sel: &DevSelect, interval: &mut Vec<(usize, DiskRow)>) {
+    sel.pick(interval, |r| &r.d);
+    for (_, DiskRow { ts, name, d, layout }) in interval.drain(..) {
+        // Discards: "-" when the capture kernel's diskstats had 14 fields
+        let has_discards = layout >= 18;
+        let discard = |v: u64| if has_discards { Cell::Int(v) } else { Cell::Missing };
+        let discard_f = |v: f64| if has_discards { Cell::Float(v) } else { Cell::Missing };
+        sink.interval(ctx, ts, &name, d.dt, &[
+            name.as_str().into(), Cell::Time(ts), ts.into(), d.dt.into(),
+            d.reads.into(), d.reads_merged.into(), d.writes.into(), d.writes_merged.into(),
+            d.avg_queue_depth().into(), d.qlen().into(),
+            d.r_s().into(), d.w_s().into(), d.rd_kbs().into(), d.wr_kbs().into(),
+            d.svctim().into(), d.await_rd().into(), d.await_wr().into(),
+            discard(d.discards), discard(d.discards_merged), discard(d.sectors_discarded),
+            discard_f(d.discard_kbs()), discard_f(d.await_discard()),
+            // Flushes: "-" when the capture kernel does not report them
+            d.flush_s().into(), d.await_flush().into(),
+        ], || bucket::disk_samples(&d, layout));
+    }
+}
+
 /* 
 Field   Name    Description
 1       user    Time spent on normal processes executing in user mode.
@@ -436,197 +403,242 @@ Field   Name    Description
 the nice field.
  */
 
-fn playback_cpu(file_path: &str) -> std::io::Result<()> {
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
-    let mut prev: Option<(u64, Vec<u64>, u64)> = None; // Updated to store guest value as well
-    let mut printed_header = false;
-
-    for line in reader.lines().flatten() {
-        if line.starts_with("#TYPE") || line.starts_with('#') { continue; }
-        let fields: Vec<&str> = line.split(',').collect();
-        if fields.get(0) != Some(&"CPU") { continue; }
-        // We now expect 13 fields (CPU, ts, 9 CPU fields, running, blocked)
-        if fields.len() < 13 { continue; }
-
-        let ts = fields[1].parse::<u64>().unwrap_or(0);
-
-        // 1. Grab the original 8 CPU fields (user to steal) at indices 2 through 9.
-        // This keeps vals.len() at 8, preserving old indices for user/nice/sys/idle/iowait.
-        let vals: Vec<u64> = fields[2..10].iter().filter_map(|v| v.parse::<u64>().ok()).collect();
-
-        // 2. Explicitly grab the Guest CPU time, which is at index 10 in the raw log line.
-        let current_guest = fields[10].parse::<u64>().ok().unwrap_or(0);
-
-        // Running and Blocked are now always the last two, at indices 11 and 12
-        let running = fields[11].parse::<u64>().ok();
-        let blocked = fields[12].parse::<u64>().ok();
-
-        // Check for 8 CPU time fields (user to steal)
-        if vals.len() >= 8 {
-            // Updated prev to check for (last_ts, last_vals, last_guest)
-            if let Some((last_ts, last_vals, last_guest)) = &prev {
-                let dt = ts.saturating_sub(*last_ts);
-                if dt == 0 { prev = Some((ts, vals, current_guest)); continue; }
-
-                // Calculate total time including the new guest value
-                // total_vals_diff = (user + nice + sys + idle + iowait + irq + softirq + steal) + guest_diff
-                let total_vals_diff: u64 = vals.iter().zip(last_vals.iter()).map(|(v, lv)| v - lv).sum();
-                let total = (total_vals_diff + (current_guest - last_guest)) as f64;
-
-                if total == 0.0 { prev = Some((ts, vals, current_guest)); continue; }
-
-                let total_inverse = 100.0 / total;
-
-                // These indices are now safely preserved because vals only holds the first 8 fields (user to steal)
-                let user   = (vals[0] - last_vals[0]) as f64 * total_inverse;
-                let nice   = (vals[1] - last_vals[1]) as f64 * total_inverse;
-                let sys    = (vals[2] - last_vals[2]) as f64 * total_inverse;
-                let idle   = (vals[3] - last_vals[3]) as f64 * total_inverse;
-                let iowait = (vals[4] - last_vals[4]) as f64 * total_inverse;
-
-                // Calculate guest percentage separately
-                let guest  = (current_guest.saturating_sub(*last_guest)) as f64 * total_inverse;
-
-                if !printed_header {
-                    println!(
-                        "{:<8} {:<10} {:<5} {:>10} {:>10} {:>10} {:>10} {:>10} {:>8} {:>8} {:>10}",
-                        "Time", "Epoch", "Δt", "User(%)", "System(%)", "Idle(%)", "IOWait(%)", "Nice(%)",
-                        "Running", "Blocked", "Guest"
-                    );
-                    printed_header = true;
-                }
+fn playback_cpu(file_path: &str, ctx: &mut TimeCtx, window: &TimeWindow, out: &OutputOpts, bucket: Option<u64>,
+                follow: bool) -> std::io::Result<()> {
+    const COLS: &[Col] = &[
+        Col::new("time", "Time").l(8),
+        Col::new("epoch", "Epoch").l(10),
+        Col::new("dt", "Δt").l(5),
+        Col::new("user", "User(%)").r(10).prec(2),
+        Col::new("system", "System(%)").r(10).prec(2),
+        Col::new("idle", "Idle(%)").r(10).prec(2),
+        Col::new("iowait", "IOWait(%)").r(10).prec(2),
+        Col::new("nice", "Nice(%)").r(10).prec(2),
+        Col::new("running", "Running").r(8),
+        Col::new("blocked", "Blocked").r(8),
+        Col::new("guest", "Guest").r(10).prec(2),
+    ];
+    let mut sink = output_sink(bucket, COLS, bucket::CPU_METRICS, bucket::SYSTEM_COLS, out);
+    let mut prev: Option<(u64, CpuStat)> = None;
 
-                let dt_obj = chrono::Local.timestamp_opt(ts as i64, 0).single().unwrap();
-                let t_hms = dt_obj.format("%H:%M:%S").to_string();
+    for rec in follow::records(file_path, follow)? {
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) if follow::caught_up(&e) => { sink.flush(); continue; }
+            Err(_) => continue,
+        };
+        let (ts, stat) = match rec {
+            Record::Tz { ts, offset, zone } => { ctx.note_host_tz(ts, offset, &zone); continue; }
+            Record::Cpu { ts, stat } => (ts, stat),
+            _ => continue,
+        };
+        if window.is_past(ts) { break; }
 
-                println!(
-                    "{:<8} {:<10} {:<5} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>8} {:>8} {:>10.2}",
-                    t_hms, ts, dt, user, sys, idle, iowait, nice,
-                    running.unwrap_or(0), blocked.unwrap_or(0), guest
-                );
+        if let Some((last_ts, last)) = &prev {
+            let dt = ts.saturating_sub(*last_ts);
+            // Percentages of all ticks in the interval (user..steal plus guest)
+            let pct = if dt == 0 || !window.contains(ts) { None } else { CpuPercent::between(last, &stat) };
+            if let Some(p) = pct {
+                sink.interval(ctx, ts, "", dt, &[
+                    Cell::Time(ts), ts.into(), dt.into(),
+                    p.user.into(), p.system.into(), p.idle.into(), p.iowait.into(), p.nice.into(),
+                    stat.procs_running.into(), stat.procs_blocked.into(), p.guest.into(),
+                ], || bucket::cpu_samples(&p, &stat, dt));
             }
-            // Update prev with the new state, including the current guest value
-            prev = Some((ts, vals, current_guest));
         }
+        prev = Some((ts, stat));
     }
-    if !printed_header {
-        println!("No CPU data found.");
-    }
+    sink.finish(ctx, "No CPU data found.");
     Ok(())
 }
 
 /// Playback memory stats from a previously captured file.
-fn playback_mem(file_path: &str) -> std::io::Result<()> {
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
-    let mut printed_header = false;
+fn playback_mem(file_path: &str, ctx: &mut TimeCtx, window: &TimeWindow, out: &OutputOpts, bucket: Option<u64>,
+                follow: bool) -> std::io::Result<()> {
+    const COLS: &[Col] = &[
+        Col::new("time", "Time").l(8),
+        Col::new("epoch", "Epoch").l(10),
+        Col::new("used_pct", "%Used").r(12).prec(2),
+        Col::new("avail_pct", "%Avail").r(12).prec(2),
+        Col::new("cached_pct", "%Cached").r(12).prec(2),
+        Col::new("free_pct", "%Free").r(12).prec(2),
+    ];
+    let mut sink = output_sink(bucket, COLS, bucket::MEM_METRICS, bucket::SYSTEM_COLS, out);
+    let mut prev_ts: Option<u64> = None;
 
-    for line in reader.lines().flatten() {
-        if line.starts_with("#TYPE") || line.starts_with('#') { continue; }
-        let mut cols = line.split(',');
-        let typ = cols.next().unwrap_or("");
-        if typ != "MEM" { continue; }
-        let ts = cols.next().unwrap_or("0").parse::<u64>().unwrap_or(0);
-        let keys = [
-            "MemTotal","MemFree","MemAvailable","Buffers","Cached",
-            "SwapTotal","SwapFree","Dirty","Writeback","Active(file)","Inactive(file)","Slab","KReclaimable","SReclaimable"
-        ];
-        let mut vals = HashMap::new();
-        for (k, v) in keys.iter().zip(cols) {
-            vals.insert((*k).to_string(), v.parse::<u64>().unwrap_or(0));
-        }
-        let mem_total = *vals.get("MemTotal").unwrap_or(&1) as f64;
-        let mem_free  = *vals.get("MemFree").unwrap_or(&0) as f64;
-        let mem_avail = *vals.get("MemAvailable").unwrap_or(&0) as f64;
-        let cached    = *vals.get("Cached").unwrap_or(&0) as f64;
-        let used = mem_total - mem_free;
-        let used_percent = if mem_total > 0.0 { used / mem_total * 100.0 } else { 0.0 };
-        let avail_percent = if mem_total > 0.0 { mem_avail / mem_total * 100.0 } else { 0.0 };
-        let cached_percent = if mem_total > 0.0 { cached / mem_total * 100.0 } else { 0.0 };
-        let free_percent = if mem_total > 0.0 { mem_free / mem_total * 100.0 } else { 0.0 };
-
-        if !printed_header {
-            println!(
-                "{:<8} {:<10} {:>12} {:>12} {:>12} {:>12}",
-                "Time", "Epoch", "%Used", "%Avail", "%Cached", "%Free"
-            );
-            printed_header = true;
-        }
-        let dt_obj = Local.timestamp_opt(ts as i64, 0).single().unwrap();
-        let t_hms = dt_obj.format("%H:%M:%S").to_string();
-        println!(
-            "{:<8} {:<10} {:>12.2} {:>12.2} {:>12.2} {:>12.2}",
-            t_hms, ts, used_percent, avail_percent, cached_percent, free_percent
-        );
-    }
-    if !printed_header {
-        println!("No MEM data found.");
+    for rec in follow::records(file_path, follow)? {
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) if follow::caught_up(&e) => { sink.flush(); continue; }
+            Err(_) => continue,
+        };
+        let (ts, stat) = match rec {
+            Record::Tz { ts, offset, zone } => { ctx.note_host_tz(ts, offset, &zone); continue; }
+            Record::Mem { ts, stat } => (ts, stat),
+            _ => continue,
+        };
+        if window.is_past(ts) { break; }
+        // Gauge: time since the previous sample (0 for the first, which buckets then skip)
+        let dt = prev_ts.map_or(0, |p| ts.saturating_sub(p));
+        prev_ts = Some(ts);
+        if !window.contains(ts) { continue; }
+        let p = MemPercent::of(&stat);
+        sink.interval(ctx, ts, "", dt, &[
+            Cell::Time(ts), ts.into(), p.used.into(), p.avail.into(), p.cached.into(), p.free.into(),
+        ], || bucket::mem_samples(&p, dt));
     }
+    sink.finish(ctx, "No MEM data found.");
     Ok(())
 }
 
 
 /// Playback network stats from a previously captured file.
 /// Shows per-interface deltas for each interval.
-fn playback_net(file_path: &str) -> std::io::Result<()> {
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
-    use std::collections::HashMap;
-    let mut prev: HashMap<String, (u64, [u64; 8])> = HashMap::new(); // iface -> (ts, [fields])
+fn playback_net(file_path: &str, ctx: &mut TimeCtx, window: &TimeWindow, out: &OutputOpts, bucket: Option<u64>,
+                follow: bool) -> std::io::Result<()> {
+    const COLS: &[Col] = &[
+        Col::new("iface", "Iface").l(10),
+        Col::new("time", "Time").l(8),
+        Col::new("epoch", "Epoch").l(10),
+        Col::new("rx_kbs", "rx_kB/s").l(10).prec(2),
+        Col::new("tx_kbs", "tx_kB/s").l(10).prec(2),
+        Col::new("rx_pkts", "rx_pkts").l(10),
+        Col::new("tx_pkts", "tx_pkts").l(10),
+        Col::new("rx_err", "rx_err").l(10),
+        Col::new("tx_err", "tx_err").l(10),
+        Col::new("drop", "drop").l(10),
+        Col::new("speed", "speed").l(12),
+        Col::new("util_pct", "util%").l(7).prec(1),
+        Col::new("rx_crc", "rx_crc").l(8),
+        Col::new("rx_fifo", "rx_fifo").l(8),
+        Col::new("rx_miss", "rx_miss"),
+        // Printed as "***" lines under the row in the text table
+        Col::new("link_events", "link_events").hidden(),
+    ];
+    let mut sink = output_sink(bucket, COLS, bucket::NET_METRICS, bucket::IFACE_COLS, out);
+    let mut prev: HashMap<String, (u64, NetStat, Option<NetExt>)> = HashMap::new(); // iface -> (ts, stat, ext)
+    // Latest NETX per iface (written just before its NET row) and link changes to flag after the row
+    let mut cur_ext: HashMap<String, NetExt> = HashMap::new();
+    let mut flags: HashMap<String, Vec<String>> = HashMap::new();
+
+    for rec in follow::records(file_path, follow)? {
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) if follow::caught_up(&e) => { sink.flush(); continue; }
+            Err(_) => continue,
+        };
+        let (ts, stat) = match rec {
+            Record::Tz { ts, offset, zone } => { ctx.note_host_tz(ts, offset, &zone); continue; }
+            Record::NetExt { ts, iface, stat: ext } => {
+                if window.is_past(ts) { break; }
+                if let Some(last) = cur_ext.get(&iface) {
+                    let changes = ext.link_changes(last);
+                    if window.contains(ts) && !changes.is_empty() {
+                        flags.entry(iface.clone()).or_default().extend(changes);
+                    }
+                }
+                cur_ext.insert(iface, ext);
+                continue;
+            }
+            Record::Net { ts, stat } => (ts, stat),
+            _ => continue,
+        };
+        if window.is_past(ts) { break; }
+        let iface = stat.iface.clone();
+        let ext = cur_ext.get(&iface).cloned();
+        if !window.contains(ts) {
+            prev.insert(iface, (ts, stat, ext));
+            continue;
+        }
+        let events = flags.remove(&iface).unwrap_or_default();
+        if let Some((last_ts, last_stat, last_ext)) = prev.get(&iface) {
+            let Some(d) = NetDelta::between(last_stat, &stat, ts.saturating_sub(*last_ts)) else { continue };
+
+            // Extended columns, "-" for captures without NETX records
+            let ext_cells: [Cell; 5] = match (&ext, last_ext) {
+                (Some(e), Some(le)) => {
+                    let busiest = d.rate(d.rx_bytes.max(d.tx_bytes));
+                    [
+                        netext::fmt_speed(e.speed_mbps).into(),
+                        e.speed_bytes().map(|b| busiest / b * 100.0).into(),
+                        e.rx_crc_errors.saturating_sub(le.rx_crc_errors).into(),
+                        e.rx_fifo_errors.saturating_sub(le.rx_fifo_errors).into(),
+                        e.rx_missed_errors.saturating_sub(le.rx_missed_errors).into(),
+                    ]
+                }
+                _ => std::array::from_fn(|_| Cell::Missing),
+            };
+            let [speed, util, crc, fifo, missed] = ext_cells;
+            let util_pct = match util { Cell::Float(u) => Some(u), _ => None };
+            let link_events = if events.is_empty() { Cell::Missing } else { events.join("; ").into() };
+            sink.interval(ctx, ts, &iface, d.dt, &[
+                iface.as_str().into(), Cell::Time(ts), ts.into(),
+                d.rx_kbs().into(), d.tx_kbs().into(),
+                d.rx_packets.into(), d.tx_packets.into(), d.rx_errs.into(), d.tx_errs.into(), (d.rx_drop + d.tx_drop).into(),
+                speed, util, crc, fifo, missed, link_events,
+            ], || bucket::net_samples(&d, util_pct));
+        }
+        // Link flaps / speed changes flagged right under the interface row
+        for f in events {
+            sink.note(&format!("{:<10} {:<8} {:<10} *** {}", iface, ctx.hms(ts), ts, f));
+        }
+        prev.insert(iface, (ts, stat, ext));
+    }
+    sink.finish(ctx, "No NET data found.");
+    Ok(())
+}
+
+
+/// Playback md RAID and DRBD state. A line is printed whenever an array changes
+/// state and every interval while it is resyncing/rebuilding.
+fn playback_raid(file_path: &str, ctx: &mut TimeCtx, window: &TimeWindow, follow: bool) -> std::io::Result<()> {
+    let mut last: HashMap<String, String> = HashMap::new();
     let mut printed_header = false;
 
-    for line in reader.lines().flatten() {
-        if line.starts_with("#TYPE") || line.starts_with('#') { continue; }
-        let mut cols = line.split(',');
-        let typ = cols.next().unwrap_or("");
-        if typ != "NET" { continue; }
-        let ts = cols.next().unwrap_or("0").parse::<u64>().unwrap_or(0);
-        let iface = cols.next().unwrap_or("unknown").to_string();
-        let fields: Vec<u64> = cols.take(8).map(|v| v.parse::<u64>().unwrap_or(0)).collect();
-        if fields.len() < 8 { continue; }
-        let [rx_bytes, tx_bytes, rx_packets, tx_packets, rx_errs, tx_errs, rx_drop, tx_drop] = match fields.as_slice() {
-            [a,b,c,d,e,f,g,h] => [*a,*b,*c,*d,*e,*f,*g,*h],
+    for rec in follow::records(file_path, follow)? {
+        let Ok(rec) = rec else { continue };
+        // (name, level/role, state, degraded, action, done%, speed, mismatch/oos, members/disk state)
+        let (ts, row) = match rec {
+            Record::Tz { ts, offset, zone } => { ctx.note_host_tz(ts, offset, &zone); continue; }
+            Record::Md { ts, stat: md } => {
+                let pct = md.sync_pct().map(|p| format!("{:.1}", p)).unwrap_or_else(|| "-".to_string());
+                (ts, (md.name.clone(), md.level.clone(), md.array_state.clone(), md.degraded.to_string(),
+                      md.sync_action.clone(), pct, md.sync_speed_kb, md.mismatch_cnt, md.members.join(","), md.is_syncing()))
+            }
+            Record::Drbd { ts, stat: d } => {
+                let pct = if d.is_syncing() { format!("{:.1}", d.sync_pct) } else { "-".to_string() };
+                (ts, (d.name.clone(), d.ro.clone(), d.cs.clone(), "-".to_string(),
+                      if d.is_syncing() { d.cs.clone() } else { "idle".to_string() }, pct, d.speed_kb, d.oos, d.ds.clone(), d.is_syncing()))
+            }
             _ => continue,
         };
-        if let Some((last_ts, last_vals)) = prev.get(&iface) {
-            let dt = ts.saturating_sub(*last_ts);
-            if dt == 0 { continue; }
-            let drx_bytes = rx_bytes.saturating_sub(last_vals[0]);
-            let dtx_bytes = tx_bytes.saturating_sub(last_vals[1]);
-            let drx_packets = rx_packets.saturating_sub(last_vals[2]);
-            let dtx_packets = tx_packets.saturating_sub(last_vals[3]);
-            let drx_errs = rx_errs.saturating_sub(last_vals[4]);
-            let dtx_errs = tx_errs.saturating_sub(last_vals[5]);
-            let drx_drop = rx_drop.saturating_sub(last_vals[6]);
-            let dtx_drop = tx_drop.saturating_sub(last_vals[7]);
-
-            // Print header on first output row
-            if !printed_header {
-                println!(
-                    "{:<10} {:<8} {:<10} {:<10} {:<10} {:<10} {:<10} {:<10} {:<10} {:<10}",
-                    "Iface", "Time", "Epoch", "rx_kB/s", "tx_kB/s", "rx_pkts", "tx_pkts", "rx_err", "tx_err", "drop"
-                );
-                printed_header = true;
-            }
-            let t_hms = chrono::Local.timestamp_opt(ts as i64, 0).single().unwrap().format("%H:%M:%S").to_string();
+        if window.is_past(ts) { break; }
+        let (name, level, state, degraded, action, pct, speed, mismatch, members, syncing) = row;
+
+        // Keep tracking state outside the window so the first line in it is not a false change
+        let key = format!("{}|{}|{}|{}|{}", level, state, degraded, action, members);
+        let changed = last.get(&name) != Some(&key);
+        last.insert(name.clone(), key);
+        if !window.contains(ts) || !(changed || syncing) { continue; }
+
+        if !printed_header {
             println!(
-                "{:<10} {:<8} {:<10} {:<10.2} {:<10.2} {:<10} {:<10} {:<10} {:<10} {:<10}",
-                iface, t_hms, ts,
-                drx_bytes as f64 / dt as f64 / 1024.0,
-                dtx_bytes as f64 / dt as f64 / 1024.0,
-                drx_packets, dtx_packets, drx_errs, dtx_errs, drx_drop + dtx_drop
+                "{:<8} {:<10} {:<8} {:<18} {:<14} {:>4} {:<12} {:>6} {:>11} {:>12}  Members/DiskState",
+                "Time", "Epoch", "Device", "Level/Role", "State", "Degr", "Action", "Done%", "Speed(KB/s)", "Mismatch/OOS"
             );
+            printed_header = true;
         }
-        prev.insert(iface, (ts, [rx_bytes, tx_bytes, rx_packets, tx_packets, rx_errs, tx_errs, rx_drop, tx_drop]));
+        println!(
+            "{:<8} {:<10} {:<8} {:<18} {:<14} {:>4} {:<12} {:>6} {:>11} {:>12}  {}",
+            ctx.hms(ts), ts, name, level, state, degraded, action, pct, speed, mismatch, members
+        );
     }
     if !printed_header {
-        println!("No NET data found.");
+        println!("No MD/DRBD data found.");
     }
     Ok(())
 }
 
-
 /// Prints command-line usage and exits with code 1.
 fn usage() {
     println!("serverstats_grab {}", VERSION_NUMBER);
@@ -634,13 +646,80 @@ fn usage() {
 "Usage:
     serverstats_grab -g <interval_seconds>                            # Gather mode (all metrics)
     serverstats_grab -g <interval_seconds> -o <output path>           # Gather mode (all metrics)
+    serverstats_grab -g <interval_seconds> [--min-free PCT] [--min-free-inodes PCT]
+                     [--on-low-space stop|pause|prune]                # Free-space guard (default: 5% / 5% / pause)
+    serverstats_grab -g <interval_seconds> [--root <dir>] [--count N]  # Read <dir>/proc and <dir>/sys (e.g. /host in a
+                                                                      # container), stop after N samples
     serverstats_grab -pD <capturefile>                                # Playback DISK
-    serverstats_grab -pD --from HH:MM:SS --to HH:MM:SS <capturefile>  # Playback DISK time window
+    serverstats_grab -pX <capturefile>                                # Playback DISK in iostat -x -t layout
+    serverstats_grab -pXk | -pXm <capturefile>                        # ... as iostat -xk / -xm (kB/s or MB/s)
     serverstats_grab -pC <capturefile>                                # Playback CPU
     serverstats_grab -pM <capturefile>                                # Playback MEM
-    serverstats_grab -pN <capturefile>                                # Playback NET
+    serverstats_grab -pN <capturefile>                                # Playback NET (link speed/util%, flaps flagged with ***)
+    serverstats_grab -pA <capturefile>                                # Playback all: one vmstat-style line per interval
+                                                                      # (CPU, run queue, memory, disk and net totals)
+    serverstats_grab -pR <capturefile>                                # Playback md RAID / DRBD state and resync progress
     serverstats_grab -a <capturefile>                                 # Analysis mode (graphs + dashboard)
+    serverstats_grab -a <directory>                                   # Fleet analysis of every .dat in the directory: per-host
+                                                                      # summaries, cross-host top-N, aligned timelines, and
+                                                                      # each host's own dashboard linked in
+    serverstats_grab -i <capturefile>                                 # Interactive terminal browser (timeline, CPU/MEM/NET
+                                                                      # panes, sortable device table); q to quit
+    serverstats_grab -r <capturefile> [report.html]                   # One self-contained HTML report (zoomable charts,
+                                                                      # device/interface pickers), opens offline from disk
+    serverstats_grab -c <before.dat> <after.dat> [before-multipath-ll.txt after-multipath-ll.txt]
+                                                                      # Compare two captures: distributions, p95 changes,
+                                                                      # overlay charts and a regression/improvement verdict
+                                                                      # per device and subsystem (maps matched by WWID)
     serverstats_grab -pMpath <multipath-ll.txt> <capturefile.dat>     # Multipath IO/KB/sec summary
+    serverstats_grab -pT <capturefile> [multipath-ll.txt]             # FC/iSCSI events with per-host path I/O
+    serverstats_grab -t <capturefile> <out.dat>                       # Write a slice of the capture (--from/--to window,
+                                                                      # --dev/--iface selection) to share as its own .dat
+
+    Time options (all playback modes, -a, -c, -i, -r, -t, -pMpath and -pT):
+      --from <time> --to <time>     time window, <time> is one of
+                                    HH:MM[:SS]            first occurrence after capture start (or --from)
+                                    2025-03-14T13:15:00   date-time, add Z or +HH:MM for an explicit offset
+                                    +30m  +2h  +1h30m     relative to capture start
+                                    @1741958100           epoch seconds
+      --tz host|local|UTC|+HH:MM|<Area/City>
+                                    display timezone (default host: the capture host's recorded timezone;
+                                    UTC for the shared tables and timelines of -a <directory>)
+      --utc                         same as --tz UTC
+      --follow                      (playback modes and -pT) print the capture so far, then keep printing
+                                    as the gatherer appends, like tail -F; moves on to the host's next
+                                    capture when the gatherer restarts. Ctrl-C to stop
+
+    Output options (-pD, -pC, -pM, -pN, -pA, -pMpath):
+      --format text|csv|json|ndjson output format (default text); machine formats use the column keys
+                                    and ISO 8601 times
+      --columns <list>              comma-separated columns to print, by key or heading, e.g.
+                                    --columns device,time,r_s,w_s,await_rd_ms,await_wr_ms
+      --bucket 1m|5m|1h             (-pD, -pC, -pM, -pN, -a, -c, -r) aggregate intervals into time buckets: one row
+                                    per bucket and metric with the time-weighted mean, min, max and p95;
+                                    --columns also takes metric names (e.g. --columns r_s,await_rd_ms).
+                                    With -a and -r, charts plot one point per bucket (chosen
+                                    automatically for long captures)
+      --where '<expr>'              print only rows matching, e.g. --where 'await_wr>20 || qlen>32' or
+                                    --where 'iowait>10': column key (unit suffix optional) compared
+                                    with > >= < <= == != and a number, && || and ( ). Repeat to AND
+      --context N                   with --where, also the N intervals before and after each match
+                                    (per device/interface); -- marks skipped intervals
+      --color auto|always|never     highlight the matching columns (default auto: on a terminal,
+                                    unless NO_COLOR is set)
+
+    Device selection (-pD; -a and -c take all but --top/--sort, -t only the name filters):
+      --dev <regex>                 only devices matching (repeatable), e.g. --dev '^dm-' --dev '^nvme'
+      --exclude-dev <regex>         drop devices matching (repeatable)
+      --no-partitions               drop partitions (sda1, nvme0n1p2, ...)
+      --no-idle                     drop devices with no I/O in the interval (-a: in the whole window)
+      --top N                       only the N worst devices of each interval
+      --sort <key>                  order devices (highest first) and rank them for --top (default io_s):
+                                    await_rd, await_wr, qlen, avg_qdepth, svctim, kb_sec, rd_kbs,
+                                    wr_kbs, io_s, r_s, w_s, util
+                                    Without --sort/--top devices are listed in capture order
+      --iface <regex>               (-a, -c, -t) only interfaces matching (repeatable), e.g. --iface '^bond'
+      --exclude-iface <regex>       (-a, -c, -t) drop interfaces matching (repeatable)
 
     After running the -a analyze option you can cd to the directory
     Then run this python lightweight web server and browse the analysis data:
@@ -652,25 +731,208 @@ fn usage() {
     );
 }
 
-/// Main program entrypoint and argument parser.
+/// Common --from/--to/--tz/--utc and --format/--columns options for playback and analysis modes.
+/// Anything that is not an option is collected into `files`.
+struct TimeOpts {
+    from: Option<String>,
+    to: Option<String>,
+    /// --tz/--utc; None means the default, host for one capture and UTC for a fleet
+    tz: Option<DisplayTz>,
+    out: OutputOpts,
+    bucket: Option<u64>,
+    sel: DevSelect,
+    ifaces: IfaceSelect,
+    follow: bool,
+    files: Vec<String>,
+}
 
-fn parse_time_hms(s: &str) -> Option<u32> {
-    // Require exactly two colons (HH:MM:SS)
-    let parts: Vec<&str> = s.split(':').collect();
-    if parts.len() != 3 {
-        eprintln!("ERROR: Time must be in HH:MM:SS format (e.g. 13:15:01)");
-        usage();
-        std::process::exit(1);
+fn parse_time_opts(args: &[String]) -> TimeOpts {
+    let mut opts = TimeOpts { from: None, to: None, tz: None, out: OutputOpts::default(), bucket: None, sel: DevSelect::default(), ifaces: IfaceSelect::default(), follow: false, files: Vec::new() };
+    let mut color: Option<bool> = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--from" if i+1 < args.len() => {
+                opts.from = Some(args[i+1].clone());
+                i += 2;
+            }
+            "--to" if i+1 < args.len() => {
+                opts.to = Some(args[i+1].clone());
+                i += 2;
+            }
+            "--tz" if i+1 < args.len() => {
+                opts.tz = Some(DisplayTz::parse(&args[i+1]).unwrap_or_else(|e| {
+                    eprintln!("ERROR: --tz: {}", e);
+                    usage();
+                    std::process::exit(1);
+                }));
+                i += 2;
+            }
+            "--utc" => {
+                opts.tz = Some(DisplayTz::parse("UTC").unwrap());
+                i += 1;
+            }
+            "--format" if i+1 < args.len() => {
+                opts.out.format = Format::parse(&args[i+1]).unwrap_or_else(|| {
+                    eprintln!("ERROR: --format must be one of text, csv, json, ndjson");
+                    usage();
+                    std::process::exit(1);
+                });
+                i += 2;
+            }
+            "--columns" if i+1 < args.len() => {
+                opts.out.columns = Some(args[i+1].clone());
+                i += 2;
+            }
+            "--where" if i+1 < args.len() => {
+                // Repeated --where options must all match
+                opts.out.filter = Some(match opts.out.filter.take() {
+                    Some(prev) => format!("({}) && ({})", prev, args[i+1]),
+                    None => args[i+1].clone(),
+                });
+                i += 2;
+            }
+            "--context" if i+1 < args.len() => {
+                opts.out.context = args[i+1].parse().unwrap_or_else(|_| {
+                    eprintln!("ERROR: --context must be a number of intervals");
+                    usage();
+                    std::process::exit(1);
+                });
+                i += 2;
+            }
+            "--color" if i+1 < args.len() => {
+                color = match args[i+1].as_str() {
+                    "auto" => None,
+                    "always" => Some(true),
+                    "never" => Some(false),
+                    _ => {
+                        eprintln!("ERROR: --color must be one of auto, always, never");
+                        usage();
+                        std::process::exit(1);
+                    }
+                };
+                i += 2;
+            }
+            "--dev" | "--exclude-dev" if i+1 < args.len() => {
+                let res = if args[i] == "--dev" { opts.sel.include(&args[i+1]) } else { opts.sel.exclude(&args[i+1]) };
+                if let Err(e) = res {
+                    eprintln!("ERROR: {}", e);
+                    std::process::exit(1);
+                }
+                i += 2;
+            }
+            "--iface" | "--exclude-iface" if i+1 < args.len() => {
+                let res = if args[i] == "--iface" { opts.ifaces.include(&args[i+1]) } else { opts.ifaces.exclude(&args[i+1]) };
+                if let Err(e) = res {
+                    eprintln!("ERROR: {}", e);
+                    std::process::exit(1);
+                }
+                i += 2;
+            }
+            "--no-partitions" => {
+                opts.sel.no_partitions = true;
+                i += 1;
+            }
+            "--no-idle" => {
+                opts.sel.no_idle = true;
+                i += 1;
+            }
+            "--top" if i+1 < args.len() => {
+                opts.sel.top = Some(args[i+1].parse().ok().filter(|&n| n > 0).unwrap_or_else(|| {
+                    eprintln!("ERROR: --top must be a number of devices");
+                    usage();
+                    std::process::exit(1);
+                }));
+                i += 2;
+            }
+            "--sort" if i+1 < args.len() => {
+                opts.sel.sort = Some(SortKey::parse(&args[i+1]).unwrap_or_else(|| {
+                    eprintln!("ERROR: --sort must be one of {}", devselect::SORT_KEYS);
+                    usage();
+                    std::process::exit(1);
+                }));
+                i += 2;
+            }
+            "--bucket" if i+1 < args.len() => {
+                opts.bucket = Some(bucket::parse_bucket(&args[i+1]).unwrap_or_else(|| {
+                    eprintln!("ERROR: --bucket must be a duration such as 1m, 5m or 1h");
+                    usage();
+                    std::process::exit(1);
+                }));
+                i += 2;
+            }
+            "--follow" => {
+                opts.follow = true;
+                i += 1;
+            }
+            s if !s.starts_with("--") => {
+                opts.files.push(s.to_string());
+                i += 1;
+            }
+            s => {
+                eprintln!("WARN: ignoring unknown option {}", s);
+                i += 1;
+            }
+        }
     }
-    let h = parts[0].parse::<u32>().ok()?;
-    let m = parts[1].parse::<u32>().ok()?;
-    let s = parts[2].parse::<u32>().ok()?;
-    if h > 23 || m > 59 || s > 59 {
-        eprintln!("ERROR: Time must be in valid HH:MM:SS range");
-        usage();
+    if opts.bucket.is_some() && (opts.sel.top.is_some() || opts.sel.sort.is_some()) {
+        eprintln!("WARN: --top/--sort pick devices per interval, ignoring them with --bucket");
+        opts.sel.top = None;
+        opts.sel.sort = None;
+    }
+    if opts.bucket.is_some() && opts.out.filter.is_some() {
+        eprintln!("WARN: --where selects intervals, ignoring it with --bucket");
+        opts.out.filter = None;
+    }
+    if opts.out.context > 0 && opts.out.filter.is_none() {
+        eprintln!("WARN: --context only applies with --where");
+    }
+    // Highlight matches on a terminal unless NO_COLOR is set (https://no-color.org)
+    opts.out.color = color.unwrap_or_else(|| io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none());
+    opts
+}
+
+impl TimeOpts {
+    fn file(&self, idx: usize) -> &str {
+        self.files.get(idx).map(|s| s.as_str()).unwrap_or("serverstats_grab.dat")
+    }
+
+    /// Reads the capture head and resolves --from/--to against it, exiting on a bad time spec.
+    fn resolve(&self, capture: &str) -> std::io::Result<(TimeCtx, TimeWindow)> {
+        let ctx = TimeCtx::for_capture(capture, self.tz.clone().unwrap_or(DisplayTz::Host))?;
+        let window = TimeWindow::resolve(self.from.as_deref(), self.to.as_deref(), &ctx)
+            .unwrap_or_else(|e| {
+                eprintln!("ERROR: {}", e);
+                usage();
+                std::process::exit(1);
+            });
+        if let Some(note) = ctx.host_tz_note() {
+            eprintln!("{}", note);
+        }
+        Ok((ctx, window))
+    }
+}
+
+/// Output for a playback mode (per-interval table, or bucket rows with --bucket),
+/// exiting on an unknown --columns or --where name.
+fn output_sink<'a>(bucket: Option<u64>, cols: &'a [Col], metrics: &[&'static str], bucket_cols: &'a [Col],
+                   out: &OutputOpts) -> Sink<'a> {
+    Sink::new(bucket, cols, metrics, bucket_cols, out).unwrap_or_else(|e| {
+        eprintln!("ERROR: {}", e);
         std::process::exit(1);
+    })
+}
+
+/// Parses a 0-100 percentage for the disk guard options.
+fn parse_pct(s: &str) -> f64 {
+    match s.trim_end_matches('%').parse::<f64>() {
+        Ok(v) if (0.0..=100.0).contains(&v) => v,
+        _ => {
+            eprintln!("ERROR: '{}' is not a percentage between 0 and 100", s);
+            usage();
+            std::process::exit(1);
+        }
     }
-    Some(h * 3600 + m * 60 + s)
 }
 
 fn main() -> std::io::Result<()> {
@@ -685,13 +947,41 @@ fn main() -> std::io::Result<()> {
             let interval = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(5);
 
             // [OUTPUT DIR PATCH START]
-            // Find optional -o <output_dir>
+            // Find optional -o <output_dir> and disk guard options
             let mut output_dir = ".".to_string(); // default is current directory
+            let mut min_free = 5.0;
+            let mut min_free_inodes = 5.0;
+            let mut action = SpaceAction::Pause;
+            let mut root = SysRoot::default();
+            let mut count: Option<u64> = None;
             let mut i = 3; // Start after "-g <interval>"
             while i < args.len() {
                 if args[i] == "-o" && i+1 < args.len() {
                     output_dir = args[i+1].clone();
                     i += 2;
+                } else if args[i] == "--root" && i+1 < args.len() {
+                    root = SysRoot::new(&args[i+1]);
+                    i += 2;
+                } else if args[i] == "--count" && i+1 < args.len() {
+                    count = Some(args[i+1].parse().unwrap_or_else(|_| {
+                        eprintln!("ERROR: --count must be a number of samples");
+                        usage();
+                        std::process::exit(1);
+                    }));
+                    i += 2;
+                } else if args[i] == "--min-free" && i+1 < args.len() {
+                    min_free = parse_pct(&args[i+1]);
+                    i += 2;
+                } else if args[i] == "--min-free-inodes" && i+1 < args.len() {
+                    min_free_inodes = parse_pct(&args[i+1]);
+                    i += 2;
+                } else if args[i] == "--on-low-space" && i+1 < args.len() {
+                    action = SpaceAction::parse(&args[i+1]).unwrap_or_else(|| {
+                        eprintln!("ERROR: --on-low-space must be one of stop, pause, prune");
+                        usage();
+                        std::process::exit(1);
+                    });
+                    i += 2;
                 } else {
                     i += 1;
                 }
@@ -711,57 +1001,169 @@ fn main() -> std::io::Result<()> {
             };
 
             println!("Writing to file: {}", fullpath);
-            gather(interval, &fullpath)
-        }
-        "-pD" => {
-            // Argument parsing for optional --from and --to
-            let mut from_sec = None;
-            let mut to_sec = None;
-            let mut file_arg = None;
-            let mut i = 2; // Start at 2 because 0 is prog, 1 is -pD
-        
-            while i < args.len() {
-                match args[i].as_str() {
-                    "--from" if i+1 < args.len() => {
-                        from_sec = parse_time_hms(&args[i+1]);
-                        i += 2;
-                    }
-                    "--to" if i+1 < args.len() => {
-                        to_sec = parse_time_hms(&args[i+1]);
-                        i += 2;
-                    }
-                    s if !s.starts_with("--") => {
-                        file_arg = Some(s.to_string());
-                        i += 1;
-                    }
-                    _ => { i += 1; }
-                }
+            if root.path() != std::path::Path::new("/") {
+                println!("Reading /proc and /sys under: {}", root.path().display());
+            }
+            let mut guard = DiskGuard::new(&fullpath, "serverstats_grab-", ".dat", min_free, min_free_inodes, action);
+            gather(interval, &fullpath, &mut guard, &root, count)
+        }
+        "-pD" | "-pC" | "-pM" | "-pN" | "-pA" | "-pR" | "-pX" | "-pXk" | "-pXm" => {
+            let opts = parse_time_opts(&args[2..]);
+            let file_path = opts.file(0);
+            let (mut ctx, window) = opts.resolve(file_path)?;
+            let mode = args[1].as_str();
+            if matches!(mode, "-pR" | "-pX" | "-pXk" | "-pXm") && !opts.out.is_default_text() {
+                eprintln!("WARN: {} prints text only, ignoring --format/--columns/--where", mode);
+            }
+            if matches!(mode, "-pA" | "-pR" | "-pX" | "-pXk" | "-pXm") && opts.bucket.is_some() {
+                eprintln!("WARN: {} prints every interval, ignoring --bucket", mode);
+            }
+            if mode != "-pD" && !opts.sel.is_default() {
+                eprintln!("WARN: device selection options apply to -pD only, ignoring them for {}", mode);
+            }
+            if !opts.ifaces.is_default() {
+                eprintln!("WARN: --iface/--exclude-iface apply to -a, -c and -t only, ignoring them for {}", mode);
+            }
+            let follow = opts.follow;
+            if follow && opts.out.format == Format::Json {
+                eprintln!("WARN: --follow never closes the JSON array, --format ndjson suits streaming better");
+            }
+            match mode {
+                "-pD" => playback_disk(file_path, &mut ctx, &window, &opts.out, opts.bucket, &opts.sel, follow),
+                "-pC" => playback_cpu(file_path, &mut ctx, &window, &opts.out, opts.bucket, follow),
+                "-pM" => playback_mem(file_path, &mut ctx, &window, &opts.out, opts.bucket, follow),
+                "-pA" => vmstat::playback_all(file_path, &mut ctx, &window, &opts.out, follow),
+                "-pR" => playback_raid(file_path, &mut ctx, &window, follow),
+                "-pX" | "-pXk" => iostat::playback_iostat(file_path, &mut ctx, &window, iostat::Unit::Kb, follow),
+                "-pXm" => iostat::playback_iostat(file_path, &mut ctx, &window, iostat::Unit::Mb, follow),
+                _ => playback_net(file_path, &mut ctx, &window, &opts.out, opts.bucket, follow),
             }
-            let file_path = file_arg.as_deref().unwrap_or("serverstats_grab.dat");
-            playback_disk(file_path, from_sec, to_sec)
         }
 
-        "-pC" => {
-            let fname = args.get(2).map(|s| s.as_str()).unwrap_or("serverstats_grab.dat");
-            playback_cpu(fname)
+        "-a" => {
+            let opts = parse_time_opts(&args[2..]);
+            let file_path = opts.file(0);
+            if Path::new(file_path).is_dir() {
+                // Hosts may sit in different zones, so the fleet needs one zone of its own
+                let display = match &opts.tz {
+                    Some(DisplayTz::Host) => {
+                        eprintln!("WARN: --tz host has no single zone for a fleet, showing fleet times in UTC");
+                        DisplayTz::parse("UTC").unwrap()
+                    }
+                    Some(tz) => tz.clone(),
+                    None => DisplayTz::parse("UTC").unwrap(),
+                };
+                return fleet::analyze_fleet(file_path, display, &|p| opts.resolve(p), &opts.sel, &opts.ifaces, opts.bucket);
+            }
+            let (ctx, window) = opts.resolve(file_path)?;
+            if opts.follow {
+                eprintln!("WARN: -a reads the whole capture, ignoring --follow");
+            }
+            if opts.out.filter.is_some() {
+                eprintln!("WARN: --where applies to playback tables, ignoring it for -a");
+            }
+            if opts.sel.top.is_some() || opts.sel.sort.is_some() {
+                eprintln!("WARN: --top/--sort pick devices per interval, -a charts every selected device");
+            }
+            analyze::analyze(file_path, ctx, &window, &opts.sel, &opts.ifaces, opts.bucket)
         }
-        "-pM" => {
-            let fname = args.get(2).map(|s| s.as_str()).unwrap_or("serverstats_grab.dat");
-            playback_mem(fname)
+        "-c" => {
+            let opts = parse_time_opts(&args[2..]);
+            if opts.files.len() != 2 && opts.files.len() != 4 {
+                eprintln!("ERROR: -c needs <before.dat> <after.dat> [before-multipath-ll.txt after-multipath-ll.txt]");
+                usage();
+                std::process::exit(1);
+            }
+            // --from/--to are resolved against each capture
+            let before = opts.resolve(&opts.files[0])?;
+            let after = opts.resolve(&opts.files[1])?;
+            if opts.follow {
+                eprintln!("WARN: -c reads whole captures, ignoring --follow");
+            }
+            if opts.out.filter.is_some() {
+                eprintln!("WARN: --where applies to playback tables, ignoring it for -c");
+            }
+            if opts.sel.top.is_some() || opts.sel.sort.is_some() {
+                eprintln!("WARN: --top/--sort pick devices per interval, -c compares every selected device");
+            }
+            let maps = (opts.files.len() == 4)
+                .then(|| (mpath::parse_multipath_ll(&opts.files[2]), mpath::parse_multipath_ll(&opts.files[3])));
+            compare::compare(&opts.files[0], &opts.files[1], before, after, maps.as_ref(), &opts.sel, &opts.ifaces, opts.bucket)
         }
-        "-pN" => {
-            let fname = args.get(2).map(|s| s.as_str()).unwrap_or("serverstats_grab.dat");
-            playback_net(fname)
+        "-i" => {
+            let opts = parse_time_opts(&args[2..]);
+            let file_path = opts.file(0);
+            let (mut ctx, window) = opts.resolve(file_path)?;
+            if opts.follow {
+                eprintln!("WARN: -i loads the capture once, ignoring --follow");
+            }
+            if opts.out.filter.is_some() {
+                eprintln!("WARN: --where applies to playback tables, ignoring it for -i");
+            }
+            tui::browse(file_path, &mut ctx, &window)
         }
-
-        "-a" => {
-            let fname = args.get(2).map(|s| s.as_str()).unwrap_or("serverstats_grab.dat");
-            analyze::analyze(fname)
+        "-r" => {
+            let opts = parse_time_opts(&args[2..]);
+            let file_path = opts.file(0);
+            let (mut ctx, window) = opts.resolve(file_path)?;
+            if opts.follow {
+                eprintln!("WARN: -r writes a snapshot of the capture, ignoring --follow");
+            }
+            if opts.out.filter.is_some() {
+                eprintln!("WARN: --where applies to playback tables, ignoring it for -r");
+            }
+            report::write_report(file_path, opts.files.get(1).map(|s| s.as_str()), &mut ctx, &window, opts.bucket)
         }
-            "-pMpath" => {
-        let mp_ll = args.get(2).expect("multipath-ll.txt required");
-        let dat = args.get(3).expect("capturefile.dat required");
-        mpath::report_mpath_stats(mp_ll, dat)
+        "-pMpath" => {
+            let opts = parse_time_opts(&args[2..]);
+            let mp_ll = opts.files.first().expect("multipath-ll.txt required");
+            let dat = opts.files.get(1).expect("capturefile.dat required");
+            let (_ctx, window) = opts.resolve(dat)?;
+            if opts.follow {
+                eprintln!("WARN: -pMpath summarizes the whole capture, ignoring --follow");
+            }
+            mpath::report_mpath_stats(mp_ll, dat, &window, &opts.out)
+        }
+        "-pT" => {
+            let opts = parse_time_opts(&args[2..]);
+            let dat = opts.file(0);
+            let (mut ctx, window) = opts.resolve(dat)?;
+            if opts.out.filter.is_some() {
+                eprintln!("WARN: --where applies to playback tables, ignoring it for -pT");
+            }
+            transport::report_transport(dat, opts.files.get(1).map(|s| s.as_str()), &mut ctx, &window, opts.follow)
+        }
+        "-t" => {
+            let opts = parse_time_opts(&args[2..]);
+            let [dat, out_path] = opts.files.as_slice() else {
+                eprintln!("ERROR: -t needs <capturefile> <out.dat>");
+                usage();
+                std::process::exit(1);
+            };
+            if fs::canonicalize(out_path).ok().is_some_and(|p| fs::canonicalize(dat).ok() == Some(p)) {
+                eprintln!("ERROR: -t would overwrite {} with its own slice, give another output file", dat);
+                std::process::exit(1);
+            }
+            let (ctx, window) = opts.resolve(dat)?;
+            if opts.follow {
+                eprintln!("WARN: -t writes a snapshot of the capture, ignoring --follow");
+            }
+            if opts.sel.no_idle || opts.sel.top.is_some() || opts.sel.sort.is_some() {
+                eprintln!("WARN: -t keeps whole samples, ignoring --no-idle/--top/--sort");
+            }
+            if !opts.out.is_default_text() || opts.bucket.is_some() {
+                eprintln!("WARN: -t writes capture records, ignoring --format/--columns/--where/--bucket");
+            }
+            let done = extract::extract(dat, out_path, &window, &opts.sel, &opts.ifaces)?;
+            if done.skipped > 0 {
+                eprintln!("WARN: skipped {} unreadable line(s) of {}", done.skipped, dat);
+            }
+            match (done.first, done.last) {
+                (Some(first), Some(last)) => println!("Wrote {} records ({} to {}, {}) to {}", done.records,
+                    ctx.format(first, "%Y-%m-%d %H:%M:%S"), ctx.format(last, "%Y-%m-%d %H:%M:%S"), ctx.label(), out_path),
+                _ => eprintln!("WARN: nothing in the selected window, {} has only the header", out_path),
+            }
+            Ok(())
         }
 
         _ => {
@@ -771,3 +1173,104 @@ fn main() -> std::io::Result<()> {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serverstats_format::testdir::TempDir;
+    use std::path::PathBuf;
+
+    fn fixture_root() -> SysRoot {
+        SysRoot::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/root"))
+    }
+
+    /// Runs gather for `samples` intervals against the fixture tree and returns the capture lines.
+    fn gather_fixture(name: &str, samples: u64) -> Vec<String> {
+        let dir = TempDir::new(&format!("serverstats_grab-test-{}", name));
+        let out = dir.join("serverstats_grab-test.dat");
+        let out = out.to_str().unwrap();
+        let mut guard = DiskGuard::new(out, "serverstats_grab-", ".dat", 0.0, 0.0, SpaceAction::Pause);
+        gather(0, out, &mut guard, &fixture_root(), Some(samples)).unwrap();
+        let text = std::fs::read_to_string(out).unwrap();
+        text.lines().map(|l| l.to_string()).collect()
+    }
+
+    fn records<'a>(lines: &'a [String], typ: &str) -> Vec<Vec<&'a str>> {
+        lines
+            .iter()
+            .filter(|l| l.split(',').next() == Some(typ))
+            .map(|l| l.split(',').collect())
+            .collect()
+    }
+
+    #[test]
+    fn gather_reads_disk_cpu_mem_net_from_root() {
+        let lines = gather_fixture("basic", 2);
+        assert_eq!(lines[0], "#TYPE,ts_epoch,<fields...>");
+        // HOST once per gather, from the fixture's kernel sysctls and cpuN lines
+        let hosts = records(&lines, "HOST");
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(&hosts[0][2..], ["db01.lab", "5.14.0-427.13.1.el9_4.x86_64", "x86_64", "2"]);
+        assert_eq!(records(&lines, "TZ").len(), 1);
+        // One DISKLAYOUT at start; the fixture kernel has 20-field diskstats
+        let layouts = records(&lines, "DISKLAYOUT");
+        assert_eq!(layouts.len(), 1);
+        assert_eq!(layouts[0][2], "20");
+
+        // sr0 is filtered out, everything else in the fixture diskstats is kept
+        let disks = records(&lines, "DISK");
+        let names: Vec<&str> = disks.iter().take(disks.len() / 2).map(|r| r[4]).collect();
+        assert_eq!(names, ["sda", "sda1", "sdb", "sdc", "sdd", "nvme0n1", "md0", "drbd0"]);
+        let sdb = &disks[2];
+        assert_eq!(sdb.len(), 2 + 3 + 17);
+        assert_eq!(&sdb[5..9], ["52110", "0", "4168800", "31022"]);
+        assert_eq!(sdb[16], "310");
+        let nvme = &disks[5];
+        assert_eq!(&nvme[20..], ["88012", "41220"]);
+
+        let cpu = records(&lines, "CPU");
+        assert_eq!(cpu.len(), 2);
+        assert_eq!(&cpu[0][2..], ["1822035", "1205", "610233", "48211022", "30122", "0", "12011", "0", "0", "3", "1"]);
+
+        let mem = records(&lines, "MEM");
+        assert_eq!(mem[0][2], "32617020");
+        assert_eq!(mem[0][15], "912204");
+
+        let net = records(&lines, "NET");
+        assert_eq!(net.len(), 4);
+        assert_eq!(&net[1][2..5], ["eth0", "9912203344", "4410220112"]);
+    }
+
+    #[test]
+    fn gather_reads_sysfs_and_raid_from_root() {
+        let lines = gather_fixture("sysfs", 2);
+
+        // NETX only for interfaces with a sysfs entry, written before their NET row
+        let netx = records(&lines, "NETX");
+        assert_eq!(netx.len(), 2);
+        assert_eq!(&netx[0][2..], ["eth0", "3", "0", "1", "0", "1203", "25000", "9000", "up", "4", "full"]);
+        let first_eth0 = lines.iter().position(|l| l.starts_with("NET,") && l.contains(",eth0,")).unwrap();
+        assert!(lines[first_eth0 - 1].starts_with("NETX,"));
+
+        let md = records(&lines, "MD");
+        assert_eq!(md.len(), 2);
+        assert_eq!(&md[0][2..], ["md0", "raid1", "clean", "0", "2", "resync", "460800", "2093056", "11520", "0", "sdd;sda1"]);
+
+        let drbd = records(&lines, "DRBD");
+        assert_eq!(&drbd[0][2..6], ["drbd0", "SyncSource", "Primary/Secondary", "UpToDate/Inconsistent"]);
+        assert_eq!(&drbd[0][10..], ["524288", "50.0", "12288"]);
+
+        assert_eq!(records(&lines, "FC").len(), 4);
+        assert_eq!(records(&lines, "ISCSI").len(), 2);
+        // SCSI addresses only when the device set changes
+        let scsi = records(&lines, "SCSIDEV");
+        assert_eq!(scsi.len(), 3);
+        assert_eq!(&scsi[0][2..], ["sdb", "5:0:0:1"]);
+    }
+
+    #[test]
+    fn gather_count_zero_writes_header_only() {
+        let lines = gather_fixture("zero", 0);
+        assert_eq!(lines, ["#TYPE,ts_epoch,<fields...>"]);
+    }
+}
@@ -11,6 +11,9 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+use serverstats_format::output::{Col, OutputOpts, Table};
+use serverstats_format::timeutil::TimeWindow;
+
 use crate::analyze::get_disk_metrics_map;
 
 #[derive(Debug)]
@@ -94,9 +97,33 @@ pub fn parse_multipath_ll(path: &str) -> Vec<MultipathDevice> {
 }
 
 
-pub fn report_mpath_stats(multipath_path: &str, dat_path: &str) -> std::io::Result<()> {
+/// Columns for --format/--columns: one row per active path, with its mpath totals.
+const COLS: &[Col] = &[
+    Col::new("mpath", "MPATH").l(8),
+    Col::new("dm", "DM").l(9),
+    Col::new("device", "DEV").l(24),
+    Col::new("attrs", "ATTRS").l(52),
+    Col::new("mpath_iops", "IOPS").r(9).prec(1),
+    Col::new("mpath_kbs", "KB/sec").r(10).prec(1),
+    Col::new("path", "PATH").l(10),
+    Col::new("path_iops", "PATH_IOPS").r(9).prec(1),
+    Col::new("path_kbs", "PATH_KB/sec").r(11).prec(1),
+    Col::new("io_pct", "IO%").r(5).prec(1),
+    Col::new("kb_pct", "KB%").r(5).prec(1),
+];
+
+pub fn report_mpath_stats(multipath_path: &str, dat_path: &str, window: &TimeWindow, out: &OutputOpts) -> std::io::Result<()> {
     let devices = parse_multipath_ll(multipath_path);
-    let disk_metrics = get_disk_metrics_map(dat_path)?;
+    let disk_metrics = get_disk_metrics_map(dat_path, window)?;
+    // The default text report keeps its grouped layout
+    let mut table = if out.is_default_text() {
+        None
+    } else {
+        Some(Table::new(COLS, out).unwrap_or_else(|e| {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
+        }))
+    };
 
     for mdev in &devices {
         let mut mpath_total_iops = 0.0;
@@ -144,6 +171,18 @@ pub fn report_mpath_stats(multipath_path: &str, dat_path: &str) -> std::io::Resu
                 )
             };
 
+        if let Some(table) = table.as_mut() {
+            for (dev, iops, kbs) in &per_path {
+                table.row(None, &[
+                    mdev.name.as_str().into(), mdev.dm_name.as_str().into(), dev_field.as_str().into(),
+                    attr_field.as_str().into(), mpath_total_iops.into(), mpath_total_kbs.into(),
+                    dev.as_str().into(), (*iops).into(), (*kbs).into(),
+                    pct_of(*iops, mpath_total_iops).into(), pct_of(*kbs, mpath_total_kbs).into(),
+                ]);
+            }
+            continue;
+        }
+
         // Header BEFORE EACH MPATH GROUP
         println!(
             "{:<8} {:<9} {:<24} {:<52} {:>9} {:>10}",
@@ -164,16 +203,8 @@ pub fn report_mpath_stats(multipath_path: &str, dat_path: &str) -> std::io::Resu
 
         // Path rows
         for (dev, iops, kbs) in &per_path {
-            let io_pct = if mpath_total_iops > 0.0 {
-                100.0 * iops / mpath_total_iops
-            } else {
-                0.0
-            };
-            let kb_pct = if mpath_total_kbs > 0.0 {
-                100.0 * kbs / mpath_total_kbs
-            } else {
-                0.0
-            };
+            let io_pct = pct_of(*iops, mpath_total_iops);
+            let kb_pct = pct_of(*kbs, mpath_total_kbs);
 
             println!(
                 "    {:<10} IOPS:{:>8.1} KB/sec:{:>10.1} (IO%:{:>5.1} KB%:{:>5.1})",
@@ -183,7 +214,15 @@ pub fn report_mpath_stats(multipath_path: &str, dat_path: &str) -> std::io::Resu
 
         println!(); // spacing between groups
     }
+    if let Some(table) = table.as_mut() {
+        table.finish("No active multipath devices found.");
+    }
 
     Ok(())
 }
 
+/// Share of the mpath total, 0 when the total is 0.
+fn pct_of(part: f64, total: f64) -> f64 {
+    if total > 0.0 { 100.0 * part / total } else { 0.0 }
+}
+
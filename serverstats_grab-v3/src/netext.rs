@@ -0,0 +1,129 @@
+/*!
+ * Extended Network Statistics Module
+ * ----------------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * /proc/net/dev only has the basic counters. For storage networks (iSCSI, NFS,
+ * NVMe/TCP) the interesting ones live in /sys/class/net/<iface>/: CRC, FIFO and
+ * missed errors, plus link speed, MTU, operstate and carrier_changes, which
+ * show link flaps and speed renegotiation.
+ *
+ * Written to the capture as a NETX record (serverstats_format::NetExt) just
+ * before the NET record of the same interface. speed_mbps is -1 when the driver
+ * does not report it (virtual interfaces, link down), duplex is then "unknown".
+ */
+
+use std::fs;
+
+pub use serverstats_format::{fmt_speed, NetExt};
+
+use crate::sysroot::SysRoot;
+
+/// Reads <sys>/class/net/<iface>; None when the interface has no sysfs entry.
+pub fn read_netext(root: &SysRoot, iface: &str) -> Option<NetExt> {
+    let base = root.sys().join("class/net").join(iface);
+    let read = |p: &str| fs::read_to_string(base.join(p)).ok().map(|s| s.trim().to_string());
+    let stat = |name: &str| read(&format!("statistics/{}", name)).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+    let operstate = read("operstate")?;
+    Some(NetExt {
+        rx_crc_errors: stat("rx_crc_errors"),
+        rx_fifo_errors: stat("rx_fifo_errors"),
+        rx_missed_errors: stat("rx_missed_errors"),
+        collisions: stat("collisions"),
+        multicast: stat("multicast"),
+        // Reading speed fails with EINVAL when the link is down; older drivers report
+        // SPEED_UNKNOWN as 65535 or 4294967295 rather than -1
+        speed_mbps: read("speed").and_then(|v| v.parse().ok())
+            .filter(|&s: &i64| s > 0 && s != 65535 && s != 4294967295)
+            .unwrap_or(-1),
+        mtu: read("mtu").and_then(|v| v.parse().ok()).unwrap_or(0),
+        operstate,
+        carrier_changes: read("carrier_changes").and_then(|v| v.parse().ok()).unwrap_or(0),
+        duplex: read("duplex").filter(|d| d == "full" || d == "half").unwrap_or_else(|| "unknown".to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serverstats_format::testdir::TempDir;
+    use std::path::PathBuf;
+
+    /// A SysRoot with one interface holding `attrs`; a `None` value makes the attribute
+    /// a directory, so reading it fails like an attribute the driver refuses.
+    fn iface_root(attrs: &[(&str, Option<&str>)]) -> (TempDir, SysRoot) {
+        let (dir, root) = SysRoot::temp("netext", &[]);
+        dir.mkdir("sys/class/net/eth0/statistics");
+        for (attr, text) in attrs {
+            let rel = format!("sys/class/net/eth0/{}", attr);
+            match text {
+                Some(t) => { dir.write(&rel, t); }
+                None => { dir.mkdir(&rel); }
+            }
+        }
+        (dir, root)
+    }
+
+    #[test]
+    fn reads_fixture_interface() {
+        let root = SysRoot::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/root"));
+        let e = read_netext(&root, "eth0").unwrap();
+        assert_eq!((e.rx_crc_errors, e.rx_missed_errors, e.multicast, e.speed_mbps, e.mtu), (3, 1, 1203, 25000, 9000));
+        assert_eq!((e.operstate.as_str(), e.carrier_changes, e.duplex.as_str()), ("up", 4, "full"));
+        // No sysfs entry (e.g. an interface that went away): no record
+        assert!(read_netext(&root, "veth9").is_none());
+    }
+
+    #[test]
+    fn sysfs_attributes() {
+        type Attrs<'a> = &'a [(&'a str, Option<&'a str>)];
+        type Want<'a> = Option<(i64, &'a str, &'a str, u64, u32, u64)>;
+        let cases: &[(&str, Attrs, Want)] = &[
+            ("link up",
+             &[("operstate", Some("up\n")), ("speed", Some("10000\n")), ("duplex", Some("full\n")),
+               ("carrier_changes", Some("2\n")), ("mtu", Some("1500\n")), ("statistics/rx_crc_errors", Some("7\n"))],
+             Some((10000, "full", "up", 2, 1500, 7))),
+            ("half duplex",
+             &[("operstate", Some("up")), ("speed", Some("100")), ("duplex", Some("half"))],
+             Some((100, "half", "up", 0, 0, 0))),
+            ("driver reports -1",
+             &[("operstate", Some("up")), ("speed", Some("-1")), ("duplex", Some("unknown"))],
+             Some((-1, "unknown", "up", 0, 0, 0))),
+            ("old driver SPEED_UNKNOWN",
+             &[("operstate", Some("unknown")), ("speed", Some("4294967295")), ("duplex", Some("unknown"))],
+             Some((-1, "unknown", "unknown", 0, 0, 0))),
+            ("link down, speed and duplex refuse reads",
+             &[("operstate", Some("down")), ("speed", None), ("duplex", None), ("carrier_changes", Some("9"))],
+             Some((-1, "unknown", "down", 9, 0, 0))),
+            ("virtual interface, no speed or duplex",
+             &[("operstate", Some("lowerlayerdown")), ("mtu", Some("9000"))],
+             Some((-1, "unknown", "lowerlayerdown", 0, 9000, 0))),
+            ("unreadable and garbled counters",
+             &[("operstate", Some("up")), ("speed", Some("fast")), ("duplex", Some("FULL")), ("carrier_changes", None),
+               ("mtu", Some("")), ("statistics/rx_crc_errors", Some("-3"))],
+             Some((-1, "unknown", "up", 0, 0, 0))),
+            ("no operstate", &[("speed", Some("1000")), ("duplex", Some("full"))], None),
+            ("unreadable operstate", &[("operstate", None)], None),
+        ];
+        for (what, attrs, want) in cases {
+            let (_dir, root) = iface_root(attrs);
+            let got = read_netext(&root, "eth0")
+                .map(|e| (e.speed_mbps, e.duplex.clone(), e.operstate.clone(), e.carrier_changes, e.mtu, e.rx_crc_errors));
+            let got = got.as_ref().map(|(s, d, o, c, m, crc)| (*s, d.as_str(), o.as_str(), *c, *m, *crc));
+            assert_eq!(got, *want, "{}", what);
+        }
+    }
+
+    #[test]
+    fn flaps_between_gathers() {
+        // What -pN flags: the link bounced and came back slower between two samples
+        let (dir, root) = iface_root(&[("operstate", Some("up")), ("speed", Some("25000")),
+                                       ("duplex", Some("full")), ("carrier_changes", Some("4")), ("mtu", Some("9000"))]);
+        let before = read_netext(&root, "eth0").unwrap();
+        dir.write("sys/class/net/eth0/carrier_changes", "6\n");
+        dir.write("sys/class/net/eth0/speed", "10000\n");
+        let after = read_netext(&root, "eth0").unwrap();
+        assert_eq!(after.link_changes(&before), ["link flap: carrier_changes +2", "speed 25000Mb/s -> 10000Mb/s"]);
+        assert!(after.link_changes(&after).is_empty());
+    }
+}
@@ -0,0 +1,267 @@
+/*!
+ * Percentile Statistics Module
+ * ----------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Distribution of one disk metric over a device's intervals for the `-a`
+ * tables and dashboard: mean, standard deviation, p50/p90/p95/p99/p99.9, max,
+ * and for latency and queue metrics the time spent above a threshold.
+ *
+ * The peak is one interval and the mean hides a sustained tail; p99 and the
+ * time above 20 ms show whether a device is slow now and then or all day.
+ * Percentiles are nearest-rank over the intervals (every interval counts once,
+ * as in the top50 tables); time above threshold adds up the interval lengths.
+ *
+ * Values are added one interval at a time (`DistAcc`), so week-long captures
+ * need no per-interval storage: the first EXACT_VALUES values are kept as is,
+ * after that they go into log-spaced buckets and percentiles are within
+ * HIST_ERROR of the exact value. Mean, stddev, max and time above are exact.
+ */
+
+use std::fmt::Write as _;
+
+use serverstats_format::output::json_str;
+
+/// Percentile columns, in table order.
+pub(crate) const PERCENTILES: [(&str, f64); 5] = [("p50", 50.0), ("p90", 90.0), ("p95", 95.0), ("p99", 99.0), ("p99.9", 99.9)];
+
+/// Metrics with a time-above-threshold column, and the threshold (same as the findings warning levels).
+const THRESHOLDS: &[(&str, f64)] = &[
+    ("await_rd", 20.0),
+    ("await_wr", 20.0),
+    ("await_discard_ms", 20.0),
+    ("await_flush_ms", 20.0),
+    ("svctim", 10.0),
+    ("qlen", 32.0),
+    ("avg_queue_depth", 32.0),
+];
+
+pub(crate) fn threshold(metric: &str) -> Option<f64> {
+    THRESHOLDS.iter().find(|(k, _)| *k == metric).map(|(_, t)| *t)
+}
+
+/// Values kept exactly before switching to the histogram.
+const EXACT_VALUES: usize = 256;
+/// Relative error of percentiles from the histogram.
+const HIST_ERROR: f64 = 0.01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Distribution {
+    pub(crate) mean: f64,
+    pub(crate) stddev: f64,
+    pub(crate) max: f64,
+    /// Values at PERCENTILES
+    pub(crate) pct: [f64; 5],
+    /// Seconds above the metric's threshold, None for metrics without one
+    pub(crate) above_secs: Option<u64>,
+    pub(crate) total_secs: u64,
+}
+
+impl Distribution {
+    /// From (value, interval length) pairs; None when there are no finite values.
+    #[cfg(test)]
+    pub(crate) fn of(values: &[(f64, u64)], threshold: Option<f64>) -> Option<Self> {
+        let mut acc = DistAcc::new(threshold);
+        for (v, dt) in values {
+            acc.add(*v, *dt);
+        }
+        acc.finish()
+    }
+
+    /// Value at one of the PERCENTILES by name ("p95").
+    pub(crate) fn at(&self, name: &str) -> f64 {
+        PERCENTILES.iter().position(|(n, _)| *n == name).map_or(f64::NAN, |i| self.pct[i])
+    }
+
+    /// Time above threshold as "SECS (PCT%)", "-" without a threshold.
+    pub(crate) fn above_text(&self) -> String {
+        match self.above_secs {
+            Some(s) if self.total_secs > 0 => format!("{}s ({:.1}%)", s, s as f64 / self.total_secs as f64 * 100.0),
+            Some(s) => format!("{}s", s),
+            None => "-".to_string(),
+        }
+    }
+}
+
+/// Log-spaced counts: bucket `k` holds values in (gamma^(k-1), gamma^k].
+#[derive(Debug, Default)]
+struct Hist {
+    /// Values <= 0
+    zeros: u64,
+    offset: i32,
+    counts: Vec<u64>,
+}
+
+impl Hist {
+    fn gamma() -> f64 {
+        (1.0 + HIST_ERROR) / (1.0 - HIST_ERROR)
+    }
+
+    fn add(&mut self, v: f64) {
+        if v <= 0.0 {
+            self.zeros += 1;
+            return;
+        }
+        let k = (v.ln() / Self::gamma().ln()).ceil() as i32;
+        if self.counts.is_empty() {
+            self.offset = k;
+        } else if k < self.offset {
+            let grow = (self.offset - k) as usize;
+            self.counts.splice(0..0, std::iter::repeat_n(0, grow));
+            self.offset = k;
+        }
+        let idx = (k - self.offset) as usize;
+        if idx >= self.counts.len() {
+            self.counts.resize(idx + 1, 0);
+        }
+        self.counts[idx] += 1;
+    }
+
+    /// Value of the `rank`th smallest (1-based): the bucket's midpoint, 0 for the zero bucket.
+    fn at_rank(&self, rank: u64) -> f64 {
+        if rank <= self.zeros { return 0.0; }
+        let mut seen = self.zeros;
+        let g = Self::gamma();
+        for (i, c) in self.counts.iter().enumerate() {
+            seen += c;
+            if seen >= rank {
+                return 2.0 * g.powi(self.offset + i as i32) / (g + 1.0);
+            }
+        }
+        f64::NAN
+    }
+}
+
+/// Builds a Distribution one interval at a time in bounded memory.
+#[derive(Debug)]
+pub(crate) struct DistAcc {
+    threshold: Option<f64>,
+    /// Finite values seen, running mean and sum of squared deviations (Welford)
+    n: u64,
+    mean: f64,
+    m2: f64,
+    min: f64,
+    max: f64,
+    above_secs: u64,
+    total_secs: u64,
+    exact: Vec<f64>,
+    hist: Option<Hist>,
+}
+
+impl DistAcc {
+    pub(crate) fn new(threshold: Option<f64>) -> Self {
+        DistAcc {
+            threshold, n: 0, mean: 0.0, m2: 0.0, min: f64::INFINITY, max: f64::NEG_INFINITY,
+            above_secs: 0, total_secs: 0, exact: Vec::new(), hist: None,
+        }
+    }
+
+    /// One interval's value and length.
+    pub(crate) fn add(&mut self, v: f64, dt: u64) {
+        self.total_secs += dt;
+        if self.threshold.is_some_and(|t| v > t) {
+            self.above_secs += dt;
+        }
+        if !v.is_finite() { return; }
+        self.n += 1;
+        let delta = v - self.mean;
+        self.mean += delta / self.n as f64;
+        self.m2 += delta * (v - self.mean);
+        self.min = self.min.min(v);
+        self.max = self.max.max(v);
+        match self.hist.as_mut() {
+            Some(h) => h.add(v),
+            None if self.exact.len() < EXACT_VALUES => self.exact.push(v),
+            None => {
+                let mut h = Hist::default();
+                for x in self.exact.drain(..).chain([v]) {
+                    h.add(x);
+                }
+                self.exact = Vec::new();
+                self.hist = Some(h);
+            }
+        }
+    }
+
+    /// None when no finite value was added.
+    pub(crate) fn finish(mut self) -> Option<Distribution> {
+        if self.n == 0 { return None; }
+        self.exact.sort_by(f64::total_cmp);
+        let n = self.n as f64;
+        let rank = |p: f64| ((p / 100.0 * n).ceil() as u64).clamp(1, self.n);
+        let pct = PERCENTILES.map(|(_, p)| match &self.hist {
+            Some(h) => h.at_rank(rank(p)).clamp(self.min, self.max),
+            None => self.exact[rank(p) as usize - 1],
+        });
+        Some(Distribution {
+            mean: self.mean,
+            stddev: (self.m2 / n).sqrt(),
+            max: self.max,
+            pct,
+            above_secs: self.threshold.map(|_| self.above_secs),
+            total_secs: self.total_secs,
+        })
+    }
+}
+
+/// `top50_<metric>_<rank>.txt` body: devices ranked by one percentile, with the whole distribution.
+pub(crate) fn ranked_table(fname: &str, metric: &str, rank_by: &str, rows: &[(&str, &Distribution)]) -> String {
+    let mut s = format!("{fname}\nMetric: {metric} (ranked by {rank_by})\n");
+    if let Some(t) = threshold(metric) {
+        let _ = writeln!(s, "Above: time with {} > {}", metric, t);
+    }
+    s.push('\n');
+    let _ = write!(s, "{:<5} {:<16} {:>10} {:>10}", "Rank", "Device", "Mean", "StdDev");
+    for (name, _) in PERCENTILES {
+        let _ = write!(s, " {:>10}", name.to_uppercase());
+    }
+    let _ = writeln!(s, " {:>10} {:>16}", "Max", "Above");
+    let _ = writeln!(s, "{}", "-".repeat(5 + 1 + 16 + 11 * 8 + 17));
+    for (i, (dev, d)) in rows.iter().take(50).enumerate() {
+        let _ = write!(s, "{:<5} {:<16} {:>10.2} {:>10.2}", i + 1, dev, d.mean, d.stddev);
+        for v in d.pct {
+            let _ = write!(s, " {:>10.2}", v);
+        }
+        let _ = writeln!(s, " {:>10.2} {:>16}", d.max, d.above_text());
+    }
+    s
+}
+
+/// `{"metric":[mean,stddev,p50,...,p99.9,max,"above"],...}` for one device on the dashboard.
+pub(crate) fn device_json(metrics: &[(&str, &Distribution)]) -> String {
+    let fields: Vec<String> = metrics.iter().map(|(key, d)| {
+        let nums: Vec<String> = [d.mean, d.stddev].into_iter().chain(d.pct).chain([d.max])
+            .map(|v| format!("{:.2}", v))
+            .collect();
+        format!("{}:[{},{}]", json_str(key), nums.join(","), json_str(&d.above_text()))
+    }).collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_and_time_above() {
+        // 1000 one-second intervals at 1..=1000 ms, plus a 10 s interval at 5000 ms
+        let mut values: Vec<(f64, u64)> = (1..=1000).map(|v| (v as f64, 1)).collect();
+        values.push((5000.0, 10));
+        let d = Distribution::of(&values, threshold("await_wr")).unwrap();
+        // Past EXACT_VALUES the percentiles come from the histogram
+        for (got, exact) in d.pct.iter().zip([501.0, 901.0, 951.0, 991.0, 1000.0]) {
+            assert!((got - exact).abs() <= exact * HIST_ERROR, "{} vs {}", got, exact);
+        }
+        assert_eq!(d.max, 5000.0);
+        assert!((d.mean - 504.995).abs() < 0.001, "{}", d.mean);
+        // 21..=1000 plus the 10 s interval
+        assert_eq!((d.above_secs, d.total_secs), (Some(990), 1010));
+        assert_eq!(d.above_text(), "990s (98.0%)");
+
+        let values: Vec<(f64, u64)> = (1..=200).map(|v| (v as f64, 1)).collect();
+        assert_eq!(Distribution::of(&values, None).unwrap().pct, [100.0, 180.0, 190.0, 198.0, 200.0]);
+        let d = Distribution::of(&[(2.0, 5), (4.0, 5)], None).unwrap();
+        assert_eq!((d.mean, d.stddev, d.pct[0], d.above_text().as_str()), (3.0, 1.0, 2.0, "-"));
+        assert!(Distribution::of(&[(f64::NAN, 1)], None).is_none());
+    }
+}
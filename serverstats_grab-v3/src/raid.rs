@@ -0,0 +1,354 @@
+/*!
+ * md RAID / DRBD Status Module
+ * ----------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Captures the state of md arrays (/proc/mdstat plus /sys/block/mdN/md/, the
+ * mdstat status and progress lines standing in for missing sysfs attributes) and
+ * DRBD 8.x resources (/proc/drbd) so resync and rebuild windows can be lined up
+ * with member disk throughput. A rebuild is invisible in diskstats alone.
+ *
+ * Written to the capture as MD and DRBD records (serverstats_format::MdStatus
+ * and DrbdStatus).
+ */
+
+use std::fs;
+
+pub use serverstats_format::{DrbdStatus, MdStatus};
+
+use crate::sysroot::SysRoot;
+
+fn read_trim(path: &std::path::Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// What /proc/mdstat says about one array, for attributes sysfs does not have.
+#[derive(Default)]
+struct MdstatArray<'a> {
+    name: &'a str,
+    /// Words after " : ", e.g. active raid1 sdb1[1] sda1[0](F)
+    words: Vec<&'a str>,
+    /// From "[2/1] [U_]": raid disks and how many are missing
+    raid_disks: Option<u32>,
+    degraded: Option<u32>,
+    /// From "resync = 22.0% (230400/1046528) ... speed=11520K/sec" or "resync=DELAYED"
+    sync_action: Option<String>,
+    /// Progress in sectors, like sync_completed (mdstat counts 1K blocks)
+    sync: Option<(u64, u64)>,
+    speed_kb: Option<u64>,
+}
+
+const MD_SYNC_ACTIONS: [&str; 5] = ["resync", "recovery", "check", "repair", "reshape"];
+
+/// Splits /proc/mdstat into arrays: the "mdN : ..." line and the indented lines under it.
+fn parse_mdstat(text: &str) -> Vec<MdstatArray<'_>> {
+    let mut out: Vec<MdstatArray> = Vec::new();
+    for line in text.lines() {
+        // md0 : active raid1 sdb1[1] sda1[0](F)
+        if let Some((name, rest)) = line.split_once(" : ").filter(|(n, _)| n.trim().starts_with("md")) {
+            out.push(MdstatArray { name: name.trim(), words: rest.split_whitespace().collect(), ..Default::default() });
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) { continue; }
+        let Some(md) = out.last_mut() else { continue };
+        let t = line.trim();
+        //       1046528 blocks super 1.2 [2/1] [U_]
+        if let Some((n, m)) = t.split_whitespace()
+            .filter_map(|w| w.strip_prefix('[')?.strip_suffix(']')?.split_once('/'))
+            .find_map(|(n, m)| Some((n.parse::<u32>().ok()?, m.parse::<u32>().ok()?)))
+        {
+            md.raid_disks = Some(n);
+            md.degraded = Some(n.saturating_sub(m));
+        }
+        //       [====>................]  resync = 22.0% (230400/1046528) finish=1.2min speed=11520K/sec
+        let body = if t.starts_with('[') { t.split_once(']').map_or("", |(_, b)| b) } else { t };
+        let Some((key, rest)) = body.split_once('=') else { continue };
+        let key = key.trim();
+        if !MD_SYNC_ACTIONS.contains(&key) { continue; }
+        md.sync_action = Some(key.to_string());
+        md.sync = rest.split_once('(')
+            .and_then(|(_, r)| r.split_once(')'))
+            .and_then(|(p, _)| p.split_once('/'))
+            .and_then(|(d, t)| Some((d.trim().parse::<u64>().ok()? * 2, t.trim().parse::<u64>().ok()? * 2)));
+        md.speed_kb = rest.split_once("speed=")
+            .and_then(|(_, v)| v.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok());
+    }
+    out
+}
+
+/// Reads all md arrays listed in /proc/mdstat, with detail from sysfs.
+/// Returns an empty list when md is not loaded.
+pub fn read_md_status(root: &SysRoot) -> Vec<MdStatus> {
+    let Ok(mdstat) = fs::read_to_string(root.proc("mdstat")) else { return Vec::new() };
+    let mut out = Vec::new();
+    for md in parse_mdstat(&mdstat) {
+        let name = md.name;
+        let words = &md.words;
+        let members: Vec<String> = words
+            .iter()
+            .filter(|w| w.contains('['))
+            .map(|w| w.split('[').next().unwrap_or("").to_string())
+            .filter(|m| !m.is_empty())
+            .collect();
+        let mdstat_level = words.iter().find(|w| w.starts_with("raid") || **w == "linear").copied().unwrap_or("");
+
+        let sys = root.sys().join("block").join(name).join("md");
+        let sysval = |attr: &str| read_trim(&sys.join(attr));
+        // sync_completed and sync_speed read "none" when idle; mdstat only stands in when they are missing
+        let (sync_done, sync_total) = match sysval("sync_completed") {
+            Some(s) => s.split_once('/')
+                .and_then(|(d, t)| Some((d.trim().parse().ok()?, t.trim().parse().ok()?)))
+                .unwrap_or((0, 0)),
+            None => md.sync.unwrap_or((0, 0)),
+        };
+        let sync_action = sysval("sync_action")
+            .or_else(|| md.sync_action.clone())
+            .unwrap_or_else(|| "-".to_string());
+        out.push(MdStatus {
+            name: name.to_string(),
+            level: sysval("level").unwrap_or_else(|| mdstat_level.to_string()),
+            array_state: sysval("array_state").unwrap_or_else(|| words.first().unwrap_or(&"").to_string()),
+            degraded: sysval("degraded").and_then(|v| v.parse().ok()).or(md.degraded).unwrap_or(0),
+            raid_disks: sysval("raid_disks").and_then(|v| v.parse().ok()).or(md.raid_disks).unwrap_or(members.len() as u32),
+            sync_speed_kb: match sysval("sync_speed") {
+                _ if sync_action == "idle" => 0,
+                Some(v) => v.parse().unwrap_or(0),
+                None => md.speed_kb.unwrap_or(0),
+            },
+            sync_action,
+            sync_done,
+            sync_total,
+            mismatch_cnt: sysval("mismatch_cnt").and_then(|v| v.parse().ok()).unwrap_or(0),
+            members,
+        });
+    }
+    out
+}
+
+/// Reads DRBD 8.x resources from /proc/drbd.
+/// DRBD 9 only reports the version there (use drbdsetup events2), so this returns nothing.
+pub fn read_drbd_status(root: &SysRoot) -> Vec<DrbdStatus> {
+    let Ok(text) = fs::read_to_string(root.proc("drbd")) else { return Vec::new() };
+    let mut out: Vec<DrbdStatus> = Vec::new();
+    for line in text.lines() {
+        let t = line.trim();
+        if let Some((minor, rest)) = t.split_once(": cs:").filter(|(m, _)| !m.is_empty() && m.chars().all(|c| c.is_ascii_digit())) {
+            // 0: cs:SyncSource ro:Primary/Secondary ds:UpToDate/Inconsistent C r-----
+            let mut st = DrbdStatus {
+                name: format!("drbd{}", minor),
+                cs: rest.split_whitespace().next().unwrap_or("").to_string(),
+                ro: String::new(),
+                ds: String::new(),
+                ns: 0, nr: 0, dw: 0, dr: 0, oos: 0,
+                sync_pct: 0.0,
+                speed_kb: 0,
+            };
+            for w in rest.split_whitespace() {
+                if let Some(v) = w.strip_prefix("ro:") { st.ro = v.to_string(); }
+                if let Some(v) = w.strip_prefix("ds:") { st.ds = v.to_string(); }
+            }
+            out.push(st);
+            continue;
+        }
+        let Some(cur) = out.last_mut() else { continue };
+        if t.starts_with("ns:") {
+            // ns:0 nr:0 dw:0 dr:0 al:0 bm:0 lo:0 pe:0 ua:0 ap:0 ep:1 wo:f oos:0
+            for w in t.split_whitespace() {
+                let Some((k, v)) = w.split_once(':') else { continue };
+                let v = v.parse::<u64>().unwrap_or(0);
+                match k {
+                    "ns" => cur.ns = v,
+                    "nr" => cur.nr = v,
+                    "dw" => cur.dw = v,
+                    "dr" => cur.dr = v,
+                    "oos" => cur.oos = v,
+                    _ => {}
+                }
+            }
+        } else if let Some(idx) = t.find("sync'ed:") {
+            // [=>..................] sync'ed: 10.2% (1234/5678)M
+            cur.sync_pct = t[idx + 8..].trim().split('%').next().and_then(|v| v.trim().parse().ok()).unwrap_or(0.0);
+        } else if let Some(idx) = t.find("speed:") {
+            // finish: 0:01:23 speed: 12,345 (12,345) K/sec
+            cur.speed_kb = t[idx + 6..].split_whitespace().next().map(|v| v.replace(',', "")).and_then(|v| v.parse().ok()).unwrap_or(0);
+        }
+    }
+    out
+}
+
+/// A resync/rebuild period for one array, with the devices it loads.
+#[derive(Debug, Clone)]
+pub struct SyncWindow {
+    /// md or drbd device name
+    pub array: String,
+    /// sync_action (md) or connection state (drbd)
+    pub action: String,
+    pub start: u64,
+    pub end: u64,
+    /// Array device plus members; charts of these devices get the window shaded
+    pub devices: Vec<String>,
+}
+
+/// Builds sync windows from per-interval array samples, fed in capture order.
+#[derive(Default)]
+pub struct SyncTracker {
+    open: std::collections::HashMap<String, SyncWindow>,
+    pub windows: Vec<SyncWindow>,
+}
+
+impl SyncTracker {
+    pub fn sample(&mut self, ts: u64, array: &str, syncing: bool, action: &str, devices: &[String]) {
+        if syncing {
+            let w = self.open.entry(array.to_string()).or_insert_with(|| SyncWindow {
+                array: array.to_string(),
+                action: action.to_string(),
+                start: ts,
+                end: ts,
+                devices: Vec::new(),
+            });
+            w.end = ts;
+            for d in devices {
+                if !w.devices.contains(d) { w.devices.push(d.clone()); }
+            }
+            if w.action != action && !w.action.contains(action) {
+                w.action = format!("{}/{}", w.action, action);
+            }
+        } else if let Some(mut w) = self.open.remove(array) {
+            w.end = ts;
+            self.windows.push(w);
+        }
+    }
+
+    /// Closes windows still open at the end of the capture.
+    pub fn finish(mut self) -> Vec<SyncWindow> {
+        let mut rest: Vec<SyncWindow> = self.open.into_values().collect();
+        rest.sort_by_key(|w| w.start);
+        self.windows.extend(rest);
+        self.windows.sort_by(|a, b| a.start.cmp(&b.start).then(a.array.cmp(&b.array)));
+        self.windows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type MdRow<'a> = (&'a str, &'a str, &'a str, u32, u32, &'a str, u64, u64, u64, Vec<&'a str>);
+
+    fn md_row(m: &MdStatus) -> MdRow<'_> {
+        (m.name.as_str(), m.level.as_str(), m.array_state.as_str(), m.degraded, m.raid_disks, m.sync_action.as_str(),
+         m.sync_done, m.sync_total, m.sync_speed_kb, m.members.iter().map(|s| s.as_str()).collect())
+    }
+
+    #[test]
+    fn mdstat_without_sysfs() {
+        // Each case is one array as /proc/mdstat shows it; sysfs is absent, so everything comes from mdstat
+        let cases: &[(&str, &str, MdRow)] = &[
+            ("resync",
+             "md0 : active raid1 sdd[1] sda1[0]\n      1046528 blocks super 1.2 [2/2] [UU]\n      [====>................]  resync = 22.0% (230400/1046528) finish=1.2min speed=11520K/sec\n",
+             ("md0", "raid1", "active", 0, 2, "resync", 460800, 2093056, 11520, vec!["sdd", "sda1"])),
+            ("recovery, degraded",
+             "md1 : active raid1 sdc1[2] sdb1[0]\n      976630464 blocks super 1.2 [2/1] [U_]\n      [>....................]  recovery =  0.4% (4032000/976630464) finish=80.2min speed=201600K/sec\n      bitmap: 8/8 pages [32KB], 65536KB chunk\n",
+             ("md1", "raid1", "active", 1, 2, "recovery", 8064000, 1953260928, 201600, vec!["sdc1", "sdb1"])),
+            ("check",
+             "md2 : active raid6 sdh[5] sdg[4] sdf[3] sde[2] sdd[1] sdc[0]\n      7813529600 blocks super 1.2 level 6, 512k chunk, algorithm 2 [6/6] [UUUUUU]\n      [===>.................]  check = 17.6% (343829504/1953382400) finish=160.3min speed=167288K/sec\n      bitmap: 0/15 pages [0KB], 65536KB chunk\n",
+             ("md2", "raid6", "active", 0, 6, "check", 687659008, 3906764800, 167288, vec!["sdh", "sdg", "sdf", "sde", "sdd", "sdc"])),
+            ("idle, no bitmap line, failed and spare members",
+             "md3 : active raid5 sdl1[3](S) sdk1[2] sdj1[1](F) sdi1[0]\n      2093056 blocks super 1.2 level 5, 512k chunk, algorithm 2 [3/2] [U_U]\n",
+             ("md3", "raid5", "active", 1, 3, "-", 0, 0, 0, vec!["sdl1", "sdk1", "sdj1", "sdi1"])),
+            ("resync delayed",
+             "md4 : active (auto-read-only) raid1 sdn1[1] sdm1[0]\n      1046528 blocks super 1.2 [2/2] [UU]\n        resync=DELAYED\n",
+             ("md4", "raid1", "active", 0, 2, "resync", 0, 0, 0, vec!["sdn1", "sdm1"])),
+            ("inactive",
+             "md127 : inactive sdo[0](S)\n      1046528 blocks super 1.2\n",
+             ("md127", "", "inactive", 0, 1, "-", 0, 0, 0, vec!["sdo"])),
+        ];
+        for (what, array, want) in cases {
+            let text = format!("Personalities : [raid1] [raid6] [raid5] [raid4]\n{}\nunused devices: <none>\n", array);
+            let (_dir, root) = SysRoot::temp("raid", &[("proc/mdstat", &text)]);
+            let got = read_md_status(&root);
+            assert_eq!(got.iter().map(md_row).collect::<Vec<_>>(), std::slice::from_ref(want), "{}", what);
+        }
+
+        // All of them in one file: detail lines stay with their own array
+        let all: String = cases.iter().map(|(_, a, _)| *a).collect();
+        let (_dir, root) = SysRoot::temp("raid", &[("proc/mdstat", &format!("Personalities : [raid1]\n{}\nunused devices: <none>\n", all))]);
+        let got = read_md_status(&root);
+        assert_eq!(got.iter().map(md_row).collect::<Vec<_>>(), cases.iter().map(|(_, _, w)| w.clone()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn md_sysfs_wins_over_mdstat() {
+        let (_dir, root) = SysRoot::temp("raid", &[
+            ("proc/mdstat", "md0 : active raid1 sdb1[1] sda1[0]\n      1046528 blocks super 1.2 [2/1] [U_]\n      [====>................]  resync = 22.0% (230400/1046528) finish=1.2min speed=11520K/sec\n"),
+            ("sys/block/md0/md/sync_action", "idle\n"),
+            ("sys/block/md0/md/sync_completed", "none\n"),
+            ("sys/block/md0/md/sync_speed", "none\n"),
+            ("sys/block/md0/md/degraded", "0\n"),
+            ("sys/block/md0/md/array_state", "clean\n"),
+            ("sys/block/md0/md/mismatch_cnt", "128\n"),
+        ]);
+        let got = read_md_status(&root);
+        assert_eq!(md_row(&got[0]), ("md0", "raid1", "clean", 0, 2, "idle", 0, 0, 0, vec!["sdb1", "sda1"]));
+        assert_eq!(got[0].mismatch_cnt, 128);
+
+        let (_dir, root) = SysRoot::temp("raid", &[]);
+        assert!(read_md_status(&root).is_empty());
+    }
+
+    type DrbdRow<'a> = (&'a str, &'a str, &'a str, &'a str, [u64; 5], f64, u64);
+
+    fn drbd_row(d: &DrbdStatus) -> DrbdRow<'_> {
+        (d.name.as_str(), d.cs.as_str(), d.ro.as_str(), d.ds.as_str(), [d.ns, d.nr, d.dw, d.dr, d.oos], d.sync_pct, d.speed_kb)
+    }
+
+    #[test]
+    fn proc_drbd_formats() {
+        let cases: &[(&str, &str, Vec<DrbdRow>)] = &[
+            ("8.4, syncing and connected resources",
+             "version: 8.4.11 (api:1/proto:86-101)\n\
+              srcversion: 0F2D2B3E5B8B5A4C2F1D3E4\n \
+              0: cs:SyncSource ro:Primary/Secondary ds:UpToDate/Inconsistent C r-----\n    \
+              ns:1048576 nr:0 dw:729600 dr:352800 al:12 bm:0 lo:0 pe:0 ua:0 ap:0 ep:1 wo:f oos:524288\n\t\
+              [=========>..........] sync'ed: 50.0% (512/1024)M\n\t\
+              finish: 0:00:42 speed: 12,288 (12,100) K/sec\n \
+              1: cs:Connected ro:Secondary/Primary ds:UpToDate/UpToDate C r-----\n    \
+              ns:0 nr:2048 dw:2048 dr:0 al:0 bm:0 lo:0 pe:0 ua:0 ap:0 ep:1 wo:d oos:0\n \
+              2: cs:Unconfigured\n",
+             vec![
+                 ("drbd0", "SyncSource", "Primary/Secondary", "UpToDate/Inconsistent", [1048576, 0, 729600, 352800, 524288], 50.0, 12288),
+                 ("drbd1", "Connected", "Secondary/Primary", "UpToDate/UpToDate", [0, 2048, 2048, 0, 0], 0.0, 0),
+                 ("drbd2", "Unconfigured", "", "", [0; 5], 0.0, 0),
+             ]),
+            ("8.4, sync target just started",
+             "version: 8.4.11 (api:1/proto:86-101)\n \
+              0: cs:SyncTarget ro:Secondary/Primary ds:Inconsistent/UpToDate C r-----\n    \
+              ns:0 nr:4096 dw:4096 dr:0 al:0 bm:0 lo:0 pe:0 ua:0 ap:0 ep:1 wo:f oos:1044480\n\t\
+              [>....................] sync'ed:  0.4% (1020/1024)M\n\t\
+              finish: 0:01:25 speed: 12,000 (12,000) want: 20,480 K/sec\n",
+             vec![("drbd0", "SyncTarget", "Secondary/Primary", "Inconsistent/UpToDate", [0, 4096, 4096, 0, 1044480], 0.4, 12000)]),
+            // DRBD 9 only reports the version here; resources are in drbdsetup events2
+            ("9.x",
+             "version: 9.2.8 (api:2/proto:86-122)\nGIT-hash: e163b05a76254c0f51f999970e861d72bb16409a build by root@db01, 2024-03-01 10:00:00\nTransports (api:21): tcp (9.2.8)\n",
+             vec![]),
+            ("empty", "", vec![]),
+            ("malformed",
+             "ns:5 oos:7\n\
+              x: cs:Connected ro:Primary/Secondary\n\
+              0 cs:Connected\n\
+              : cs:Connected\n \
+              3: cs:\n    \
+              ns:abc nr: dw:-1 oos:12 junk\n\t\
+              [>....] sync'ed: x%\n\t\
+              finish: speed: K/sec\n",
+             vec![("drbd3", "", "", "", [0, 0, 0, 0, 12], 0.0, 0)]),
+        ];
+        for (what, text, want) in cases {
+            let (_dir, root) = SysRoot::temp("raid", &[("proc/drbd", text)]);
+            let got = read_drbd_status(&root);
+            assert_eq!(got.iter().map(drbd_row).collect::<Vec<_>>(), *want, "{}", what);
+        }
+        let (_dir, root) = SysRoot::temp("raid", &[]);
+        assert!(read_drbd_status(&root).is_empty());
+    }
+}
@@ -0,0 +1,564 @@
+/*!
+ * Single-File HTML Report Module
+ * ------------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * `-r <capturefile> [report.html]` writes one self-contained HTML file that can
+ * be mailed or attached to a case and opened straight from disk, offline: the
+ * chart data is embedded as JSON and drawn in the browser on <canvas> by the
+ * small script below (no plotters output, no external scripts or fonts).
+ *
+ *   - Disk and Network tabs with device/interface pickers (filter box,
+ *     multi-select to overlay several devices) and metric checkboxes
+ *   - CPU and Memory tabs
+ *   - mouse wheel zooms, drag pans, double-click resets; all charts share the
+ *     zoomed time range
+ *   - hover shows the time and exact value of every line
+ *
+ * Series are the same as the `-a` charts, read in one streaming pass with the
+ * same trackers (stream.rs) and bucketed the same way: `--bucket`, or
+ * automatically for captures with more than 2000 samples, so the file stays
+ * small enough to open. `--from`/`--to` and `--tz` apply.
+ */
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use serverstats_format::{DiskStat, Reader, Record};
+use serverstats_format::output::json_str;
+use serverstats_format::timeutil::{TimeCtx, TimeWindow};
+
+use crate::analyze::{self, Chart, IntervalDiskMetrics, IntervalNetMetrics, NetRow, DISK_CHARTS, NET_CHARTS};
+use crate::findings;
+use crate::netext::NetExt;
+use crate::stream::{self, MemTracker, Tracker};
+
+/// Chart value: rounded to 3 decimals, null when not a number.
+fn num(v: f64) -> String {
+    if v.is_finite() { format!("{}", (v * 1000.0).round() / 1000.0) } else { "null".to_string() }
+}
+
+fn nums(values: impl IntoIterator<Item = f64>) -> String {
+    values.into_iter().map(num).collect::<Vec<_>>().join(",")
+}
+
+fn times(ts: impl IntoIterator<Item = u64>) -> String {
+    ts.into_iter().map(|t| t.to_string()).collect::<Vec<_>>().join(",")
+}
+
+/// `{"name":..,"t":[..],"v":{"key":[..],..}}` for one device/interface or system series.
+fn entity_json<M>(name: &str, series: &[M], ts: fn(&M) -> u64, metrics: &[Chart<M>]) -> String {
+    let values: Vec<String> = metrics.iter()
+        .map(|(key, _, f)| format!("{}:[{}]", json_str(key), nums(series.iter().map(f))))
+        .collect();
+    format!("{{\"name\":{},\"t\":[{}],\"v\":{{{}}}}}", json_str(name), times(series.iter().map(ts)), values.join(","))
+}
+
+fn metric_list<M>(metrics: &[Chart<M>]) -> String {
+    let list: Vec<String> = metrics.iter().map(|(k, l, _)| format!("[{},{}]", json_str(k), json_str(l))).collect();
+    format!("[{}]", list.join(","))
+}
+
+pub(crate) fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+pub(crate) const CPU_CHARTS: &[Chart<analyze::CpuMetrics>] = &[
+    ("user", "User %", |m| m.user),
+    ("sys", "System %", |m| m.sys),
+    ("iowait", "IOWait %", |m| m.iowait),
+    ("idle", "Idle %", |m| m.idle),
+    ("guest", "Guest %", |m| m.guest),
+    ("running", "Running", |m| m.running.map_or(f64::NAN, |v| v as f64)),
+    ("blocked", "Blocked", |m| m.blocked.map_or(f64::NAN, |v| v as f64)),
+];
+
+const MEM_CHARTS: &[Chart<analyze::MemMetrics>] = &[
+    ("used", "Used %", |m| m.used_percent),
+    ("avail", "Available %", |m| m.avail_percent),
+    ("cached", "Cached %", |m| m.cached_percent),
+    ("free", "Free %", |m| m.free_percent),
+];
+
+/// Writes the report for `file_path` to `out_path` (default `<capture stem>.html`).
+pub fn write_report(file_path: &str, out_path: Option<&str>, ctx: &mut TimeCtx, window: &TimeWindow,
+                    bucket: Option<u64>) -> std::io::Result<()> {
+    let chart_bucket = stream::chart_bucket(file_path, window, bucket)?;
+    // Devices and interfaces in first-seen order
+    let mut disks: Vec<(String, Tracker<DiskStat, IntervalDiskMetrics>)> = Vec::new();
+    let mut dev_idx: HashMap<String, usize> = HashMap::new();
+    let mut nets: Vec<(String, Tracker<NetRow, IntervalNetMetrics>)> = Vec::new();
+    let mut net_idx: HashMap<String, usize> = HashMap::new();
+    let mut pending_netx: HashMap<String, (u64, NetExt)> = HashMap::new();
+    let mut cpu_tracker = Tracker::new(chart_bucket, analyze::cpu_interval, &[], findings::cpu_detector());
+    let mut mem_tracker = MemTracker::new(chart_bucket);
+    let mut min_layout: Option<u8> = None;
+    let mut flush_counters = false;
+
+    for rec in Reader::open(file_path)?.flatten() {
+        if let Record::Tz { ts, offset, zone } = &rec {
+            ctx.note_host_tz(*ts, *offset, zone);
+            continue;
+        }
+        if window.is_past(rec.ts()) { break; }
+        match rec {
+            Record::DiskLayout { fields, .. } => min_layout = Some(min_layout.map_or(fields, |l| l.min(fields))),
+            Record::Disk { ts, stat } => {
+                flush_counters |= stat.flushes.is_some();
+                let i = *dev_idx.entry(stat.name.clone()).or_insert_with(|| {
+                    let t = Tracker::new(chart_bucket, analyze::disk_interval, &[], findings::disk_detector(&stat.name));
+                    disks.push((stat.name.clone(), t));
+                    disks.len() - 1
+                });
+                disks[i].1.push(ts, stat, window, ctx);
+            }
+            Record::Net { ts, stat } => {
+                let ext = pending_netx.remove(&stat.iface).filter(|(t, _)| *t == ts).map(|(_, e)| e);
+                let i = *net_idx.entry(stat.iface.clone()).or_insert_with(|| {
+                    let t = Tracker::new(chart_bucket, analyze::net_row_interval, &[], findings::net_detector(&stat.iface));
+                    nets.push((stat.iface.clone(), t));
+                    nets.len() - 1
+                });
+                nets[i].1.push(ts, (stat, ext), window, ctx);
+            }
+            Record::Cpu { ts, stat } => cpu_tracker.push(ts, stat, window, ctx),
+            Record::Mem { ts, stat } if window.contains(ts) => mem_tracker.push(analyze::mem_point(ts, &stat), ctx),
+            Record::NetExt { ts, iface, stat } => {
+                pending_netx.insert(iface, (ts, stat));
+            }
+            _ => {}
+        }
+    }
+
+    let mut all_ts: BTreeSet<u64> = BTreeSet::new();
+    let mut devices: Vec<String> = Vec::new();
+    for (name, tracker) in disks {
+        let series = tracker.finish().chart;
+        if series.is_empty() { continue; }
+        all_ts.extend(series.iter().map(|m| m.ts));
+        devices.push(entity_json(&name, &series, |m| m.ts, DISK_CHARTS));
+    }
+    let mut ifaces: Vec<String> = Vec::new();
+    for (name, tracker) in nets {
+        let series = tracker.finish().chart;
+        if series.is_empty() { continue; }
+        all_ts.extend(series.iter().map(|m| m.ts));
+        ifaces.push(entity_json(&name, &series, |m| m.ts, NET_CHARTS));
+    }
+    let cpu = cpu_tracker.finish().chart;
+    all_ts.extend(cpu.iter().map(|m| m.ts));
+    let (mem, _) = mem_tracker.finish();
+    all_ts.extend(mem.iter().map(|m| m.ts));
+
+    // Disk metrics the capture kernel did not report
+    let unavailable: Vec<String> = analyze::unavailable_disk_metrics(min_layout, flush_counters).into_iter()
+        .map(|(key, why)| format!("{}:{}", json_str(key), json_str(&why)))
+        .collect();
+
+    // Display timezone as UTC offset changes, so the browser shows the same times as playback
+    let mut offsets: Vec<String> = Vec::new();
+    let mut last_off: Option<i32> = None;
+    for &ts in &all_ts {
+        let off = ctx.offset_at(ts).local_minus_utc();
+        if last_off != Some(off) {
+            offsets.push(format!("[{},{}]", ts, off));
+            last_off = Some(off);
+        }
+    }
+
+    let name = Path::new(file_path).file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let range = match (all_ts.first(), all_ts.last()) {
+        (Some(f), Some(l)) => format!("{} to {}", ctx.format(*f, "%Y-%m-%d %H:%M:%S"), ctx.format(*l, "%Y-%m-%d %H:%M:%S")),
+        _ => "no data".to_string(),
+    };
+    let subtitle = format!("{} ({}){}", range, ctx.label(),
+        chart_bucket.map(|b| format!(", {} buckets", analyze::fmt_bucket(b))).unwrap_or_default());
+    let data = format!(
+        "{{\"disk\":{{\"metrics\":{},\"unavailable\":{{{}}},\"items\":[{}]}},\"net\":{{\"metrics\":{},\"items\":[{}]}},\
+         \"cpu\":{},\"mem\":{},\"offsets\":[{}]}}",
+        metric_list(DISK_CHARTS), unavailable.join(","), devices.join(","),
+        metric_list(NET_CHARTS), ifaces.join(","),
+        entity_json("cpu", &cpu, |m| m.ts, CPU_CHARTS),
+        entity_json("mem", &mem, |m| m.ts, MEM_CHARTS),
+        offsets.join(","),
+    );
+    let html = PAGE
+        .replace("{{TITLE}}", &html_escape(&name))
+        .replace("{{SUBTITLE}}", &html_escape(&subtitle))
+        // Keep "</script>" in a device name from ending the data block
+        .replace("{{DATA}}", &data.replace("</", "<\\/"));
+
+    let out_path = match out_path {
+        Some(p) => p.to_string(),
+        None => format!("{}.html", Path::new(file_path).file_stem().unwrap_or_default().to_string_lossy()),
+    };
+    fs::write(&out_path, &html)?;
+    println!("Report: {} ({} KB, {} devices, {} interfaces)", out_path, html.len() / 1024, devices.len(), ifaces.len());
+    Ok(())
+}
+
+const PAGE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="UTF-8">
+<title>{{TITLE}}</title>
+<style>
+  body { font-family: sans-serif; margin: 1.5em; color: #222; }
+  h1 { margin: 0; font-size: 1.4em; }
+  .sub { color: #666; margin: 0.3em 0 1em; }
+  .tab { cursor: pointer; display: inline-block; margin-right: 0.6em; padding: 0.4em 1em; border-radius: 1em; background: #ddd; }
+  .tab.active { background: #333; color: #fff; }
+  .section { display: none; margin-top: 1em; }
+  .section.active { display: block; }
+  .controls { display: flex; gap: 1.5em; align-items: flex-start; margin-bottom: 0.5em; }
+  .controls select { min-width: 14em; }
+  .metrics label { display: inline-block; min-width: 12em; }
+  .metrics label.na { color: #aaa; }
+  .chart { position: relative; margin: 0.8em 0; }
+  .chart canvas { width: 100%; height: 260px; border: 1px solid #ccc; cursor: grab; }
+  .tip { position: absolute; pointer-events: none; background: rgba(255,255,255,0.95); border: 1px solid #999;
+         padding: 4px 6px; font: 12px monospace; white-space: pre; display: none; z-index: 2; }
+  .help { color: #666; font-size: 0.9em; }
+</style>
+</head>
+<body>
+<h1>{{TITLE}}</h1>
+<div class="sub">{{SUBTITLE}}</div>
+<div>
+  <span class="tab active" data-s="disk">Disk</span><span class="tab" data-s="cpu">CPU</span><span class="tab" data-s="mem">Memory</span><span class="tab" data-s="net">Network</span>
+  <button id="reset">Reset zoom</button>
+  <span class="help">Mouse wheel zooms, drag pans, double-click resets. All charts share the time range.</span>
+</div>
+<div id="disk" class="section active"></div>
+<div id="cpu" class="section"></div>
+<div id="mem" class="section"></div>
+<div id="net" class="section"></div>
+<script id="data" type="application/json">{{DATA}}</script>
+<script>
+"use strict";
+const D = JSON.parse(document.getElementById("data").textContent);
+const COLORS = ["#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b", "#e377c2", "#17becf", "#7f7f7f", "#bcbd22"];
+const TICKS = [1, 5, 10, 15, 30, 60, 120, 300, 600, 900, 1800, 3600, 7200, 10800, 21600, 43200, 86400, 172800, 604800];
+let view = null;      // shared [t0, t1], null = whole capture
+let charts = [];
+
+function offsetAt(ts) {
+  let off = D.offsets.length ? D.offsets[0][1] : 0;
+  for (const [t, o] of D.offsets) { if (t <= ts) off = o; else break; }
+  return off;
+}
+function pad(n) { return String(n).padStart(2, "0"); }
+function fmtTime(ts, withDate) {
+  const d = new Date((ts + offsetAt(ts)) * 1000);
+  const hms = pad(d.getUTCHours()) + ":" + pad(d.getUTCMinutes()) + ":" + pad(d.getUTCSeconds());
+  return withDate ? d.getUTCFullYear() + "-" + pad(d.getUTCMonth() + 1) + "-" + pad(d.getUTCDate()) + " " + hms : hms;
+}
+function fmtNum(v) { return v === null ? "-" : v.toLocaleString(undefined, { maximumFractionDigits: 3 }); }
+function niceStep(range, n) {
+  const raw = range / n, mag = Math.pow(10, Math.floor(Math.log10(raw)));
+  for (const m of [1, 2, 5, 10]) if (m * mag >= raw) return m * mag;
+  return 10 * mag;
+}
+function lowerBound(t, x) {
+  let lo = 0, hi = t.length;
+  while (lo < hi) { const mid = (lo + hi) >> 1; if (t[mid] < x) lo = mid + 1; else hi = mid; }
+  return lo;
+}
+
+class Chart {
+  // lines: [{name, t: [...], v: [...]}]
+  constructor(parent, title, lines) {
+    this.title = title;
+    this.lines = lines;
+    this.div = document.createElement("div");
+    this.div.className = "chart";
+    this.canvas = document.createElement("canvas");
+    this.tip = document.createElement("div");
+    this.tip.className = "tip";
+    this.div.append(this.canvas, this.tip);
+    parent.appendChild(this.div);
+    const all = lines.flatMap(l => [l.t[0], l.t[l.t.length - 1]]).filter(x => x !== undefined);
+    this.full = [Math.min(...all), Math.max(...all)];
+    this.hover = null;
+    this.drag = null;
+    const c = this.canvas;
+    c.addEventListener("mousemove", e => this.onMove(e));
+    c.addEventListener("mouseleave", () => { this.hover = null; this.tip.style.display = "none"; this.draw(); });
+    c.addEventListener("mousedown", e => { this.drag = { x: e.clientX, range: this.range() }; c.style.cursor = "grabbing"; });
+    window.addEventListener("mouseup", () => { this.drag = null; c.style.cursor = "grab"; });
+    c.addEventListener("wheel", e => this.onWheel(e), { passive: false });
+    c.addEventListener("dblclick", () => setView(null));
+  }
+  range() { return view || this.full; }
+  plot() { return { l: 64, r: this.w - 12, t: 24, b: this.h - 28 }; }
+  tsAt(x) { const p = this.plot(), [t0, t1] = this.range(); return t0 + (x - p.l) / (p.r - p.l) * (t1 - t0); }
+  onMove(e) {
+    const x = e.offsetX;
+    if (this.drag) {
+      const p = this.plot(), [t0, t1] = this.drag.range;
+      const dt = (e.clientX - this.drag.x) / (p.r - p.l) * (t1 - t0);
+      setView([t0 - dt, t1 - dt]);
+      return;
+    }
+    this.hover = this.tsAt(x);
+    this.draw();
+    this.showTip(x, e.offsetY);
+  }
+  onWheel(e) {
+    e.preventDefault();
+    const [t0, t1] = this.range(), at = this.tsAt(e.offsetX);
+    const f = e.deltaY < 0 ? 0.8 : 1.25;
+    setView([at - (at - t0) * f, at + (t1 - at) * f]);
+  }
+  showTip(x, y) {
+    let rows = [], when = null;
+    this.lines.forEach((l, i) => {
+      const j = this.nearest(l.t, this.hover);
+      if (j < 0) return;
+      when = when === null ? l.t[j] : when;
+      rows.push([COLORS[i % COLORS.length], l.name + ": " + fmtNum(l.v[j])]);
+    });
+    if (when === null) { this.tip.style.display = "none"; return; }
+    this.tip.textContent = "";
+    const head = document.createElement("div");
+    head.textContent = fmtTime(when, true);
+    this.tip.appendChild(head);
+    for (const [color, text] of rows) {
+      const d = document.createElement("div");
+      d.style.color = color;
+      d.textContent = text;
+      this.tip.appendChild(d);
+    }
+    this.tip.style.display = "block";
+    const left = x + 16 + this.tip.offsetWidth > this.w ? x - 16 - this.tip.offsetWidth : x + 16;
+    this.tip.style.left = left + "px";
+    this.tip.style.top = Math.max(0, y - 10) + "px";
+  }
+  nearest(t, ts) {
+    if (!t.length) return -1;
+    const i = lowerBound(t, ts);
+    if (i === 0) return 0;
+    if (i === t.length) return t.length - 1;
+    return ts - t[i - 1] < t[i] - ts ? i - 1 : i;
+  }
+  draw() {
+    const c = this.canvas, dpr = window.devicePixelRatio || 1;
+    this.w = c.clientWidth; this.h = c.clientHeight;
+    if (!this.w) return;           // hidden tab
+    c.width = this.w * dpr; c.height = this.h * dpr;
+    const g = c.getContext("2d");
+    g.setTransform(dpr, 0, 0, dpr, 0, 0);
+    g.clearRect(0, 0, this.w, this.h);
+    g.font = "12px sans-serif";
+    const p = this.plot(), [t0, t1] = this.range();
+    const X = t => p.l + (t - t0) / (t1 - t0 || 1) * (p.r - p.l);
+
+    // Y range over the visible points
+    let lo = 0, hi = 0;
+    for (const l of this.lines) {
+      const a = Math.max(0, lowerBound(l.t, t0) - 1), b = Math.min(l.t.length, lowerBound(l.t, t1) + 1);
+      for (let i = a; i < b; i++) if (l.v[i] !== null) { lo = Math.min(lo, l.v[i]); hi = Math.max(hi, l.v[i]); }
+    }
+    if (hi === lo) hi = lo + 1;
+    const step = niceStep(hi - lo, 5);
+    hi = Math.ceil(hi / step) * step; lo = Math.floor(lo / step) * step;
+    const Y = v => p.b - (v - lo) / (hi - lo) * (p.b - p.t);
+
+    g.strokeStyle = "#eee"; g.fillStyle = "#555"; g.lineWidth = 1;
+    g.textAlign = "right"; g.textBaseline = "middle";
+    for (let v = lo; v <= hi + step / 2; v += step) {
+      g.beginPath(); g.moveTo(p.l, Y(v)); g.lineTo(p.r, Y(v)); g.stroke();
+      g.fillText(fmtNum(Math.round(v * 1e6) / 1e6), p.l - 4, Y(v));
+    }
+    const span = t1 - t0, tstep = TICKS.find(s => span / s <= (p.r - p.l) / 90) || 604800;
+    const withDate = span > 86400;
+    g.textAlign = "center"; g.textBaseline = "top";
+    const first = Math.ceil((t0 + offsetAt(t0)) / tstep) * tstep - offsetAt(t0);
+    for (let t = first; t <= t1; t += tstep) {
+      g.beginPath(); g.moveTo(X(t), p.t); g.lineTo(X(t), p.b); g.stroke();
+      g.fillText(withDate ? fmtTime(t, true).slice(5, 16) : fmtTime(t, false), X(t), p.b + 4);
+    }
+    g.strokeStyle = "#999"; g.strokeRect(p.l, p.t, p.r - p.l, p.b - p.t);
+
+    g.save();
+    g.beginPath(); g.rect(p.l, p.t, p.r - p.l, p.b - p.t); g.clip();
+    this.lines.forEach((l, i) => {
+      g.strokeStyle = COLORS[i % COLORS.length]; g.lineWidth = 1.5;
+      const a = Math.max(0, lowerBound(l.t, t0) - 1), b = Math.min(l.t.length, lowerBound(l.t, t1) + 1);
+      g.beginPath();
+      let pen = false;
+      for (let j = a; j < b; j++) {
+        if (l.v[j] === null) { pen = false; continue; }
+        if (pen) g.lineTo(X(l.t[j]), Y(l.v[j])); else g.moveTo(X(l.t[j]), Y(l.v[j]));
+        pen = true;
+      }
+      g.stroke();
+    });
+    if (this.hover !== null) {
+      g.strokeStyle = "#444"; g.lineWidth = 1;
+      g.beginPath(); g.moveTo(X(this.hover), p.t); g.lineTo(X(this.hover), p.b); g.stroke();
+    }
+    g.restore();
+
+    // Title and legend
+    g.textAlign = "left"; g.textBaseline = "middle"; g.fillStyle = "#222"; g.font = "bold 13px sans-serif";
+    g.fillText(this.title, p.l, 12);
+    let x = p.l + g.measureText(this.title).width + 24;
+    g.font = "12px sans-serif";
+    this.lines.forEach((l, i) => {
+      g.fillStyle = COLORS[i % COLORS.length]; g.fillRect(x, 7, 10, 10);
+      g.fillStyle = "#222"; g.fillText(l.name, x + 14, 12);
+      x += g.measureText(l.name).width + 30;
+    });
+  }
+}
+
+function setView(v) {
+  if (v) {
+    const full = charts.length ? charts[0].full : null;
+    if (full) {
+      const len = Math.max(10, Math.min(v[1] - v[0], full[1] - full[0]));
+      let a = Math.max(full[0], Math.min(v[0], full[1] - len));
+      v = [a, a + len];
+    }
+  }
+  view = v;
+  charts.forEach(c => c.draw());
+}
+
+function clearCharts(section) {
+  charts = charts.filter(c => !section.contains(c.div));
+  section.querySelectorAll(".chart").forEach(d => d.remove());
+}
+
+// Disk / Network: picker + metric checkboxes, one chart per metric with a line per selected item
+function pickerSection(id, group, defaults, noun) {
+  const sec = document.getElementById(id);
+  if (!group.items.length) { sec.textContent = "No " + noun + " data in this capture."; return; }
+  const ctl = document.createElement("div");
+  ctl.className = "controls";
+  const left = document.createElement("div");
+  const filter = document.createElement("input");
+  filter.placeholder = "filter " + noun + "s...";
+  const sel = document.createElement("select");
+  sel.multiple = true; sel.size = 8;
+  group.items.forEach((it, i) => sel.add(new Option(it.name, i, i === 0, i === 0)));
+  left.append(filter, document.createElement("br"), sel);
+  const mets = document.createElement("div");
+  mets.className = "metrics";
+  const na = group.unavailable || {};
+  for (const [key, label] of group.metrics) {
+    const lab = document.createElement("label");
+    const cb = document.createElement("input");
+    cb.type = "checkbox"; cb.value = key;
+    cb.checked = defaults.includes(key) && !na[key];
+    cb.disabled = !!na[key];
+    if (na[key]) { lab.className = "na"; lab.title = na[key]; }
+    lab.append(cb, " " + label);
+    mets.appendChild(lab);
+  }
+  ctl.append(left, mets);
+  sec.appendChild(ctl);
+  const charts_div = document.createElement("div");
+  sec.appendChild(charts_div);
+
+  function build() {
+    clearCharts(charts_div);
+    const items = Array.from(sel.selectedOptions).map(o => group.items[+o.value]);
+    for (const cb of mets.querySelectorAll("input:checked")) {
+      const label = group.metrics.find(m => m[0] === cb.value)[1];
+      const lines = items.map(it => ({ name: it.name, t: it.t, v: it.v[cb.value] }));
+      if (lines.length) charts.push(new Chart(charts_div, label, lines));
+    }
+    charts.forEach(c => c.draw());
+  }
+  filter.addEventListener("input", () => {
+    const re = filter.value.toLowerCase();
+    for (const o of sel.options) o.hidden = !o.text.toLowerCase().includes(re);
+  });
+  sel.addEventListener("change", build);
+  mets.addEventListener("change", build);
+  build();
+}
+
+function systemSection(id, series, groups) {
+  const sec = document.getElementById(id);
+  if (!series.t.length) { sec.textContent = "No " + id.toUpperCase() + " data in this capture."; return; }
+  for (const [title, keys] of groups) {
+    const lines = keys.map(([key, name]) => ({ name, t: series.t, v: series.v[key] }));
+    charts.push(new Chart(sec, title, lines));
+  }
+}
+
+pickerSection("disk", D.disk, ["io_sec", "kb_sec", "await_rd", "await_wr", "qlen"], "disk");
+pickerSection("net", D.net, ["rx_bytes", "tx_bytes"], "interface");
+systemSection("cpu", D.cpu, [
+  ["CPU %", [["user", "user"], ["sys", "system"], ["iowait", "iowait"], ["idle", "idle"], ["guest", "guest"]]],
+  ["Run queue", [["running", "running"], ["blocked", "blocked"]]],
+]);
+systemSection("mem", D.mem, [["Memory %", [["used", "used"], ["avail", "available"], ["cached", "cached"], ["free", "free"]]]]);
+
+for (const tab of document.querySelectorAll(".tab")) {
+  tab.addEventListener("click", () => {
+    document.querySelectorAll(".tab, .section").forEach(e => e.classList.remove("active"));
+    tab.classList.add("active");
+    document.getElementById(tab.dataset.s).classList.add("active");
+    charts.forEach(c => c.draw());
+  });
+}
+document.getElementById("reset").addEventListener("click", () => setView(null));
+window.addEventListener("resize", () => charts.forEach(c => c.draw()));
+charts.forEach(c => c.draw());
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serverstats_format::timeutil::DisplayTz;
+    use serverstats_format::testdir::TempDir;
+    use serverstats_format::{CpuStat, MemStat, Writer};
+
+    #[test]
+    fn report_is_one_offline_file() {
+        let dir = TempDir::new("serverstats_grab-report");
+        let mut w = Writer::new(Vec::new());
+        w.write_header().unwrap();
+        for (i, ts) in [1000u64, 1010, 1020].into_iter().enumerate() {
+            let n = i as u64 * 100;
+            w.write(&Record::Tz { ts, offset: 3600, zone: "Europe/Berlin".to_string() }).unwrap();
+            let disk = format!("8 0 sda {} 0 {} {} {} 0 {} {} 0 {} {}", n, n * 8, n, n, n * 8, n, n, n * 2);
+            w.write(&Record::Disk { ts, stat: DiskStat::from_line(&disk).unwrap() }).unwrap();
+            let disk = format!("8 16 sd</script>b {} 0 0 0 0 0 0 0 0 0 0", n);
+            w.write(&Record::Disk { ts, stat: DiskStat::from_line(&disk).unwrap() }).unwrap();
+            let cpu = format!("cpu {} 0 {} {} 0 0 0 0 0 0\nprocs_running 3\nprocs_blocked 1\n", n, n, n * 2);
+            w.write(&Record::Cpu { ts, stat: CpuStat::from_proc_stat(&cpu).unwrap() }).unwrap();
+            w.write(&Record::Mem { ts, stat: MemStat::from_meminfo("MemTotal: 1000 kB\nMemFree: 500 kB\nMemAvailable: 600 kB\n") }).unwrap();
+        }
+        let cap = dir.write("cap.dat", w.into_inner());
+        let out = dir.join("r.html");
+        let mut ctx = TimeCtx::for_capture(cap.to_str().unwrap(), DisplayTz::Host).unwrap();
+        let window = TimeWindow::resolve(None, None, &ctx).unwrap();
+        write_report(cap.to_str().unwrap(), out.to_str().unwrap().into(), &mut ctx, &window, None).unwrap();
+        let html = fs::read_to_string(&out).unwrap();
+
+        // Nothing loaded from elsewhere
+        assert!(!html.contains("src=") && !html.contains("href=") && !html.contains("http"));
+        assert_eq!(html.matches("</script>").count(), 2);
+        assert!(html.contains(r#"{"name":"sda","t":[1010,1020],"v":{"rps":[10,10],"#));
+        assert!(html.contains(r#""name":"sd<\/script>b""#));
+        assert!(html.contains(r#""offsets":[[1000,3600]]"#));
+        assert!(html.contains(r#""flush_s":"not available"#));
+        assert!(html.contains(r#""used":[50,50,50],"avail":[60,60,60]"#));
+
+        // The row before --from is only the first delta's baseline
+        let window = TimeWindow::resolve(Some("@1020"), None, &ctx).unwrap();
+        write_report(cap.to_str().unwrap(), out.to_str().unwrap().into(), &mut ctx, &window, None).unwrap();
+        let html = fs::read_to_string(&out).unwrap();
+        assert!(html.contains(r#"{"name":"sda","t":[1020],"v":{"rps":[10],"#));
+        assert!(html.contains(r#""used":[50],"avail":[60]"#));
+    }
+}
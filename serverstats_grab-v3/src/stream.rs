@@ -0,0 +1,339 @@
+/*!
+ * Streaming Analysis Module
+ * -------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * One-pass building blocks for `-a` on week-long, multi-GB captures from
+ * hosts with thousands of devices. Nothing holds a whole series:
+ *
+ *   - Tracker keeps the last row of one device or interface, turns each new
+ *     row into that interval's metrics and feeds them to the findings rules
+ *     and the percentile accumulators, then drops them
+ *   - only the chart points are kept: one per interval for short captures,
+ *     one per chart bucket otherwise (counters resampled at bucket ends, as
+ *     bucket::bucket_ends does for a whole series)
+ *   - the chart bucket is chosen up front from the first and last timestamps
+ *     and the sample interval (`extent`), so it is known during the pass
+ *   - Progress reports how far parsing and chart rendering are, with timing
+ *
+ * Memory is then bounded by devices x chart points, not by capture length.
+ */
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use serverstats_format::{Reader, Record};
+use serverstats_format::timeutil::{TimeCtx, TimeWindow};
+
+use crate::analyze::{self, Chart, MemBuckets, MemMetrics};
+use crate::bucket;
+use crate::findings::{Detector, Finding, MemDetector};
+use crate::percentile::{self, DistAcc, Distribution};
+
+// ==================== Progress ====================
+
+/// Progress of one phase, printed at every PROGRESS_STEP percent. Safe to share across threads.
+pub(crate) struct Progress {
+    label: &'static str,
+    total: u64,
+    done: AtomicU64,
+    /// Next percentage to report
+    next: AtomicU64,
+    fmt: fn(u64) -> String,
+    start: Instant,
+}
+
+const PROGRESS_STEP: u64 = 10;
+
+impl Progress {
+    pub(crate) fn new(label: &'static str, total: u64, fmt: fn(u64) -> String) -> Self {
+        Progress { label, total, done: AtomicU64::new(0), next: AtomicU64::new(PROGRESS_STEP), fmt, start: Instant::now() }
+    }
+
+    pub(crate) fn add(&self, n: u64) {
+        let done = self.done.fetch_add(n, Ordering::Relaxed) + n;
+        if self.total == 0 { return; }
+        let pct = (done * 100 / self.total).min(100);
+        let next = self.next.load(Ordering::Relaxed);
+        if pct >= next
+            && self.next.compare_exchange(next, (pct / PROGRESS_STEP + 1) * PROGRESS_STEP, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+        {
+            println!("{}: {:>3}% ({} of {}) {:.1}s", self.label, pct, (self.fmt)(done), (self.fmt)(self.total), self.secs());
+        }
+    }
+
+    pub(crate) fn secs(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+}
+
+pub(crate) fn fmt_bytes(n: u64) -> String {
+    match n {
+        n if n >= 1 << 30 => format!("{:.1} GB", n as f64 / (1u64 << 30) as f64),
+        n if n >= 1 << 20 => format!("{:.1} MB", n as f64 / (1u64 << 20) as f64),
+        n => format!("{:.1} KB", n as f64 / 1024.0),
+    }
+}
+
+pub(crate) fn fmt_count(n: u64) -> String {
+    n.to_string()
+}
+
+/// Counts the bytes read into a Progress.
+pub(crate) struct CountingReader<'a, R> {
+    inner: R,
+    progress: &'a Progress,
+}
+
+impl<'a, R: Read> CountingReader<'a, R> {
+    pub(crate) fn new(inner: R, progress: &'a Progress) -> Self {
+        CountingReader { inner, progress }
+    }
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.add(n as u64);
+        Ok(n)
+    }
+}
+
+// ==================== Capture extent ====================
+
+/// Bytes read from the end of the capture to find the last timestamp.
+const TAIL_BYTES: u64 = 64 * 1024;
+
+/// Timestamp of a data record; None for TZ, EVENT and DISKLAYOUT.
+fn sample_ts(rec: Record) -> Option<u64> {
+    match rec {
+        Record::Tz { .. } | Record::Event { .. } | Record::DiskLayout { .. } | Record::Host { .. } => None,
+        rec => Some(rec.ts()),
+    }
+}
+
+/// First and last sample timestamps and the sample interval, from the head and tail of the
+/// capture only. None for a capture with fewer than two samples.
+pub(crate) fn extent(path: &str) -> io::Result<Option<(u64, u64, u64)>> {
+    let mut file = File::open(path)?;
+    let mut first: Option<u64> = None;
+    let mut interval: Option<u64> = None;
+    for rec in Reader::new(BufReader::new(&mut file)).map_while(Result::ok) {
+        let Some(ts) = sample_ts(rec) else { continue };
+        match first {
+            None => first = Some(ts),
+            Some(f) if ts > f => {
+                interval = Some(ts - f);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let (Some(first), Some(interval)) = (first, interval) else { return Ok(None) };
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    // The first tail line may be cut off, only the last record counts
+    let last = Reader::new(tail.as_slice()).flatten().filter_map(sample_ts).last().unwrap_or(first);
+    Ok(Some((first, last.max(first), interval)))
+}
+
+/// Chart bucket for `-a`: --bucket, or automatic when the window holds more than the chart
+/// points (see analyze::auto_bucket).
+pub(crate) fn chart_bucket(path: &str, window: &TimeWindow, bucket: Option<u64>) -> io::Result<Option<u64>> {
+    if let Some(b) = bucket {
+        println!("Chart buckets: {}", analyze::fmt_bucket(b));
+        return Ok(Some(b));
+    }
+    let Some((first, last, interval)) = extent(path)? else { return Ok(None) };
+    let first = window.from.map_or(first, |f| f.max(first));
+    let last = window.to.map_or(last, |t| t.min(last));
+    let span = last.saturating_sub(first);
+    let samples = (span / interval + 1) as usize;
+    let b = analyze::auto_bucket(samples, span);
+    if let Some(b) = b {
+        println!("NOTE: {} samples, charting {} buckets (use --bucket to change)", samples, analyze::fmt_bucket(b));
+    }
+    Ok(b)
+}
+
+// ==================== Per-series state ====================
+
+/// Metrics between two (ts, row) rows of a series.
+pub(crate) type Delta<T, M> = fn(&(u64, T), &(u64, T)) -> Option<M>;
+
+/// Last row of a counter series and, with a chart bucket, the row at the last bucket end.
+pub(crate) struct Series<T, M> {
+    bucket: Option<u64>,
+    /// Metrics between two rows
+    delta: Delta<T, M>,
+    base: Option<(u64, T)>,
+    last: Option<(u64, T)>,
+}
+
+/// What one new row produced.
+pub(crate) struct Step<M> {
+    /// The interval since the previous row, and its length
+    pub(crate) interval: Option<(M, u64)>,
+    /// A chart bucket that just closed
+    pub(crate) bucket: Option<M>,
+}
+
+impl<T: Clone, M> Series<T, M> {
+    pub(crate) fn new(bucket: Option<u64>, delta: Delta<T, M>) -> Self {
+        Series { bucket, delta, base: None, last: None }
+    }
+
+    /// A row before the window: only the baseline of the first interval.
+    pub(crate) fn baseline(&mut self, ts: u64, row: T) {
+        self.base = self.bucket.map(|_| (ts, row.clone()));
+        self.last = Some((ts, row));
+    }
+
+    pub(crate) fn push(&mut self, ts: u64, row: T, ctx: &TimeCtx) -> Step<M> {
+        let row = (ts, row);
+        let mut step = Step { interval: None, bucket: None };
+        match self.last.take() {
+            None => self.base = self.bucket.map(|_| row.clone()),
+            Some(last) => {
+                step.interval = (self.delta)(&last, &row).map(|m| (m, ts.saturating_sub(last.0)));
+                if let Some(b) = self.bucket
+                    && bucket::bucket_start(last.0, b, ctx) != bucket::bucket_start(ts, b, ctx)
+                {
+                    step.bucket = self.close(&last);
+                    self.base = Some(last);
+                }
+            }
+        }
+        self.last = Some(row);
+        step
+    }
+
+    /// The last, partial chart bucket.
+    pub(crate) fn finish(&mut self) -> Option<M> {
+        let last = self.last.take()?;
+        self.bucket?;
+        self.close(&last)
+    }
+
+    fn close(&self, end: &(u64, T)) -> Option<M> {
+        let base = self.base.as_ref()?;
+        if base.0 == end.0 { return None; }
+        (self.delta)(base, end)
+    }
+}
+
+/// Everything `-a` keeps for one device, interface or the CPU row.
+pub(crate) struct Tracker<T, M: 'static> {
+    series: Series<T, M>,
+    /// Chart points: every interval, or one per chart bucket
+    chart: Vec<M>,
+    /// Metrics with percentile statistics (disks only)
+    stats: &'static [Chart<M>],
+    dists: Vec<DistAcc>,
+    detector: Detector<M>,
+}
+
+/// Distribution of each `stats` metric by key, None when it had no finite value.
+pub(crate) type Dists = Vec<(&'static str, Option<Distribution>)>;
+
+/// A finished Tracker.
+pub(crate) struct Tracked<M: 'static> {
+    pub(crate) chart: Vec<M>,
+    pub(crate) dists: Dists,
+    pub(crate) findings: Vec<Finding>,
+}
+
+impl<T: Clone, M: Clone> Tracker<T, M> {
+    pub(crate) fn new(bucket: Option<u64>, delta: Delta<T, M>,
+                      stats: &'static [Chart<M>], detector: Detector<M>) -> Self {
+        let dists = stats.iter().map(|(key, _, _)| DistAcc::new(percentile::threshold(key))).collect();
+        Tracker { series: Series::new(bucket, delta), chart: Vec::new(), stats, dists, detector }
+    }
+
+    /// Next row; rows before `window` only set the baseline.
+    pub(crate) fn push(&mut self, ts: u64, row: T, window: &TimeWindow, ctx: &TimeCtx) {
+        if !window.contains(ts) {
+            self.series.baseline(ts, row);
+            return;
+        }
+        let step = self.series.push(ts, row, ctx);
+        if let Some((m, dt)) = step.interval {
+            for ((_, _, f), acc) in self.stats.iter().zip(&mut self.dists) {
+                acc.add(f(&m), dt);
+            }
+            self.detector.push(&m, dt);
+            if self.series.bucket.is_none() {
+                self.chart.push(m);
+            }
+        }
+        self.chart.extend(step.bucket);
+    }
+
+    pub(crate) fn finish(mut self) -> Tracked<M> {
+        self.chart.extend(self.series.finish());
+        Tracked {
+            chart: self.chart,
+            dists: self.stats.iter().zip(self.dists).map(|((key, _, _), acc)| (*key, acc.finish())).collect(),
+            findings: self.detector.finish(),
+        }
+    }
+}
+
+/// Memory is a gauge: each sample is a point, averaged per chart bucket.
+pub(crate) struct MemTracker {
+    buckets: Option<MemBuckets>,
+    chart: Vec<MemMetrics>,
+    detector: MemDetector,
+}
+
+impl MemTracker {
+    pub(crate) fn new(bucket: Option<u64>) -> Self {
+        MemTracker { buckets: bucket.map(MemBuckets::new), chart: Vec::new(), detector: MemDetector::new() }
+    }
+
+    pub(crate) fn push(&mut self, m: MemMetrics, ctx: &TimeCtx) {
+        self.detector.push(&m);
+        match self.buckets.as_mut() {
+            Some(b) => self.chart.extend(b.push(&m, ctx)),
+            None => self.chart.push(m),
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> (Vec<MemMetrics>, Vec<Finding>) {
+        if let Some(b) = self.buckets.as_mut() {
+            self.chart.extend(b.finish());
+        }
+        (self.chart, self.detector.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serverstats_format::timeutil::DisplayTz;
+
+    #[test]
+    fn series_matches_bucket_ends() {
+        // Counter rows every 10 s; the chart point of each minute is the delta between bucket ends
+        let ctx = TimeCtx::new(DisplayTz::parse("UTC").unwrap());
+        let rows: Vec<(u64, u64)> = (0..20).map(|i| (1741957205 + i * 10, i * i)).collect();
+        let delta: Delta<u64, (u64, u64)> = |a, b| Some((b.0, b.1 - a.1));
+        let mut s = Series::new(Some(60), delta);
+        let mut intervals = 0;
+        let mut points: Vec<(u64, u64)> = Vec::new();
+        for (ts, v) in &rows {
+            let step = s.push(*ts, *v, &ctx);
+            intervals += step.interval.iter().count();
+            points.extend(step.bucket);
+        }
+        points.extend(s.finish());
+        let ends = bucket::bucket_ends(&rows, 60, &ctx);
+        let expect: Vec<(u64, u64)> = ends.windows(2).map(|w| (w[1].0, w[1].1 - w[0].1)).collect();
+        assert_eq!(intervals, 19);
+        assert_eq!(points, expect);
+    }
+}
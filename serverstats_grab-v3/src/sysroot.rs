@@ -0,0 +1,54 @@
+/*!
+ * procfs / sysfs Root Module
+ * --------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Every collector reads /proc and /sys through a SysRoot, so gather can run
+ * against the host's filesystems mounted elsewhere (an OpenShift DaemonSet with
+ * hostPath mounts at /host/proc and /host/sys uses `--root /host`) or against a
+ * fixture tree in tests. The default root is `/`.
+ */
+
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct SysRoot {
+    root: PathBuf,
+}
+
+impl Default for SysRoot {
+    fn default() -> Self {
+        SysRoot { root: PathBuf::from("/") }
+    }
+}
+
+impl SysRoot {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        SysRoot { root: root.into() }
+    }
+
+    /// `<root>/proc/<rel>`, e.g. proc("diskstats").
+    pub fn proc(&self, rel: &str) -> PathBuf {
+        self.root.join("proc").join(rel)
+    }
+
+    /// `<root>/sys`
+    pub fn sys(&self) -> PathBuf {
+        self.root.join("sys")
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+}
+
+#[cfg(test)]
+impl SysRoot {
+    /// A SysRoot in a scratch directory holding `files` (paths relative to the root);
+    /// the directory goes away when the returned TempDir is dropped.
+    pub(crate) fn temp(name: &str, files: &[(&str, &str)]) -> (serverstats_format::testdir::TempDir, SysRoot) {
+        let dir = serverstats_format::testdir::TempDir::with_files(&format!("serverstats_grab-{}", name), files);
+        let root = SysRoot::new(dir.path());
+        (dir, root)
+    }
+}
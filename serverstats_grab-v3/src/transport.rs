@@ -0,0 +1,454 @@
+/*!
+ * Storage Transport Module (Fibre Channel / iSCSI)
+ * ------------------------------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * Collects HBA port state and error counters from /sys/class/fc_host and iSCSI
+ * session/connection state from /sys/class/iscsi_session and iscsi_connection,
+ * plus the SCSI H:C:T:L address of each sd device so transport events can be
+ * put on the same timeline as the I/O of the paths behind that host.
+ *
+ * Written to the capture as FC and ISCSI records (serverstats_format::FcHost and
+ * IscsiSession), plus SCSIDEV,ts,dev,H:C:T:L at start and whenever the device
+ * set changes.
+ *
+ * Every reader takes the sysfs root so it can be pointed at a fixture tree.
+ */
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub use serverstats_format::{FcHost, IscsiSession};
+use serverstats_format::Record;
+use serverstats_format::timeutil::{TimeCtx, TimeWindow};
+use crate::mpath::parse_multipath_ll;
+
+fn read_attr(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().replace(',', ";"))
+}
+
+/// fc_host statistics are hex ("0x1f"); unsupported counters read as all ones.
+fn read_counter(path: &Path) -> u64 {
+    let Some(s) = read_attr(path) else { return 0 };
+    let v = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).unwrap_or(0),
+        None => s.parse().unwrap_or(0),
+    };
+    if v == u64::MAX { 0 } else { v }
+}
+
+/// Sorted directory entry names, empty when the directory does not exist.
+fn list_dir(path: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(path)
+        .map(|rd| rd.filter_map(Result::ok).map(|e| e.file_name().to_string_lossy().to_string()).collect())
+        .unwrap_or_default();
+    names.sort_by_key(|n| natural_key(n));
+    names
+}
+
+/// Sort key so host10 comes after host9.
+fn natural_key(s: &str) -> (String, u64) {
+    let digits: String = s.chars().rev().take_while(|c| c.is_ascii_digit()).collect::<Vec<_>>().into_iter().rev().collect();
+    let prefix = s[..s.len() - digits.len()].to_string();
+    (prefix, digits.parse().unwrap_or(0))
+}
+
+/// Reads every FC HBA port under `<sysfs>/class/fc_host`.
+pub fn read_fc_hosts(sysfs: &Path) -> Vec<FcHost> {
+    let base = sysfs.join("class/fc_host");
+    list_dir(&base)
+        .into_iter()
+        .map(|host| {
+            let dir = base.join(&host);
+            let stats = dir.join("statistics");
+            FcHost {
+                port_name: read_attr(&dir.join("port_name")).unwrap_or_default(),
+                port_state: read_attr(&dir.join("port_state")).unwrap_or_default(),
+                speed: read_attr(&dir.join("speed")).unwrap_or_default(),
+                link_failure_count: read_counter(&stats.join("link_failure_count")),
+                loss_of_sync_count: read_counter(&stats.join("loss_of_sync_count")),
+                loss_of_signal_count: read_counter(&stats.join("loss_of_signal_count")),
+                invalid_crc_count: read_counter(&stats.join("invalid_crc_count")),
+                error_frames: read_counter(&stats.join("error_frames")),
+                tx_frames: read_counter(&stats.join("tx_frames")),
+                rx_frames: read_counter(&stats.join("rx_frames")),
+                host,
+            }
+        })
+        .collect()
+}
+
+/// First path component named hostN in a sysfs link target.
+fn host_in_link(path: &Path) -> Option<String> {
+    let target = fs::read_link(path).ok()?;
+    target
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .find(|c| c.starts_with("host") && c[4..].chars().all(|ch| ch.is_ascii_digit()) && c.len() > 4)
+}
+
+/// Reads every iSCSI session under `<sysfs>/class/iscsi_session`, with the
+/// state and portal of its first connection from `<sysfs>/class/iscsi_connection`.
+pub fn read_iscsi_sessions(sysfs: &Path) -> Vec<IscsiSession> {
+    let base = sysfs.join("class/iscsi_session");
+    let conns = sysfs.join("class/iscsi_connection");
+    let conn_names = list_dir(&conns);
+    list_dir(&base)
+        .into_iter()
+        .map(|session| {
+            let dir = base.join(&session);
+            let sid = session.trim_start_matches("session");
+            let conn = conn_names
+                .iter()
+                .find(|c| c.trim_start_matches("connection").split(':').next() == Some(sid))
+                .map(|c| conns.join(c));
+            let (conn_state, address) = match conn {
+                Some(c) => {
+                    let addr = read_attr(&c.join("persistent_address")).unwrap_or_default();
+                    let port = read_attr(&c.join("persistent_port")).unwrap_or_default();
+                    let address = if port.is_empty() { addr } else { format!("{}:{}", addr, port) };
+                    // connection "state" exists on 5.x+ kernels only
+                    (read_attr(&c.join("state")).unwrap_or_else(|| "-".to_string()), address)
+                }
+                None => ("-".to_string(), String::new()),
+            };
+            IscsiSession {
+                host: host_in_link(&dir.join("device")).unwrap_or_default(),
+                target: read_attr(&dir.join("targetname")).unwrap_or_default(),
+                state: read_attr(&dir.join("state")).unwrap_or_default(),
+                conn_state,
+                address,
+                session,
+            }
+        })
+        .collect()
+}
+
+/// (sd device, H:C:T:L) for every SCSI disk under `<sysfs>/block`.
+pub fn read_scsi_devices(sysfs: &Path) -> Vec<(String, String)> {
+    list_dir(&sysfs.join("block"))
+        .into_iter()
+        .filter(|d| d.starts_with("sd"))
+        .filter_map(|d| {
+            let target = fs::read_link(sysfs.join("block").join(&d).join("device")).ok()?;
+            let hctl = target.file_name()?.to_string_lossy().to_string();
+            (hctl.split(':').count() == 4).then_some((d, hctl))
+        })
+        .collect()
+}
+
+/// "5:0:1:3" -> "host5"
+pub fn host_of_hctl(hctl: &str) -> String {
+    format!("host{}", hctl.split(':').next().unwrap_or(""))
+}
+
+/// A state change or error counter increase worth putting on the timeline.
+#[derive(Debug, Clone)]
+pub struct TransportEvent {
+    pub ts: u64,
+    /// FC or ISCSI
+    pub source: &'static str,
+    /// SCSI host the event affects (hostN)
+    pub host: String,
+    /// fc host or iSCSI session name
+    pub object: String,
+    pub detail: String,
+}
+
+/// Turns successive FC/ISCSI samples into events.
+#[derive(Default)]
+pub struct TransportTracker {
+    fc: HashMap<String, FcHost>,
+    iscsi: HashMap<String, IscsiSession>,
+}
+
+impl TransportTracker {
+    pub fn fc(&mut self, ts: u64, cur: FcHost) -> Vec<TransportEvent> {
+        let mut details = Vec::new();
+        if let Some(prev) = self.fc.get(&cur.host) {
+            if prev.port_state != cur.port_state {
+                details.push(format!("port_state {} -> {}", prev.port_state, cur.port_state));
+            }
+            if prev.speed != cur.speed {
+                details.push(format!("speed {} -> {}", prev.speed, cur.speed));
+            }
+            let counts: Vec<String> = cur
+                .error_counters()
+                .iter()
+                .zip(prev.error_counters().iter())
+                .filter(|((_, now), (_, before))| now > before)
+                .map(|((name, now), (_, before))| format!("{} +{}", name, now - before))
+                .collect();
+            if !counts.is_empty() {
+                details.push(counts.join(" "));
+            }
+        }
+        let events = details
+            .into_iter()
+            .map(|detail| TransportEvent { ts, source: "FC", host: cur.host.clone(), object: cur.host.clone(), detail })
+            .collect();
+        self.fc.insert(cur.host.clone(), cur);
+        events
+    }
+
+    pub fn iscsi(&mut self, ts: u64, cur: IscsiSession) -> Vec<TransportEvent> {
+        let mut details = Vec::new();
+        if let Some(prev) = self.iscsi.get(&cur.session) {
+            if prev.state != cur.state {
+                details.push(format!("session {} -> {} ({})", prev.state, cur.state, cur.target));
+            }
+            if prev.conn_state != cur.conn_state {
+                details.push(format!("connection {} -> {} ({})", prev.conn_state, cur.conn_state, cur.address));
+            }
+        }
+        let events = details
+            .into_iter()
+            .map(|detail| TransportEvent { ts, source: "ISCSI", host: cur.host.clone(), object: cur.session.clone(), detail })
+            .collect();
+        self.iscsi.insert(cur.session.clone(), cur);
+        events
+    }
+}
+
+/// Per-host path I/O accumulated from the DISK records of one interval.
+#[derive(Default)]
+struct HostIo {
+    ios: u64,
+    sectors: u64,
+    dt: u64,
+}
+
+impl HostIo {
+    fn iops(&self) -> f64 {
+        if self.dt == 0 { 0.0 } else { self.ios as f64 / self.dt as f64 }
+    }
+    fn kbs(&self) -> f64 {
+        if self.dt == 0 { 0.0 } else { self.sectors as f64 * 0.5 / self.dt as f64 }
+    }
+}
+
+/// Playback of FC/iSCSI events on one timeline with the path I/O of the affected SCSI host.
+/// Path I/O per host comes from the DISK records of the sd devices behind it (SCSIDEV
+/// records, or the H:C:T:L in `multipath -ll` output for older captures). Besides the
+/// transport events, a line is printed when path I/O on a host stops or resumes.
+pub fn report_transport(dat_path: &str, multipath_path: Option<&str>, ctx: &mut TimeCtx, window: &TimeWindow,
+                        follow: bool) -> std::io::Result<()> {
+    let mut dev_host: HashMap<String, String> = HashMap::new();
+    let mut dev_mpath: HashMap<String, String> = HashMap::new();
+    if let Some(mp) = multipath_path {
+        for mdev in parse_multipath_ll(mp) {
+            for p in &mdev.paths {
+                dev_mpath.insert(p.dev_name.clone(), mdev.name.clone());
+                if p.bus.split(':').count() == 4 {
+                    dev_host.insert(p.dev_name.clone(), host_of_hctl(&p.bus));
+                }
+            }
+        }
+    }
+
+    // --follow "caught up" notices are not interval boundaries here, any other error ends the read
+    let mut records = serverstats_format::follow::records(dat_path, follow)?
+        .filter(|r| !matches!(r, Err(e) if serverstats_format::follow::caught_up(e)))
+        .map_while(Result::ok);
+    let mut tracker = TransportTracker::default();
+    let mut transport_hosts: Vec<String> = Vec::new();
+    let mut prev_disk: HashMap<String, (u64, u64, u64)> = HashMap::new();
+    let mut cur_ts = 0u64;
+    let mut cur_io: HashMap<String, HostIo> = HashMap::new();
+    let mut cur_events: Vec<TransportEvent> = Vec::new();
+    let mut last_iops: HashMap<String, f64> = HashMap::new();
+    let mut printed_header = false;
+
+    loop {
+        let rec = records.next();
+        let ts = rec.as_ref().map(Record::ts);
+
+        // Interval complete: print its events and any path I/O stop/resume
+        if rec.is_none() || (ts.is_some() && ts != Some(cur_ts) && cur_ts != 0) {
+            if cur_ts != 0 && window.contains(cur_ts) {
+                let mut rows: Vec<(String, &'static str, String, String)> = cur_events
+                    .iter()
+                    .map(|e| (e.host.clone(), e.source, e.object.clone(), e.detail.clone()))
+                    .collect();
+                for host in transport_hosts.iter().filter(|_| !cur_io.is_empty()) {
+                    let now = cur_io.get(host).map(|h| h.iops()).unwrap_or(0.0);
+                    let Some(before) = last_iops.get(host) else { continue };
+                    if *before > 0.0 && now == 0.0 {
+                        rows.push((host.clone(), "PATHIO", host.clone(), format!("path I/O stopped (was {:.1} IOPS)", before)));
+                    } else if *before == 0.0 && now > 0.0 {
+                        rows.push((host.clone(), "PATHIO", host.clone(), "path I/O resumed".to_string()));
+                    }
+                }
+                for (host, source, object, detail) in rows {
+                    if !printed_header {
+                        println!(
+                            "{:<8} {:<10} {:<8} {:<7} {:<10} {:>10} {:>12}  Event",
+                            "Time", "Epoch", "Host", "Source", "Object", "PathIOPS", "PathKB/s"
+                        );
+                        printed_header = true;
+                    }
+                    let io = cur_io.get(&host);
+                    println!(
+                        "{:<8} {:<10} {:<8} {:<7} {:<10} {:>10.1} {:>12.1}  {}",
+                        ctx.hms(cur_ts), cur_ts, host, source, object,
+                        io.map(|h| h.iops()).unwrap_or(0.0), io.map(|h| h.kbs()).unwrap_or(0.0), detail
+                    );
+                }
+            }
+            // Keep the previous rates current outside the window too
+            if !cur_io.is_empty() {
+                for host in transport_hosts.iter().filter(|h| dev_host.values().any(|v| v == *h)) {
+                    last_iops.insert(host.clone(), cur_io.get(host).map(|h| h.iops()).unwrap_or(0.0));
+                }
+            }
+            cur_io.clear();
+            cur_events.clear();
+            if cur_ts != 0 && window.is_past(cur_ts) { break; }
+        }
+        let Some(rec) = rec else { break };
+        if let Record::Tz { ts, offset, zone } = &rec {
+            ctx.note_host_tz(*ts, *offset, zone);
+            continue;
+        }
+        let ts = rec.ts();
+        cur_ts = ts;
+
+        match rec {
+            Record::ScsiDev { dev, hctl, .. } => {
+                dev_host.insert(dev, host_of_hctl(&hctl));
+            }
+            Record::Disk { stat, .. } => {
+                let Some(host) = dev_host.get(&stat.name) else { continue };
+                let ios = stat.reads + stat.writes;
+                let sectors = stat.sectors_read + stat.sectors_written;
+                if let Some((last_ts, last_ios, last_sectors)) = prev_disk.get(&stat.name) {
+                    let acc = cur_io.entry(host.clone()).or_default();
+                    acc.ios += ios.saturating_sub(*last_ios);
+                    acc.sectors += sectors.saturating_sub(*last_sectors);
+                    acc.dt = ts.saturating_sub(*last_ts);
+                }
+                prev_disk.insert(stat.name.clone(), (ts, ios, sectors));
+            }
+            Record::Fc { stat: fc, .. } => {
+                if !transport_hosts.contains(&fc.host) { transport_hosts.push(fc.host.clone()); }
+                cur_events.extend(tracker.fc(ts, fc));
+            }
+            Record::Iscsi { stat: s, .. } => {
+                if !s.host.is_empty() && !transport_hosts.contains(&s.host) { transport_hosts.push(s.host.clone()); }
+                cur_events.extend(tracker.iscsi(ts, s));
+            }
+            _ => {}
+        }
+    }
+
+    if transport_hosts.is_empty() {
+        println!("No FC/iSCSI data found.");
+        return Ok(());
+    }
+    if !printed_header {
+        println!("No transport events or path I/O changes in the selected window.");
+    }
+    println!();
+    println!("Paths per host:");
+    for host in &transport_hosts {
+        let mut devs: Vec<String> = dev_host
+            .iter()
+            .filter(|(_, h)| *h == host)
+            .map(|(d, _)| match dev_mpath.get(d) {
+                Some(m) => format!("{}({})", d, m),
+                None => d.clone(),
+            })
+            .collect();
+        devs.sort_by_key(|d| natural_key(d));
+        println!("  {:<8} {}", host, if devs.is_empty() { "-".to_string() } else { devs.join(" ") });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/root/sys")
+    }
+
+    #[test]
+    fn reads_fc_hosts_from_fixture() {
+        let hosts = read_fc_hosts(&fixture());
+        assert_eq!(hosts.len(), 2);
+        let h = &hosts[0];
+        assert_eq!(h.host, "host5");
+        assert_eq!(h.port_state, "Online");
+        assert_eq!(h.speed, "16 Gbit");
+        assert_eq!(h.link_failure_count, 3);
+        assert_eq!(h.invalid_crc_count, 0x1a);
+        // all-ones means the HBA does not support the counter
+        assert_eq!(h.loss_of_signal_count, 0);
+        assert_eq!(hosts[1].host, "host6");
+        assert_eq!(hosts[1].port_state, "Linkdown");
+    }
+
+    #[test]
+    fn fc_record_round_trip() {
+        let h = read_fc_hosts(&fixture()).remove(0);
+        let line = Record::Fc { ts: 100, stat: h.clone() }.to_string();
+        assert_eq!(Record::parse(&line), Some(Record::Fc { ts: 100, stat: h }));
+    }
+
+    #[test]
+    fn reads_iscsi_sessions_from_fixture() {
+        let s = read_iscsi_sessions(&fixture());
+        assert_eq!(s.len(), 1);
+        assert_eq!(s[0].session, "session1");
+        assert_eq!(s[0].host, "host3");
+        assert_eq!(s[0].state, "LOGGED_IN");
+        assert_eq!(s[0].conn_state, "up");
+        assert_eq!(s[0].address, "192.168.10.20:3260");
+        assert_eq!(s[0].target, "iqn.2001-05.com.example:storage.lun1");
+    }
+
+    #[test]
+    fn reads_scsi_addresses_from_fixture() {
+        let devs = read_scsi_devices(&fixture());
+        assert_eq!(devs, vec![
+            ("sdb".to_string(), "5:0:0:1".to_string()),
+            ("sdc".to_string(), "6:0:0:1".to_string()),
+            ("sdd".to_string(), "3:0:0:0".to_string()),
+        ]);
+        assert_eq!(host_of_hctl("5:0:0:1"), "host5");
+    }
+
+    #[test]
+    fn missing_sysfs_classes_yield_nothing() {
+        let empty = fixture().join("does-not-exist");
+        assert!(read_fc_hosts(&empty).is_empty());
+        assert!(read_iscsi_sessions(&empty).is_empty());
+        assert!(read_scsi_devices(&empty).is_empty());
+    }
+
+    #[test]
+    fn tracker_flags_state_changes_and_counter_increases() {
+        let mut t = TransportTracker::default();
+        let h = read_fc_hosts(&fixture()).remove(0);
+        assert!(t.fc(100, h.clone()).is_empty());
+        let mut next = h.clone();
+        next.port_state = "Linkdown".to_string();
+        next.loss_of_sync_count += 4;
+        let ev = t.fc(105, next);
+        assert_eq!(ev.len(), 2);
+        assert_eq!(ev[0].detail, "port_state Online -> Linkdown");
+        assert_eq!(ev[1].detail, "loss_of_sync +4");
+        assert_eq!(ev[1].host, "host5");
+
+        let s = read_iscsi_sessions(&fixture()).remove(0);
+        assert!(t.iscsi(100, s.clone()).is_empty());
+        let mut failed = s;
+        failed.state = "FAILED".to_string();
+        let ev = t.iscsi(110, failed);
+        assert_eq!(ev.len(), 1);
+        assert!(ev[0].detail.starts_with("session LOGGED_IN -> FAILED"));
+    }
+}
@@ -0,0 +1,478 @@
+/*!
+ * Interactive Capture Browser Module
+ * ----------------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * `serverstats_grab -i <capturefile>` opens a full-screen terminal UI over a
+ * capture, for hosts reached over SSH where the `-a` dashboard cannot be viewed:
+ *
+ *   - a timeline of CPU busy % and total disk IOPS with a cursor to scrub and zoom
+ *   - CPU, MEM and NET panes for the interval under the cursor
+ *   - a sortable device table for that interval, with a sparkline per device
+ *     of the sort column over the visible part of the timeline
+ *
+ * The whole capture (or the --from/--to window) is loaded into memory first.
+ *
+ * Keys:
+ *   ←/→ h/l        previous / next interval     PgUp/PgDn  move 1/10 of the view
+ *   Home/End       first / last interval        +/-  0     zoom in / out / reset
+ *   ↑/↓ k/j        select device                Tab/S      next / previous sort column
+ *   r              reverse sort                 q Esc      quit
+ */
+
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Sparkline, Table, TableState};
+use ratatui::Frame;
+use serverstats_format::{CpuPercent, CpuStat, DiskDelta, DiskStat, MemPercent, NetDelta, NetStat, Reader, Record};
+use serverstats_format::timeutil::{TimeCtx, TimeWindow};
+
+use crate::devselect::{is_partition, SortKey};
+use crate::vmstat::is_stacked;
+
+/// Everything captured for one interval end time.
+#[derive(Default)]
+struct Interval {
+    ts: u64,
+    cpu: Option<(CpuPercent, u64, u64)>, // (percentages, running, blocked)
+    mem: Option<MemPercent>,
+    disk: HashMap<usize, DiskDelta>,
+    net: HashMap<usize, NetDelta>,
+}
+
+impl Interval {
+    fn cpu_busy(&self) -> u64 {
+        self.cpu.as_ref().map_or(0, |(p, _, _)| (100.0 - p.idle).round().max(0.0) as u64)
+    }
+
+    /// IOPS over whole disks (partitions and dm/md are already counted below them).
+    fn disk_iops(&self, devs: &[String]) -> u64 {
+        self.disk.iter()
+            .filter(|(i, _)| !is_partition(&devs[**i]) && !is_stacked(&devs[**i]))
+            .map(|(_, d)| d.io_s())
+            .sum::<f64>()
+            .round() as u64
+    }
+}
+
+/// A capture loaded for browsing: intervals in time order, devices and interfaces in first-seen order.
+#[derive(Default)]
+struct Capture {
+    intervals: Vec<Interval>,
+    devs: Vec<String>,
+    ifaces: Vec<String>,
+}
+
+impl Capture {
+    fn load(file_path: &str, ctx: &mut TimeCtx, window: &TimeWindow) -> std::io::Result<Self> {
+        let mut cap = Capture::default();
+        let mut dev_idx: HashMap<String, usize> = HashMap::new();
+        let mut if_idx: HashMap<String, usize> = HashMap::new();
+        let mut prev_cpu: Option<CpuStat> = None;
+        let mut prev_disk: HashMap<String, (u64, DiskStat)> = HashMap::new();
+        let mut prev_net: HashMap<String, (u64, NetStat)> = HashMap::new();
+
+        for rec in Reader::open(file_path)?.flatten() {
+            if let Record::Tz { ts, offset, zone } = &rec {
+                ctx.note_host_tz(*ts, *offset, zone);
+                continue;
+            }
+            if !matches!(rec, Record::Cpu { .. } | Record::Mem { .. } | Record::Disk { .. } | Record::Net { .. }) { continue; }
+            let ts = rec.ts();
+            if window.is_past(ts) { break; }
+            // Keep prev current outside the window so the first interval in it is a true delta
+            let in_window = window.contains(ts);
+            if in_window && cap.intervals.last().is_none_or(|i| i.ts != ts) {
+                cap.intervals.push(Interval { ts, ..Default::default() });
+            }
+            let cur = cap.intervals.last_mut().filter(|_| in_window);
+            match rec {
+                Record::Cpu { stat, .. } => {
+                    if let Some(cur) = cur
+                        && let Some(p) = prev_cpu.as_ref().and_then(|last| CpuPercent::between(last, &stat))
+                    {
+                        cur.cpu = Some((p, stat.procs_running, stat.procs_blocked));
+                    }
+                    prev_cpu = Some(stat);
+                }
+                Record::Mem { stat, .. } => {
+                    if let Some(cur) = cur {
+                        cur.mem = Some(MemPercent::of(&stat));
+                    }
+                }
+                Record::Disk { ts, stat } => {
+                    let key = format!("{}-{}-{}", stat.major, stat.minor, stat.name);
+                    if let Some(cur) = cur
+                        && let Some((last_ts, last)) = prev_disk.get(&key)
+                        && let Some(d) = DiskDelta::between(last, &stat, ts.saturating_sub(*last_ts))
+                    {
+                        let n = dev_idx.len();
+                        let idx = *dev_idx.entry(stat.name.clone()).or_insert(n);
+                        if idx == cap.devs.len() { cap.devs.push(stat.name.clone()); }
+                        cur.disk.insert(idx, d);
+                    }
+                    prev_disk.insert(key, (ts, stat));
+                }
+                Record::Net { ts, stat } => {
+                    if let Some(cur) = cur
+                        && let Some((last_ts, last)) = prev_net.get(&stat.iface)
+                        && let Some(d) = NetDelta::between(last, &stat, ts.saturating_sub(*last_ts))
+                    {
+                        let n = if_idx.len();
+                        let idx = *if_idx.entry(stat.iface.clone()).or_insert(n);
+                        if idx == cap.ifaces.len() { cap.ifaces.push(stat.iface.clone()); }
+                        cur.net.insert(idx, d);
+                    }
+                    prev_net.insert(stat.iface.clone(), (ts, stat));
+                }
+                _ => {}
+            }
+        }
+        // The first sample only sets the baseline
+        cap.intervals.retain(|i| i.cpu.is_some() || !i.disk.is_empty() || !i.net.is_empty());
+        Ok(cap)
+    }
+}
+
+/// Device table columns: heading and the value sorted on (None: capture order).
+const DEV_COLS: &[(&str, Option<SortKey>)] = &[
+    ("Device", None),
+    ("r/s", Some(SortKey::RS)),
+    ("w/s", Some(SortKey::WS)),
+    ("rkB/s", Some(SortKey::RdKbs)),
+    ("wkB/s", Some(SortKey::WrKbs)),
+    ("r_await", Some(SortKey::AwaitRd)),
+    ("w_await", Some(SortKey::AwaitWr)),
+    ("aqu-sz", Some(SortKey::AvgQdepth)),
+    ("qlen", Some(SortKey::Qlen)),
+    ("%util", Some(SortKey::Util)),
+];
+
+/// Sparkline of `values` squeezed into `width` characters (max of each slice).
+fn spark_text(values: &[f64], width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    if values.is_empty() || width == 0 { return String::new(); }
+    let cols = squeeze(values, width);
+    let top = cols.iter().cloned().fold(0.0, f64::max);
+    cols.iter()
+        .map(|&v| if top > 0.0 { BARS[((v / top) * 7.0).round() as usize] } else { BARS[0] })
+        .collect()
+}
+
+/// Downsamples to at most `width` points, keeping the peak of each slice.
+fn squeeze(values: &[f64], width: usize) -> Vec<f64> {
+    let n = values.len();
+    if n <= width { return values.to_vec(); }
+    (0..width)
+        .map(|c| values[c * n / width..((c + 1) * n / width).max(c * n / width + 1)]
+            .iter().cloned().fold(0.0, f64::max))
+        .collect()
+}
+
+/// Horizontal bar for a percentage.
+fn bar(pct: f64, width: usize) -> String {
+    let n = ((pct.clamp(0.0, 100.0) / 100.0) * width as f64).round() as usize;
+    format!("{}{}", "█".repeat(n), "·".repeat(width - n))
+}
+
+struct App<'a> {
+    cap: Capture,
+    ctx: &'a TimeCtx,
+    cursor: usize,
+    /// Visible part of the timeline, [lo, hi)
+    lo: usize,
+    hi: usize,
+    sort: usize,
+    desc: bool,
+    table: TableState,
+}
+
+impl<'a> App<'a> {
+    fn new(cap: Capture, ctx: &'a TimeCtx) -> Self {
+        let hi = cap.intervals.len();
+        App { cap, ctx, cursor: 0, lo: 0, hi, sort: 0, desc: true, table: TableState::default().with_selected(0) }
+    }
+
+    fn len(&self) -> usize { self.cap.intervals.len() }
+
+    fn step(&self) -> usize { ((self.hi - self.lo) / 10).max(1) }
+
+    /// Moves the cursor, panning the view to keep it visible.
+    fn seek(&mut self, to: isize) {
+        let last = self.len().saturating_sub(1) as isize;
+        self.cursor = to.clamp(0, last.max(0)) as usize;
+        let span = self.hi - self.lo;
+        if self.cursor < self.lo {
+            self.lo = self.cursor;
+            self.hi = self.lo + span;
+        } else if self.cursor >= self.hi {
+            self.hi = self.cursor + 1;
+            self.lo = self.hi - span;
+        }
+    }
+
+    /// Zooms the view around the cursor; factor < 1 zooms in.
+    fn zoom(&mut self, factor: f64) {
+        let len = self.len();
+        let span = (((self.hi - self.lo) as f64 * factor).round() as usize).clamp(10.min(len), len);
+        let lo = self.cursor.saturating_sub(span / 2).min(len - span);
+        self.lo = lo;
+        self.hi = lo + span;
+    }
+
+    /// Returns false to quit.
+    fn key(&mut self, code: KeyCode) -> bool {
+        let cur = self.cursor as isize;
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Left | KeyCode::Char('h') => self.seek(cur - 1),
+            KeyCode::Right | KeyCode::Char('l') => self.seek(cur + 1),
+            KeyCode::PageUp => self.seek(cur - self.step() as isize),
+            KeyCode::PageDown => self.seek(cur + self.step() as isize),
+            KeyCode::Home => self.seek(0),
+            KeyCode::End => self.seek(self.len() as isize),
+            KeyCode::Char('+') | KeyCode::Char('=') => self.zoom(0.5),
+            KeyCode::Char('-') | KeyCode::Char('_') => self.zoom(2.0),
+            KeyCode::Char('0') => { self.lo = 0; self.hi = self.len(); }
+            KeyCode::Tab => self.sort = (self.sort + 1) % DEV_COLS.len(),
+            KeyCode::BackTab | KeyCode::Char('S') => self.sort = (self.sort + DEV_COLS.len() - 1) % DEV_COLS.len(),
+            KeyCode::Char('r') => self.desc = !self.desc,
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            _ => {}
+        }
+        true
+    }
+
+    /// Devices with a delta at the cursor, in table order.
+    fn device_rows(&self) -> Vec<(usize, &DiskDelta)> {
+        let iv = &self.cap.intervals[self.cursor];
+        let mut rows: Vec<(usize, &DiskDelta)> = iv.disk.iter().map(|(i, d)| (*i, d)).collect();
+        rows.sort_by_key(|(i, _)| *i);
+        if let Some(key) = DEV_COLS[self.sort].1 {
+            rows.sort_by(|a, b| key.value(b.1).total_cmp(&key.value(a.1)));
+        }
+        if !self.desc { rows.reverse(); }
+        rows
+    }
+
+    fn render(&mut self, f: &mut Frame) {
+        let [head, timeline, panes, devices, help] = Layout::vertical([
+            Constraint::Length(1), Constraint::Length(7), Constraint::Length(8), Constraint::Min(5), Constraint::Length(1),
+        ]).areas(f.area());
+        let iv = &self.cap.intervals[self.cursor];
+        let ctx = self.ctx;
+        f.render_widget(Line::from(format!(
+            " {}  interval {}/{}  ({})", ctx.format(iv.ts, "%Y-%m-%d %H:%M:%S"), self.cursor + 1, self.len(), ctx.label()
+        )).bold(), head);
+        self.render_timeline(f, timeline);
+        let [cpu, mem, net] = Layout::horizontal([Constraint::Fill(1); 3]).areas(panes);
+        self.render_cpu_mem(f, cpu, mem);
+        self.render_net(f, net);
+        self.render_devices(f, devices);
+        f.render_widget(Line::from(
+            " ←/→ scrub  PgUp/PgDn jump  +/- zoom  0 reset  Tab/S sort  r reverse  ↑/↓ select  q quit"
+        ).dim(), help);
+    }
+
+    fn render_timeline(&self, f: &mut Frame, area: Rect) {
+        let ctx = self.ctx;
+        let ivs = &self.cap.intervals[self.lo..self.hi];
+        let zoom = self.len() as f64 / (self.hi - self.lo).max(1) as f64;
+        let title = format!(
+            " Timeline {} - {}{} ", ctx.hms(ivs[0].ts), ctx.hms(ivs[ivs.len() - 1].ts),
+            if zoom > 1.0 { format!("  (zoom {:.0}x)", zoom) } else { String::new() }
+        );
+        let block = Block::bordered().title(title);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+        let [cpu_l, cpu_s, io_l, io_s, mark] = Layout::vertical([Constraint::Length(1); 5]).areas(inner);
+        let width = inner.width as usize;
+        let cpu: Vec<f64> = ivs.iter().map(|i| i.cpu_busy() as f64).collect();
+        let io: Vec<f64> = ivs.iter().map(|i| i.disk_iops(&self.cap.devs) as f64).collect();
+        let peak = |v: &[f64]| v.iter().cloned().fold(0.0, f64::max);
+        f.render_widget(Line::from(format!("CPU busy %  (peak {:.0})", peak(&cpu))).dim(), cpu_l);
+        f.render_widget(Sparkline::default().data(squeeze(&cpu, width).iter().map(|v| *v as u64).collect::<Vec<_>>())
+            .max(100).style(Style::default().fg(Color::Green)), cpu_s);
+        f.render_widget(Line::from(format!("Disk IOPS   (peak {:.0})", peak(&io))).dim(), io_l);
+        f.render_widget(Sparkline::default().data(squeeze(&io, width).iter().map(|v| *v as u64).collect::<Vec<_>>())
+            .style(Style::default().fg(Color::Cyan)), io_s);
+        let span = (self.hi - self.lo).max(1);
+        let pos = ((self.cursor - self.lo) * width.min(span).max(1) / span).min(width.saturating_sub(1));
+        f.render_widget(Line::from(format!("{}▲ {}", " ".repeat(pos), ctx.hms(self.cap.intervals[self.cursor].ts))).yellow(), mark);
+    }
+
+    fn render_cpu_mem(&self, f: &mut Frame, cpu_area: Rect, mem_area: Rect) {
+        let iv = &self.cap.intervals[self.cursor];
+        let bw = cpu_area.width.saturating_sub(14) as usize;
+        let line = |name: &str, v: f64, bw: usize| Line::from(format!("{:<6}{:>6.1} {}", name, v, bar(v, bw)));
+        let cpu: Vec<Line> = match &iv.cpu {
+            Some((p, running, blocked)) => vec![
+                line("user", p.user + p.nice, bw), line("sys", p.system, bw), line("iowait", p.iowait, bw),
+                line("steal", p.steal, bw), line("idle", p.idle, bw),
+                Line::from(format!("run queue {}  blocked {}", running, blocked)),
+            ],
+            None => vec![Line::from("no CPU sample")],
+        };
+        f.render_widget(Paragraph::new(cpu).block(Block::bordered().title(" CPU % ")), cpu_area);
+        let bw = mem_area.width.saturating_sub(14) as usize;
+        let mem: Vec<Line> = match &iv.mem {
+            Some(m) => vec![
+                line("used", m.used, bw), line("avail", m.avail, bw), line("cached", m.cached, bw), line("free", m.free, bw),
+            ],
+            None => vec![Line::from("no MEM sample")],
+        };
+        f.render_widget(Paragraph::new(mem).block(Block::bordered().title(" MEM % ")), mem_area);
+    }
+
+    fn render_net(&self, f: &mut Frame, area: Rect) {
+        let iv = &self.cap.intervals[self.cursor];
+        let mut rows: Vec<(usize, &NetDelta)> = iv.net.iter().map(|(i, d)| (*i, d)).collect();
+        rows.sort_by(|a, b| (b.1.rx_kbs() + b.1.tx_kbs()).total_cmp(&(a.1.rx_kbs() + a.1.tx_kbs())));
+        let rows = rows.into_iter().map(|(i, d)| Row::new(vec![
+            self.cap.ifaces[i].clone(), format!("{:.1}", d.rx_kbs()), format!("{:.1}", d.tx_kbs()),
+            format!("{}", d.rx_errs + d.tx_errs + d.rx_drop + d.tx_drop),
+        ]));
+        let table = Table::new(rows, [Constraint::Fill(1), Constraint::Length(10), Constraint::Length(10), Constraint::Length(7)])
+            .header(Row::new(["Iface", "rx_kB/s", "tx_kB/s", "err+drp"]).bold())
+            .block(Block::bordered().title(" NET "));
+        f.render_widget(table, area);
+    }
+
+    fn render_devices(&mut self, f: &mut Frame, area: Rect) {
+        const TREND: u16 = 24;
+        let ivs = &self.cap.intervals[self.lo..self.hi];
+        let rows = self.device_rows();
+        let key = DEV_COLS[self.sort].1;
+        let table_rows: Vec<Row> = rows.iter().map(|(i, d)| {
+            let trend: Vec<f64> = ivs.iter()
+                .map(|iv| iv.disk.get(i).map_or(0.0, |d| key.unwrap_or(SortKey::IoS).value(d)))
+                .collect();
+            Row::new(vec![
+                self.cap.devs[*i].clone(),
+                format!("{:.1}", d.r_s()), format!("{:.1}", d.w_s()),
+                format!("{:.1}", d.rd_kbs()), format!("{:.1}", d.wr_kbs()),
+                format!("{:.2}", d.await_rd()), format!("{:.2}", d.await_wr()),
+                format!("{:.2}", d.avg_queue_depth()), format!("{:.2}", d.qlen()),
+                format!("{:.1}", SortKey::Util.value(d).min(100.0)),
+                spark_text(&trend, TREND as usize),
+            ])
+        }).collect();
+        let mut header: Vec<String> = DEV_COLS.iter().enumerate()
+            .map(|(n, (title, _))| if n == self.sort { format!("{}{}", title, if self.desc { "▼" } else { "▲" }) } else { title.to_string() })
+            .collect();
+        header.push(format!("trend ({})", if key.is_some() { DEV_COLS[self.sort].0 } else { "io/s" }));
+        let mut widths = vec![Constraint::Length(12)];
+        widths.extend([Constraint::Length(9); 9]);
+        widths.push(Constraint::Min(TREND));
+        let count = table_rows.len();
+        let table = Table::new(table_rows, widths)
+            .header(Row::new(header).bold())
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .block(Block::bordered().title(format!(" Devices ({}) ", count)));
+        if self.table.selected().is_some_and(|s| s >= count) {
+            self.table.select(Some(count.saturating_sub(1)));
+        }
+        f.render_stateful_widget(table, area, &mut self.table);
+    }
+}
+
+/// Browse a capture in a full-screen terminal UI.
+pub fn browse(file_path: &str, ctx: &mut TimeCtx, window: &TimeWindow) -> std::io::Result<()> {
+    if !std::io::stdout().is_terminal() {
+        eprintln!("ERROR: -i needs a terminal; use -pA or -pD for piped output");
+        std::process::exit(1);
+    }
+    let cap = Capture::load(file_path, ctx, window)?;
+    if cap.intervals.is_empty() {
+        println!("No data found.");
+        return Ok(());
+    }
+    let mut app = App::new(cap, ctx);
+    let mut terminal = ratatui::init();
+    let result = (|| -> std::io::Result<()> {
+        loop {
+            terminal.draw(|f| app.render(f))?;
+            if let Event::Key(k) = event::read()?
+                && k.kind == KeyEventKind::Press
+                && !app.key(k.code)
+            {
+                return Ok(());
+            }
+        }
+    })();
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serverstats_format::timeutil::DisplayTz;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn capture(n: usize) -> Capture {
+        let zero = DiskStat::from_line("8 0 x 0 0 0 0 0 0 0 0 0 0 0").unwrap();
+        let disk = |reads: u64| {
+            let cur = DiskStat::from_line(&format!("8 0 x {} 0 {} {} 0 0 0 0 0 {} 0", reads, reads * 8, reads * 2, reads)).unwrap();
+            DiskDelta::between(&zero, &cur, 10).unwrap()
+        };
+        let mut cap = Capture { devs: vec!["sda".into(), "sdb".into()], ..Default::default() };
+        for i in 0..n {
+            let mut iv = Interval { ts: 1741958100 + i as u64 * 10, ..Default::default() };
+            iv.disk.insert(0, disk(10 * i as u64));
+            iv.disk.insert(1, disk(500 - i as u64));
+            cap.intervals.push(iv);
+        }
+        cap
+    }
+
+    #[test]
+    fn scrub_zoom_and_sort() {
+        let ctx = TimeCtx::new(DisplayTz::parse("UTC").unwrap());
+        let mut app = App::new(capture(100), &ctx);
+        app.key(KeyCode::Char('+'));
+        assert_eq!((app.lo, app.hi), (0, 50));
+        app.key(KeyCode::End);
+        assert_eq!((app.cursor, app.lo, app.hi), (99, 50, 100));
+        app.key(KeyCode::PageUp);
+        assert_eq!(app.cursor, 94);
+        app.key(KeyCode::Char('-'));
+        assert_eq!((app.lo, app.hi), (0, 100));
+        app.key(KeyCode::Home);
+        app.key(KeyCode::Left);
+        assert_eq!(app.cursor, 0);
+
+        // Capture order, then r/s highest first, then reversed
+        app.seek(10);
+        assert_eq!(app.device_rows().iter().map(|r| r.0).collect::<Vec<_>>(), [0, 1]);
+        app.key(KeyCode::Tab);
+        assert_eq!(app.device_rows()[0].0, 1);
+        app.key(KeyCode::Char('r'));
+        assert_eq!(app.device_rows()[0].0, 0);
+        assert!(!app.key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn sparklines_and_render() {
+        assert_eq!(spark_text(&[0.0, 1.0, 2.0, 7.0], 4), "▁▂▃█");
+        assert_eq!(spark_text(&[0.0, 7.0, 0.0, 0.0], 2), "█▁");
+        assert_eq!(squeeze(&[1.0, 5.0, 2.0, 3.0, 4.0, 0.0], 3), [5.0, 3.0, 4.0]);
+        assert_eq!(bar(50.0, 4), "██··");
+
+        let ctx = TimeCtx::new(DisplayTz::parse("UTC").unwrap());
+        let mut app = App::new(capture(30), &ctx);
+        let mut term = Terminal::new(TestBackend::new(140, 40)).unwrap();
+        term.draw(|f| app.render(f)).unwrap();
+        let screen: String = term.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+        assert!(screen.contains("2025-03-14 13:15:00"));
+        assert!(screen.contains("Devices (2)"));
+        assert!(screen.contains("sdb"));
+    }
+}
@@ -0,0 +1,213 @@
+/*!
+ * All-Subsystem Playback Module
+ * -----------------------------
+ * Copyright (C) 2025 Laurence Oberman <loberman@redhat.com>
+ *
+ * `-pA` prints one vmstat-style line per interval with the key figures of every
+ * subsystem, so CPU, memory, disk and network no longer have to be lined up by
+ * eye from `-pC`, `-pM`, `-pD` and `-pN`:
+ *
+ *   CPU      us/sy/wa/st/id %, run queue (r) and blocked (b)
+ *   Memory   MemAvailable %, Dirty and swap used (MB)
+ *   Disk     total IOPS and kB/s, and the worst await with the device it was on
+ *   Network  total rx/tx kB/s over all interfaces except lo
+ *
+ * Disk totals count whole disks only: partitions and stacked devices (dm-*, md*,
+ * drbd*, emcpower*) are left out so I/O is not counted twice. The worst await
+ * looks at every device, dm and md included.
+ */
+
+use std::collections::HashMap;
+
+use serverstats_format::{CpuPercent, CpuStat, DiskDelta, DiskStat, MemStat, NetDelta, NetStat, Record};
+use serverstats_format::output::{Cell, Col, OutputOpts, Table};
+use serverstats_format::timeutil::{TimeCtx, TimeWindow};
+
+use crate::devselect::is_partition;
+
+const COLS: &[Col] = &[
+    Col::new("time", "Time").l(8),
+    Col::new("epoch", "Epoch").l(10).hidden(),
+    Col::new("dt", "Δt").r(4),
+    Col::new("user", "us").r(5).prec(1),
+    Col::new("system", "sy").r(5).prec(1),
+    Col::new("iowait", "wa").r(5).prec(1),
+    Col::new("steal", "st").r(5).prec(1),
+    Col::new("idle", "id").r(5).prec(1),
+    Col::new("running", "r").r(4),
+    Col::new("blocked", "b").r(4),
+    Col::new("avail_pct", "avail%").r(7).prec(1),
+    Col::new("dirty_mb", "dirtyMB").r(9).prec(1),
+    Col::new("swap_used_mb", "swapMB").r(9).prec(1),
+    Col::new("disk_iops", "IOPS").r(10).prec(1),
+    Col::new("disk_kbs", "disk_kB/s").r(11).prec(1),
+    Col::new("max_await_ms", "max_await").r(10).prec(2),
+    Col::new("max_await_dev", "on").l(10),
+    Col::new("net_rx_kbs", "rx_kB/s").r(11).prec(1),
+    Col::new("net_tx_kbs", "tx_kB/s").r(11).prec(1),
+];
+
+/// Stacked block devices whose I/O is already counted on the disks below them.
+pub(crate) fn is_stacked(name: &str) -> bool {
+    ["dm-", "md", "drbd", "emcpower"].iter().any(|p| name.starts_with(p))
+}
+
+/// Disk, memory and network figures of one interval, filled as its records are read.
+#[derive(Default)]
+struct Interval {
+    ts: u64,
+    cpu: Option<(u64, CpuPercent, u64, u64)>, // (dt, percentages, running, blocked)
+    mem: Option<MemStat>,
+    disks: bool,
+    iops: f64,
+    kbs: f64,
+    worst: Option<(f64, String)>,
+    net: bool,
+    rx_kbs: f64,
+    tx_kbs: f64,
+}
+
+impl Interval {
+    fn add_disk(&mut self, name: &str, d: &DiskDelta) {
+        self.disks = true;
+        if !is_partition(name) && !is_stacked(name) {
+            self.iops += d.io_s();
+            self.kbs += d.kb_s();
+        }
+        let wait = d.await_rd().max(d.await_wr());
+        if d.reads + d.writes > 0 && self.worst.as_ref().is_none_or(|(w, _)| wait > *w) {
+            self.worst = Some((wait, name.to_string()));
+        }
+    }
+
+    /// Table cells, or None when there is no CPU delta to anchor the line.
+    fn cells(&self) -> Option<Vec<Cell>> {
+        let (dt, p, running, blocked) = self.cpu.as_ref()?;
+        let mem = |key: &str| self.mem.as_ref().and_then(|m| m.get(key));
+        let total = mem("MemTotal").filter(|&t| t > 0);
+        let mb = |kb: Option<u64>| kb.map(|v| v as f64 / 1024.0);
+        let disk = |v: f64| if self.disks { Cell::Float(v) } else { Cell::Missing };
+        let net = |v: f64| if self.net { Cell::Float(v) } else { Cell::Missing };
+        Some(vec![
+            Cell::Time(self.ts), self.ts.into(), (*dt).into(),
+            (p.user + p.nice).into(), p.system.into(), p.iowait.into(), p.steal.into(), p.idle.into(),
+            (*running).into(), (*blocked).into(),
+            total.and_then(|t| mem("MemAvailable").map(|a| a as f64 * 100.0 / t as f64)).into(),
+            mb(mem("Dirty")).into(),
+            mb(mem("SwapTotal").zip(mem("SwapFree")).map(|(t, f)| t.saturating_sub(f))).into(),
+            disk(self.iops), disk(self.kbs),
+            self.worst.as_ref().map(|(w, _)| *w).into(),
+            self.worst.as_ref().map(|(_, dev)| dev.clone()).into(),
+            net(self.rx_kbs), net(self.tx_kbs),
+        ])
+    }
+}
+
+/// Playback every subsystem as one line per interval.
+pub fn playback_all(file_path: &str, ctx: &mut TimeCtx, window: &TimeWindow, out: &OutputOpts, follow: bool) -> std::io::Result<()> {
+    let mut table = Table::new(COLS, out).unwrap_or_else(|e| {
+        eprintln!("ERROR: {}", e);
+        std::process::exit(1);
+    });
+    let mut prev_cpu: Option<(u64, CpuStat)> = None;
+    let mut prev_disk: HashMap<String, (u64, DiskStat)> = HashMap::new();
+    let mut prev_net: HashMap<String, (u64, NetStat)> = HashMap::new();
+    let mut cur = Interval::default();
+    // --follow prints an interval once caught up; its ts is kept so it is not printed twice
+    let mut printed: Option<u64> = None;
+
+    for rec in serverstats_format::follow::records(file_path, follow)? {
+        let rec = match rec {
+            Ok(rec) => rec,
+            Err(e) if serverstats_format::follow::caught_up(&e) => {
+                if printed != Some(cur.ts) && let Some(cells) = cur.cells() {
+                    table.row(Some(ctx), &cells);
+                    table.flush();
+                    printed = Some(cur.ts);
+                }
+                continue;
+            }
+            Err(_) => continue,
+        };
+        if let Record::Tz { ts, offset, zone } = &rec {
+            ctx.note_host_tz(*ts, *offset, zone);
+            continue;
+        }
+        if !matches!(rec, Record::Cpu { .. } | Record::Mem { .. } | Record::Disk { .. } | Record::Net { .. }) { continue; }
+        let ts = rec.ts();
+        if window.is_past(ts) { break; }
+        if ts != cur.ts {
+            if printed != Some(cur.ts) && let Some(cells) = cur.cells() {
+                table.row(Some(ctx), &cells);
+            }
+            cur = Interval { ts, ..Default::default() };
+        }
+        // Keep prev current outside the window so the first line in it is a true delta
+        let in_window = window.contains(ts);
+        match rec {
+            Record::Cpu { ts, stat } => {
+                if in_window
+                    && let Some((last_ts, last)) = &prev_cpu
+                    && let Some(p) = CpuPercent::between(last, &stat)
+                {
+                    cur.cpu = Some((ts.saturating_sub(*last_ts), p, stat.procs_running, stat.procs_blocked));
+                }
+                prev_cpu = Some((ts, stat));
+            }
+            Record::Mem { stat, .. } => cur.mem = Some(stat),
+            Record::Disk { ts, stat } => {
+                let key = format!("{}-{}-{}", stat.major, stat.minor, stat.name);
+                if in_window
+                    && let Some((last_ts, last)) = prev_disk.get(&key)
+                    && let Some(d) = DiskDelta::between(last, &stat, ts.saturating_sub(*last_ts))
+                {
+                    cur.add_disk(&stat.name, &d);
+                }
+                prev_disk.insert(key, (ts, stat));
+            }
+            Record::Net { ts, stat } => {
+                if in_window
+                    && stat.iface != "lo"
+                    && let Some((last_ts, last)) = prev_net.get(&stat.iface)
+                    && let Some(d) = NetDelta::between(last, &stat, ts.saturating_sub(*last_ts))
+                {
+                    cur.net = true;
+                    cur.rx_kbs += d.rx_kbs();
+                    cur.tx_kbs += d.tx_kbs();
+                }
+                prev_net.insert(stat.iface.clone(), (ts, stat));
+            }
+            _ => {}
+        }
+    }
+    if printed != Some(cur.ts) && let Some(cells) = cur.cells() {
+        table.row(Some(ctx), &cells);
+    }
+    table.finish("No CPU data found.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disk_totals_skip_partitions_and_stacked_devices() {
+        let zero = DiskStat::from_line("8 0 x 0 0 0 0 0 0 0 0 0 0 0").unwrap();
+        let d = |reads: u64, read_ms: u64| {
+            let cur = DiskStat::from_line(&format!("8 0 x {} 0 {} {} 0 0 0 0 0 0 0", reads, reads * 4, read_ms)).unwrap();
+            DiskDelta::between(&zero, &cur, 10).unwrap()
+        };
+        let mut iv = Interval::default();
+        iv.add_disk("sda", &d(100, 100));
+        iv.add_disk("sda1", &d(100, 100));
+        iv.add_disk("sdb", &d(50, 500));
+        iv.add_disk("dm-0", &d(150, 3000));
+        iv.add_disk("sdc", &d(0, 0));
+        assert_eq!(iv.iops, 15.0);
+        assert_eq!(iv.kbs, 30.0);
+        assert_eq!(iv.worst, Some((20.0, "dm-0".to_string())));
+        // No line without a CPU delta
+        assert!(iv.cells().is_none());
+    }
+}